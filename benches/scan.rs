@@ -0,0 +1,68 @@
+// `cargo bench`: loading the advisory index, scanning lockfiles and building SBOMs on
+// synthetic data from tests/support, so no advisory-db clone or network is needed.
+// Compare a change against an earlier run with `cargo bench -- --save-baseline main`
+// before it and `cargo bench -- --baseline main` after.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use rustpj::Scanner;
+use rustpj::cpe::CpeMappings;
+use rustpj::get_sbom::{SbomOptions, build_sbom};
+use rustpj::output::Stamp;
+use rustpj::registry::RegistryMap;
+use rustpj::workspace::WorkspaceMembers;
+use std::collections::BTreeMap;
+use std::hint::black_box;
+
+#[path = "../tests/support/mod.rs"]
+mod support;
+
+const ADVISORIES: usize = 1_000;
+const LOCKFILE_SIZES: [usize; 3] = [100, 1_000, 5_000];
+
+fn index(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let db = support::synthetic_advisory_db(dir.path(), ADVISORIES);
+    c.bench_function("index/load_1000_advisories", |b| b.iter(|| Scanner::new(black_box(&db)).unwrap()));
+}
+
+fn scan(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let scanner = Scanner::new(support::synthetic_advisory_db(dir.path(), ADVISORIES)).unwrap();
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(20);
+    for size in LOCKFILE_SIZES {
+        let lockfile = support::synthetic_lockfile(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &lockfile, |b, lockfile| {
+            b.iter(|| scanner.scan_lockfile(black_box(lockfile)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn sbom(c: &mut Criterion) {
+    let members = WorkspaceMembers::default();
+    let cpes = CpeMappings::embedded();
+    let component_types = BTreeMap::new();
+    let options = SbomOptions {
+        metadata: None,
+        members: &members,
+        cpes: &cpes,
+        component_types: &component_types,
+        report: None,
+        project: None,
+        registries: &RegistryMap::default(),
+        stamp: Stamp::now(),
+    };
+    let mut group = c.benchmark_group("sbom");
+    group.sample_size(20);
+    for size in LOCKFILE_SIZES {
+        let lockfile = support::synthetic_lockfile(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &lockfile, |b, lockfile| {
+            b.iter(|| build_sbom(black_box(lockfile), &options))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, index, scan, sbom);
+criterion_main!(benches);
@@ -0,0 +1,168 @@
+use anyhow::{Context, Result, bail};
+use rustsec::advisory::Advisory;
+use rustsec::database::Database;
+use rustsec::repository::git::Repository;
+use rustsec::Collection;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+// How an advisory DB checkout is read. rustsec's `Database::load_from_repo` fails as a
+// whole on the first bad file, so one hand-edited advisory with a TOML error in a local
+// fork would stop every scan. By default (`Lenient`) the checkout is walked file by file
+// instead, with the same layout rules as rustsec (`<collection>/<package>/<id>.md`); a
+// file that does not parse, is misplaced or repeats an id is skipped and recorded as a
+// `DbWarning`, which ends up in the report's `metadata.db_warnings`. `--strict-db` selects
+// `Strict`, rustsec's own loader, where such a file fails the run.
+
+/// advisory DB 的加载方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AdvisorySource {
+    // 逐个文件加载，跳过无法解析的文件
+    #[default]
+    Lenient,
+    // rustsec 的加载器：任一文件有误即失败（--strict-db）
+    Strict,
+}
+
+/// 宽松加载时被跳过的 advisory 文件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DbWarning {
+    // 所在的 advisory DB（--db-path）
+    pub db: String,
+    // 相对于 DB 根目录的路径
+    pub path: String,
+    pub error: String,
+}
+
+/// 从一个 DB 克隆读到的内容
+#[derive(Debug, Default)]
+pub struct LoadedDb {
+    pub advisories: Vec<Advisory>,
+    // HEAD 提交
+    pub commit: Option<String>,
+    pub warnings: Vec<DbWarning>,
+}
+
+impl AdvisorySource {
+    /// 加载 path 处的 advisory DB 克隆；name 是报告中显示的 DB 路径
+    pub fn load(self, path: &Path, name: &str) -> Result<LoadedDb> {
+        let repo = Repository::open(path)
+            .with_context(|| format!("failed to open advisory DB git repository {}", path.display()))?;
+        match self {
+            AdvisorySource::Strict => {
+                let db = Database::load_from_repo(&repo)
+                    .with_context(|| format!("failed to load advisory database {}", path.display()))?;
+                let commit = db.latest_commit().map(|c| c.commit_id.to_string());
+                Ok(LoadedDb { advisories: db.into_iter().collect(), commit, warnings: Vec::new() })
+            }
+            AdvisorySource::Lenient => {
+                let commit = repo
+                    .latest_commit()
+                    .with_context(|| format!("failed to read the HEAD commit of advisory DB {}", path.display()))?
+                    .commit_id
+                    .to_string();
+                let mut loaded = load_files(path, name)?;
+                loaded.commit = Some(commit);
+                Ok(loaded)
+            }
+        }
+    }
+}
+
+fn load_files(root: &Path, name: &str) -> Result<LoadedDb> {
+    let mut loaded = LoadedDb::default();
+    let mut ids = HashSet::new();
+    for &collection in Collection::all() {
+        let Ok(packages) = fs::read_dir(root.join(collection.as_str())) else {
+            continue;
+        };
+        let mut files = Vec::new();
+        for package in packages {
+            let package = package?;
+            if !package.file_type()?.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(package.path())? {
+                let file = file?.path();
+                // Dotfiles like .DS_Store
+                if !file.file_name().and_then(|f| f.to_str()).is_some_and(|f| f.starts_with('.')) {
+                    files.push(file);
+                }
+            }
+        }
+        // read_dir order is arbitrary; warnings and duplicate handling should not be
+        files.sort();
+        for file in files {
+            let relative = file.strip_prefix(root).unwrap_or(&file).display().to_string();
+            match load_file(&file, collection) {
+                Ok(advisory) if advisory.metadata.id.is_placeholder() => {}
+                Ok(advisory) if !ids.insert(advisory.metadata.id.clone()) => loaded.warnings.push(DbWarning {
+                    db: name.to_string(),
+                    path: relative,
+                    error: format!("duplicate advisory ID: {}", advisory.metadata.id),
+                }),
+                Ok(advisory) => loaded.advisories.push(advisory),
+                Err(e) => loaded.warnings.push(DbWarning { db: name.to_string(), path: relative, error: format!("{:#}", e) }),
+            }
+        }
+    }
+    Ok(loaded)
+}
+
+// The checks rustsec makes while loading a database
+fn load_file(path: &Path, collection: Collection) -> Result<Advisory> {
+    if path.extension().and_then(|e| e.to_str()) != Some("md") {
+        bail!("unexpected file extension");
+    }
+    let text = fs::read_to_string(path).context("failed to read the advisory")?;
+    let mut advisory: Advisory = text.parse().context("invalid advisory")?;
+    let expected = format!("{}.md", advisory.metadata.id);
+    if path.file_name().and_then(|f| f.to_str()) != Some(expected.as_str()) {
+        bail!("expected the file to be named {}", expected);
+    }
+    let package_dir = path.parent().and_then(|p| p.file_name()).and_then(|f| f.to_str());
+    if package_dir != Some(advisory.metadata.package.as_str()) {
+        bail!("expected {} to be in a directory named {}", advisory.metadata.id, advisory.metadata.package);
+    }
+    match advisory.metadata.collection {
+        Some(declared) if declared != collection => bail!("collection mismatch for {}", advisory.metadata.id),
+        Some(_) => {}
+        None => advisory.metadata.collection = Some(collection),
+    }
+    Ok(advisory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::AdvisoryText;
+
+    #[test]
+    fn test_broken_misplaced_and_duplicate_files_are_skipped() {
+        let advisory = AdvisoryText::new("RUSTSEC-2020-0001", "demo").markdown();
+        let dir = tempfile::tempdir().unwrap();
+        let write = |rel: &str, text: &str| {
+            let path = dir.path().join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write("crates/demo/RUSTSEC-2020-0001.md", &advisory);
+        write("crates/demo/.DS_Store", "");
+        write("crates/other/RUSTSEC-2020-0001.md", &advisory);
+        write("crates/demo/RUSTSEC-2020-0002.md", &advisory.replace("0001", "0002").replace("[versions]", "[versions"));
+        write("rust/demo/RUSTSEC-2020-0001.md", &advisory);
+        write("rust/demo/RUSTSEC-2020-0003.md", &advisory.replace("0001", "0003"));
+
+        let loaded = load_files(dir.path(), "db").unwrap();
+        assert_eq!(loaded.advisories.len(), 2);
+        assert_eq!(loaded.advisories[1].metadata.collection, Some(Collection::Rust));
+        let warnings: Vec<(&str, &str)> = loaded.warnings.iter().map(|w| (w.path.as_str(), w.error.as_str())).collect();
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(warnings[0].0, "crates/demo/RUSTSEC-2020-0002.md");
+        assert!(warnings[0].1.starts_with("invalid advisory"));
+        assert_eq!(warnings[1], ("crates/other/RUSTSEC-2020-0001.md", "expected RUSTSEC-2020-0001 to be in a directory named demo"));
+        assert_eq!(warnings[2], ("rust/demo/RUSTSEC-2020-0001.md", "duplicate advisory ID: RUSTSEC-2020-0001"));
+    }
+}
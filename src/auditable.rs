@@ -0,0 +1,146 @@
+use anyhow::{Context, Result, anyhow, bail};
+use cargo_lock::{Dependency, Lockfile, Package, ResolveVersion, SourceId};
+use flate2::read::ZlibDecoder;
+use object::{Object, ObjectSection};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+// Input mode for compiled binaries built with `cargo auditable`: the dependency list is
+// stored as zlib-compressed JSON in a `.dep-v0` section of the ELF/PE/Mach-O file.
+// Only names, versions, source kinds and the dependency graph survive, so there are no
+// licenses and no dev/normal scope information.
+
+const SECTION_NAME: &str = ".dep-v0";
+// Guards against decompression bombs; real dependency lists are a few hundred KiB at most
+const MAX_DECOMPRESSED_SIZE: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct VersionInfo {
+    packages: Vec<AuditablePackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditablePackage {
+    name: String,
+    version: String,
+    // "crates.io", "git", "local", "registry" 或 "other"
+    source: String,
+    #[serde(default)]
+    dependencies: Vec<usize>,
+}
+
+/// 根据文件头判断是否为 ELF / PE / Mach-O 可执行文件
+pub fn is_binary(path: &Path) -> Result<bool> {
+    let mut magic = [0u8; 4];
+    let mut file = fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    if file.read(&mut magic)? < 4 {
+        return Ok(false);
+    }
+    Ok(magic == *b"\x7fELF"
+        || magic[..2] == *b"MZ"
+        || matches!(u32::from_be_bytes(magic), 0xfeedface | 0xfeedfacf | 0xcefaedfe | 0xcffaedfe | 0xcafebabe))
+}
+
+/// 从可执行文件中读出 `.dep-v0` 段的原始（压缩）数据
+pub fn extract_section(binary: &[u8]) -> Result<Vec<u8>> {
+    let file = object::File::parse(binary).map_err(|e| anyhow!("unrecognized executable format: {}", e))?;
+    let section = file
+        .sections()
+        .find(|s| s.name() == Ok(SECTION_NAME))
+        .ok_or_else(|| anyhow!("no {} section found; was the binary built with `cargo auditable`?", SECTION_NAME))?;
+    let data = section.data().map_err(|e| anyhow!("failed to read {} section: {}", SECTION_NAME, e))?;
+    Ok(data.to_vec())
+}
+
+/// 把 `.dep-v0` 段的数据转换为内存中的 Lockfile，供扫描和 SBOM 使用
+pub fn parse_section(compressed: &[u8]) -> Result<Lockfile> {
+    let mut json = Vec::new();
+    ZlibDecoder::new(compressed)
+        .take(MAX_DECOMPRESSED_SIZE + 1)
+        .read_to_end(&mut json)
+        .context("failed to decompress cargo-auditable data")?;
+    if json.len() as u64 > MAX_DECOMPRESSED_SIZE {
+        bail!("cargo-auditable data exceeds {} bytes", MAX_DECOMPRESSED_SIZE);
+    }
+    let info: VersionInfo = serde_json::from_slice(&json).context("invalid cargo-auditable JSON")?;
+
+    let mut packages = Vec::with_capacity(info.packages.len());
+    for pkg in &info.packages {
+        packages.push(Package {
+            name: cargo_lock::Name::from_str(&pkg.name)?,
+            version: semver::Version::parse(&pkg.version)
+                .with_context(|| format!("invalid version for {}: {}", pkg.name, pkg.version))?,
+            // Only crates.io is identified precisely; other sources carry no URL
+            source: (pkg.source == "crates.io").then(SourceId::default),
+            checksum: None,
+            dependencies: vec![],
+            replace: None,
+        });
+    }
+    for (i, pkg) in info.packages.iter().enumerate() {
+        let mut dependencies = Vec::new();
+        for &index in &pkg.dependencies {
+            let dep = packages
+                .get(index)
+                .ok_or_else(|| anyhow!("{} depends on out-of-range package index {}", pkg.name, index))?;
+            dependencies.push(Dependency::from(dep));
+        }
+        packages[i].dependencies = dependencies;
+    }
+
+    Ok(Lockfile {
+        version: ResolveVersion::V3,
+        packages,
+        root: None,
+        metadata: BTreeMap::new(),
+        patch: Default::default(),
+    })
+}
+
+pub fn load_lockfile(path: &Path) -> Result<Lockfile> {
+    let binary = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    parse_section(&extract_section(&binary)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
+
+    #[test]
+    fn test_parse_raw_section() {
+        let lockfile = parse_section(&fs::read(fixture("auditable.dep-v0")).unwrap()).unwrap();
+        let names: Vec<_> = lockfile.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["hello", "time", "cc", "libc"]);
+
+        let time = &lockfile.packages[1];
+        assert_eq!(time.version.to_string(), "0.1.45");
+        assert!(time.source.as_ref().is_some_and(|s| s.is_default_registry()));
+        assert_eq!(time.dependencies[0].name.as_str(), "libc");
+        // The local root crate has no source, like a workspace member in Cargo.lock
+        assert!(lockfile.packages[0].source.is_none());
+        assert_eq!(lockfile.packages[0].dependencies.len(), 2);
+    }
+
+    #[test]
+    fn test_load_from_elf_binary() {
+        let path = fixture("auditable_hello");
+        assert!(is_binary(&path).unwrap());
+        assert!(!is_binary(&fixture("auditable.dep-v0")).unwrap());
+        assert_eq!(load_lockfile(&path).unwrap().packages.len(), 4);
+    }
+
+    #[test]
+    fn test_rejects_bad_data() {
+        assert!(parse_section(b"not zlib").is_err());
+        assert!(extract_section(b"\x7fELF garbage").is_err());
+    }
+}
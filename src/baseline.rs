@@ -0,0 +1,153 @@
+use crate::scanner::{PackageReport, VulnReport};
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+// `--baseline <report.json>`: the findings of an earlier report are accepted debt. A fresh
+// scan still reports everything, but findings already in the baseline are marked
+// `in_baseline` and no longer count for `--fail-on`, so only newly introduced
+// vulnerabilities fail the build. Findings match on (package, version, advisory id): an
+// upgrade that stays vulnerable counts as new, since the old acceptance was for another
+// version. The baseline can be any JSON report rustpj wrote, gzipped or not. A new
+// finding that triage already waived is not new debt and is not counted either.
+
+/// baseline 报告中的所有漏洞
+#[derive(Debug, Default)]
+pub struct Baseline {
+    path: String,
+    findings: HashSet<(String, String, String)>,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("failed to open baseline {}", path.display()))?;
+        let reader: Box<dyn Read> = if crate::output::is_compressed(&path.to_string_lossy()) {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+        let report: Value = serde_json::from_reader(reader)
+            .with_context(|| format!("failed to parse baseline {}", path.display()))?;
+        Self::from_report(&path.display().to_string(), &report)
+    }
+
+    /// 从已序列化的报告中读取漏洞（顶层 packages 及各项目的 packages）
+    pub fn from_report(path: &str, report: &Value) -> Result<Self> {
+        let Some(packages) = report.get("packages").and_then(Value::as_array) else {
+            bail!("baseline {} is not a rustpj vulnerability report (no \"packages\" list)", path);
+        };
+        let projects = report.get("projects").and_then(Value::as_array).into_iter().flatten();
+        let project_packages = projects.filter_map(|p| p.get("packages")?.as_array()).flatten();
+
+        let mut findings = HashSet::new();
+        for package in packages.iter().chain(project_packages) {
+            let field = |key: &str| package.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+            let (name, version) = (field("package_name"), field("package_version"));
+            let advisories = package.get("advisories").and_then(Value::as_array).into_iter().flatten();
+            for id in advisories.filter_map(|a| a.get("id")?.as_str()) {
+                findings.insert((name.clone(), version.clone(), id.to_string()));
+            }
+        }
+        Ok(Baseline { path: path.to_string(), findings })
+    }
+
+    pub fn len(&self) -> usize {
+        self.findings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    pub fn contains(&self, package: &str, version: &str, id: &str) -> bool {
+        self.findings.contains(&(package.to_string(), version.to_string(), id.to_string()))
+    }
+
+    /// 标记报告中已在 baseline 里的漏洞，并在 summary 中记录新增漏洞数（分诊屏蔽的不算新增）
+    pub fn apply(&self, report: &mut VulnReport) {
+        let new = self.mark(&mut report.packages);
+        report.summary.new_since_baseline = Some(new);
+        report.metadata.baseline = Some(self.path.clone());
+        for project in &mut report.projects {
+            project.summary.new_since_baseline = Some(self.mark(&mut project.packages));
+        }
+    }
+
+    fn mark(&self, packages: &mut [PackageReport]) -> usize {
+        let mut new = 0;
+        for package in packages {
+            for finding in &mut package.advisories {
+                finding.in_baseline = self.contains(&package.package_name, &package.package_version, &finding.id);
+                if finding.is_new_since_baseline() {
+                    new += 1;
+                }
+            }
+        }
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::AdvisoryFinding;
+    use crate::vex::{Analysis, AnalysisState};
+    use serde_json::json;
+
+    #[test]
+    fn test_baseline_from_report() {
+        let baseline = Baseline::from_report(
+            "old.json",
+            &json!({
+                "packages": [{"package_name": "time", "package_version": "0.1.45", "advisories": [{"id": "RUSTSEC-2020-0071"}]}],
+                "projects": [{"packages": [{"package_name": "zip", "package_version": "0.5.0", "advisories": [{"id": "RUSTSEC-2099-0001"}]}]}],
+            }),
+        )
+        .unwrap();
+        assert_eq!(baseline.len(), 2);
+        assert!(baseline.contains("zip", "0.5.0", "RUSTSEC-2099-0001"));
+        // Same advisory on an upgraded (still affected) version is new
+        assert!(!baseline.contains("time", "0.1.46", "RUSTSEC-2020-0071"));
+
+        assert!(Baseline::from_report("batch.json", &json!({"entries": []})).is_err());
+    }
+
+    #[test]
+    fn test_triaged_findings_are_not_new() {
+        let baseline = Baseline::from_report(
+            "old.json",
+            &json!({"packages": [{"package_name": "time", "package_version": "0.1.45", "advisories": [{"id": "RUSTSEC-2020-0071"}]}]}),
+        )
+        .unwrap();
+        let finding = |id: &str, analysis: Option<AnalysisState>| AdvisoryFinding {
+            id: id.to_string(),
+            analysis: analysis.map(|state| Analysis { state, ..Default::default() }),
+            ..Default::default()
+        };
+        let mut report = VulnReport {
+            packages: vec![PackageReport {
+                package_name: "time".to_string(),
+                package_version: "0.1.45".to_string(),
+                advisories: vec![
+                    finding("RUSTSEC-2020-0071", None),
+                    finding("RUSTSEC-2099-0001", Some(AnalysisState::FalsePositive)),
+                    finding("RUSTSEC-2099-0002", Some(AnalysisState::InTriage)),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        baseline.apply(&mut report);
+
+        let advisories = &report.packages[0].advisories;
+        assert!(advisories[0].in_baseline);
+        // Triaged as a false positive: not in the baseline, but not new debt either
+        assert!(!advisories[1].in_baseline && !advisories[1].is_new_since_baseline());
+        assert!(advisories[2].is_new_since_baseline());
+        assert_eq!(report.summary.new_since_baseline, Some(1));
+    }
+}
@@ -0,0 +1,276 @@
+use crate::get_lockfile::{TempGuard, discover_lockfiles_in, extract_archive_to, merge_lockfiles};
+use crate::package_filter::{self, PackageExclusion};
+use crate::project::{ProjectIdentity, ProjectOverrides};
+use crate::registry::RegistryMap;
+use crate::scanner::{Scanner, SeverityCounts, VulnReport};
+use crate::staleness::StalenessCheck;
+use anyhow::{Context, Result, bail};
+use cargo_lock::Lockfile;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// Batch scans (`rustpj batch <dir>`): every archive is extracted into its own temporary
+// directory and scanned by a bounded pool of worker threads. The advisory DB is read-only
+// once loaded, so all workers share a single `Scanner` behind an `Arc`.
+
+/// 批量扫描中单个压缩包的结果
+#[derive(Debug, Serialize)]
+pub struct BatchEntry {
+    pub archive: String,
+    // 解压、查找 lockfile 或扫描失败时的错误信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<VulnReport>,
+    // 扫描过的 lockfile（合并、排除之后），供之后评估 `[[policy]]` 规则
+    #[serde(skip)]
+    pub lockfile: Option<Lockfile>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct BatchReport {
+    pub archives: usize,
+    pub failed: usize,
+    pub total_vulnerabilities: usize,
+    pub by_severity: SeverityCounts,
+    // 与输入顺序一致
+    pub entries: Vec<BatchEntry>,
+}
+
+/// 展开输入：目录取其中的 *.zip 与 *.crate（按文件名排序），文件原样保留
+pub fn collect_archives(inputs: &[String]) -> Result<Vec<PathBuf>> {
+    let mut archives = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        if path.is_dir() {
+            let mut found: Vec<PathBuf> = std::fs::read_dir(path)
+                .with_context(|| format!("failed to read directory {}", path.display()))?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip") || ext.eq_ignore_ascii_case("crate")))
+                .collect();
+            found.sort();
+            archives.extend(found);
+        } else if path.is_file() {
+            archives.push(path.to_path_buf());
+        } else {
+            bail!("no such file or directory: {}", input);
+        }
+    }
+    if archives.is_empty() {
+        bail!("no .zip or .crate archives found in the batch input");
+    }
+    Ok(archives)
+}
+
+/// 每个压缩包自己的报告路径：输出目录下以压缩包文件名（去掉扩展名）命名的子目录中的
+/// vuln_report.json；两个压缩包会写到同一目录时报错
+pub fn entry_report_paths(output_dir: &Path, archives: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut seen = std::collections::HashMap::new();
+    let mut paths = Vec::new();
+    for archive in archives {
+        let name = archive.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        if let Some(other) = seen.insert(name.clone(), archive) {
+            bail!("{} and {} would share the output directory {}; rename one of them", other.display(), archive.display(), name);
+        }
+        paths.push(output_dir.join(&name).join("vuln_report.json"));
+    }
+    Ok(paths)
+}
+
+/// 每个压缩包扫描前后的额外步骤
+#[derive(Default)]
+pub struct BatchOptions {
+    // 扫描前从 lockfile 中去掉的包
+    pub exclusions: Vec<PackageExclusion>,
+    // 配置文件 `[registries]`，用于提示未配置的私有 registry
+    pub registries: RegistryMap,
+    // --check-staleness
+    pub staleness: Option<StalenessCheck>,
+}
+
+/// 用最多 jobs 个线程并发扫描所有压缩包；单个压缩包失败不影响其余
+pub fn scan_archives(scanner: Arc<Scanner>, archives: &[PathBuf], jobs: usize, options: Arc<BatchOptions>) -> BatchReport {
+    let next = Arc::new(AtomicUsize::new(0));
+    let archives: Arc<Vec<PathBuf>> = Arc::new(archives.to_vec());
+    let results: Arc<Mutex<Vec<Option<BatchEntry>>>> =
+        Arc::new(Mutex::new(archives.iter().map(|_| None).collect()));
+
+    let workers: Vec<_> = (0..jobs.clamp(1, archives.len().max(1)))
+        .map(|_| {
+            let (scanner, next, archives, results) =
+                (Arc::clone(&scanner), Arc::clone(&next), Arc::clone(&archives), Arc::clone(&results));
+            let options = Arc::clone(&options);
+            thread::spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(archive) = archives.get(index) else { break };
+                    let entry = match scan_one(&scanner, &options, archive) {
+                        Ok((report, lockfile)) => BatchEntry {
+                            archive: archive.display().to_string(),
+                            error: None,
+                            report: Some(report),
+                            lockfile: Some(lockfile),
+                        },
+                        Err(e) => BatchEntry {
+                            archive: archive.display().to_string(),
+                            error: Some(format!("{:#}", e)),
+                            report: None,
+                            lockfile: None,
+                        },
+                    };
+                    results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(entry);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        // A panicking worker leaves its archive without an entry; it is reported below
+        let _ = worker.join();
+    }
+
+    let results = std::mem::take(&mut *results.lock().unwrap_or_else(|e| e.into_inner()));
+    let mut batch = BatchReport { archives: archives.len(), ..Default::default() };
+    for (entry, archive) in results.into_iter().zip(archives.iter()) {
+        let entry = entry.unwrap_or_else(|| BatchEntry {
+            archive: archive.display().to_string(),
+            error: Some("worker thread panicked".to_string()),
+            report: None,
+            lockfile: None,
+        });
+        match &entry.report {
+            Some(report) => {
+                batch.total_vulnerabilities += report.summary.total_vulnerabilities;
+                batch.by_severity.merge(&report.summary.by_severity);
+            }
+            None => batch.failed += 1,
+        }
+        batch.entries.push(entry);
+    }
+    batch
+}
+
+// Each archive gets a private temp dir, removed as soon as its scan finishes (unless --keep-temp).
+// The lockfile is returned with the report: policy rules run only after triage and the baseline
+fn scan_one(scanner: &Scanner, options: &BatchOptions, archive: &Path) -> Result<(VulnReport, Lockfile)> {
+    let dir = tempfile::Builder::new()
+        .prefix("rustpj-batch-")
+        .tempdir()
+        .context("failed to create temporary directory")?;
+    let guard = TempGuard::new(dir.keep());
+    extract_archive_to(&archive.to_string_lossy(), guard.path())?;
+    let projects = discover_lockfiles_in(guard.path())?;
+    let lockfile = merge_lockfiles(projects.iter().map(|p| &p.lockfile));
+    let (lockfile, excluded) = package_filter::exclude(&lockfile, &options.exclusions);
+    options.registries.warn_unmapped(&lockfile);
+    let mut report = scanner.scan_lockfile(&lockfile)?;
+    report.describe_input(&projects, &ProjectIdentity::of_projects(&ProjectOverrides::default(), &projects, archive));
+    report.metadata.excluded_packages = excluded;
+    if let Some(check) = &options.staleness {
+        let checked = check.check(&lockfile);
+        report.stale_packages = checked.stale;
+        report.yanked_packages = checked.yanked;
+    }
+    report.sort_by_severity();
+    Ok((report, lockfile))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, AdvisoryText};
+    use std::fs;
+    use std::io::Write;
+    use zip::ZipWriter;
+    use zip::write::FileOptions;
+
+    fn write_archive(path: &Path, crate_version: &str) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        zip.start_file("proj-main/Cargo.lock", FileOptions::default()).unwrap();
+        write!(
+            zip,
+            "version = 3\n\n[[package]]\nname = \"first-crate\"\nversion = \"{}\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+            crate_version
+        )
+        .unwrap();
+        zip.finish().unwrap();
+    }
+
+    // Two independent workspaces in one archive, each with its own lockfile
+    fn write_two_workspace_archive(path: &Path) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        for (dir, version) in [("app", "1.0.0"), ("tools", "1.5.0")] {
+            zip.start_file(format!("proj-main/{}/Cargo.lock", dir), FileOptions::default()).unwrap();
+            write!(
+                zip,
+                "version = 3\n\n[[package]]\nname = \"first-crate\"\nversion = \"{}\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+                version
+            )
+            .unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn advisory_db(root: &Path) -> PathBuf {
+        test_support::advisory_db(root, &[AdvisoryText::new("RUSTSEC-2020-0001", "first-crate")])
+    }
+
+    #[test]
+    fn test_entry_report_paths() {
+        let archives = [PathBuf::from("in/a.zip"), PathBuf::from("in/b.crate")];
+        let paths = entry_report_paths(Path::new("out"), &archives).unwrap();
+        assert_eq!(paths, [PathBuf::from("out/a/vuln_report.json"), PathBuf::from("out/b/vuln_report.json")]);
+        let clash = [PathBuf::from("in/a.zip"), PathBuf::from("other/a.crate")];
+        assert!(entry_report_paths(Path::new("out"), &clash).is_err());
+    }
+
+    #[test]
+    fn test_batch_scan_shares_scanner_and_isolates_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let scanner = Arc::new(Scanner::new(advisory_db(dir.path())).unwrap());
+        let inputs = dir.path().join("archives");
+        fs::create_dir(&inputs).unwrap();
+        write_archive(&inputs.join("a.zip"), "1.0.0");
+        write_archive(&inputs.join("b.zip"), "2.0.0");
+        write_archive(&inputs.join("c.zip"), "1.5.0");
+        fs::write(inputs.join("broken.zip"), "not a zip").unwrap();
+        fs::write(inputs.join("notes.txt"), "ignored").unwrap();
+
+        let archives = collect_archives(&[inputs.to_string_lossy().to_string()]).unwrap();
+        assert_eq!(archives.len(), 4);
+        let batch = scan_archives(Arc::clone(&scanner), &archives, 3, Arc::default());
+
+        assert_eq!(batch.archives, 4);
+        assert_eq!(batch.failed, 1);
+        assert_eq!(batch.total_vulnerabilities, 2);
+        let names: Vec<_> = batch.entries.iter().map(|e| e.archive.rsplit('/').next().unwrap()).collect();
+        assert_eq!(names, vec!["a.zip", "b.zip", "broken.zip", "c.zip"]);
+        assert_eq!(batch.entries[0].report.as_ref().unwrap().summary.total_vulnerabilities, 1);
+        // Without a Cargo.toml the archive names the project
+        assert_eq!(batch.entries[0].report.as_ref().unwrap().metadata.project.as_ref().unwrap().name, "a");
+        assert!(batch.entries[0].report.as_ref().unwrap().packages[0].lockfile_line.is_some());
+        assert_eq!(batch.entries[1].report.as_ref().unwrap().summary.total_vulnerabilities, 0);
+        assert!(batch.entries[2].error.is_some());
+        // Every worker clone of the Arc is released once the pool finishes
+        assert_eq!(Arc::strong_count(&scanner), 1);
+    }
+
+    #[test]
+    fn test_batch_scans_every_lockfile_in_an_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let scanner = Arc::new(Scanner::new(advisory_db(dir.path())).unwrap());
+        let archive = dir.path().join("workspaces.zip");
+        write_two_workspace_archive(&archive);
+
+        let batch = scan_archives(scanner, &[archive], 1, Arc::default());
+        let report = batch.entries[0].report.as_ref().unwrap();
+        let mut versions: Vec<_> = report.packages.iter().map(|p| p.package_version.as_str()).collect();
+        versions.sort();
+        assert_eq!(versions, ["1.0.0", "1.5.0"]);
+        assert_eq!(batch.total_vulnerabilities, 2);
+        // No single line for a package of the merged lockfile
+        assert!(report.packages.iter().all(|p| p.lockfile_line.is_none()));
+    }
+}
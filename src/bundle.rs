@@ -0,0 +1,141 @@
+use crate::output::AtomicFile;
+use crate::signing::sha256_file;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+// Single-file scan bundle (`--bundle out.zip`) with stable member paths:
+//
+//     bundle.json                 index of members with their sha256
+//     sbom/sbom.json
+//     report/<report files>       JSON report, VEX, rendered templates, snapshots
+//     manifest/manifest.json(.sig)
+//     logs/run.log
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    Sbom,
+    Report,
+    Manifest,
+    // --bundle 本身，只用于签名之后的发布
+    Bundle,
+}
+
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub kind: ArtifactKind,
+    pub path: PathBuf,
+}
+
+impl Artifact {
+    pub fn new(kind: ArtifactKind, path: impl Into<PathBuf>) -> Self {
+        Artifact { kind, path: path.into() }
+    }
+
+    fn member_name(&self) -> String {
+        let file_name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match self.kind {
+            ArtifactKind::Sbom => format!("sbom/{}", file_name),
+            ArtifactKind::Report => format!("report/{}", file_name),
+            ArtifactKind::Manifest => format!("manifest/{}", file_name),
+            ArtifactKind::Bundle => file_name,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BundleIndex {
+    tool: String,
+    tool_version: String,
+    created: String,
+    members: Vec<BundleMember>,
+}
+
+#[derive(Debug, Serialize)]
+struct BundleMember {
+    path: String,
+    sha256: String,
+}
+
+/// 把所有输出文件和运行日志打包成一个 zip，返回 zip 文件的 sha256
+pub fn write_bundle(bundle_path: &Path, artifacts: &[Artifact], run_log: &str) -> Result<String> {
+    let file = AtomicFile::create(bundle_path)
+        .with_context(|| format!("failed to create bundle: {}", bundle_path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut members = Vec::new();
+
+    let mut add = |zip: &mut ZipWriter<AtomicFile>, name: &str, bytes: &[u8]| -> Result<()> {
+        zip.start_file(name, options)
+            .with_context(|| format!("failed to add {} to bundle", name))?;
+        zip.write_all(bytes)?;
+        members.push(BundleMember { path: name.to_string(), sha256: crate::signing::sha256_bytes(bytes) });
+        Ok(())
+    };
+
+    for artifact in artifacts {
+        let bytes = fs::read(&artifact.path)
+            .with_context(|| format!("failed to read {}", artifact.path.display()))?;
+        add(&mut zip, &artifact.member_name(), &bytes)?;
+    }
+    add(&mut zip, "logs/run.log", run_log.as_bytes())?;
+
+    let index = BundleIndex {
+        tool: env!("CARGO_PKG_NAME").to_string(),
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        created: chrono::Utc::now().to_rfc3339(),
+        members,
+    };
+    zip.start_file("bundle.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&index)?)?;
+    zip.finish()
+        .context("failed to finalize bundle")?
+        .commit()
+        .with_context(|| format!("failed to write bundle: {}", bundle_path.display()))?;
+
+    sha256_file(bundle_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let sbom = dir.path().join("sbom.json");
+        let report = dir.path().join("vuln_report.json");
+        fs::write(&sbom, r#"{"bomFormat":"CycloneDX"}"#).unwrap();
+        fs::write(&report, r#"{"total_packages":3}"#).unwrap();
+
+        let bundle = dir.path().join("out/bundle.zip");
+        let artifacts = [Artifact::new(ArtifactKind::Sbom, &sbom), Artifact::new(ArtifactKind::Report, &report)];
+        let digest = write_bundle(&bundle, &artifacts, "EXTRACTED: Cargo.lock").unwrap();
+        assert_eq!(digest, sha256_file(&bundle).unwrap());
+
+        let mut archive = ZipArchive::new(File::open(&bundle).unwrap()).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(names, vec!["bundle.json", "logs/run.log", "report/vuln_report.json", "sbom/sbom.json"]);
+
+        for name in ["bundle.json", "report/vuln_report.json", "sbom/sbom.json"] {
+            let mut text = String::new();
+            archive.by_name(name).unwrap().read_to_string(&mut text).unwrap();
+            serde_json::from_str::<serde_json::Value>(&text).unwrap();
+        }
+        let mut log = String::new();
+        archive.by_name("logs/run.log").unwrap().read_to_string(&mut log).unwrap();
+        assert_eq!(log, "EXTRACTED: Cargo.lock");
+    }
+}
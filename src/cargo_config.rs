@@ -0,0 +1,225 @@
+use anyhow::{Context, Result};
+use cargo_lock::package::{SourceId, SourceKind};
+use cargo_lock::Lockfile;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Registry settings from the project's `.cargo/config.toml`:
+//
+//     [registries.corp]                     # alias -> index URL
+//     index = "sparse+https://cargo.corp.example.com/index/"
+//
+//     [source.crates-io]                    # crates.io served through a mirror
+//     replace-with = "corp-mirror"
+//
+//     [source.corp-mirror]
+//     registry = "sparse+https://cargo.corp.example.com/crates-io/"
+//
+//     [source.vendored-sources]             # `cargo vendor` output
+//     directory = "vendor"
+//
+// Packages resolved from a crates.io mirror are crates.io packages: `apply` rewrites their
+// lockfile source to crates.io, so that PURLs, the typosquat check and everything else
+// that asks "is this from crates.io?" treat them as such. Packages from other registries
+// keep their index URL. Without a config file the lockfile is left untouched.
+
+/// `.cargo/config.toml` 中与 registry 相关的配置
+#[derive(Debug, Default)]
+pub struct RegistryConfig {
+    // registry 别名 -> index URL（去掉 `sparse+` / `registry+` 前缀和末尾的 `/`）
+    pub registries: BTreeMap<String, String>,
+    // 作为 crates.io 镜像（`[source.crates-io] replace-with`）的 index URL
+    pub crates_io_mirrors: BTreeSet<String>,
+    // directory source 的路径（`cargo vendor` 的输出目录），相对于 `.cargo` 所在目录
+    pub directory_sources: Vec<PathBuf>,
+}
+
+impl RegistryConfig {
+    /// 从 project_root 向上直到 archive_root 查找 `.cargo/config.toml`（或旧的 `.cargo/config`），
+    /// 与 cargo 一样离项目最近的配置优先；都没有时返回 None
+    pub fn discover(project_root: &Path, archive_root: &Path) -> Result<Option<(PathBuf, Self)>> {
+        for dir in project_root.ancestors() {
+            for name in ["config.toml", "config"] {
+                let path = dir.join(".cargo").join(name);
+                if path.is_file() {
+                    let text = fs::read_to_string(&path)
+                        .with_context(|| format!("failed to read {}", path.display()))?;
+                    let config = Self::parse(&text).with_context(|| format!("invalid cargo config: {}", path.display()))?;
+                    return Ok(Some((path, config)));
+                }
+            }
+            if dir == archive_root {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let table: toml::Table = toml::from_str(text)?;
+        let section = |name: &str| table.get(name).and_then(|v| v.as_table()).cloned().unwrap_or_default();
+        let (registries_table, sources) = (section("registries"), section("source"));
+
+        let mut config = RegistryConfig::default();
+        for (alias, entry) in &registries_table {
+            if let Some(index) = entry.get("index").and_then(|v| v.as_str()) {
+                config.registries.insert(alias.clone(), normalize(index));
+            }
+        }
+
+        for source in sources.values() {
+            if let Some(dir) = source.get("directory").and_then(|v| v.as_str()) {
+                config.directory_sources.push(PathBuf::from(dir));
+            }
+        }
+
+        // Follow the replace-with chain from crates-io; every registry on it serves crates.io
+        let mut current = "crates-io".to_string();
+        let mut visited = BTreeSet::new();
+        while visited.insert(current.clone()) {
+            let Some(next) = sources.get(&current).and_then(|s| s.get("replace-with")).and_then(|v| v.as_str()) else {
+                break;
+            };
+            let url = sources
+                .get(next)
+                .and_then(|s| s.get("registry"))
+                .and_then(|v| v.as_str())
+                .map(normalize)
+                .or_else(|| config.registries.get(next).cloned());
+            if let Some(url) = url {
+                config.crates_io_mirrors.insert(url);
+            }
+            current = next.to_string();
+        }
+        Ok(config)
+    }
+
+    /// source 是否为 crates.io（官方 index 或配置的镜像）
+    pub fn is_crates_io(&self, source: &SourceId) -> bool {
+        source.is_default_registry()
+            || matches!(source.kind(), SourceKind::Registry | SourceKind::SparseRegistry)
+                && self.crates_io_mirrors.contains(&normalize(source.url().as_str()))
+    }
+
+    /// source 对应的 registry 别名（只有测试用来检查别名解析）
+    #[cfg(test)]
+    fn registry_name(&self, source: &SourceId) -> Option<&str> {
+        let url = normalize(source.url().as_str());
+        self.registries.iter().find(|(_, index)| **index == url).map(|(alias, _)| alias.as_str())
+    }
+
+    /// 把来自 crates.io 镜像的包（及指向它们的依赖）改写为 crates.io 来源，返回改写的包数
+    pub fn apply(&self, lockfile: &mut Lockfile) -> usize {
+        if self.crates_io_mirrors.is_empty() {
+            return 0;
+        }
+        let mut rewritten = 0;
+        for package in &mut lockfile.packages {
+            if let Some(source) = &mut package.source
+                && !source.is_default_registry()
+                && self.is_crates_io(source)
+            {
+                *source = SourceId::default();
+                rewritten += 1;
+            }
+            for dep in &mut package.dependencies {
+                if let Some(source) = &mut dep.source
+                    && !source.is_default_registry()
+                    && self.is_crates_io(source)
+                {
+                    *source = SourceId::default();
+                }
+            }
+        }
+        rewritten
+    }
+}
+
+// Compare index URLs without the protocol prefix or a trailing slash
+pub(crate) fn normalize(url: &str) -> String {
+    let url = url.strip_prefix("sparse+").or_else(|| url.strip_prefix("registry+")).unwrap_or(url);
+    url.trim_end_matches('/').to_string()
+}
+
+// A registry other than crates.io itself; mirrors count until `apply` has rewritten them
+pub(crate) fn is_private_registry(source: &SourceId) -> bool {
+    matches!(source.kind(), SourceKind::Registry | SourceKind::SparseRegistry) && !source.is_default_registry()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::RegistryMap;
+
+    fn package_url(package: &cargo_lock::Package) -> String {
+        crate::purl::package_url(package, &RegistryMap::default())
+    }
+
+    const CONFIG: &str = r#"
+[registries.corp]
+index = "sparse+https://cargo.corp.example.com/index/"
+
+[registries.corp-mirror]
+index = "https://git.corp.example.com/crates-io-index.git"
+
+[source.crates-io]
+replace-with = "corp-mirror"
+"#;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "internal-auth",
+ "serde",
+]
+
+[[package]]
+name = "internal-auth"
+version = "1.2.0"
+source = "sparse+https://cargo.corp.example.com/index/"
+
+[[package]]
+name = "serde"
+version = "1.0.0"
+source = "registry+https://git.corp.example.com/crates-io-index.git"
+"#;
+
+    #[test]
+    fn test_mirror_packages_attributed_to_crates_io() {
+        let config = RegistryConfig::parse(CONFIG).unwrap();
+        let mut lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let internal = lockfile.packages[1].source.clone().unwrap();
+        assert_eq!(config.registry_name(&internal), Some("corp"));
+        assert!(!config.is_crates_io(&internal));
+
+        assert_eq!(config.apply(&mut lockfile), 1);
+        assert_eq!(package_url(&lockfile.packages[2]), "pkg:cargo/serde@1.0.0");
+        assert_eq!(
+            package_url(&lockfile.packages[1]),
+            "pkg:cargo/internal-auth@1.2.0?repository_url=https%3A%2F%2Fcargo.corp.example.com%2Findex%2F"
+        );
+    }
+
+    #[test]
+    fn test_directory_sources() {
+        let config = RegistryConfig::parse(
+            "[source.crates-io]\nreplace-with = \"vendored-sources\"\n\n[source.vendored-sources]\ndirectory = \"third_party/vendor\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.directory_sources, [PathBuf::from("third_party/vendor")]);
+        assert!(config.crates_io_mirrors.is_empty());
+    }
+
+    #[test]
+    fn test_without_replacement_lockfile_is_untouched() {
+        let config = RegistryConfig::parse("[registries.corp]\nindex = \"https://git.corp.example.com/crates-io-index.git\"\n").unwrap();
+        let mut lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        assert_eq!(config.apply(&mut lockfile), 0);
+        assert!(package_url(&lockfile.packages[2]).contains("repository_url="));
+    }
+}
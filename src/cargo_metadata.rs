@@ -0,0 +1,480 @@
+use cargo_lock::Lockfile;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+// Per-crate information pulled from `cargo metadata`
+#[derive(Debug, Default, Clone)]
+pub struct CrateInfo {
+    pub license: Option<String>,
+    pub rust_version: Option<String>,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    pub homepage: Option<String>,
+    pub repository: Option<String>,
+    pub documentation: Option<String>,
+    // 有 proc-macro 目标
+    pub proc_macro: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct CargoMetadata {
+    // 键为 (name, version)
+    pub crates: HashMap<(String, String), CrateInfo>,
+    pub workspace_members: Vec<(String, String)>,
+    // `cargo metadata` 的 resolve 依赖图；只读取清单（--no-exec）时为 None
+    pub resolve: Option<ResolvedGraph>,
+}
+
+/// 包的标识 (name, version, source)；path 依赖的 source 为 None，与 lockfile 中相同
+pub type PackageKey = (String, String, Option<String>);
+
+/// 依赖边的类型（Cargo.toml 中的 `[dependencies]` / `[build-dependencies]` / `[dev-dependencies]`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    Normal,
+    Build,
+    Dev,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedDependency {
+    pub package: PackageKey,
+    // 同一依赖可能同时以多种类型声明
+    pub kinds: Vec<EdgeKind>,
+}
+
+// Cargo.lock records which exact package every edge points at, but not why: a
+// dev-dependency edge looks like any other. `cargo metadata` resolves the same graph and
+// keeps the kinds, which is what tells test-only crates apart from shipped ones.
+/// `cargo metadata` 解析出的依赖图，带依赖类型
+#[derive(Debug, Default, Clone)]
+pub struct ResolvedGraph {
+    pub nodes: HashMap<PackageKey, Vec<ResolvedDependency>>,
+}
+
+impl ResolvedGraph {
+    fn from_json(resolve: &serde_json::Value, keys: &HashMap<String, PackageKey>) -> Option<Self> {
+        let mut nodes = HashMap::new();
+        for node in resolve["nodes"].as_array()? {
+            let Some(key) = node["id"].as_str().and_then(|id| keys.get(id)) else {
+                continue;
+            };
+            let deps = node["deps"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|dep| {
+                    let package = keys.get(dep["pkg"].as_str()?)?.clone();
+                    let mut kinds = Vec::new();
+                    for kind in dep["dep_kinds"].as_array().into_iter().flatten() {
+                        let kind = match kind["kind"].as_str() {
+                            Some("dev") => EdgeKind::Dev,
+                            Some("build") => EdgeKind::Build,
+                            _ => EdgeKind::Normal,
+                        };
+                        if !kinds.contains(&kind) {
+                            kinds.push(kind);
+                        }
+                    }
+                    Some(ResolvedDependency { package, kinds })
+                })
+                .collect();
+            nodes.insert(key.clone(), deps);
+        }
+        Some(ResolvedGraph { nodes })
+    }
+
+    pub fn dependencies(&self, package: &PackageKey) -> Option<&[ResolvedDependency]> {
+        self.nodes.get(package).map(Vec::as_slice)
+    }
+
+    /// 从 roots 出发、不经过 dev-dependency 边能到达的包（含 roots 自身）
+    pub fn without_dev(&self, roots: &[PackageKey]) -> HashSet<PackageKey> {
+        let mut reached: HashSet<PackageKey> = roots.iter().cloned().collect();
+        let mut queue: Vec<&PackageKey> = roots.iter().collect();
+        while let Some(key) = queue.pop() {
+            for dep in self.nodes.get(key).into_iter().flatten() {
+                // An edge declared both as dev- and as normal dependency is a normal one
+                if dep.kinds.iter().any(|k| *k != EdgeKind::Dev) && reached.insert(dep.package.clone()) {
+                    queue.push(&dep.package);
+                }
+            }
+        }
+        reached
+    }
+}
+
+impl CargoMetadata {
+    pub fn get(&self, name: &str, version: &str) -> Option<&CrateInfo> {
+        self.crates.get(&(name.to_string(), version.to_string()))
+    }
+
+    /// 合并另一个工作区的 metadata（扫描含多个 lockfile 的压缩包时）
+    pub fn merge(&mut self, other: CargoMetadata) {
+        for (key, info) in other.crates {
+            self.crates.entry(key).or_insert(info);
+        }
+        for member in other.workspace_members {
+            if !self.workspace_members.contains(&member) {
+                self.workspace_members.push(member);
+            }
+        }
+        match (&mut self.resolve, other.resolve) {
+            (Some(resolve), Some(other)) => {
+                for (key, deps) in other.nodes {
+                    resolve.nodes.entry(key).or_insert(deps);
+                }
+            }
+            // Half a graph with kinds is worse than none: the SBOM falls back to the lockfile
+            (resolve, _) => *resolve = None,
+        }
+    }
+
+    pub fn from_json(metadata: &serde_json::Value) -> Self {
+        let mut result = CargoMetadata::default();
+        let mut ids = HashMap::new();
+        let mut keys = HashMap::new();
+
+        // 遍历所有包并提取许可证等信息
+        if let Some(packages) = metadata["packages"].as_array() {
+            for pkg in packages {
+                let (Some(name), Some(version)) = (pkg["name"].as_str(), pkg["version"].as_str()) else {
+                    continue;
+                };
+                let key = (name.to_string(), version.to_string());
+                if let Some(id) = pkg["id"].as_str() {
+                    ids.insert(id.to_string(), key.clone());
+                    keys.insert(id.to_string(), (key.0.clone(), key.1.clone(), text(&pkg["source"])));
+                }
+                result.crates.insert(
+                    key,
+                    CrateInfo {
+                        license: text(&pkg["license"]),
+                        rust_version: text(&pkg["rust_version"]),
+                        description: text(&pkg["description"]).map(|d| d.trim().to_string()),
+                        authors: pkg["authors"]
+                            .as_array()
+                            .map(|a| a.iter().filter_map(|s| s.as_str().map(str::to_string)).collect())
+                            .unwrap_or_default(),
+                        homepage: text(&pkg["homepage"]),
+                        repository: text(&pkg["repository"]),
+                        documentation: text(&pkg["documentation"]),
+                        proc_macro: pkg["targets"].as_array().is_some_and(|targets| {
+                            targets.iter().any(|t| t["kind"].as_array().is_some_and(|k| k.iter().any(|k| k == "proc-macro")))
+                        }),
+                    },
+                );
+            }
+        }
+
+        if let Some(members) = metadata["workspace_members"].as_array() {
+            result.workspace_members = members
+                .iter()
+                .filter_map(|id| id.as_str().and_then(|id| ids.get(id)).cloned())
+                .collect();
+        }
+        if !metadata["resolve"].is_null() {
+            result.resolve = ResolvedGraph::from_json(&metadata["resolve"], &keys);
+        }
+        result
+    }
+
+    /// 只读取 project_root 下的 Cargo.toml（项目自身的清单和 vendored 依赖的清单），不执行 cargo；
+    /// 没有 vendor 的依赖因此没有许可证等信息
+    pub fn from_manifests(project_root: &Path) -> Self {
+        let mut result = CargoMetadata::default();
+        // `license.workspace = true` and friends come from the root's [workspace.package]
+        let inherited = read_table(&project_root.join("Cargo.toml"))
+            .and_then(|root| root.get("workspace")?.get("package")?.as_table().cloned())
+            .unwrap_or_default();
+        let walker = WalkDir::new(project_root)
+            .into_iter()
+            .filter_entry(|e| !(e.file_type().is_dir() && matches!(e.file_name().to_str(), Some("target" | ".git"))));
+        for entry in walker.filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() || entry.file_name() != "Cargo.toml" {
+                continue;
+            }
+            let Some(manifest) = read_table(entry.path()) else {
+                continue;
+            };
+            let Some(package) = manifest.get("package").and_then(|p| p.as_table()) else {
+                continue;
+            };
+            let field = |key: &str| {
+                let value = package.get(key)?;
+                let value = if value.get("workspace").and_then(|w| w.as_bool()) == Some(true) { inherited.get(key)? } else { value };
+                Some(value.clone())
+            };
+            let string = |key: &str| field(key).and_then(|v| v.as_str().map(str::to_string)).filter(|s| !s.is_empty());
+            let (Some(name), Some(version)) = (string("name"), string("version")) else {
+                continue;
+            };
+            let key = (name, version);
+            let vendored = entry.path().strip_prefix(project_root).is_ok_and(|rel| rel.components().any(|c| c.as_os_str() == "vendor"));
+            if !vendored && !result.workspace_members.contains(&key) {
+                result.workspace_members.push(key.clone());
+            }
+            let lib = manifest.get("lib");
+            result.crates.entry(key).or_insert(CrateInfo {
+                license: string("license"),
+                rust_version: string("rust-version"),
+                description: string("description").map(|d| d.trim().to_string()),
+                authors: field("authors")
+                    .and_then(|a| a.as_array().map(|a| a.iter().filter_map(|s| s.as_str().map(str::to_string)).collect()))
+                    .unwrap_or_default(),
+                homepage: string("homepage"),
+                repository: string("repository"),
+                documentation: string("documentation"),
+                proc_macro: ["proc-macro", "proc_macro"]
+                    .iter()
+                    .any(|key| lib.and_then(|l| l.get(key)).and_then(|v| v.as_bool()) == Some(true)),
+            });
+        }
+        result
+    }
+}
+
+/// 列出 SBOM 中缺少许可证信息的组件（name@version）；没有元数据时即为全部组件
+pub fn missing_licenses(lockfile: &Lockfile, metadata: Option<&CargoMetadata>) -> Vec<String> {
+    lockfile
+        .packages
+        .iter()
+        .filter(|pkg| {
+            metadata
+                .and_then(|m| m.get(pkg.name.as_str(), &pkg.version.to_string()))
+                .and_then(|info| info.license.as_ref())
+                .is_none()
+        })
+        .map(|pkg| format!("{}@{}", pkg.name, pkg.version))
+        .collect()
+}
+
+/// 直方图中没有许可证信息的组件所在的一栏
+pub const UNKNOWN_LICENSE: &str = "unknown";
+
+/// 组件的许可证分布：规范化后的表达式 -> 组件数；没有许可证信息的组件单独列出
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LicenseHistogram {
+    pub counts: BTreeMap<String, usize>,
+    // 没有许可证信息的组件（name@version）
+    pub unknown: Vec<String>,
+}
+
+impl LicenseHistogram {
+    /// 统计 lockfile 中每个组件（name@version 去重）的许可证
+    pub fn build(lockfile: &Lockfile, metadata: &CargoMetadata) -> Self {
+        let mut histogram = LicenseHistogram::default();
+        let mut seen = HashSet::new();
+        for pkg in &lockfile.packages {
+            let version = pkg.version.to_string();
+            if !seen.insert((pkg.name.as_str(), version.clone())) {
+                continue;
+            }
+            let key = match metadata.get(pkg.name.as_str(), &version).and_then(|info| info.license.as_deref()) {
+                Some(license) => normalize_license(license),
+                None => {
+                    histogram.unknown.push(format!("{}@{}", pkg.name, version));
+                    UNKNOWN_LICENSE.to_string()
+                }
+            };
+            *histogram.counts.entry(key).or_default() += 1;
+        }
+        histogram
+    }
+
+    /// 按组件数从多到少（相同时按名称）排列
+    pub fn ranked(&self) -> Vec<(&str, usize)> {
+        let mut ranked: Vec<(&str, usize)> = self.counts.iter().map(|(k, &v)| (k.as_str(), v)).collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        ranked
+    }
+}
+
+/// 规范化许可证表达式，使写法不同的同一组许可证落在一栏：旧式的 `MIT/Apache-2.0` 改为
+/// `OR`，运算符大写，多余空白去掉；只由同一种运算符连接、不带括号的表达式按标识符排序
+/// （`Apache-2.0 OR MIT` 与 `MIT OR Apache-2.0` 相同）
+pub fn normalize_license(expression: &str) -> String {
+    let tokens = license_tokens(expression);
+    let operators: HashSet<&str> = tokens.iter().skip(1).step_by(2).map(String::as_str).collect();
+    let flat = !expression.contains(['(', ')']) && operators.len() == 1 && !operators.contains("WITH");
+    let well_formed = tokens.len() % 2 == 1 && tokens.iter().step_by(2).all(|t| !is_license_operator(t));
+    if flat && well_formed {
+        let operator = format!(" {} ", operators.into_iter().next().unwrap_or("OR"));
+        let mut ids: Vec<&str> = tokens.iter().step_by(2).map(String::as_str).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        return ids.join(&operator);
+    }
+    tokens.join(" ")
+}
+
+/// 许可证表达式中的标识符（`MIT OR Apache-2.0`、旧式的 `MIT/Apache-2.0`），不含运算符和括号
+pub fn license_ids(expression: &str) -> Vec<String> {
+    license_tokens(expression)
+        .into_iter()
+        .filter(|t| !is_license_operator(t))
+        .map(|t| t.trim_matches(['(', ')']).to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+// An expression split at whitespace, the legacy `/` read as `OR` and operators upper-cased;
+// parentheses stay attached to the identifiers next to them
+fn license_tokens(expression: &str) -> Vec<String> {
+    expression
+        .replace('/', " OR ")
+        .split_whitespace()
+        .map(|t| match t.to_ascii_uppercase().as_str() {
+            op @ ("OR" | "AND" | "WITH") => op.to_string(),
+            _ => t.to_string(),
+        })
+        .collect()
+}
+
+fn is_license_operator(token: &str) -> bool {
+    matches!(token, "OR" | "AND" | "WITH")
+}
+
+/// --no-license-lookup：记录一条警告，使 SBOM 和报告注明许可证信息是有意省略的
+pub fn license_lookup_disabled() {
+    crate::console::warn(
+        "license-lookup-disabled",
+        "license lookup disabled (--no-license-lookup); cargo metadata was not run, licenses and component details are omitted",
+    );
+}
+
+/// 只有 Cargo.lock、没有清单的项目（lockfile 路径）：记录一条警告，使 SBOM 和报告注明
+/// 许可证等信息因缺少清单而无法获得
+pub fn manifests_missing(lockfiles: &[String]) {
+    crate::console::warn(
+        "manifests-missing",
+        format!(
+            "no Cargo.toml next to {}; cargo metadata was not run, licenses, component details and dependency scopes are unavailable",
+            lockfiles.join(", ")
+        ),
+    );
+}
+
+fn read_table(path: &Path) -> Option<toml::Table> {
+    fs::read_to_string(path).ok()?.parse().ok()
+}
+
+// Non-empty string field, `None` for null/missing/empty
+fn text(value: &serde_json::Value) -> Option<String> {
+    value.as_str().filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+/// 在解压出的项目根目录中以离线模式执行一次 `cargo metadata`；失败时返回 None
+///
+/// 注意安全：这会在被扫描的项目里运行 cargo。项目自带的 `.cargo/config.toml` 可以把
+/// `build.rustc`、`build.rustc-wrapper` 等指向项目中的任意程序，而 `cargo metadata`
+/// 会调用 rustc 获取目标信息，也就是执行了压缩包里的代码。扫描不受信任的压缩包时
+/// 请使用 `--no-license-lookup`（`license_lookup_disabled`），完全不调用本函数。
+///
+/// `--no-exec` 时不启动 cargo，改为只解析磁盘上的清单（`from_manifests`）。
+pub fn fetch_all_metadata(current_dir: &Path) -> Result<Option<CargoMetadata>, Box<dyn std::error::Error>> {
+    let Ok(mut command) = crate::exec::command("cargo", &["metadata", "--format-version=1", "--offline", "--locked"]) else {
+        return Ok(Some(CargoMetadata::from_manifests(current_dir)));
+    };
+    let output = command.current_dir(current_dir).output()?;
+    if !output.status.success() {
+        crate::console::warn(
+            "metadata-unavailable",
+            "cargo metadata failed in offline mode, licenses and component details will not be included",
+        );
+        crate::console::record(String::from_utf8_lossy(&output.stderr).trim_end());
+        return Ok(None);
+    }
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    Ok(Some(CargoMetadata::from_json(&metadata)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+dependencies = ["unlicensed"]
+
+[[package]]
+name = "unlicensed"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    #[test]
+    fn test_missing_licenses() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        let metadata = CargoMetadata::from_json(&json!({
+            "packages": [
+                { "id": "demo-id", "name": "demo", "version": "0.1.0", "license": "MIT" },
+                { "id": "dep-id", "name": "unlicensed", "version": "1.0.0", "license": null },
+            ],
+            "workspace_members": ["demo-id"],
+        }));
+        assert_eq!(missing_licenses(&lockfile, Some(&metadata)), vec!["unlicensed@1.0.0"]);
+        // Without metadata nothing has a license
+        assert_eq!(missing_licenses(&lockfile, None).len(), 2);
+    }
+
+    #[test]
+    fn test_license_histogram() {
+        let lockfile = Lockfile::from_str(
+            "version = 3\n\n[[package]]\nname = \"a\"\nversion = \"1.0.0\"\n\n[[package]]\nname = \"b\"\nversion = \"1.0.0\"\n\n\
+             [[package]]\nname = \"c\"\nversion = \"1.0.0\"\n\n[[package]]\nname = \"d\"\nversion = \"1.0.0\"\n\n\
+             [[package]]\nname = \"e\"\nversion = \"1.0.0\"\n\n[[package]]\nname = \"f\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+        let package = |name: &str, license: serde_json::Value| json!({ "id": name, "name": name, "version": "1.0.0", "license": license });
+        let metadata = CargoMetadata::from_json(&json!({
+            "packages": [
+                package("a", json!("MIT OR Apache-2.0")),
+                package("b", json!("Apache-2.0/MIT")),
+                package("c", json!("MIT")),
+                package("d", json!("(MIT OR Apache-2.0) AND Unicode-DFS-2016")),
+                package("e", json!(null)),
+            ],
+        }));
+        let histogram = LicenseHistogram::build(&lockfile, &metadata);
+        assert_eq!(
+            histogram.ranked(),
+            [("Apache-2.0 OR MIT", 2), ("unknown", 2), ("(MIT OR Apache-2.0) AND Unicode-DFS-2016", 1), ("MIT", 1)]
+        );
+        // f is not in the metadata at all
+        assert_eq!(histogram.unknown, ["e@1.0.0", "f@1.0.0"]);
+        assert_eq!(normalize_license("MIT  or  Apache-2.0"), "Apache-2.0 OR MIT");
+        assert_eq!(normalize_license("Apache-2.0 WITH LLVM-exception"), "Apache-2.0 WITH LLVM-exception");
+        assert_eq!(license_ids("(MIT or Apache-2.0) AND Unicode-DFS-2016"), ["MIT", "Apache-2.0", "Unicode-DFS-2016"]);
+        assert_eq!(license_ids("MIT/Apache-2.0"), ["MIT", "Apache-2.0"]);
+    }
+
+    #[test]
+    fn test_metadata_from_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |rel: &str, text: &str| {
+            let path = dir.path().join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write("Cargo.toml", "[workspace]\nmembers = [\"app\"]\n\n[workspace.package]\nversion = \"1.2.0\"\nlicense = \"MIT\"\n");
+        write("app/Cargo.toml", "[package]\nname = \"app\"\nversion.workspace = true\nlicense.workspace = true\n");
+        write("vendor/derive/Cargo.toml", "[package]\nname = \"derive\"\nversion = \"0.4.0\"\nlicense = \"Apache-2.0\"\n\n[lib]\nproc-macro = true\n");
+
+        let metadata = CargoMetadata::from_manifests(dir.path());
+        assert_eq!(metadata.get("app", "1.2.0").unwrap().license.as_deref(), Some("MIT"));
+        let derive = metadata.get("derive", "0.4.0").unwrap();
+        assert_eq!(derive.license.as_deref(), Some("Apache-2.0"));
+        assert!(derive.proc_macro);
+        // Vendored copies are dependencies, not members
+        assert_eq!(metadata.workspace_members, [("app".to_string(), "1.2.0".to_string())]);
+    }
+}
@@ -0,0 +1,1175 @@
+use rustpj::download;
+use rustpj::output;
+use rustpj::signing;
+use rustpj::github_snapshot::SnapshotContext;
+use rustpj::config::ScanConfig;
+use rustpj::description::DescriptionMode;
+use rustpj::get_sbom::MergeConflicts;
+use rustpj::package_filter::{PackageExclusion, PackageFilter, PackageSelection};
+use rustpj::project::ProjectOverrides;
+use rustpj::scanner::{CategoryFilter, Collection};
+use rustpj::severity::Severity;
+use anyhow::{Context, Result, anyhow, bail};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: rustpj [options] <path-to-zip-file>
+       rustpj --online [options] <http(s) URL of a zip|crate|binary>
+       rustpj verify <output-dir> --public-key <file>
+       rustpj doctor                          Check the advisory DB, cargo and paths (alias: check)
+       rustpj query <crate> <version> [--json] [--target <triple>]
+                                              List advisories affecting one crate version
+       rustpj why <crate>[@<version>] <zip|crate|Cargo.lock|binary> [--json]
+                                              Show which packages pull in a crate, up to the workspace
+       rustpj batch <dir|archive>... [--jobs <n>]
+                                              Scan many archives concurrently with one loaded DB
+       rustpj db update [--db-url <url>] [--config <file>]
+                                              Clone (depth 1) or fast-forward the advisory DB
+       rustpj serve [--listen <addr>] [--jobs <n>]
+                                              HTTP scanning service (needs the `server` feature)
+
+Options:
+  --report-format <json|github-snapshot|trivy|junit>
+                                          Report emitter (default: json; trivy: Trivy-compatible JSON; junit: JUnit XML)
+  --report-path <path|->                  Where to write the report; `-` streams pure JSON to stdout
+  --report-template <file.tera>           Also render the report through a Tera template
+  --report-output <path|->                Output of the rendered template
+  --config <file>                         Triage/ignore config (TOML)
+  --no-project-config                     Ignore a rustpj.toml shipped next to the project's Cargo.lock
+  --trust-project-config                  Let that rustpj.toml also set ignores, exclusions, the gate and thresholds
+  --interactive                           After the scan, triage each untriaged finding at a prompt
+  --triage-file <file>                    Where --interactive appends its [[ignore]] entries (default: ./triage.toml)
+  --embed-vulnerabilities                 Embed findings (with VEX analysis) into the SBOM
+  --vex-output <path>                     Write a standalone CycloneDX VEX document
+  --dot-output <path>                     Write the dependency graph as Graphviz DOT, colored by severity
+  --popular-crates <file>                 Popular crate names for the typosquatting check (one per line)
+  --cpe-mapping <file>                    Extra crate -> CPE vendor/product mappings for the SBOM (TOML)
+  --project-name <name>                   Name of the project in the SBOM and report (default: root manifest, then file name)
+  --project-version <version>             Version of the project (default: root manifest)
+  --project-group <group>                 Group / namespace of the project component
+  --fail-on-suspicious                    Exit with code 1 if a package name resembles a popular crate
+  --require-licenses                      Fail if any SBOM component has no license information
+  --no-license-lookup                     Don't run `cargo metadata` on the input (untrusted archives); no licenses in the SBOM
+  --no-exec                               Never start external programs: licenses from manifests only, no lockfile generation
+  --no-sbom                               Skip SBOM generation
+  --sbom-format <json|protobuf>           SBOM encoding; protobuf writes CycloneDX Protobuf to sbom.cdx.bin (default: json)
+  --merge-sbom <file>                     Merge this run's components into an earlier SBOM (may be the output itself)
+  --merge-conflicts <error|warn>          When a bom-ref names a different package in both SBOMs (default: error)
+  --no-scan                               Skip the vulnerability scan (the advisory DB is not loaded)
+  --check-msrv                            Report dependencies needing a newer Rust than the project's rust-version
+  --target <triple>                       Only report advisories that apply to this target platform
+  --collection <crates|rust|all>          Advisory collections to scan (default: crates); repeatable
+  --toolchain <version>                   Rust version the project builds with; checked against the rust collection
+  --package <name>[@<version>]            Only scan (and list in the SBOM) this crate; repeatable
+  --with-deps                             With --package, also include the selected crates' dependencies
+  --exclude-package <name>[@<version>]    Drop this crate from the scan and the SBOM (repeatable)
+  --exclude-reason <text>                 Why the --exclude-package crates are left out (recorded in the report)
+  --exclude-workspace                     Don't scan the workspace's own crates (local path packages)
+  --only-category <category>              Only report advisories in this category (e.g. memory-corruption); repeatable
+  --exclude-category <category>           Leave advisories in this category out of the findings; repeatable
+  --include-withdrawn                     List withdrawn advisories that would have matched (never counted)
+  --description <truncate[:N]|full|omit>  Advisory descriptions in the JSON report (default: truncate:500)
+  --canonical                             Sort by name/version/id and drop timestamps for byte-stable reports
+  --compress                              Gzip the JSON report, SBOM and VEX (appends .gz; any path ending .gz is gzipped)
+  --pretty | --minify                     Write JSON outputs indented (default) or on one line
+  --canonical-json                        Write JSON outputs with sorted keys and no whitespace (for hashing)
+  --timeout <seconds>                     Abort the whole run (exit code 2) if it takes longer than this
+  --progress-format <text|ndjson>         ndjson: stderr carries only JSON progress events, one per line
+  --db-path <path>                        Advisory DB clone to use (default: $RUSTSEC_DB_PATH or ./data/advisory-db);
+                                          repeatable, later DBs override advisories with the same id
+  --db-rev <commit>                       Scan against this advisory DB commit (reproducible reports)
+  --strict-db                             Fail when an advisory file in the DB does not load, instead of skipping it
+  --osv-scores <dir>                      Also rate findings by CVSS scores in these OSV documents (e.g. GHSA)
+  --sign-key <file>                       Write manifest.json + ed25519 signature over the outputs
+  --bundle <path.zip>                     Also archive every output and the run log into one zip
+  --output-url <url>                      Publish the outputs to s3://bucket/prefix/ (s3 feature) or file:///dir
+  --output-dir <dir>                      Where the SBOM, default report and signed manifest go (default ./output)
+  --force                                 Overwrite output files left by an earlier run (refused by default)
+  --keep-temp                             Keep the extracted files in ./tmp after the run (removed by default)
+  --verbose                               Also list the components without license information
+  --quiet                                 Print only warnings and errors (no progress lines, no early findings)
+  --online                                Look up each vulnerable crate's latest release on the registry;
+                                          also allows the input to be an http(s) URL, downloaded first
+  --registry-url <url>                    crates.io-compatible registry API for online lookups
+  --check-staleness                       List crates.io dependencies without a release in 3 years (needs --online
+                                          or --crates-snapshot)
+  --crates-snapshot <dir>                 Read publish dates from saved registry responses instead of the network
+  --advisory-db-url <url>                 Git URL of the advisory DB (for mirrors; alias: --db-url)
+  --user-agent <string>                   User-Agent for HTTP requests
+  --fail-on <critical|high|medium|low|any>
+                                          Exit with code 1 if an untriaged finding reaches this severity
+  --min-cvss <score>                      Exit with code 1 if an untriaged finding has a CVSS base score of at least this
+  --fail-on-unscored                      With --min-cvss, also fail on findings without a CVSS score
+  --baseline <report.json>                Findings already in this earlier report don't count for --fail-on
+  --github-job <id>                       Snapshot job id (default: $GITHUB_RUN_ID)
+  --github-correlator <name>              Snapshot job correlator (default: $GITHUB_WORKFLOW_$GITHUB_JOB)
+  --github-sha <sha>                      Snapshot commit sha (default: $GITHUB_SHA)
+  --github-ref <ref>                      Snapshot git ref (default: $GITHUB_REF)
+  -h, --help                              Print this help";
+
+/// `serve` 默认监听的地址
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    GithubSnapshot,
+    // Trivy JSON 结构的漏洞报告
+    Trivy,
+    // JUnit XML，每个包一个测试用例
+    Junit,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(ReportFormat::Json),
+            "github-snapshot" => Ok(ReportFormat::GithubSnapshot),
+            "trivy" => Ok(ReportFormat::Trivy),
+            "junit" => Ok(ReportFormat::Junit),
+            other => Err(anyhow!("unknown report format: {}", other)),
+        }
+    }
+}
+
+/// --sbom-format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SbomFormat {
+    #[default]
+    Json,
+    // CycloneDX Protobuf（sbom.cdx.bin）
+    Protobuf,
+}
+
+impl FromStr for SbomFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(SbomFormat::Json),
+            "protobuf" => Ok(SbomFormat::Protobuf),
+            other => Err(anyhow!("unknown SBOM format: {}", other)),
+        }
+    }
+}
+
+/// --progress-format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProgressFormat {
+    #[default]
+    Text,
+    // 每个进度事件一行 JSON，写到 stderr
+    Ndjson,
+}
+
+impl FromStr for ProgressFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(ProgressFormat::Text),
+            "ndjson" => Ok(ProgressFormat::Ndjson),
+            other => Err(anyhow!("unknown progress format: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Command {
+    #[default]
+    Scan,
+    // 校验输出目录与签名清单是否一致
+    Verify { dir: String, public_key: String },
+    // 自检：advisory DB、cargo 以及临时 / 输出目录
+    Doctor,
+    // 查询单个 crate 版本受哪些漏洞影响
+    Query { name: String, version: semver::Version, json: bool },
+    // 反向依赖：哪些包（一直到工作区成员）引入了某个 crate
+    Why { package: PackageFilter, input: String, json: bool },
+    // 并发扫描多个压缩包，共享同一个已加载的 advisory DB
+    Batch { inputs: Vec<String>, jobs: Option<usize> },
+    // 克隆或快进更新 advisory DB
+    DbUpdate,
+    // 常驻 HTTP 扫描服务，jobs 为并发处理的请求数
+    Serve { listen: String, jobs: Option<usize> },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub command: Command,
+    pub zip_path: String,
+    pub report_format: ReportFormat,
+    pub report_path: Option<String>,
+    pub config_path: Option<String>,
+    // 不读取压缩包中项目自带的 rustpj.toml
+    pub no_project_config: bool,
+    // 信任项目自带的 rustpj.toml，允许其中可能放行扫描的设置（ignore、gate、thresholds 等）
+    pub trust_project_config: bool,
+    // 扫描后逐条询问分诊决定，追加到 triage_file（默认 triage::DEFAULT_TRIAGE_FILE）
+    pub interactive: bool,
+    pub triage_file: Option<String>,
+    pub baseline: Option<String>,
+    pub report_template: Option<String>,
+    pub report_output: Option<String>,
+    pub embed_vulnerabilities: bool,
+    pub vex_output: Option<String>,
+    pub dot_output: Option<String>,
+    pub fail_on: Option<Severity>,
+    // --min-cvss 阈值，以及没有 CVSS 分数的漏洞是否也判为 fail
+    pub min_cvss: Option<f64>,
+    pub fail_on_unscored: bool,
+    pub check_msrv: bool,
+    pub no_sbom: bool,
+    pub sbom_format: SbomFormat,
+    // 并入本次结果的已有 SBOM
+    pub merge_sbom: Option<String>,
+    pub merge_conflicts: Option<MergeConflicts>,
+    pub no_scan: bool,
+    pub require_licenses: bool,
+    // 不对输入执行 `cargo metadata`
+    pub no_license_lookup: bool,
+    // 不启动任何外部程序（ExternalCommands::Forbidden）
+    pub no_exec: bool,
+    pub popular_crates: Option<String>,
+    pub cpe_mapping: Option<String>,
+    // --project-name / --project-version / --project-group
+    pub project: ProjectOverrides,
+    pub fail_on_suspicious: bool,
+    pub sign_key: Option<String>,
+    pub registry_url: Option<String>,
+    pub advisory_db_url: Option<String>,
+    pub user_agent: Option<String>,
+    pub bundle: Option<String>,
+    pub output_url: Option<String>,
+    pub output_dir: Option<String>,
+    // --db-path；为空时取 RUSTSEC_DB_PATH
+    pub db_paths: Vec<String>,
+    pub db_rev: Option<String>,
+    // 任一 advisory 文件加载失败即报错，而不是跳过该文件
+    pub strict_db: bool,
+    pub osv_scores: Option<String>,
+    pub target: Option<String>,
+    // --collection；为空时只扫描 crates 集合
+    pub collections: Vec<Collection>,
+    pub toolchain: Option<semver::Version>,
+    pub include_withdrawn: bool,
+    pub packages: Vec<PackageFilter>,
+    pub with_deps: bool,
+    pub exclude_packages: Vec<PackageFilter>,
+    pub exclude_reason: Option<String>,
+    pub categories: CategoryFilter,
+    pub exclude_workspace: bool,
+    pub canonical: bool,
+    pub compress: bool,
+    pub json_style: output::JsonStyle,
+    pub force: bool,
+    pub keep_temp: bool,
+    pub verbose: bool,
+    pub quiet: bool,
+    // 未给出时取配置文件 `[output]`，再取默认值
+    pub description: Option<DescriptionMode>,
+    pub online: bool,
+    pub check_staleness: bool,
+    // 本地保存的 registry 响应目录，代替网络查询发布日期
+    pub crates_snapshot: Option<String>,
+    pub timeout: Option<Duration>,
+    pub progress_format: ProgressFormat,
+    pub snapshot: SnapshotContext,
+    pub help: bool,
+}
+
+impl Options {
+    /// 解析命令行参数（不含程序名）
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options> {
+        let mut opts = Options::default();
+        let mut positional = Vec::new();
+        let mut args = args.into_iter().peekable();
+        let verify = args.peek().is_some_and(|a| a == "verify");
+        if verify {
+            args.next();
+        }
+        let doctor = !verify && args.peek().is_some_and(|a| a == "doctor" || a == "check" || a == "--doctor");
+        if doctor {
+            args.next();
+        }
+        let query = !verify && !doctor && args.peek().is_some_and(|a| a == "query");
+        if query {
+            args.next();
+        }
+        let batch = !verify && !doctor && !query && args.peek().is_some_and(|a| a == "batch");
+        if batch {
+            args.next();
+        }
+        let why = !verify && !doctor && !query && !batch && args.peek().is_some_and(|a| a == "why");
+        if why {
+            args.next();
+        }
+        let db = !verify && !doctor && !query && !batch && !why && args.peek().is_some_and(|a| a == "db");
+        if db {
+            args.next();
+            if args.next().as_deref() != Some("update") {
+                bail!("db expects a subcommand: update");
+            }
+        }
+        let serve = !verify && !doctor && !query && !batch && !why && !db && args.peek().is_some_and(|a| a == "serve");
+        if serve {
+            args.next();
+        }
+        let mut offline = false;
+        let mut listen = None;
+        let mut json = false;
+        let mut jobs = None;
+        let mut public_key = None;
+
+        while let Some(arg) = args.next() {
+            // Accept both `--flag value` and `--flag=value`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((f, v)) if f.starts_with("--") => (f.to_string(), Some(v.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = |name: &str| -> Result<String> {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| anyhow!("missing value for {}", name))
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => opts.help = true,
+                "--report-format" => opts.report_format = value(&flag)?.parse()?,
+                "--report-path" => opts.report_path = Some(value(&flag)?),
+                "--config" => opts.config_path = Some(value(&flag)?),
+                "--interactive" => opts.interactive = true,
+                "--no-project-config" => opts.no_project_config = true,
+                "--trust-project-config" => opts.trust_project_config = true,
+                "--triage-file" => opts.triage_file = Some(value(&flag)?),
+                "--baseline" => opts.baseline = Some(value(&flag)?),
+                "--report-template" => opts.report_template = Some(value(&flag)?),
+                "--report-output" => opts.report_output = Some(value(&flag)?),
+                "--embed-vulnerabilities" => opts.embed_vulnerabilities = true,
+                "--vex-output" => opts.vex_output = Some(value(&flag)?),
+                "--dot-output" => opts.dot_output = Some(value(&flag)?),
+                "--check-msrv" => opts.check_msrv = true,
+                "--include-withdrawn" => opts.include_withdrawn = true,
+                "--canonical" => opts.canonical = true,
+                "--compress" => opts.compress = true,
+                "--sbom-format" => opts.sbom_format = value(&flag)?.parse()?,
+                "--pretty" => opts.json_style = output::JsonStyle::Pretty,
+                "--minify" => opts.json_style = output::JsonStyle::Minified,
+                "--canonical-json" => opts.json_style = output::JsonStyle::Canonical,
+                "--force" => opts.force = true,
+                "--keep-temp" => opts.keep_temp = true,
+                "--verbose" => opts.verbose = true,
+                "--quiet" => opts.quiet = true,
+                "--description" => opts.description = Some(value(&flag)?.parse()?),
+                "--online" => opts.online = true,
+                "--check-staleness" => opts.check_staleness = true,
+                "--crates-snapshot" => opts.crates_snapshot = Some(value(&flag)?),
+                "--package" => opts.packages.push(value(&flag)?.parse()?),
+                "--with-deps" => opts.with_deps = true,
+                "--exclude-package" => opts.exclude_packages.push(value(&flag)?.parse()?),
+                "--exclude-reason" => opts.exclude_reason = Some(value(&flag)?),
+                "--exclude-workspace" => opts.exclude_workspace = true,
+                "--only-category" => opts.categories.only.push(advisory_category(&value(&flag)?)?),
+                "--exclude-category" => opts.categories.exclude.push(advisory_category(&value(&flag)?)?),
+                "--no-sbom" => opts.no_sbom = true,
+                "--merge-sbom" => opts.merge_sbom = Some(value(&flag)?),
+                "--merge-conflicts" => opts.merge_conflicts = Some(value(&flag)?.parse()?),
+                "--no-scan" => opts.no_scan = true,
+                "--require-licenses" => opts.require_licenses = true,
+                "--no-license-lookup" => opts.no_license_lookup = true,
+                "--no-exec" => opts.no_exec = true,
+                "--popular-crates" => opts.popular_crates = Some(value(&flag)?),
+                "--cpe-mapping" => opts.cpe_mapping = Some(value(&flag)?),
+                "--project-name" => opts.project.name = Some(value(&flag)?),
+                "--project-version" => opts.project.version = Some(value(&flag)?),
+                "--project-group" => opts.project.group = Some(value(&flag)?),
+                "--fail-on-suspicious" => opts.fail_on_suspicious = true,
+                "--fail-on-unscored" => opts.fail_on_unscored = true,
+                "--sign-key" => opts.sign_key = Some(value(&flag)?),
+                "--registry-url" => opts.registry_url = Some(value(&flag)?),
+                "--advisory-db-url" | "--db-url" => opts.advisory_db_url = Some(value(&flag)?),
+                "--user-agent" => opts.user_agent = Some(value(&flag)?),
+                "--bundle" => opts.bundle = Some(value(&flag)?),
+                "--output-url" => opts.output_url = Some(value(&flag)?),
+                "--output-dir" => opts.output_dir = Some(value(&flag)?),
+                "--db-path" => opts.db_paths.push(value(&flag)?),
+                "--db-rev" => opts.db_rev = Some(value(&flag)?),
+                "--strict-db" => opts.strict_db = true,
+                "--osv-scores" => opts.osv_scores = Some(value(&flag)?),
+                "--progress-format" => opts.progress_format = value(&flag)?.parse()?,
+                "--timeout" => {
+                    let raw = value(&flag)?;
+                    match raw.parse::<u64>() {
+                        Ok(secs) if secs > 0 => opts.timeout = Some(Duration::from_secs(secs)),
+                        _ => bail!("invalid --timeout value: {}", raw),
+                    }
+                }
+                "--target" => {
+                    let triple = value(&flag)?;
+                    if rustsec::platforms::Platform::find(&triple).is_none() {
+                        bail!("unknown target triple: {}", triple);
+                    }
+                    opts.target = Some(triple);
+                }
+                "--collection" => {
+                    let raw = value(&flag)?;
+                    let selected: &[Collection] = match raw.as_str() {
+                        "all" => Collection::all(),
+                        _ => &[raw.parse().map_err(|_| anyhow!("unknown advisory collection: {} (expected crates, rust or all)", raw))?],
+                    };
+                    for &collection in selected {
+                        if !opts.collections.contains(&collection) {
+                            opts.collections.push(collection);
+                        }
+                    }
+                }
+                "--toolchain" => {
+                    let raw = value(&flag)?;
+                    let version = raw.trim_start_matches("rust-").parse().map_err(|_| anyhow!("invalid --toolchain version: {} (expected e.g. 1.75.0)", raw))?;
+                    opts.toolchain = Some(version);
+                }
+                "--public-key" if verify => public_key = Some(value(&flag)?),
+                "--json" if query || why => json = true,
+                "--offline" if db => offline = true,
+                "--listen" if serve => listen = Some(value(&flag)?),
+                "--jobs" if batch || serve => {
+                    let raw = value(&flag)?;
+                    match raw.parse::<usize>() {
+                        Ok(n) if n > 0 => jobs = Some(n),
+                        _ => bail!("invalid --jobs value: {}", raw),
+                    }
+                }
+                "--fail-on" => {
+                    let raw = value(&flag)?;
+                    // `any` matches every finding, including ones without a CVSS score
+                    let severity = match raw.to_lowercase().as_str() {
+                        "any" => Severity::Unknown,
+                        _ => raw.parse().map_err(|_| anyhow::anyhow!("invalid --fail-on severity: {}", raw))?,
+                    };
+                    opts.fail_on = Some(severity);
+                }
+                "--min-cvss" => {
+                    let raw = value(&flag)?;
+                    match raw.parse::<f64>() {
+                        Ok(score) if (0.0..=10.0).contains(&score) => opts.min_cvss = Some(score),
+                        _ => bail!("invalid --min-cvss score (expected 0.0-10.0): {}", raw),
+                    }
+                }
+                "--github-job" => opts.snapshot.job_id = Some(value(&flag)?),
+                "--github-correlator" => opts.snapshot.correlator = Some(value(&flag)?),
+                "--github-sha" => opts.snapshot.sha = Some(value(&flag)?),
+                "--github-ref" => opts.snapshot.git_ref = Some(value(&flag)?),
+                f if f.starts_with('-') && f.len() > 1 => bail!("unknown option: {}", f),
+                _ => positional.push(arg),
+            }
+        }
+
+        if opts.help {
+            return Ok(opts);
+        }
+        // A commit of one repository means nothing in another
+        if opts.db_rev.is_some() && opts.db_paths.len() > 1 {
+            bail!("--db-rev pins a single advisory DB; it cannot be combined with several --db-path");
+        }
+        if verify {
+            let public_key = public_key.ok_or_else(|| anyhow!("verify requires --public-key <file>"))?;
+            let dir = match positional.len() {
+                0 => "./output".to_string(),
+                1 => positional.remove(0),
+                _ => bail!("verify expects a single output directory"),
+            };
+            opts.command = Command::Verify { dir, public_key };
+            return Ok(opts);
+        }
+        if db {
+            if !positional.is_empty() {
+                bail!("db update does not take an input");
+            }
+            if offline {
+                bail!("db update has to fetch the advisory DB; it cannot run with --offline");
+            }
+            opts.command = Command::DbUpdate;
+            return Ok(opts);
+        }
+        if serve {
+            if !positional.is_empty() {
+                bail!("serve takes archives over HTTP, not on the command line");
+            }
+            // Responses carry the report; there is no exit code to gate and no terminal to prompt on
+            let unsupported = [
+                ("--fail-on", opts.fail_on.is_some()),
+                ("--min-cvss", opts.min_cvss.is_some()),
+                ("--fail-on-unscored", opts.fail_on_unscored),
+                ("--baseline", opts.baseline.is_some()),
+                ("--fail-on-suspicious", opts.fail_on_suspicious),
+                ("--interactive", opts.interactive),
+                ("--triage-file", opts.triage_file.is_some()),
+            ];
+            if let Some((flag, _)) = unsupported.iter().find(|(_, set)| *set) {
+                bail!("{} is not supported with serve", flag);
+            }
+            let listen = listen.unwrap_or_else(|| DEFAULT_LISTEN.to_string());
+            opts.command = Command::Serve { listen, jobs };
+            return Ok(opts);
+        }
+        if query {
+            let [name, version]: [String; 2] = positional
+                .try_into()
+                .map_err(|_| anyhow!("query expects <crate> <version>"))?;
+            let version = version
+                .parse()
+                .map_err(|e| anyhow!("invalid version {}: {}", version, e))?;
+            opts.command = Command::Query { name, version, json };
+            return Ok(opts);
+        }
+        if why {
+            let [package, input]: [String; 2] = positional
+                .try_into()
+                .map_err(|_| anyhow!("why expects <crate>[@<version>] <input>"))?;
+            opts.command = Command::Why { package: package.parse()?, input, json };
+            return Ok(opts);
+        }
+        // Publish dates come from the network only when it is allowed, or from a snapshot
+        if opts.check_staleness && !opts.online && opts.crates_snapshot.is_none() {
+            bail!("--check-staleness looks up publish dates on the registry; add --online, or --crates-snapshot <dir>");
+        }
+        if opts.crates_snapshot.is_some() && !opts.check_staleness {
+            bail!("--crates-snapshot is only read by --check-staleness");
+        }
+        if batch {
+            // Only options that make sense per archive in an aggregated JSON report
+            let unsupported = [
+                ("--report-template", opts.report_template.is_some()),
+                ("--vex-output", opts.vex_output.is_some()),
+                ("--dot-output", opts.dot_output.is_some()),
+                ("--embed-vulnerabilities", opts.embed_vulnerabilities),
+                ("--bundle", opts.bundle.is_some()),
+                ("--output-url", opts.output_url.is_some()),
+                ("--sign-key", opts.sign_key.is_some()),
+                ("--check-msrv", opts.check_msrv),
+                ("--merge-sbom", opts.merge_sbom.is_some()),
+                ("--sbom-format", opts.sbom_format != SbomFormat::Json),
+                ("--package", !opts.packages.is_empty()),
+                ("--report-format", opts.report_format != ReportFormat::Json),
+                ("--trust-project-config", opts.trust_project_config),
+                ("--fail-on-suspicious", opts.fail_on_suspicious),
+                ("--popular-crates", opts.popular_crates.is_some()),
+                ("--require-licenses", opts.require_licenses),
+                ("--cpe-mapping", opts.cpe_mapping.is_some()),
+                ("--project-name", opts.project.name.is_some()),
+                ("--project-version", opts.project.version.is_some()),
+                ("--project-group", opts.project.group.is_some()),
+                ("--interactive", opts.interactive),
+                ("--triage-file", opts.triage_file.is_some()),
+            ];
+            if let Some((flag, _)) = unsupported.iter().find(|(_, set)| *set) {
+                bail!("{} is not supported with batch", flag);
+            }
+            if positional.is_empty() {
+                bail!("batch expects at least one directory or archive");
+            }
+            opts.command = Command::Batch { inputs: positional, jobs };
+            opts.compress_paths();
+            return Ok(opts);
+        }
+        if doctor {
+            if !positional.is_empty() {
+                bail!("doctor does not take an input");
+            }
+            opts.command = Command::Doctor;
+            return Ok(opts);
+        }
+        if opts.no_sbom && opts.no_scan {
+            bail!("--no-sbom and --no-scan together leave nothing to do");
+        }
+        if opts.no_sbom && opts.merge_sbom.is_some() {
+            bail!("--merge-sbom writes the SBOM; drop --no-sbom");
+        }
+        if opts.sbom_format == SbomFormat::Protobuf && opts.merge_sbom.is_some() {
+            bail!("--merge-sbom reads and writes JSON SBOMs; drop --sbom-format protobuf");
+        }
+        if opts.merge_conflicts.is_some() && opts.merge_sbom.is_none() {
+            bail!("--merge-conflicts requires --merge-sbom");
+        }
+        if opts.trust_project_config && opts.no_project_config {
+            bail!("--trust-project-config applies to the rustpj.toml that --no-project-config ignores; pick one");
+        }
+        // The toolchain version is only ever compared with the rust collection
+        if opts.toolchain.is_some() {
+            if opts.collections.is_empty() {
+                opts.collections = vec![Collection::Crates, Collection::Rust];
+            } else if !opts.collections.contains(&Collection::Rust) {
+                bail!("--toolchain is checked against the rust advisory collection; add --collection rust");
+            }
+        }
+        if opts.no_license_lookup && opts.require_licenses {
+            bail!("--require-licenses needs the license lookup; drop --no-license-lookup");
+        }
+        if opts.no_sbom && (opts.embed_vulnerabilities || opts.vex_output.is_some()) {
+            bail!("--embed-vulnerabilities and --vex-output need the SBOM; drop --no-sbom");
+        }
+        if opts.triage_file.is_some() && !opts.interactive {
+            bail!("--triage-file only applies to --interactive");
+        }
+        opts.check_thresholds()?;
+        if opts.no_scan
+            && (opts.fail_on.is_some() || opts.min_cvss.is_some() || opts.interactive || opts.vex_output.is_some() || opts.report_template.is_some() || opts.baseline.is_some())
+        {
+            bail!("--fail-on, --min-cvss, --interactive, --baseline, --vex-output and --report-template need the vulnerability scan; drop --no-scan");
+        }
+        if opts.no_scan && matches!(opts.report_format, ReportFormat::Trivy | ReportFormat::Junit) {
+            bail!("--report-format trivy and junit report vulnerability findings; drop --no-scan");
+        }
+        // Downloading the input is the one online feature that works without the scan
+        let url_input = positional.first().is_some_and(|p| download::is_url(p));
+        if url_input && !opts.online {
+            bail!("downloading the input from a URL needs --online");
+        }
+        if opts.no_scan && opts.online && !url_input {
+            bail!("--online only annotates vulnerability findings; drop --no-scan");
+        }
+        if opts.no_scan && opts.check_staleness {
+            bail!("--check-staleness writes to the vulnerability report; drop --no-scan");
+        }
+        if opts.no_scan && opts.osv_scores.is_some() {
+            bail!("--osv-scores only rates vulnerability findings; drop --no-scan");
+        }
+        if opts.with_deps && opts.packages.is_empty() {
+            bail!("--with-deps requires --package");
+        }
+        if opts.exclude_reason.is_some() && opts.exclude_packages.is_empty() {
+            bail!("--exclude-reason requires --exclude-package");
+        }
+        if opts.report_template.is_some() && opts.report_output.is_none() {
+            bail!("--report-template requires --report-output");
+        }
+        let stdout_outputs = [
+            opts.report_to_stdout(),
+            opts.report_output.as_deref() == Some("-"),
+            opts.dot_output.as_deref() == Some("-"),
+        ];
+        if stdout_outputs.iter().filter(|&&s| s).count() > 1 {
+            bail!("only one of --report-path, --report-output and --dot-output can be stdout");
+        }
+        match positional.len() {
+            1 => opts.zip_path = positional.remove(0),
+            0 => bail!("missing <path-to-zip-file>"),
+            _ => bail!("expected exactly one input, got {}", positional.len()),
+        }
+        opts.compress_paths();
+        Ok(opts)
+    }
+
+    // --compress: the JSON outputs move to `<path>.gz`; stdout stays uncompressed
+    fn compress_paths(&mut self) {
+        if !self.compress {
+            return;
+        }
+        self.report_path = Some(output::compressed_path(&self.report_path()));
+        self.vex_output = self.vex_output.as_deref().map(output::compressed_path);
+    }
+
+    /// 把所有输出路径解析为绝对路径：默认文件名位于输出目录下，命令行给出的相对路径基于 cwd
+    pub fn resolve_paths(&mut self, cwd: &Path) {
+        let resolve = |path: &str| match path {
+            "-" => path.to_string(),
+            _ => output::absolute(cwd, Path::new(path)).to_string_lossy().to_string(),
+        };
+        self.output_dir = Some(resolve(&self.output_dir().to_string_lossy()));
+        self.report_path = Some(resolve(&self.report_path()));
+        for path in [&mut self.vex_output, &mut self.dot_output, &mut self.report_output, &mut self.bundle, &mut self.merge_sbom] {
+            *path = path.as_deref().map(resolve);
+        }
+    }
+
+    /// SBOM、默认报告和签名清单所在的目录
+    pub fn output_dir(&self) -> PathBuf {
+        PathBuf::from(self.output_dir.as_deref().unwrap_or("./output"))
+    }
+
+    /// SBOM 输出路径
+    pub fn sbom_path(&self) -> String {
+        let name = match self.sbom_format {
+            SbomFormat::Json => "sbom.json",
+            SbomFormat::Protobuf => "sbom.cdx.bin",
+        };
+        let path = self.output_dir().join(name).to_string_lossy().to_string();
+        if self.compress { output::compressed_path(&path) } else { path }
+    }
+
+    /// --package / --with-deps 组成的过滤条件，未指定 --package 时为 None
+    pub fn package_selection(&self) -> Option<PackageSelection> {
+        if self.packages.is_empty() {
+            return None;
+        }
+        Some(PackageSelection { filters: self.packages.clone(), with_deps: self.with_deps })
+    }
+
+    /// --merge-sbom 指向的就是本次的 SBOM 输出路径
+    pub fn merges_sbom_in_place(&self) -> bool {
+        self.merge_sbom.as_deref().is_some_and(|path| Path::new(path) == Path::new(&self.sbom_path()))
+    }
+
+    /// 命令行未给出的阈值和输出选项取配置文件中的 `[thresholds]`（只来自 --config）/ `[output]`；
+    /// 合并后的阈值与命令行一样校验
+    pub fn with_config_defaults(&self, config: &ScanConfig) -> Result<Options> {
+        let mut opts = self.clone();
+        opts.fail_on = opts.fail_on.or(config.thresholds.fail_on);
+        opts.min_cvss = opts.min_cvss.or(config.thresholds.min_cvss);
+        opts.fail_on_unscored |= config.thresholds.fail_on_unscored.unwrap_or(false);
+        opts.description = opts.description.or(config.output.description());
+        opts.canonical |= config.output.canonical.unwrap_or(false);
+        opts.check_thresholds().context("with the [thresholds] of the config file")?;
+        Ok(opts)
+    }
+
+    fn check_thresholds(&self) -> Result<()> {
+        if self.fail_on_unscored && self.min_cvss.is_none() {
+            bail!("--fail-on-unscored only applies to --min-cvss");
+        }
+        Ok(())
+    }
+
+    /// --exclude-package 与配置中的排除条件合并；命令行的条件使用 --exclude-reason 作为原因
+    pub fn exclusions(&self, configured: &[PackageExclusion]) -> Vec<PackageExclusion> {
+        let from_flags = self
+            .exclude_packages
+            .iter()
+            .map(|package| PackageExclusion { package: package.clone(), reason: self.exclude_reason.clone() });
+        from_flags.chain(configured.iter().cloned()).collect()
+    }
+
+    /// 报告输出路径，未指定时按报告格式选择输出目录下的默认文件
+    pub fn report_path(&self) -> String {
+        if let Some(path) = &self.report_path {
+            return path.clone();
+        }
+        let name = match self.report_format {
+            _ if matches!(self.command, Command::Batch { .. }) => "batch_report.json",
+            ReportFormat::Json => "vuln_report.json",
+            ReportFormat::GithubSnapshot => "github_snapshot.json",
+            ReportFormat::Trivy => "trivy_report.json",
+            ReportFormat::Junit => "junit_report.xml",
+        };
+        self.output_dir().join(name).to_string_lossy().to_string()
+    }
+
+    pub fn report_to_stdout(&self) -> bool {
+        self.report_path.as_deref() == Some("-")
+    }
+
+    /// 本次运行将写出的文件（不含 stdout），用于在扫描前检查是否会覆盖已有文件
+    pub fn planned_outputs(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        let report = match self.command {
+            Command::Scan => !self.no_scan || self.report_format == ReportFormat::GithubSnapshot,
+            Command::Batch { .. } => true,
+            _ => false,
+        };
+        if report && !self.report_to_stdout() {
+            paths.push(self.report_path());
+        }
+        if let Command::Scan = self.command {
+            // Extending the SBOM in place is what --merge-sbom asked for
+            if !self.no_sbom && !self.merges_sbom_in_place() {
+                paths.push(self.sbom_path());
+            }
+            let optional = [&self.dot_output, &self.report_output, &self.vex_output, &self.bundle];
+            paths.extend(optional.into_iter().flatten().filter(|p| *p != "-").cloned());
+            if self.sign_key.is_some() {
+                let dir = self.output_dir();
+                paths.push(dir.join(signing::MANIFEST_NAME).to_string_lossy().to_string());
+                paths.push(dir.join(signing::SIGNATURE_NAME).to_string_lossy().to_string());
+            }
+        }
+        paths.into_iter().map(PathBuf::from).collect()
+    }
+}
+
+// Unknown names are almost certainly typos, and a typo in a filter would silently drop
+// (or keep) findings
+fn advisory_category(raw: &str) -> Result<String> {
+    match raw.parse::<rustsec::advisory::Category>() {
+        Ok(rustsec::advisory::Category::Other(_)) | Err(_) => bail!(
+            "unknown advisory category: {} (expected one of: code-execution, crypto-failure, denial-of-service, \
+             file-disclosure, format-injection, memory-corruption, memory-exposure, privilege-escalation, thread-safety)",
+            raw
+        ),
+        Ok(category) => Ok(category.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options> {
+        Options::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_report_format() {
+        let opts = parse(&["--report-format", "github-snapshot", "--github-sha=abc", "p.zip"]).unwrap();
+        assert_eq!(opts.report_format, ReportFormat::GithubSnapshot);
+        assert_eq!(opts.snapshot.sha.as_deref(), Some("abc"));
+        assert_eq!(opts.zip_path, "p.zip");
+
+        assert_eq!(parse(&["p.zip"]).unwrap().report_format, ReportFormat::Json);
+        assert!(parse(&["--report-format", "xml", "p.zip"]).is_err());
+
+        let opts = parse(&["--report-format", "trivy", "p.zip"]).unwrap();
+        assert_eq!(opts.report_path(), "./output/trivy_report.json");
+        assert!(parse(&["--report-format", "trivy", "--no-scan", "p.zip"]).is_err());
+        assert_eq!(parse(&["--report-format", "junit", "p.zip"]).unwrap().report_path(), "./output/junit_report.xml");
+    }
+
+    #[test]
+    fn test_report_path_defaults_and_stdout() {
+        let opts = parse(&["p.zip"]).unwrap();
+        assert_eq!(opts.report_path(), "./output/vuln_report.json");
+        assert!(!opts.report_to_stdout());
+
+        let opts = parse(&["--report-path", "-", "p.zip"]).unwrap();
+        assert!(opts.report_to_stdout());
+
+        let opts = parse(&["--report-format", "github-snapshot", "p.zip"]).unwrap();
+        assert_eq!(opts.report_path(), "./output/github_snapshot.json");
+    }
+
+    #[test]
+    fn test_relative_paths_resolve_under_output_dir_or_cwd() {
+        let cwd = Path::new("/work/project");
+        let mut opts = parse(&["--output-dir", "build/out", "--vex-output", "./reports/vex.json", "p.zip"]).unwrap();
+        opts.resolve_paths(cwd);
+        assert_eq!(opts.output_dir(), Path::new("/work/project/build/out"));
+        assert_eq!(opts.sbom_path(), "/work/project/build/out/sbom.json");
+        assert_eq!(opts.report_path(), "/work/project/build/out/vuln_report.json");
+        assert_eq!(opts.vex_output.as_deref(), Some("/work/project/reports/vex.json"));
+
+        let mut opts = parse(&["--report-path", "-", "--dot-output", "/tmp/deps.dot", "p.zip"]).unwrap();
+        opts.resolve_paths(cwd);
+        assert!(opts.report_to_stdout());
+        assert_eq!(opts.sbom_path(), "/work/project/output/sbom.json");
+        assert_eq!(opts.dot_output.as_deref(), Some("/tmp/deps.dot"));
+    }
+
+    #[test]
+    fn test_planned_outputs() {
+        let opts = parse(&["--force", "--dot-output", "-", "--sign-key", "k.pem", "--bundle", "out.zip", "p.zip"]).unwrap();
+        assert!(opts.force);
+        let planned: Vec<String> = opts.planned_outputs().iter().map(|p| p.to_string_lossy().to_string()).collect();
+        assert_eq!(
+            planned,
+            [
+                "./output/vuln_report.json",
+                "./output/sbom.json",
+                "out.zip",
+                "./output/manifest.json",
+                "./output/manifest.json.sig"
+            ]
+        );
+
+        let opts = parse(&["--no-scan", "p.zip"]).unwrap();
+        assert!(!opts.force);
+        assert_eq!(opts.planned_outputs(), [PathBuf::from("./output/sbom.json")]);
+        let opts = parse(&["batch", "archives/"]).unwrap();
+        assert_eq!(opts.planned_outputs(), [PathBuf::from("./output/batch_report.json")]);
+    }
+
+    #[test]
+    fn test_json_style_last_wins() {
+        use output::JsonStyle;
+        assert_eq!(parse(&["p.zip"]).unwrap().json_style, JsonStyle::Pretty);
+        assert_eq!(parse(&["--minify", "p.zip"]).unwrap().json_style, JsonStyle::Minified);
+        assert_eq!(parse(&["--minify", "--pretty", "p.zip"]).unwrap().json_style, JsonStyle::Pretty);
+        assert_eq!(parse(&["--pretty", "--canonical-json", "p.zip"]).unwrap().json_style, JsonStyle::Canonical);
+    }
+
+    #[test]
+    fn test_no_license_lookup_conflicts_with_require_licenses() {
+        assert!(parse(&["--no-license-lookup", "p.zip"]).unwrap().no_license_lookup);
+        assert!(parse(&["--no-license-lookup", "--require-licenses", "p.zip"]).is_err());
+        // Licenses then come from the manifests, so --require-licenses still has something to check
+        assert!(parse(&["--no-exec", "--require-licenses", "p.zip"]).unwrap().no_exec);
+        assert!(parse(&["doctor", "--no-exec"]).unwrap().no_exec);
+    }
+
+    #[test]
+    fn test_parse_project_overrides() {
+        let opts = parse(&["--project-name", "acme", "--project-group=com.example", "p.zip"]).unwrap();
+        assert_eq!(opts.project.name.as_deref(), Some("acme"));
+        assert_eq!(opts.project.version, None);
+        assert_eq!(opts.project.group.as_deref(), Some("com.example"));
+        assert!(parse(&["--project-version"]).is_err());
+    }
+
+    #[test]
+    fn test_compress_moves_json_outputs_to_gz() {
+        let opts = parse(&["--compress", "--vex-output", "out/vex.json", "p.zip"]).unwrap();
+        assert_eq!(opts.report_path(), "./output/vuln_report.json.gz");
+        assert_eq!(opts.sbom_path(), "./output/sbom.json.gz");
+        assert_eq!(opts.vex_output.as_deref(), Some("out/vex.json.gz"));
+
+        let opts = parse(&["--compress", "--report-path", "-", "p.zip"]).unwrap();
+        assert!(opts.report_to_stdout());
+        let opts = parse(&["batch", "--compress", "archives/"]).unwrap();
+        assert_eq!(opts.report_path(), "./output/batch_report.json.gz");
+    }
+
+    #[test]
+    fn test_parse_verify_subcommand() {
+        let opts = parse(&["verify", "out", "--public-key", "k.pub"]).unwrap();
+        assert_eq!(
+            opts.command,
+            Command::Verify { dir: "out".to_string(), public_key: "k.pub".to_string() }
+        );
+        assert!(parse(&["verify", "out"]).is_err());
+        // --public-key only makes sense for verify
+        assert!(parse(&["--public-key", "k.pub", "p.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_doctor_subcommand() {
+        assert_eq!(parse(&["doctor"]).unwrap().command, Command::Doctor);
+        assert_eq!(parse(&["check"]).unwrap().command, Command::Doctor);
+        assert_eq!(parse(&["--doctor"]).unwrap().command, Command::Doctor);
+        assert!(parse(&["doctor", "p.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_db_update_subcommand() {
+        assert_eq!(parse(&["db", "update"]).unwrap().command, Command::DbUpdate);
+        let opts = parse(&["db", "update", "--db-url", "https://mirror.example/advisory-db.git"]).unwrap();
+        assert_eq!(opts.command, Command::DbUpdate);
+        assert_eq!(opts.advisory_db_url.as_deref(), Some("https://mirror.example/advisory-db.git"));
+        assert!(parse(&["db", "update", "--offline"]).is_err());
+        assert!(parse(&["db"]).is_err());
+        assert!(parse(&["db", "update", "p.zip"]).is_err());
+        assert!(parse(&["--offline", "p.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_serve_subcommand() {
+        assert_eq!(parse(&["serve"]).unwrap().command, Command::Serve { listen: DEFAULT_LISTEN.to_string(), jobs: None });
+        assert_eq!(
+            parse(&["serve", "--listen", "0.0.0.0:9000", "--jobs", "2"]).unwrap().command,
+            Command::Serve { listen: "0.0.0.0:9000".to_string(), jobs: Some(2) }
+        );
+        assert!(parse(&["serve", "p.zip"]).is_err());
+        assert!(parse(&["serve", "--fail-on", "high"]).is_err());
+        assert!(parse(&["serve", "--min-cvss", "7"]).is_err());
+        assert!(parse(&["serve", "--baseline", "old.json"]).is_err());
+        assert!(parse(&["serve", "--fail-on-suspicious"]).is_err());
+        assert!(parse(&["serve", "--interactive"]).is_err());
+        assert!(parse(&["serve", "--config", "rustpj.toml", "--description", "full"]).is_ok());
+        assert!(parse(&["--listen", "0.0.0.0:9000", "p.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_query_subcommand() {
+        let opts = parse(&["query", "tokio", "1.18.2", "--json", "--target", "x86_64-unknown-linux-gnu"]).unwrap();
+        assert_eq!(
+            opts.command,
+            Command::Query { name: "tokio".to_string(), version: semver::Version::new(1, 18, 2), json: true }
+        );
+        assert_eq!(opts.target.as_deref(), Some("x86_64-unknown-linux-gnu"));
+        assert!(parse(&["query", "tokio"]).is_err());
+        assert!(parse(&["query", "tokio", "one"]).is_err());
+        assert!(parse(&["--json", "p.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_why_subcommand() {
+        let opts = parse(&["why", "time@0.1.45", "proj.zip", "--json"]).unwrap();
+        assert_eq!(
+            opts.command,
+            Command::Why { package: "time@0.1.45".parse().unwrap(), input: "proj.zip".to_string(), json: true }
+        );
+        assert!(parse(&["why", "time"]).is_err());
+        assert!(parse(&["why", "time@x", "proj.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_batch_subcommand() {
+        let opts = parse(&["batch", "archives/", "extra.zip", "--jobs", "4", "--fail-on", "high"]).unwrap();
+        assert_eq!(
+            opts.command,
+            Command::Batch { inputs: vec!["archives/".to_string(), "extra.zip".to_string()], jobs: Some(4) }
+        );
+        assert_eq!(opts.fail_on, Some(Severity::High));
+        let opts = parse(&["batch", "--check-staleness", "--crates-snapshot", "index/", "archives/"]).unwrap();
+        assert_eq!(opts.crates_snapshot.as_deref(), Some("index/"));
+        assert!(parse(&["batch"]).is_err());
+        assert!(parse(&["batch", "a/", "--jobs", "0"]).is_err());
+        assert!(parse(&["batch", "a/", "--vex-output", "v.json"]).is_err());
+        assert!(parse(&["batch", "a/", "--output-url", "s3://scans/"]).is_err());
+        assert!(parse(&["batch", "a/", "--fail-on-suspicious"]).is_err());
+        assert!(parse(&["batch", "a/", "--popular-crates", "popular.txt"]).is_err());
+        assert!(parse(&["batch", "a/", "--require-licenses"]).is_err());
+        assert!(parse(&["batch", "a/", "--cpe-mapping", "cpe.toml"]).is_err());
+        assert!(parse(&["batch", "a/", "--project-name", "app"]).is_err());
+        assert!(parse(&["batch", "a/", "--project-version", "1.0.0"]).is_err());
+        assert!(parse(&["batch", "a/", "--project-group", "com.example"]).is_err());
+        assert!(parse(&["batch", "a/", "--interactive"]).is_err());
+        assert!(parse(&["batch", "a/", "--interactive", "--triage-file", "ci/triage.toml"]).is_err());
+        assert!(parse(&["--jobs", "2", "p.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_input() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["a.zip", "b.zip"]).is_err());
+        assert!(parse(&["--bogus", "a.zip"]).is_err());
+        assert!(parse(&["a.zip", "--github-sha"]).is_err());
+        assert!(parse(&["--fail-on", "severe", "a.zip"]).is_err());
+        assert!(parse(&["--no-sbom", "--no-scan", "a.zip"]).is_err());
+        assert!(parse(&["--target", "x86_64-unknown-nowhere", "a.zip"]).is_err());
+        assert!(parse(&["--no-scan", "--fail-on", "high", "a.zip"]).is_err());
+        assert!(parse(&["--no-sbom", "--vex-output", "vex.json", "a.zip"]).is_err());
+        assert!(parse(&["--report-path", "-", "--dot-output", "-", "a.zip"]).is_err());
+        assert!(parse(&["--with-deps", "a.zip"]).is_err());
+        assert!(parse(&["--online", "--no-scan", "a.zip"]).is_err());
+        assert!(parse(&["https://ci.example.com/a.zip"]).is_err());
+        assert!(parse(&["--online", "--no-scan", "https://ci.example.com/a.zip"]).is_ok());
+        assert!(parse(&["--check-staleness", "a.zip"]).is_err());
+        assert!(parse(&["--crates-snapshot", "index", "a.zip"]).is_err());
+        assert!(parse(&["--check-staleness", "--online", "--no-scan", "a.zip"]).is_err());
+        assert!(parse(&["--osv-scores", "ghsa", "--no-scan", "a.zip"]).is_err());
+        assert!(parse(&["--baseline", "old.json", "--no-scan", "a.zip"]).is_err());
+        assert!(parse(&["--timeout", "0", "a.zip"]).is_err());
+        assert!(parse(&["--timeout", "1.5", "a.zip"]).is_err());
+        assert!(parse(&["--package", "openssl@latest", "a.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_package_filters() {
+        let opts = parse(&["--package", "openssl", "--package=time@0.1.45", "--with-deps", "a.zip"]).unwrap();
+        let selection = opts.package_selection().unwrap();
+        assert_eq!(selection.to_string(), "openssl, time@0.1.45 (with dependencies)");
+        assert!(parse(&["a.zip"]).unwrap().package_selection().is_none());
+
+        let opts = parse(&["--exclude-package", "openssl@0.10.55", "--exclude-reason", "patched fork", "a.zip"]).unwrap();
+        let configured = [PackageExclusion { package: "time".parse().unwrap(), reason: None }];
+        let exclusions = opts.exclusions(&configured);
+        assert_eq!(exclusions[0].package.to_string(), "openssl@0.10.55");
+        assert_eq!(exclusions[0].reason.as_deref(), Some("patched fork"));
+        assert_eq!(exclusions[1], configured[0]);
+        assert!(parse(&["--exclude-reason", "fork", "a.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_merge_sbom() {
+        let opts = parse(&["--merge-sbom", "./output/sbom.json", "--merge-conflicts", "warn", "a.zip"]).unwrap();
+        assert_eq!(opts.merge_conflicts, Some(MergeConflicts::Warn));
+        assert!(opts.merges_sbom_in_place());
+        assert!(!parse(&["--merge-sbom", "base.json", "a.zip"]).unwrap().merges_sbom_in_place());
+        assert!(parse(&["--merge-conflicts", "warn", "a.zip"]).is_err());
+        assert!(parse(&["--merge-sbom", "base.json", "--merge-conflicts", "ignore", "a.zip"]).is_err());
+        assert!(parse(&["--merge-sbom", "base.json", "--no-sbom", "a.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_sbom_format() {
+        assert_eq!(parse(&["--sbom-format", "protobuf", "a.zip"]).unwrap().sbom_path(), "./output/sbom.cdx.bin");
+        let opts = parse(&["--sbom-format", "protobuf", "--compress", "a.zip"]).unwrap();
+        assert_eq!(opts.sbom_path(), "./output/sbom.cdx.bin.gz");
+        assert!(parse(&["--sbom-format", "xml", "a.zip"]).is_err());
+        assert!(parse(&["--sbom-format", "protobuf", "--merge-sbom", "base.json", "a.zip"]).is_err());
+        assert!(parse(&["batch", "--sbom-format", "protobuf", "archives/"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_description_mode() {
+        // Unset, so the config's `[output]` or the default (truncate:500) applies
+        assert_eq!(parse(&["a.zip"]).unwrap().description, None);
+        assert_eq!(parse(&["--description", "truncate:80", "a.zip"]).unwrap().description, Some(DescriptionMode::Truncate(80)));
+        assert_eq!(parse(&["--description=omit", "a.zip"]).unwrap().description, Some(DescriptionMode::Omit));
+        assert!(parse(&["--description", "short", "a.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_category_filters() {
+        let opts = parse(&["--only-category", "memory-corruption", "--exclude-category=denial-of-service", "a.zip"]).unwrap();
+        assert_eq!(opts.categories.only, ["memory-corruption"]);
+        assert_eq!(opts.categories.exclude, ["denial-of-service"]);
+        assert!(parse(&["--exclude-category", "denial-of-servce", "a.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_collections() {
+        assert!(parse(&["a.zip"]).unwrap().collections.is_empty());
+        let opts = parse(&["--collection", "rust", "--collection=all", "--toolchain", "1.75.0", "a.zip"]).unwrap();
+        assert_eq!(opts.collections, [Collection::Rust, Collection::Crates]);
+        assert_eq!(opts.toolchain, Some(semver::Version::new(1, 75, 0)));
+        assert!(parse(&["--collection", "toolchain", "a.zip"]).is_err());
+        assert!(parse(&["--toolchain", "stable", "a.zip"]).is_err());
+        // --toolchain alone adds the rust collection next to the default one
+        assert_eq!(parse(&["--toolchain", "1.75.0", "a.zip"]).unwrap().collections, [Collection::Crates, Collection::Rust]);
+        assert!(parse(&["--collection", "crates", "--toolchain", "1.75.0", "a.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_db_paths() {
+        assert!(parse(&["a.zip"]).unwrap().db_paths.is_empty());
+        let opts = parse(&["--db-path", "data/advisory-db", "--db-path=data/internal-db", "a.zip"]).unwrap();
+        assert_eq!(opts.db_paths, ["data/advisory-db", "data/internal-db"]);
+        assert!(parse(&["--db-path", "data/advisory-db", "--db-rev", "abc123", "a.zip"]).is_ok());
+        assert!(parse(&["--db-path", "a", "--db-path", "b", "--db-rev", "abc123", "a.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_fail_on_threshold() {
+        assert_eq!(parse(&["--fail-on", "HIGH", "a.zip"]).unwrap().fail_on, Some(Severity::High));
+        // `any` is the lowest threshold, so findings without a CVSS score also count
+        assert_eq!(parse(&["--fail-on=any", "a.zip"]).unwrap().fail_on, Some(Severity::Unknown));
+        assert_eq!(parse(&["--timeout", "300", "a.zip"]).unwrap().timeout, Some(Duration::from_secs(300)));
+        assert_eq!(parse(&["--progress-format", "ndjson", "a.zip"]).unwrap().progress_format, ProgressFormat::Ndjson);
+        assert!(parse(&["--progress-format", "json", "a.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_min_cvss() {
+        let opts = parse(&["--min-cvss", "7", "--fail-on-unscored", "a.zip"]).unwrap();
+        assert_eq!((opts.min_cvss, opts.fail_on_unscored), (Some(7.0), true));
+        assert!(parse(&["--min-cvss", "10.5", "a.zip"]).is_err());
+        assert!(parse(&["--min-cvss", "high", "a.zip"]).is_err());
+        assert!(parse(&["--fail-on-unscored", "a.zip"]).is_err());
+        assert!(parse(&["--no-scan", "--min-cvss", "7.0", "a.zip"]).is_err());
+    }
+
+    #[test]
+    fn test_config_defaults_yield_to_flags() {
+        let config = ScanConfig::parse(
+            "[thresholds]\nfail_on = \"high\"\nmin_cvss = 7.0\n\n[output]\ndescription = \"omit\"\ncanonical = true\n",
+        )
+        .unwrap();
+        let opts = parse(&["a.zip"]).unwrap().with_config_defaults(&config).unwrap();
+        assert_eq!((opts.fail_on, opts.min_cvss, opts.description), (Some(Severity::High), Some(7.0), Some(DescriptionMode::Omit)));
+        assert!(opts.canonical && !opts.fail_on_unscored);
+
+        let opts = parse(&["--fail-on", "critical", "--min-cvss", "9", "--description", "full", "a.zip"]).unwrap();
+        let opts = opts.with_config_defaults(&config).unwrap();
+        assert_eq!((opts.fail_on, opts.min_cvss, opts.description), (Some(Severity::Critical), Some(9.0), Some(DescriptionMode::Full)));
+
+        // Checked like the flags once merged
+        let unscored = ScanConfig::parse("[thresholds]\nfail_on_unscored = true\n").unwrap();
+        let error = parse(&["a.zip"]).unwrap().with_config_defaults(&unscored).unwrap_err();
+        assert!(format!("{:#}", error).contains("--fail-on-unscored only applies to --min-cvss"));
+        assert!(parse(&["--min-cvss", "7", "a.zip"]).unwrap().with_config_defaults(&unscored).unwrap().fail_on_unscored);
+    }
+
+    #[test]
+    fn test_parse_interactive() {
+        let opts = parse(&["--interactive", "--triage-file", "ci/triage.toml", "a.zip"]).unwrap();
+        assert!(opts.interactive);
+        assert_eq!(opts.triage_file.as_deref(), Some("ci/triage.toml"));
+        assert!(parse(&["--triage-file", "t.toml", "a.zip"]).is_err());
+        assert!(parse(&["--no-scan", "--interactive", "a.zip"]).is_err());
+    }
+}
@@ -0,0 +1,530 @@
+use crate::description::DescriptionMode;
+use crate::get_sbom::ComponentType;
+use crate::online::OnlineConfig;
+use crate::package_filter::PackageExclusion;
+use crate::policy::{PolicyRule, SeverityGate};
+use crate::registry::RegistryMap;
+use crate::severity::Severity;
+use crate::staleness::StalenessConfig;
+use std::collections::BTreeMap;
+use crate::scanner::{RiskWeights, VulnReport};
+use crate::vex::{Analysis, AnalysisState, Justification};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Triage / ignore configuration, loaded from a TOML file passed with `--config`:
+//
+//     [[ignore]]
+//     id = "RUSTSEC-2020-0071"
+//     package = "time"                      # optional, restricts the entry to one crate
+//     state = "not_affected"                # optional, defaults to not_affected
+//     justification = "code_not_reachable"
+//     detail = "we never call localtime_r"
+//
+//     [severity_overrides]                  # optional, re-rate advisories for our context
+//     "RUSTSEC-2020-0071" = "critical"
+//
+//     [risk_weights]                        # optional, weights for summary.risk_score
+//     critical = 10
+//     high = 5
+//     medium = 2
+//     low = 1
+//     unknown = 0
+//
+//     [component_types]                     # optional, CycloneDX type per crate in the SBOM
+//     "serde_derive" = "framework"
+//
+//     [[policy]]                            # optional, declarative rules, see `policy.rs`
+//     name = "no-criticals"
+//     when = "severity_count"
+//     severity = "critical"
+//     action = "fail"
+//
+//     [gate]                                # optional, action per severity, see `policy.rs`
+//     high = "fail"
+//     medium = "warn"
+//
+//     [staleness]                           # optional, --check-staleness, see `staleness.rs`
+//     max_age_days = 730
+//
+//     [[exclude]]                           # optional, see `package_filter.rs`
+//     package = "openssl@0.10.55"
+//     reason = "patched fork"
+//
+//     [registries."sparse+https://crates.internal.example/index/"]   # optional, see `registry.rs`
+//     name = "internal"
+//     advisories = false
+//
+//     [thresholds]                          # optional, defaults for --fail-on / --min-cvss
+//     fail_on = "high"
+//     min_cvss = 7.0
+//     fail_on_unscored = false
+//
+//     [output]                              # optional, defaults for --description / --canonical
+//     description = "omit"
+//     canonical = true
+//
+// A project can also carry its own config as `rustpj.toml` next to its root Cargo.lock
+// (PROJECT_CONFIG). It is found after extraction and merged under --config: the policy
+// rules of both apply, --config wins wherever only one value can, and command-line flags
+// override both. The archive is the input being checked, so it never redirects network
+// access or caches (`[online]`, `[staleness]`). Anything that can turn a failing verdict
+// into a passing one (ignores, exclusions, severity overrides, the gate and thresholds, or
+// `advisories = false` for a registry) is refused as well, unless the caller vouches for
+// the archive with `--trust-project-config`, e.g. when scanning its own repository in CI.
+// `--no-project-config` ignores the file altogether.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScanConfig {
+    #[serde(default)]
+    pub ignore: Vec<IgnoreEntry>,
+    #[serde(default)]
+    pub risk_weights: Option<RiskWeights>,
+    // advisory id -> 严重程度，覆盖上游评级
+    #[serde(default)]
+    pub severity_overrides: BTreeMap<String, Severity>,
+    // 在线操作的端点和 User-Agent，见 `online.rs`
+    #[serde(default)]
+    pub online: OnlineConfig,
+    #[serde(default)]
+    pub policy: Vec<PolicyRule>,
+    // 严重程度 -> fail / warn / ignore；命令行给出 --fail-on 时不使用
+    #[serde(default)]
+    pub gate: Option<SeverityGate>,
+    // crate 名 -> SBOM 组件类型，优先于自动推断
+    #[serde(default)]
+    pub component_types: BTreeMap<String, ComponentType>,
+    // --check-staleness 的阈值与缓存
+    #[serde(default)]
+    pub staleness: StalenessConfig,
+    // 从 lockfile 中去掉的包，与 --exclude-package 合并使用
+    #[serde(default)]
+    pub exclude: Vec<PackageExclusion>,
+    // 私有 registry 的名称、purl repository_url 与是否匹配 advisory
+    #[serde(default)]
+    pub registries: RegistryMap,
+    // 命令行未给出 --fail-on / --min-cvss 时使用的阈值
+    #[serde(default)]
+    pub thresholds: Thresholds,
+    // 命令行未给出时使用的输出选项
+    #[serde(default)]
+    pub output: OutputPreferences,
+}
+
+/// 项目自带的配置文件名，位于根 Cargo.lock 所在目录
+pub const PROJECT_CONFIG: &str = "rustpj.toml";
+
+// Sections a project config may never set, see the header
+const PROJECT_FORBIDDEN: [&str; 2] = ["online", "staleness"];
+
+// Sections a project config may set only under --trust-project-config
+const PROJECT_TRUSTED_ONLY: [&str; 5] = ["ignore", "exclude", "severity_overrides", "gate", "thresholds"];
+
+/// 配置文件 `[thresholds]`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Thresholds {
+    pub fail_on: Option<Severity>,
+    pub min_cvss: Option<f64>,
+    pub fail_on_unscored: Option<bool>,
+}
+
+/// 配置文件 `[output]`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputPreferences {
+    // 与 --description 的取值相同，在 `parse` 中校验
+    pub description: Option<String>,
+    pub canonical: Option<bool>,
+}
+
+impl OutputPreferences {
+    pub fn description(&self) -> Option<DescriptionMode> {
+        self.description.as_deref().and_then(|d| d.parse().ok())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct IgnoreEntry {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<AnalysisState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub justification: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl IgnoreEntry {
+    fn analysis(&self) -> Result<Analysis> {
+        let justification = self
+            .justification
+            .as_deref()
+            .map(str::parse::<Justification>)
+            .transpose()
+            .with_context(|| format!("invalid ignore entry for {}", self.id))?;
+        Ok(Analysis {
+            state: self.state.unwrap_or(AnalysisState::NotAffected),
+            justification,
+            detail: self.detail.clone(),
+        })
+    }
+
+    fn matches(&self, id: &str, package: &str) -> bool {
+        self.id == id && self.package.as_deref().is_none_or(|p| p == package)
+    }
+}
+
+/// 该漏洞是否会被这些 `[[ignore]]` 条目屏蔽（与 `apply_triage` 的匹配相同）
+pub fn suppressed_by(ignore: &[IgnoreEntry], id: &str, package: &str) -> bool {
+    ignore
+        .iter()
+        .find(|e| e.matches(id, package))
+        .and_then(|e| e.analysis().ok())
+        .is_some_and(|a| a.state.suppresses())
+}
+
+impl ScanConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file: {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("invalid config file: {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let config: ScanConfig = toml::from_str(text)?;
+        // Validate justifications up front rather than on first match
+        for entry in &config.ignore {
+            entry.analysis()?;
+        }
+        config.online.validate()?;
+        if let Some(min_cvss) = config.thresholds.min_cvss
+            && !(0.0..=10.0).contains(&min_cvss)
+        {
+            anyhow::bail!("thresholds.min_cvss must be between 0.0 and 10.0, got {}", min_cvss);
+        }
+        if let Some(description) = &config.output.description {
+            description.parse::<DescriptionMode>().context("invalid output.description")?;
+        }
+        Ok(config)
+    }
+
+    /// 读取 root 下的项目配置（PROJECT_CONFIG）并合并到本配置之下，返回其路径；没有该文件时为 None。
+    /// trusted 为 `--trust-project-config`，允许其中的 ignore、gate、thresholds 等设置
+    pub fn merge_project_config(&mut self, root: &Path, trusted: bool) -> Result<Option<PathBuf>> {
+        let path = root.join(PROJECT_CONFIG);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path).with_context(|| format!("failed to read project config: {}", path.display()))?;
+        let project = Self::parse_project(&text, trusted).with_context(|| format!("invalid project config: {}", path.display()))?;
+        self.merge_under(project);
+        Ok(Some(path))
+    }
+
+    fn parse_project(text: &str, trusted: bool) -> Result<Self> {
+        let table: toml::Table = toml::from_str(text)?;
+        if let Some(section) = PROJECT_FORBIDDEN.iter().find(|s| table.contains_key(**s)) {
+            anyhow::bail!("[{}] can only be set in the --config file, not in {}", section, PROJECT_CONFIG);
+        }
+        if !trusted && let Some(section) = PROJECT_TRUSTED_ONLY.iter().find(|s| table.contains_key(**s)) {
+            anyhow::bail!(
+                "[{}] in {} can make a failing scan pass; set it in the --config file, or pass --trust-project-config",
+                section,
+                PROJECT_CONFIG
+            );
+        }
+        let config = Self::parse(text)?;
+        if !trusted && config.registries.skips_any() {
+            anyhow::bail!(
+                "[registries] advisories = false in {} can make a failing scan pass; set it in the --config file, or pass --trust-project-config",
+                PROJECT_CONFIG
+            );
+        }
+        Ok(config)
+    }
+
+    // `self` (--config) wins over `project` wherever the two cannot both apply; without
+    // --trust-project-config the sections `parse_project` refuses are empty in `project`
+    fn merge_under(&mut self, project: ScanConfig) {
+        for (name, kind) in project.component_types {
+            self.component_types.entry(name).or_insert(kind);
+        }
+        self.risk_weights = self.risk_weights.take().or(project.risk_weights);
+        self.policy.extend(project.policy);
+        self.registries.merge_under(project.registries);
+        // The first matching ignore entry decides
+        self.ignore.extend(project.ignore);
+        for (id, severity) in project.severity_overrides {
+            self.severity_overrides.entry(id).or_insert(severity);
+        }
+        self.gate = self.gate.take().or(project.gate);
+        self.exclude.extend(project.exclude);
+        let thresholds = &mut self.thresholds;
+        thresholds.fail_on = thresholds.fail_on.or(project.thresholds.fail_on);
+        thresholds.min_cvss = thresholds.min_cvss.or(project.thresholds.min_cvss);
+        thresholds.fail_on_unscored = thresholds.fail_on_unscored.or(project.thresholds.fail_on_unscored);
+        self.output.description = self.output.description.take().or(project.output.description);
+        self.output.canonical = self.output.canonical.or(project.output.canonical);
+    }
+
+    /// 把分诊结论写入报告中对应的漏洞；未分诊的漏洞保持 analysis 为空（即 in_triage）
+    pub fn apply_triage(&self, report: &mut VulnReport) {
+        for pkg in &mut report.packages {
+            for finding in &mut pkg.advisories {
+                if let Some(entry) = self.ignore.iter().find(|e| e.matches(&finding.id, &pkg.package_name)) {
+                    // Already validated in `parse`
+                    finding.analysis = entry.analysis().ok();
+                }
+            }
+        }
+        if let Some(weights) = &self.risk_weights {
+            report.summary.risk_score = weights.score(&report.summary.by_severity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{AdvisoryFinding, PackageReport, SeverityCounts};
+    use crate::output::Stamp;
+    use crate::vex;
+
+    fn finding(id: &str, severity: Severity) -> AdvisoryFinding {
+        AdvisoryFinding {
+            id: id.to_string(),
+            severity,
+            patched_versions: Some(">=2.0.0".to_string()),
+            patched: vec![">=2.0.0".to_string()],
+            remediation: "Upgrade to >=2.0.0".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn report() -> VulnReport {
+        VulnReport {
+            total_packages: 2,
+            packages: vec![
+                PackageReport {
+                    package_name: "time".to_string(),
+                    package_version: "0.1.0".to_string(),
+                    max_severity: Severity::Critical,
+                    advisories: vec![finding("RUSTSEC-0000-0001", Severity::Critical)],
+                    ..Default::default()
+                },
+                PackageReport {
+                    package_name: "zip".to_string(),
+                    package_version: "0.6.0".to_string(),
+                    max_severity: Severity::Low,
+                    advisories: vec![finding("RUSTSEC-0000-0002", Severity::Low)],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    const CONFIG: &str = r#"
+[[ignore]]
+id = "RUSTSEC-0000-0001"
+package = "time"
+justification = "vulnerable_code_not_in_execute_path"
+detail = "never called"
+"#;
+
+    #[test]
+    fn test_triaged_finding_round_trips_through_vex() {
+        let config = ScanConfig::parse(CONFIG).unwrap();
+        let mut report = report();
+        config.apply_triage(&mut report);
+
+        let doc = vex::build_vex(&report, "urn:uuid:11111111-2222-3333-4444-555555555555", &Stamp::now());
+        let json = serde_json::to_string(&doc).unwrap();
+        let parsed: vex::VexDocument = serde_json::from_str(&json).unwrap();
+
+        let triaged = parsed.vulnerabilities.iter().find(|v| v.id == "RUSTSEC-0000-0001").unwrap();
+        assert_eq!(triaged.analysis.state, AnalysisState::NotAffected);
+        assert_eq!(triaged.analysis.justification, Some(Justification::CodeNotReachable));
+        assert_eq!(triaged.analysis.detail.as_deref(), Some("never called"));
+        assert_eq!(
+            triaged.affects[0].reference,
+            "urn:cdx:11111111-2222-3333-4444-555555555555/1#time@0.1.0"
+        );
+
+        let untriaged = parsed.vulnerabilities.iter().find(|v| v.id == "RUSTSEC-0000-0002").unwrap();
+        assert_eq!(untriaged.analysis.state, AnalysisState::InTriage);
+    }
+
+    #[test]
+    fn test_triaged_finding_excluded_from_exit_code() {
+        let mut report = report();
+        assert!(report.has_actionable_at_or_above(Severity::Critical));
+
+        ScanConfig::parse(CONFIG).unwrap().apply_triage(&mut report);
+        assert!(!report.has_actionable_at_or_above(Severity::Critical));
+        // The untriaged low finding still counts
+        assert!(report.has_actionable_at_or_above(Severity::Low));
+    }
+
+    #[test]
+    fn test_custom_risk_weights() {
+        let mut report = report();
+        report.summary.by_severity = SeverityCounts { critical: 1, low: 1, ..Default::default() };
+        report.summary.risk_score = RiskWeights::default().score(&report.summary.by_severity);
+        assert_eq!(report.summary.risk_score, 11);
+
+        ScanConfig::parse("[risk_weights]\ncritical = 100\n").unwrap().apply_triage(&mut report);
+        // Unspecified weights keep their defaults
+        assert_eq!(report.summary.risk_score, 101);
+    }
+
+    #[test]
+    fn test_package_scoped_entry_and_bad_justification() {
+        let config = ScanConfig::parse("[[ignore]]\nid = \"RUSTSEC-0000-0002\"\npackage = \"other\"\n").unwrap();
+        let mut report = report();
+        config.apply_triage(&mut report);
+        assert!(report.packages[1].advisories[0].analysis.is_none());
+
+        assert!(ScanConfig::parse("[[ignore]]\nid = \"X\"\njustification = \"because\"\n").is_err());
+    }
+
+    #[test]
+    fn test_component_types() {
+        let config = ScanConfig::parse("[component_types]\nserde_derive = \"framework\"\nmy-tool = \"application\"\n").unwrap();
+        assert_eq!(config.component_types["serde_derive"], ComponentType::Framework);
+        assert_eq!(config.component_types["my-tool"], ComponentType::Application);
+        assert!(ScanConfig::parse("[component_types]\nserde_derive = \"plugin\"\n").is_err());
+    }
+
+    #[test]
+    fn test_project_config_merges_under_the_explicit_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = ScanConfig::parse(
+            r#"
+[component_types]
+serde_derive = "framework"
+
+[output]
+canonical = false
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.merge_project_config(dir.path(), false).unwrap(), None);
+
+        fs::write(
+            dir.path().join(PROJECT_CONFIG),
+            r#"
+[component_types]
+serde_derive = "library"
+demo-cli = "application"
+
+[[policy]]
+name = "no-criticals"
+when = "severity_count"
+severity = "critical"
+action = "fail"
+
+[output]
+description = "omit"
+canonical = true
+"#,
+        )
+        .unwrap();
+        let path = config.merge_project_config(dir.path(), false).unwrap();
+        assert_eq!(path, Some(dir.path().join(PROJECT_CONFIG)));
+        // --config wins where only one value can apply; the project fills in the rest
+        assert_eq!(config.component_types["serde_derive"], ComponentType::Framework);
+        assert_eq!(config.component_types["demo-cli"], ComponentType::Application);
+        assert_eq!(config.output, OutputPreferences { description: Some("omit".to_string()), canonical: Some(false) });
+        assert_eq!(config.policy.len(), 1);
+    }
+
+    #[test]
+    fn test_project_config_errors_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = |text: &str| {
+            fs::write(dir.path().join(PROJECT_CONFIG), text).unwrap();
+            format!("{:#}", ScanConfig::default().merge_project_config(dir.path(), false).unwrap_err())
+        };
+        assert!(error("[online]\nuser_agent = \"x\"\n").contains("[online] can only be set in the --config file"));
+        // The archive must not be able to waive its own findings
+        for waiver in [
+            "[[ignore]]\nid = \"RUSTSEC-0000-0001\"\n",
+            "[[exclude]]\npackage = \"openssl\"\n",
+            "[severity_overrides]\n\"RUSTSEC-0000-0001\" = \"low\"\n",
+            "[gate]\ncritical = \"ignore\"\n",
+            "[thresholds]\nmin_cvss = 9.9\n",
+        ] {
+            assert!(error(waiver).contains("pass --trust-project-config"), "{}", waiver);
+        }
+        let registry = "[registries.\"sparse+https://crates.internal.example/index/\"]\nname = \"internal\"\n";
+        assert!(error(&format!("{}advisories = false\n", registry)).contains("advisories = false in rustpj.toml can make"));
+        fs::write(dir.path().join(PROJECT_CONFIG), registry).unwrap();
+        assert!(ScanConfig::default().merge_project_config(dir.path(), false).is_ok());
+        assert!(error("[output]\ndescription = \"short\"\n").contains("invalid output.description"));
+        let typo = error("[output]\ncanonicle = true\n");
+        assert!(typo.contains("invalid project config") && typo.contains("canonicle"));
+    }
+
+    #[test]
+    fn test_trusted_project_config_can_waive_findings() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(PROJECT_CONFIG),
+            r#"
+[[ignore]]
+id = "RUSTSEC-0000-0001"
+detail = "not reachable from the CLI"
+
+[[exclude]]
+package = "openssl"
+
+[severity_overrides]
+"RUSTSEC-0000-0001" = "low"
+"RUSTSEC-0000-0002" = "low"
+
+[thresholds]
+fail_on = "critical"
+min_cvss = 9.0
+
+[registries."sparse+https://crates.internal.example/index/"]
+name = "internal"
+advisories = false
+"#,
+        )
+        .unwrap();
+        let mut config = ScanConfig::parse(
+            r#"
+[[ignore]]
+id = "RUSTSEC-0000-0003"
+
+[severity_overrides]
+"RUSTSEC-0000-0001" = "critical"
+
+[thresholds]
+fail_on = "high"
+"#,
+        )
+        .unwrap();
+        config.merge_project_config(dir.path(), true).unwrap();
+        // --config entries come first and win; the project adds its own
+        let ids: Vec<&str> = config.ignore.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["RUSTSEC-0000-0003", "RUSTSEC-0000-0001"]);
+        assert_eq!(config.exclude.len(), 1);
+        assert_eq!(config.severity_overrides["RUSTSEC-0000-0001"], Severity::Critical);
+        assert_eq!(config.severity_overrides["RUSTSEC-0000-0002"], Severity::Low);
+        assert_eq!(config.thresholds.fail_on, Some(Severity::High));
+        assert_eq!(config.thresholds.min_cvss, Some(9.0));
+        assert!(config.registries.skips_any());
+        // [online] stays off limits even for a trusted archive
+        fs::write(dir.path().join(PROJECT_CONFIG), "[online]\nuser_agent = \"x\"\n").unwrap();
+        assert!(ScanConfig::default().merge_project_config(dir.path(), true).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::severity::Severity;
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// When the report is streamed to stdout (`--report-path -`), stdout must carry nothing
+// but JSON, so every human-readable line is redirected to stderr instead.
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+// Everything printed through `status!` (plus captured cargo output) is also kept here so
+// that it can be shipped as a run log, e.g. inside `--bundle`.
+static RUN_LOG: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+// Things that degraded the run without failing it (no license data, skipped ZIP entries,
+// ...). They are printed as they happen and also end up in the report's `warnings` and
+// the SBOM metadata, so consumers don't have to scrape stderr.
+static WARNINGS: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+
+// `--quiet`: status lines are still kept for the run log, just not printed
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// A long-lived process (`serve`) prints status lines and warnings but must not keep
+// accumulating them for a run log or report it will never write.
+static RETAIN: AtomicBool = AtomicBool::new(true);
+
+/// 运行中出现、但不至于失败的问题；code 是稳定的机器可读标识
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Warning {
+    pub code: String,
+    pub message: String,
+}
+
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::SeqCst);
+}
+
+pub fn stdout_reserved() -> bool {
+    STDOUT_RESERVED.load(Ordering::SeqCst)
+}
+
+pub fn set_quiet() {
+    QUIET.store(true, Ordering::SeqCst);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::SeqCst)
+}
+
+/// 停止收集运行日志和警告（仍然打印）
+pub fn stop_retaining() {
+    RETAIN.store(false, Ordering::SeqCst);
+}
+
+pub fn record(line: &str) {
+    if !RETAIN.load(Ordering::SeqCst) {
+        return;
+    }
+    if let Ok(mut log) = RUN_LOG.lock() {
+        log.push(strip_ansi(line));
+    }
+}
+
+// Colors only make sense on an interactive terminal; NO_COLOR (no-color.org) disables them
+fn color_enabled() -> bool {
+    !stdout_reserved() && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
+
+/// 按严重程度为终端输出着色
+pub fn paint(severity: Severity, text: &str) -> String {
+    if color_enabled() {
+        format!("{}{}\x1b[0m", severity.color(), text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip a CSI sequence up to its final byte
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// 输出并收集一条警告
+pub fn warn(code: &str, message: impl Into<String>) {
+    let warning = Warning { code: code.to_string(), message: message.into() };
+    let line = format!("Warning: {}", warning.message);
+    record(&line);
+    if crate::progress::stderr_takes_events() {
+        crate::progress::emit(crate::progress::ProgressEvent::Warning {
+            code: warning.code.clone(),
+            message: warning.message.clone(),
+        });
+    } else {
+        eprintln!("{}", line);
+    }
+    if !RETAIN.load(Ordering::SeqCst) {
+        return;
+    }
+    if let Ok(mut warnings) = WARNINGS.lock() {
+        warnings.push(warning);
+    }
+}
+
+/// 到目前为止收集到的警告
+pub fn warnings() -> Vec<Warning> {
+    WARNINGS.lock().map(|w| w.clone()).unwrap_or_default()
+}
+
+pub fn run_log() -> String {
+    RUN_LOG.lock().map(|log| log.join("\n")).unwrap_or_default()
+}
+
+/// 写到 stderr 的文字；`--progress-format ndjson` 时改为 message 事件
+pub fn eprint_line(line: &str) {
+    if crate::progress::stderr_takes_events() {
+        crate::progress::emit(crate::progress::ProgressEvent::Message { text: strip_ansi(line) });
+    } else {
+        eprintln!("{}", line);
+    }
+}
+
+/// 输出进度信息：默认写到 stdout，若 stdout 被报告占用则写到 stderr；--quiet 时只记录不输出
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        $crate::console::record(&line);
+        if $crate::console::quiet() {
+        } else if $crate::console::stdout_reserved() {
+            $crate::console::eprint_line(&line);
+        } else {
+            println!("{}", line);
+        }
+    }};
+}
@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use cargo_lock::Package;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// CPE 2.3 identifiers for SBOM components, for platforms that correlate on CPE rather
+// than purl. A crate only gets a CPE when a mapping names its NVD vendor and product;
+// guessing them from the crate name would produce identifiers that match the wrong
+// product or nothing at all, so unmapped crates are left without one.
+// https://nvlpubs.nist.gov/nistpubs/Legacy/IR/nistir7695.pdf
+
+const EMBEDDED_MAPPINGS: &str = include_str!("cpe_mappings.toml");
+
+/// 一个 crate 对应的 CPE vendor / product
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CpeMapping {
+    pub vendor: String,
+    pub product: String,
+    // 版本号取 crate 版本的构建元数据（捆绑的 C 库版本）
+    #[serde(default)]
+    pub upstream_version: bool,
+}
+
+/// crate 名到 CPE 的映射表
+#[derive(Debug, Clone, Default)]
+pub struct CpeMappings {
+    crates: BTreeMap<String, CpeMapping>,
+}
+
+impl CpeMappings {
+    pub fn embedded() -> Self {
+        Self::parse(EMBEDDED_MAPPINGS).expect("embedded CPE mappings are valid")
+    }
+
+    /// 内置映射加上文件中的映射（TOML，`[crate]` 下写 vendor / product），同名以文件为准
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read CPE mapping file: {}", path.display()))?;
+        let extra = Self::parse(&text).with_context(|| format!("invalid CPE mapping file: {}", path.display()))?;
+        let mut mappings = Self::embedded();
+        mappings.crates.extend(extra.crates);
+        Ok(mappings)
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        Ok(CpeMappings { crates: toml::from_str(text)? })
+    }
+
+    /// 包的 CPE 2.3 格式化字符串；没有映射时为 None
+    pub fn cpe(&self, package: &Package) -> Option<String> {
+        let mapping = self.crates.get(package.name.as_str())?;
+        let version = if mapping.upstream_version {
+            package.version.build.as_str().to_string()
+        } else {
+            package.version.to_string()
+        };
+        // A mapping that asks for an upstream version the crate doesn't carry has nothing to say
+        if version.is_empty() {
+            return None;
+        }
+        Some(format_cpe(&mapping.vendor, &mapping.product, &version))
+    }
+}
+
+/// `cpe:2.3:a:<vendor>:<product>:<version>:*:*:*:*:*:*:*`
+pub fn format_cpe(vendor: &str, product: &str, version: &str) -> String {
+    format!("cpe:2.3:a:{}:{}:{}:*:*:*:*:*:*:*", escape(vendor), escape(product), escape(version))
+}
+
+// Formatted-string binding: letters, digits, `_`, `-` and `.` stand for themselves, every
+// other printable character is quoted with a backslash, and values are lowercase
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.trim().to_lowercase().chars() {
+        match c {
+            'a'..='z' | '0'..='9' | '_' | '-' | '.' => out.push(c),
+            ' ' => out.push('_'),
+            _ => {
+                out.push('\\');
+                out.push(c);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cargo_lock::Lockfile;
+
+    fn packages() -> Lockfile {
+        r#"
+version = 3
+
+[[package]]
+name = "openssl-src"
+version = "300.1.5+3.1.4"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "hyper"
+version = "0.14.27"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "left-pad"
+version = "1.0.0-rc.1+build.5"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_format_escapes_special_characters() {
+        assert_eq!(format_cpe("openssl", "openssl", "3.1.4"), "cpe:2.3:a:openssl:openssl:3.1.4:*:*:*:*:*:*:*");
+        assert_eq!(
+            format_cpe("Acme Corp", "widget", "1.0.0-RC.1+build:5"),
+            "cpe:2.3:a:acme_corp:widget:1.0.0-rc.1\\+build\\:5:*:*:*:*:*:*:*"
+        );
+        assert_eq!(escape("a*b?c\\d"), "a\\*b\\?c\\\\d");
+    }
+
+    #[test]
+    fn test_embedded_mappings_and_unmapped_crates() {
+        let lockfile = packages();
+        let mappings = CpeMappings::embedded();
+        let cpe = |i: usize| mappings.cpe(&lockfile.packages[i]);
+        assert_eq!(cpe(0).as_deref(), Some("cpe:2.3:a:openssl:openssl:3.1.4:*:*:*:*:*:*:*"));
+        assert_eq!(cpe(1).as_deref(), Some("cpe:2.3:a:hyper:hyper:0.14.27:*:*:*:*:*:*:*"));
+        assert_eq!(cpe(2), None);
+        assert_eq!(cpe(3), None);
+    }
+
+    #[test]
+    fn test_load_user_mappings_over_embedded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cpe.toml");
+        fs::write(&path, "[left-pad]\nvendor = \"example\"\nproduct = \"left_pad\"\n\n[hyper]\nvendor = \"hyperium\"\nproduct = \"hyper\"\n").unwrap();
+        let lockfile = packages();
+        let mappings = CpeMappings::load(&path).unwrap();
+        let cpe = |i: usize| mappings.cpe(&lockfile.packages[i]);
+        assert_eq!(cpe(0).as_deref(), Some("cpe:2.3:a:openssl:openssl:3.1.4:*:*:*:*:*:*:*"));
+        assert_eq!(cpe(1).as_deref(), Some("cpe:2.3:a:hyperium:hyper:0.14.27:*:*:*:*:*:*:*"));
+        assert_eq!(cpe(2).as_deref(), Some("cpe:2.3:a:example:left_pad:1.0.0-rc.1\\+build.5:*:*:*:*:*:*:*"));
+
+        fs::write(&path, "[left-pad]\nvendor = \"example\"\n").unwrap();
+        assert!(CpeMappings::load(&path).is_err());
+        assert!(CpeMappings::load(&dir.path().join("missing.toml")).is_err());
+    }
+}
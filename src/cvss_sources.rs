@@ -0,0 +1,221 @@
+use crate::scanner::AdvisoryFinding;
+use crate::severity::Severity;
+use anyhow::{Context, Result};
+use cvss::Cvss;
+use rustsec::Advisory;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+// Additional CVSS scores for RustSec advisories, read from a directory of OSV documents
+// (e.g. a clone of github/advisory-database). A GHSA record often scores the same
+// vulnerability differently from RustSec, sometimes with both a v3 and a v4 vector; the
+// documents are matched through the advisory's id and aliases, and the finding takes the
+// highest severity among all of them. `severity_source` says which score won.
+
+/// 一个 CVSS 向量及其来源（所在的 advisory id）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CvssScore {
+    pub origin: String,
+    pub cvss: Cvss,
+}
+
+impl CvssScore {
+    pub fn severity(&self) -> Severity {
+        Severity::from(self.cvss.severity())
+    }
+
+    /// 如 `GHSA-xxxx-xxxx-xxxx CVSS v4.0`
+    pub fn source(&self) -> String {
+        let version = match &self.cvss {
+            Cvss::CvssV30(_) => "3.0",
+            Cvss::CvssV31(_) => "3.1",
+            Cvss::CvssV40(_) => "4.0",
+            _ => "?",
+        };
+        format!("{} CVSS v{}", self.origin, version)
+    }
+}
+
+#[derive(Deserialize)]
+struct OsvDocument {
+    id: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    severity: Vec<OsvSeverity>,
+}
+
+#[derive(Deserialize)]
+struct OsvSeverity {
+    score: String,
+}
+
+/// 按 id / alias 索引的额外 CVSS 分数
+#[derive(Debug, Clone, Default)]
+pub struct AliasScores {
+    by_id: HashMap<String, Vec<CvssScore>>,
+}
+
+impl AliasScores {
+    /// 读取目录下所有 OSV JSON 文件；无法解析的文件跳过并记一条警告
+    pub fn load(dir: &Path) -> Result<Self> {
+        if !dir.is_dir() {
+            anyhow::bail!("OSV score directory does not exist: {}", dir.display());
+        }
+        let mut scores = AliasScores::default();
+        let mut invalid = 0;
+        for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() || entry.path().extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let text = fs::read_to_string(entry.path())
+                .with_context(|| format!("failed to read OSV document: {}", entry.path().display()))?;
+            match serde_json::from_str::<OsvDocument>(&text) {
+                Ok(document) => scores.insert(document),
+                Err(_) => invalid += 1,
+            }
+        }
+        if invalid > 0 {
+            crate::console::warn(
+                "osv-document-invalid",
+                format!("{} file(s) under {} are not OSV documents and were skipped", invalid, dir.display()),
+            );
+        }
+        Ok(scores)
+    }
+
+    fn insert(&mut self, document: OsvDocument) {
+        // Vectors this version of the cvss crate cannot parse (v2, say) carry no usable score
+        let vectors: Vec<CvssScore> = document
+            .severity
+            .iter()
+            .filter_map(|s| s.score.parse().ok())
+            .map(|cvss| CvssScore { origin: document.id.clone(), cvss })
+            .collect();
+        if vectors.is_empty() {
+            return;
+        }
+        for id in std::iter::once(&document.id).chain(&document.aliases) {
+            let entries = self.by_id.entry(id.clone()).or_default();
+            for score in &vectors {
+                if !entries.contains(score) {
+                    entries.push(score.clone());
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    /// advisory 自身的 CVSS 分数在前，其后是 id 或 alias 相同的 OSV 文档中的分数
+    pub fn scores_for(&self, advisory: &Advisory) -> Vec<CvssScore> {
+        let id = advisory.metadata.id.to_string();
+        let mut scores: Vec<CvssScore> = advisory
+            .metadata
+            .cvss
+            .iter()
+            .map(|base| {
+                let cvss = if base.minor_version == 0 { Cvss::CvssV30(base.clone()) } else { Cvss::CvssV31(base.clone()) };
+                CvssScore { origin: id.clone(), cvss }
+            })
+            .collect();
+        let ids = std::iter::once(id.clone()).chain(advisory.metadata.aliases.iter().map(|a| a.to_string()));
+        for id in ids {
+            for score in self.by_id.get(&id).into_iter().flatten() {
+                if !scores.contains(score) {
+                    scores.push(score.clone());
+                }
+            }
+        }
+        scores
+    }
+
+    /// 把 finding 的严重程度改为所有分数中最高的一个，并记录其来源
+    pub fn apply(&self, finding: &mut AdvisoryFinding, advisory: &Advisory) {
+        if let Some(best) = highest(&self.scores_for(advisory)) {
+            finding.severity = best.severity();
+            finding.severity_source = Some(best.source());
+            finding.cvss_score = Some(best.cvss.score());
+        }
+    }
+}
+
+// Ties keep the earlier score, so the advisory's own rating wins unless another is higher
+pub fn highest(scores: &[CvssScore]) -> Option<&CvssScore> {
+    scores.iter().fold(None, |best: Option<&CvssScore>, score| match best {
+        Some(best) if best.severity() >= score.severity() => Some(best),
+        _ => Some(score),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::AdvisoryText;
+
+    const V3_MEDIUM: &str = "CVSS:3.1/AV:N/AC:H/PR:N/UI:R/S:U/C:L/I:L/A:N";
+    const V4_HIGH: &str = "CVSS:4.0/AV:N/AC:L/AT:N/PR:N/UI:N/VC:H/VI:N/VA:N/SC:N/SI:N/SA:N";
+
+    fn advisory(cvss: Option<&str>) -> Advisory {
+        let text = AdvisoryText::new("RUSTSEC-2099-0100", "demo").with_metadata("aliases = [\"GHSA-aaaa-bbbb-cccc\", \"CVE-2099-0001\"]");
+        match cvss {
+            Some(cvss) => text.with_metadata(&format!("cvss = \"{}\"", cvss)),
+            None => text,
+        }
+        .to_advisory()
+    }
+
+    fn scores(documents: &[&str]) -> AliasScores {
+        let dir = tempfile::tempdir().unwrap();
+        for (i, document) in documents.iter().enumerate() {
+            fs::write(dir.path().join(format!("{}.json", i)), document).unwrap();
+        }
+        fs::write(dir.path().join("README.md"), "not a document").unwrap();
+        AliasScores::load(dir.path()).unwrap()
+    }
+
+    #[test]
+    fn test_highest_score_across_sources_wins() {
+        let scores = scores(&[&format!(
+            r#"{{"id": "GHSA-aaaa-bbbb-cccc", "aliases": ["CVE-2099-0001"], "severity": [{{"type": "CVSS_V3", "score": "{}"}}, {{"type": "CVSS_V4", "score": "{}"}}]}}"#,
+            V3_MEDIUM, V4_HIGH
+        )]);
+        let advisory = advisory(Some(V3_MEDIUM));
+        let mut finding = AdvisoryFinding::from_advisory(&advisory);
+        assert_eq!((finding.severity, finding.severity_source.as_deref()), (Severity::Medium, Some("RUSTSEC-2099-0100 CVSS v3.1")));
+
+        scores.apply(&mut finding, &advisory);
+        assert_eq!(finding.severity, Severity::High);
+        assert_eq!(finding.severity_source.as_deref(), Some("GHSA-aaaa-bbbb-cccc CVSS v4.0"));
+    }
+
+    #[test]
+    fn test_single_score_keeps_existing_behavior() {
+        let scores = scores(&[&format!(
+            r#"{{"id": "GHSA-aaaa-bbbb-cccc", "severity": [{{"type": "CVSS_V3", "score": "{}"}}]}}"#,
+            V3_MEDIUM
+        )]);
+        // An equal score from elsewhere does not displace the advisory's own
+        let advisory = advisory(Some(V3_MEDIUM));
+        let mut finding = AdvisoryFinding::from_advisory(&advisory);
+        scores.apply(&mut finding, &advisory);
+        assert_eq!(finding.severity, Severity::Medium);
+        assert_eq!(finding.severity_source.as_deref(), Some("RUSTSEC-2099-0100 CVSS v3.1"));
+
+        // No score at all stays unknown, with no source
+        let advisory = self::advisory(None);
+        let mut finding = AdvisoryFinding::from_advisory(&advisory);
+        AliasScores::default().apply(&mut finding, &advisory);
+        assert_eq!((finding.severity, finding.severity_source.as_deref()), (Severity::Unknown, None));
+
+        // Only the OSV record has a score
+        scores.apply(&mut finding, &advisory);
+        assert_eq!(finding.severity, Severity::Medium);
+        assert_eq!(finding.severity_source.as_deref(), Some("GHSA-aaaa-bbbb-cccc CVSS v3.1"));
+    }
+}
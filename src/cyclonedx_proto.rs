@@ -0,0 +1,422 @@
+use crate::get_sbom::CycloneDxBom;
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::{Map, Value};
+
+// The SBOM in the CycloneDX Protobuf encoding (`--sbom-format protobuf`, `sbom.cdx.bin`),
+// for pipelines that ingest protobuf only. The BOM is the one written as JSON, converted
+// field by field from its JSON form, so both encodings always carry the same data: the
+// tables below map each JSON key to its field number and type in the `cyclonedx.v1_4`
+// messages of bom-1.4.proto, matching the `specVersion` of the JSON SBOM. `bomFormat` has
+// no protobuf field, enum strings become the enum numbers, `timestamp` a
+// google.protobuf.Timestamp and every `dependsOn` entry a nested `Dependency` with only its
+// `ref`. A JSON key without a mapping is an error rather than silently dropped data.
+//
+// `decode` reads such a file back into the JSON form, which is how the round trip is
+// checked; fields it does not know are skipped, as protobuf readers do.
+
+#[derive(Clone, Copy)]
+enum Kind {
+    String,
+    Int,
+    // Names in enum-number order; "" for numbers the SBOM never uses
+    Enum(&'static [&'static str]),
+    Message(&'static [Field]),
+    Timestamp,
+    // A string in JSON, a `Dependency { ref }` message in protobuf
+    Ref,
+}
+
+struct Field {
+    name: &'static str,
+    number: u64,
+    kind: Kind,
+    repeated: bool,
+}
+
+const fn one(name: &'static str, number: u64, kind: Kind) -> Field {
+    Field { name, number, kind, repeated: false }
+}
+
+const fn many(name: &'static str, number: u64, kind: Kind) -> Field {
+    Field { name, number, kind, repeated: true }
+}
+
+// Fields are listed in the order serde writes them, so decoding gives the same key order
+const BOM: &[Field] = &[
+    one("specVersion", 1, Kind::String),
+    one("serialNumber", 3, Kind::String),
+    one("version", 2, Kind::Int),
+    one("metadata", 4, Kind::Message(METADATA)),
+    many("components", 5, Kind::Message(COMPONENT)),
+    many("dependencies", 8, Kind::Message(DEPENDENCY)),
+    many("vulnerabilities", 10, Kind::Message(VULNERABILITY)),
+];
+
+const METADATA: &[Field] = &[
+    one("timestamp", 1, Kind::Timestamp),
+    many("tools", 2, Kind::Message(TOOL)),
+    one("component", 4, Kind::Message(COMPONENT)),
+    many("properties", 8, Kind::Message(PROPERTY)),
+];
+
+const TOOL: &[Field] = &[one("vendor", 1, Kind::String), one("name", 2, Kind::String), one("version", 3, Kind::String)];
+
+const COMPONENT: &[Field] = &[
+    one("type", 1, Kind::Enum(CLASSIFICATIONS)),
+    one("group", 7, Kind::String),
+    one("name", 8, Kind::String),
+    one("version", 9, Kind::String),
+    one("cpe", 15, Kind::String),
+    one("purl", 16, Kind::String),
+    one("bom-ref", 3, Kind::String),
+    many("licenses", 13, Kind::Message(LICENSE_CHOICE)),
+    one("author", 5, Kind::String),
+    one("description", 10, Kind::String),
+    one("scope", 11, Kind::Enum(SCOPES)),
+    many("externalReferences", 20, Kind::Message(EXTERNAL_REFERENCE)),
+    many("properties", 22, Kind::Message(PROPERTY)),
+];
+
+const LICENSE_CHOICE: &[Field] = &[one("license", 1, Kind::Message(LICENSE)), one("expression", 2, Kind::String)];
+
+const LICENSE: &[Field] = &[one("id", 1, Kind::String), one("name", 2, Kind::String)];
+
+const EXTERNAL_REFERENCE: &[Field] = &[one("type", 1, Kind::Enum(REFERENCE_TYPES)), one("url", 2, Kind::String)];
+
+const PROPERTY: &[Field] = &[one("name", 1, Kind::String), one("value", 2, Kind::String)];
+
+const DEPENDENCY: &[Field] = &[one("ref", 1, Kind::String), many("dependsOn", 2, Kind::Ref)];
+
+const VULNERABILITY: &[Field] = &[
+    one("bom-ref", 1, Kind::String),
+    one("id", 2, Kind::String),
+    one("source", 3, Kind::Message(SOURCE)),
+    many("ratings", 5, Kind::Message(RATING)),
+    one("description", 7, Kind::String),
+    one("recommendation", 9, Kind::String),
+    many("advisories", 10, Kind::Message(ADVISORY)),
+    many("affects", 17, Kind::Message(AFFECTS)),
+    one("analysis", 16, Kind::Message(ANALYSIS)),
+];
+
+const SOURCE: &[Field] = &[one("name", 1, Kind::String), one("url", 2, Kind::String)];
+
+const RATING: &[Field] = &[one("severity", 3, Kind::Enum(SEVERITIES))];
+
+const ADVISORY: &[Field] = &[one("url", 2, Kind::String)];
+
+const AFFECTS: &[Field] = &[one("ref", 1, Kind::String)];
+
+const ANALYSIS: &[Field] =
+    &[one("state", 1, Kind::Enum(ANALYSIS_STATES)), one("justification", 2, Kind::Enum(JUSTIFICATIONS)), one("detail", 4, Kind::String)];
+
+const CLASSIFICATIONS: &[&str] =
+    &["", "application", "framework", "library", "operating-system", "device", "file", "container", "firmware"];
+
+const SCOPES: &[&str] = &["", "required", "optional", "excluded"];
+
+const REFERENCE_TYPES: &[&str] = &[
+    "other",
+    "vcs",
+    "issue-tracker",
+    "website",
+    "advisories",
+    "bom",
+    "mailing-list",
+    "social",
+    "chat",
+    "documentation",
+    "support",
+    "distribution",
+    "license",
+    "build-meta",
+    "build-system",
+    "release-notes",
+];
+
+const SEVERITIES: &[&str] = &["unknown", "critical", "high", "medium", "low", "info", "none"];
+
+const ANALYSIS_STATES: &[&str] =
+    &["", "resolved", "resolved_with_pedigree", "exploitable", "in_triage", "false_positive", "not_affected"];
+
+const JUSTIFICATIONS: &[&str] = &[
+    "",
+    "code_not_present",
+    "code_not_reachable",
+    "requires_configuration",
+    "requires_dependency",
+    "requires_environment",
+    "protected_by_compiler",
+    "protected_at_runtime",
+    "protected_at_perimeter",
+    "protected_by_mitigating_control",
+];
+
+/// 路径（去掉 `.gz` 后）以 `.cdx.bin` 结尾时 SBOM 以 Protobuf 编码写出
+pub fn is_protobuf(path: &str) -> bool {
+    path.strip_suffix(".gz").unwrap_or(path).ends_with(".cdx.bin")
+}
+
+/// 把 SBOM 编码为 CycloneDX Protobuf（`cyclonedx.v1_4.Bom`）
+pub fn encode(bom: &CycloneDxBom) -> Result<Vec<u8>> {
+    encode_value(&serde_json::to_value(bom)?)
+}
+
+fn encode_value(bom: &Value) -> Result<Vec<u8>> {
+    let mut bom = bom.as_object().ok_or_else(|| anyhow!("the SBOM is not a JSON object"))?.clone();
+    match bom.remove("bomFormat") {
+        Some(Value::String(format)) if format == "CycloneDX" => {}
+        other => bail!("not a CycloneDX SBOM (bomFormat {:?})", other),
+    }
+    let mut out = Vec::new();
+    encode_message(&mut out, &bom, BOM, "")?;
+    Ok(out)
+}
+
+/// 把 Protobuf 编码的 SBOM 读回为其 JSON 形式
+pub fn decode(bytes: &[u8]) -> Result<Value> {
+    let mut bom = Map::new();
+    bom.insert("bomFormat".to_string(), Value::String("CycloneDX".to_string()));
+    bom.extend(decode_message(bytes, BOM)?);
+    Ok(Value::Object(bom))
+}
+
+fn encode_message(out: &mut Vec<u8>, object: &Map<String, Value>, fields: &[Field], path: &str) -> Result<()> {
+    if let Some(key) = object.keys().find(|key| !fields.iter().any(|f| f.name == *key)) {
+        bail!("{}/{}: no CycloneDX protobuf field for this key", path, key);
+    }
+    // In table order, so the bytes do not depend on the order of the JSON keys
+    for field in fields {
+        let Some(value) = object.get(field.name) else { continue };
+        let path = format!("{}/{}", path, field.name);
+        match (field.repeated, value) {
+            (true, Value::Array(items)) => {
+                for item in items {
+                    encode_field(out, field, item, &path)?;
+                }
+            }
+            (true, _) => bail!("{}: expected an array", path),
+            (false, value) => encode_field(out, field, value, &path)?,
+        }
+    }
+    Ok(())
+}
+
+fn encode_field(out: &mut Vec<u8>, field: &Field, value: &Value, path: &str) -> Result<()> {
+    let text = || value.as_str().ok_or_else(|| anyhow!("{}: expected a string", path));
+    match field.kind {
+        Kind::String => put_bytes(out, field.number, text()?.as_bytes()),
+        Kind::Int => {
+            let n = value.as_u64().ok_or_else(|| anyhow!("{}: expected a non-negative integer", path))?;
+            put_varint_field(out, field.number, n);
+        }
+        Kind::Enum(names) => {
+            let name = text()?;
+            let n = names
+                .iter()
+                .position(|&n| !n.is_empty() && n == name)
+                .ok_or_else(|| anyhow!("{}: {:?} has no CycloneDX protobuf enum value", path, name))?;
+            put_varint_field(out, field.number, n as u64);
+        }
+        Kind::Message(fields) => {
+            let object = value.as_object().ok_or_else(|| anyhow!("{}: expected an object", path))?;
+            let mut inner = Vec::new();
+            encode_message(&mut inner, object, fields, path)?;
+            put_bytes(out, field.number, &inner);
+        }
+        Kind::Timestamp => {
+            let time = chrono::DateTime::parse_from_rfc3339(text()?).with_context(|| format!("{}: invalid timestamp", path))?;
+            let mut inner = Vec::new();
+            // int64 seconds; a negative value is sent as its two's complement, as protobuf does
+            put_varint_field(&mut inner, 1, time.timestamp() as u64);
+            if time.timestamp_subsec_nanos() != 0 {
+                put_varint_field(&mut inner, 2, u64::from(time.timestamp_subsec_nanos()));
+            }
+            put_bytes(out, field.number, &inner);
+        }
+        Kind::Ref => {
+            let mut inner = Vec::new();
+            put_bytes(&mut inner, 1, text()?.as_bytes());
+            put_bytes(out, field.number, &inner);
+        }
+    }
+    Ok(())
+}
+
+fn put_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+// Wire type 0
+fn put_varint_field(out: &mut Vec<u8>, number: u64, n: u64) {
+    put_varint(out, number << 3);
+    put_varint(out, n);
+}
+
+// Wire type 2: strings and nested messages
+fn put_bytes(out: &mut Vec<u8>, number: u64, bytes: &[u8]) {
+    put_varint(out, (number << 3) | 2);
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+enum Wire<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+// (field number, value) pairs in wire order
+fn read_fields(mut bytes: &[u8]) -> Result<Vec<(u64, Wire<'_>)>> {
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let key = read_varint(&mut bytes)?;
+        let value = match key & 7 {
+            0 => Wire::Varint(read_varint(&mut bytes)?),
+            2 => {
+                let len = usize::try_from(read_varint(&mut bytes)?)?;
+                if len > bytes.len() {
+                    bail!("truncated protobuf field {}", key >> 3);
+                }
+                let (value, rest) = bytes.split_at(len);
+                bytes = rest;
+                Wire::Bytes(value)
+            }
+            // Fixed-width fields are never written; skip them like any unknown field
+            1 | 5 => {
+                let width = if key & 7 == 1 { 8 } else { 4 };
+                bytes = bytes.get(width..).ok_or_else(|| anyhow!("truncated protobuf field {}", key >> 3))?;
+                continue;
+            }
+            other => bail!("unsupported protobuf wire type {}", other),
+        };
+        fields.push((key >> 3, value));
+    }
+    Ok(fields)
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or_else(|| anyhow!("truncated protobuf varint"))?;
+        *bytes = rest;
+        n |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    bail!("protobuf varint longer than 64 bits")
+}
+
+fn decode_message(bytes: &[u8], fields: &[Field]) -> Result<Map<String, Value>> {
+    let wire = read_fields(bytes)?;
+    let mut object = Map::new();
+    for field in fields {
+        let mut values = Vec::new();
+        for (_, value) in wire.iter().filter(|(number, _)| *number == field.number) {
+            values.push(decode_field(field, value)?);
+        }
+        if field.repeated {
+            if !values.is_empty() {
+                object.insert(field.name.to_string(), Value::Array(values));
+            }
+        } else if let Some(value) = values.pop() {
+            object.insert(field.name.to_string(), value);
+        }
+    }
+    Ok(object)
+}
+
+fn decode_field(field: &Field, value: &Wire) -> Result<Value> {
+    let mismatch = || anyhow!("protobuf field {} ({}) has the wrong wire type", field.number, field.name);
+    Ok(match (field.kind, value) {
+        (Kind::String, Wire::Bytes(bytes)) => Value::String(String::from_utf8(bytes.to_vec())?),
+        (Kind::Int, Wire::Varint(n)) => Value::from(*n),
+        (Kind::Enum(names), Wire::Varint(n)) => {
+            let name = usize::try_from(*n).ok().and_then(|n| names.get(n)).filter(|n| !n.is_empty());
+            Value::String(name.ok_or_else(|| anyhow!("unknown value {} for enum field {}", n, field.name))?.to_string())
+        }
+        (Kind::Message(fields), Wire::Bytes(bytes)) => Value::Object(decode_message(bytes, fields)?),
+        (Kind::Timestamp, Wire::Bytes(bytes)) => {
+            let (mut seconds, mut nanos) = (0, 0);
+            for (number, value) in read_fields(bytes)? {
+                match (number, value) {
+                    (1, Wire::Varint(n)) => seconds = n as i64,
+                    (2, Wire::Varint(n)) => nanos = u32::try_from(n)?,
+                    _ => {}
+                }
+            }
+            let time = chrono::DateTime::from_timestamp(seconds, nanos).ok_or_else(|| anyhow!("timestamp out of range"))?;
+            Value::String(time.to_rfc3339())
+        }
+        (Kind::Ref, Wire::Bytes(bytes)) => {
+            let reference = read_fields(bytes)?.into_iter().find_map(|(number, value)| match (number, value) {
+                (1, Wire::Bytes(bytes)) => Some(bytes),
+                _ => None,
+            });
+            Value::String(String::from_utf8(reference.unwrap_or_default().to_vec())?)
+        }
+        _ => return Err(mismatch()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fields_round_trip() {
+        let bom = json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.4",
+            "serialNumber": "urn:uuid:6f1c2d3e-4b5a-4c6d-8e7f-0a1b2c3d4e5f",
+            "version": 1,
+            "metadata": {
+                "timestamp": "2024-01-01T12:30:00.250+00:00",
+                "tools": [{"vendor": "Custom", "name": "cargo-sbom-generator", "version": "1.0.0"}],
+                "properties": [{"name": "rustpj:dependency-graph", "value": "resolved"}]
+            },
+            "components": [{
+                "type": "library",
+                "name": "serde",
+                "version": "1.0.200",
+                "purl": "pkg:cargo/serde@1.0.200",
+                "bom-ref": "serde@1.0.200",
+                "licenses": [{"expression": "MIT OR Apache-2.0"}, {"license": {"name": "Custom"}}],
+                "author": "Erick Tryzelaar",
+                "scope": "excluded",
+                "externalReferences": [{"type": "vcs", "url": "https://github.com/serde-rs/serde"}]
+            }],
+            "dependencies": [{"ref": "serde@1.0.200", "dependsOn": ["serde_derive@1.0.200"]}],
+            "vulnerabilities": [{
+                "bom-ref": "RUSTSEC-2020-0001-serde@1.0.200",
+                "id": "RUSTSEC-2020-0001",
+                "source": {"name": "RustSec", "url": "https://rustsec.org/advisories/RUSTSEC-2020-0001"},
+                "ratings": [{"severity": "high"}],
+                "advisories": [{"url": "https://github.com/advisories/GHSA-xxxx"}],
+                "affects": [{"ref": "serde@1.0.200"}],
+                "analysis": {"state": "not_affected", "justification": "code_not_reachable", "detail": "unused"}
+            }]
+        });
+        let bytes = encode_value(&bom).unwrap();
+        // specVersion first: field 1, wire type 2, "1.4"
+        assert_eq!(bytes[..5], [0x0a, 3, b'1', b'.', b'4']);
+        assert_eq!(decode(&bytes).unwrap(), bom);
+
+        let mut unmapped = bom.clone();
+        unmapped["components"][0]["hashes"] = json!([]);
+        let error = encode_value(&unmapped).unwrap_err().to_string();
+        assert_eq!(error, "/components/hashes: no CycloneDX protobuf field for this key");
+        assert!(encode_value(&json!({"bomFormat": "SPDX"})).is_err());
+    }
+
+    #[test]
+    fn test_is_protobuf() {
+        assert!(is_protobuf("./output/sbom.cdx.bin") && is_protobuf("sbom.cdx.bin.gz"));
+        assert!(!is_protobuf("sbom.json") && !is_protobuf("sbom.bin"));
+    }
+}
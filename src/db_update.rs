@@ -0,0 +1,239 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{FetchOptions, Oid, Progress, RemoteCallbacks, Repository, StatusOptions};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// `rustpj db update`: get the advisory DB to the current upstream HEAD. A missing or empty
+// DB path gets a depth-1 clone (a few MB instead of the whole history). An existing
+// checkout is fetched and fast-forwarded. When that cannot work cleanly (local changes
+// to tracked files, history that no longer descends from the local HEAD after an
+// upstream rewrite) a fresh clone is made in a temporary directory next to the DB and
+// renamed into place, so the old checkout stays usable until the new one is complete.
+// A directory that is not a git checkout at all is left alone.
+
+/// 更新方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateAction {
+    Cloned,
+    FastForwarded { from: String },
+    UpToDate,
+    // 本地 checkout 无法快进（原因），已重新克隆并替换
+    Recloned { reason: String },
+}
+
+/// 更新结果：更新方式以及更新后的 HEAD
+#[derive(Debug, Clone)]
+pub struct DbUpdate {
+    pub action: UpdateAction,
+    pub commit: String,
+    pub commit_time: DateTime<Utc>,
+}
+
+enum FastForward {
+    Done(UpdateAction),
+    Blocked(String),
+}
+
+/// 把 path 处的 advisory DB 更新到 url 的 HEAD；path 不存在或为空目录时浅克隆
+pub fn update(path: &Path, url: &str) -> Result<DbUpdate> {
+    if is_empty_dir(path)? {
+        let repo = clone(url, path)?;
+        return finish(&repo, UpdateAction::Cloned);
+    }
+    let repo = Repository::open(path).with_context(|| {
+        format!("{} exists but is not a git checkout; move it away or point RUSTSEC_DB_PATH elsewhere", path.display())
+    })?;
+    match fast_forward(&repo, url)? {
+        FastForward::Done(action) => finish(&repo, action),
+        FastForward::Blocked(reason) => {
+            status!("{}; re-cloning {}", reason, url);
+            drop(repo);
+            let repo = reclone(path, url)?;
+            finish(&repo, UpdateAction::Recloned { reason })
+        }
+    }
+}
+
+fn is_empty_dir(path: &Path) -> Result<bool> {
+    match fs::read_dir(path) {
+        Ok(mut entries) => Ok(entries.next().is_none()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+fn fetch_options(depth: Option<i32>) -> FetchOptions<'static> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut shown = None;
+    callbacks.transfer_progress(move |progress| {
+        report_progress(&progress, &mut shown);
+        true
+    });
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(callbacks);
+    if let Some(depth) = depth {
+        options.depth(depth);
+    }
+    options
+}
+
+// One line, rewritten in place whenever the percentage changes; stderr keeps stdout clean
+fn report_progress(progress: &Progress<'_>, shown: &mut Option<usize>) {
+    let (received, total) = (progress.received_objects(), progress.total_objects());
+    if total == 0 {
+        return;
+    }
+    let percent = received * 100 / total;
+    if *shown == Some(percent) {
+        return;
+    }
+    *shown = Some(percent);
+    let mut err = std::io::stderr();
+    let _ = write!(err, "\rReceiving objects: {:3}% ({}/{}), {} KiB", percent, received, total, progress.received_bytes() / 1024);
+    if received == total {
+        let _ = writeln!(err);
+    }
+}
+
+fn clone(url: &str, path: &Path) -> Result<Repository> {
+    status!("Cloning {} (depth 1) into {}", url, path.display());
+    RepoBuilder::new()
+        .fetch_options(fetch_options(Some(1)))
+        .clone(url, path)
+        .with_context(|| format!("failed to clone {}", url))
+}
+
+fn fast_forward(repo: &Repository, url: &str) -> Result<FastForward> {
+    let statuses = repo.statuses(Some(StatusOptions::new().include_untracked(false)))?;
+    if !statuses.is_empty() {
+        return Ok(FastForward::Blocked(format!("{} tracked file(s) have local changes", statuses.len())));
+    }
+    let head = repo.head()?.peel_to_commit()?.id();
+    status!("Fetching {}", url);
+    // No depth here: a shallow checkout only receives the commits it is missing
+    repo.remote_anonymous(url)?
+        .fetch(&["HEAD"], Some(&mut fetch_options(None)), None)
+        .with_context(|| format!("failed to fetch {}", url))?;
+    let fetched = repo.find_reference("FETCH_HEAD")?.peel_to_commit()?.id();
+    if fetched == head {
+        return Ok(FastForward::Done(UpdateAction::UpToDate));
+    }
+    // A shallow history may end before the local HEAD; that also counts as diverged
+    if !repo.graph_descendant_of(fetched, head).unwrap_or(false) {
+        return Ok(FastForward::Blocked(format!("upstream history no longer contains the local HEAD {}", short(head))));
+    }
+    move_head(repo, fetched)?;
+    Ok(FastForward::Done(UpdateAction::FastForwarded { from: head.to_string() }))
+}
+
+fn move_head(repo: &Repository, target: Oid) -> Result<()> {
+    let mut head = repo.head()?;
+    if head.is_branch() {
+        head.set_target(target, "rustpj db update: fast-forward")?;
+    } else {
+        repo.set_head_detached(target)?;
+    }
+    repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+    Ok(())
+}
+
+fn reclone(path: &Path, url: &str) -> Result<Repository> {
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "advisory-db".to_string());
+    // Next to the DB, so both renames stay on one filesystem
+    let staging = tempfile::Builder::new()
+        .prefix(&format!(".{}.rustpj-", name))
+        .tempdir_in(parent)
+        .with_context(|| format!("failed to create a temporary directory in {}", parent.display()))?;
+    let fresh = staging.path().join("new");
+    clone(url, &fresh)?;
+    let old: PathBuf = staging.path().join("old");
+    fs::rename(path, &old).with_context(|| format!("failed to move {} aside", path.display()))?;
+    if let Err(e) = fs::rename(&fresh, path) {
+        // Put the previous checkout back rather than leave no DB at all
+        let _ = fs::rename(&old, path);
+        return Err(e).with_context(|| format!("failed to move the new clone to {}", path.display()));
+    }
+    // `staging` (with the old checkout inside) is removed on drop
+    Repository::open(path).map_err(Into::into)
+}
+
+fn finish(repo: &Repository, action: UpdateAction) -> Result<DbUpdate> {
+    let commit = repo.head()?.peel_to_commit()?;
+    let Some(commit_time) = DateTime::from_timestamp(commit.time().seconds(), 0) else {
+        bail!("advisory DB HEAD {} has an invalid commit time", commit.id());
+    };
+    Ok(DbUpdate { action, commit: commit.id().to_string(), commit_time })
+}
+
+fn short(oid: Oid) -> String {
+    oid.to_string()[..12].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+
+    fn commit_file(repo: &Repository, name: &str, text: &str, parents: &[&git2::Commit<'_>]) -> Oid {
+        let root = repo.workdir().unwrap();
+        fs::write(root.join(name), text).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        let oid = repo.commit(None, &sig, &sig, name, &tree, parents).unwrap();
+        // Also for a new root commit, which `commit(Some("HEAD"), ..)` refuses
+        match repo.head() {
+            Ok(mut head) => drop(head.set_target(oid, name).unwrap()),
+            Err(_) => drop(repo.reference("refs/heads/main", oid, true, name).unwrap()),
+        }
+        repo.set_head("refs/heads/main").unwrap();
+        oid
+    }
+
+    #[test]
+    fn test_clone_fast_forward_and_reclone() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream_path = dir.path().join("upstream");
+        let upstream = Repository::init(&upstream_path).unwrap();
+        let first = commit_file(&upstream, "a.md", "one", &[]);
+        let url = format!("file://{}", upstream_path.display());
+        let db = dir.path().join("data/advisory-db");
+
+        let cloned = update(&db, &url).unwrap();
+        assert_eq!(cloned.action, UpdateAction::Cloned);
+        assert_eq!(cloned.commit, first.to_string());
+        assert_eq!(update(&db, &url).unwrap().action, UpdateAction::UpToDate);
+
+        let parent = upstream.find_commit(first).unwrap();
+        let second = commit_file(&upstream, "b.md", "two", &[&parent]);
+        let updated = update(&db, &url).unwrap();
+        assert_eq!(updated.action, UpdateAction::FastForwarded { from: first.to_string() });
+        assert_eq!(updated.commit, second.to_string());
+        assert!(db.join("b.md").is_file());
+
+        // Local edits to a tracked file: replaced by a fresh clone
+        fs::write(db.join("a.md"), "edited").unwrap();
+        let recloned = update(&db, &url).unwrap();
+        assert!(matches!(recloned.action, UpdateAction::Recloned { .. }));
+        assert_eq!(fs::read_to_string(db.join("a.md")).unwrap(), "one");
+
+        // Upstream rewritten: a new root commit that does not descend from ours
+        let rewritten = commit_file(&upstream, "c.md", "three", &[]);
+        let recloned = update(&db, &url).unwrap();
+        assert!(matches!(recloned.action, UpdateAction::Recloned { .. }));
+        assert_eq!(recloned.commit, rewritten.to_string());
+        // Nothing left behind next to the DB
+        assert_eq!(fs::read_dir(db.parent().unwrap()).unwrap().count(), 1);
+
+        fs::create_dir_all(dir.path().join("plain")).unwrap();
+        fs::write(dir.path().join("plain/notes.txt"), "mine").unwrap();
+        assert!(update(&dir.path().join("plain"), &url).is_err());
+        assert!(dir.path().join("plain/notes.txt").is_file());
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::{Result, anyhow, bail};
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+// How advisory descriptions appear in the JSON report (`--description`). Some advisories
+// carry kilobytes of Markdown, which adds up over a big workspace and trips field-size
+// limits downstream. The findings themselves always keep the full text, and templates
+// render from them; only the serialized report is shaped here, after `to_value`.
+// Shortened or omitted descriptions are marked `description_truncated` and point at the
+// advisory page for the full text.
+
+pub const DEFAULT_LIMIT: usize = 500;
+
+/// `--description truncate[:N]|full|omit`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DescriptionMode {
+    // 只保留第一段，并且最多 N 个字符
+    Truncate(usize),
+    Full,
+    Omit,
+}
+
+impl Default for DescriptionMode {
+    fn default() -> Self {
+        DescriptionMode::Truncate(DEFAULT_LIMIT)
+    }
+}
+
+impl FromStr for DescriptionMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            None if s == "truncate" => Ok(DescriptionMode::default()),
+            None if s == "full" => Ok(DescriptionMode::Full),
+            None if s == "omit" => Ok(DescriptionMode::Omit),
+            Some(("truncate", limit)) => match limit.parse::<usize>() {
+                Ok(limit) if limit > 0 => Ok(DescriptionMode::Truncate(limit)),
+                _ => bail!("invalid description length: {}", limit),
+            },
+            _ => Err(anyhow!("unknown description mode: {} (expected truncate[:N], full or omit)", s)),
+        }
+    }
+}
+
+/// 按 mode 改写序列化后的报告（`VulnReport` 的 JSON）中所有漏洞的 description
+pub fn apply(report: &mut Value, mode: DescriptionMode) {
+    if mode == DescriptionMode::Full {
+        return;
+    }
+    let Some(report) = report.as_object_mut() else {
+        return;
+    };
+    for key in ["packages", "not_applicable_to_target", "excluded_by_category"] {
+        if let Some(packages) = report.get_mut(key) {
+            apply_to_packages(packages, mode);
+        }
+    }
+    for project in report.get_mut("projects").and_then(Value::as_array_mut).into_iter().flatten() {
+        if let Some(packages) = project.get_mut("packages") {
+            apply_to_packages(packages, mode);
+        }
+    }
+}
+
+fn apply_to_packages(packages: &mut Value, mode: DescriptionMode) {
+    for package in packages.as_array_mut().into_iter().flatten() {
+        let findings = package.get_mut("advisories").and_then(Value::as_array_mut);
+        for finding in findings.into_iter().flatten().filter_map(Value::as_object_mut) {
+            apply_to_finding(finding, mode);
+        }
+    }
+}
+
+fn apply_to_finding(finding: &mut Map<String, Value>, mode: DescriptionMode) {
+    let Some(text) = finding.get("description").and_then(Value::as_str) else {
+        return;
+    };
+    let Some(index) = finding.keys().position(|k| k == "description") else {
+        return;
+    };
+    let mut next = index + 1;
+    match mode {
+        DescriptionMode::Full => return,
+        DescriptionMode::Truncate(limit) => {
+            let Some(short) = shorten(text, limit) else {
+                return;
+            };
+            finding.insert("description".to_string(), Value::String(short));
+        }
+        DescriptionMode::Omit => {
+            if text.is_empty() {
+                return;
+            }
+            finding.shift_remove("description");
+            next = index;
+        }
+    }
+    // Keep the markers next to where the description was
+    let url = finding.get("id").and_then(Value::as_str).map(advisory_url);
+    finding.shift_insert(next, "description_truncated".to_string(), Value::Bool(true));
+    if let Some(url) = url {
+        finding.shift_insert(next + 1, "advisory_url".to_string(), Value::String(url));
+    }
+}
+
+/// advisory 的完整说明页面
+pub fn advisory_url(id: &str) -> String {
+    format!("https://rustsec.org/advisories/{}.html", id)
+}
+
+// The first paragraph, cut to `limit` characters (at a word boundary when there is one
+// in the second half) with an ellipsis; None when that is already the whole text
+fn shorten(text: &str, limit: usize) -> Option<String> {
+    let text = text.trim();
+    let paragraph = text.split("\n\n").next().unwrap_or_default().trim_end();
+    let short = if paragraph.chars().count() > limit {
+        let cut: String = paragraph.chars().take(limit).collect();
+        let cut = match cut.rfind(char::is_whitespace) {
+            Some(i) if i > cut.len() / 2 => &cut[..i],
+            _ => &cut,
+        };
+        format!("{}…", cut.trim_end())
+    } else {
+        paragraph.to_string()
+    };
+    (short != text).then_some(short)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_modes() {
+        assert_eq!("truncate".parse::<DescriptionMode>().unwrap(), DescriptionMode::Truncate(DEFAULT_LIMIT));
+        assert_eq!("truncate:80".parse::<DescriptionMode>().unwrap(), DescriptionMode::Truncate(80));
+        assert_eq!("full".parse::<DescriptionMode>().unwrap(), DescriptionMode::Full);
+        assert_eq!("omit".parse::<DescriptionMode>().unwrap(), DescriptionMode::Omit);
+        assert!("truncate:0".parse::<DescriptionMode>().is_err());
+        assert!("full:10".parse::<DescriptionMode>().is_err());
+        assert!("short".parse::<DescriptionMode>().is_err());
+    }
+
+    #[test]
+    fn test_shorten_first_paragraph_then_length() {
+        assert_eq!(shorten("One line.", 100), None);
+        assert_eq!(shorten("First paragraph.\n\nDetails follow.", 100).as_deref(), Some("First paragraph."));
+        assert_eq!(shorten("alpha beta gamma delta", 14).as_deref(), Some("alpha beta…"));
+        // no whitespace to break at, and characters rather than bytes
+        assert_eq!(shorten("ééééééééé", 4).as_deref(), Some("éééé…"));
+    }
+
+    #[test]
+    fn test_apply_marks_shortened_findings() {
+        let finding = |id: &str, description: &str| json!({"id": id, "description": description, "severity": "high"});
+        let long = "Memory corruption in `parse`.\n\n## Impact\n\nLots of detail.";
+        let report = json!({
+            "packages": [{"package_name": "a", "advisories": [finding("RUSTSEC-2020-0001", long), finding("RUSTSEC-2020-0002", "Short.")]}],
+            "projects": [{"packages": [{"package_name": "a", "advisories": [finding("RUSTSEC-2020-0001", long)]}]}],
+        });
+
+        let mut truncated = report.clone();
+        apply(&mut truncated, DescriptionMode::default());
+        let first = truncated["packages"][0]["advisories"][0].as_object().unwrap();
+        assert_eq!(first["description"], "Memory corruption in `parse`.");
+        assert_eq!(first["description_truncated"], true);
+        assert_eq!(first["advisory_url"], "https://rustsec.org/advisories/RUSTSEC-2020-0001.html");
+        let keys: Vec<&str> = first.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["id", "description", "description_truncated", "advisory_url", "severity"]);
+        assert!(truncated["packages"][0]["advisories"][1].get("description_truncated").is_none());
+        assert_eq!(truncated["projects"][0]["packages"][0]["advisories"][0]["description_truncated"], true);
+
+        let mut omitted = report.clone();
+        apply(&mut omitted, DescriptionMode::Omit);
+        let keys: Vec<&str> = omitted["packages"][0]["advisories"][1].as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(keys, ["id", "description_truncated", "advisory_url", "severity"]);
+
+        let mut full = report.clone();
+        apply(&mut full, DescriptionMode::Full);
+        assert_eq!(full, report);
+    }
+}
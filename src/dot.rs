@@ -0,0 +1,111 @@
+use crate::get_sbom::{BomRefs, depends_on};
+use crate::graph::DependencyGraph;
+use crate::scanner::VulnReport;
+use crate::severity::Severity;
+use cargo_lock::Lockfile;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Graphviz DOT rendering of the lockfile graph (`--dot-output`). Edges are the SBOM's
+// `dependsOn` relationships; nodes with untriaged findings are filled by their highest
+// severity, so `dot -Tsvg deps.dot > deps.svg` gives a quick visual for reviews.
+
+// (fill, font) colors per severity
+fn colors(severity: Severity) -> (&'static str, &'static str) {
+    match severity {
+        Severity::Critical => ("#b2182b", "white"),
+        Severity::High => ("#ef8a62", "black"),
+        Severity::Medium => ("#fddbc7", "black"),
+        Severity::Low => ("#d1e5f0", "black"),
+        Severity::None | Severity::Unknown => ("#e0e0e0", "black"),
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// 生成 DOT 文本；report 为空（--no-scan）时只画依赖关系
+pub fn render(lockfile: &Lockfile, report: Option<&VulnReport>) -> String {
+    // name@version -> (highest severity, advisory ids) of the findings still needing action
+    let mut vulnerable: HashMap<String, (Severity, Vec<&str>)> = HashMap::new();
+    for pkg in report.map(|r| r.packages.as_slice()).unwrap_or_default() {
+        let open: Vec<_> = pkg.advisories.iter().filter(|f| !f.is_suppressed()).collect();
+        if let Some(max) = open.iter().map(|f| f.severity).max() {
+            let ids = open.iter().map(|f| f.id.as_str()).collect();
+            vulnerable.insert(format!("{}@{}", pkg.package_name, pkg.package_version), (max, ids));
+        }
+    }
+
+    let refs = BomRefs::new(lockfile);
+    let mut out = String::from("digraph dependencies {\n");
+    out.push_str("    rankdir=LR;\n");
+    out.push_str("    node [shape=box, style=\"rounded,filled\", fillcolor=\"white\", fontname=\"Helvetica\"];\n");
+    for package in &lockfile.packages {
+        let id = refs.get(package);
+        match vulnerable.get(&id) {
+            Some((severity, ids)) => {
+                let (fill, font) = colors(*severity);
+                let tooltip = format!("{}: {}", severity, ids.join(", "));
+                let _ = writeln!(
+                    out,
+                    "    {} [fillcolor={}, fontcolor={}, tooltip={}];",
+                    quote(&id),
+                    quote(fill),
+                    quote(font),
+                    quote(&tooltip)
+                );
+            }
+            None => {
+                let _ = writeln!(out, "    {};", quote(&id));
+            }
+        }
+    }
+    let graph = DependencyGraph::from_lockfile(lockfile);
+    for (node, package) in lockfile.packages.iter().enumerate() {
+        let id = quote(&refs.get(package));
+        for dep in depends_on(&graph, &refs, node) {
+            let _ = writeln!(out, "    {} -> {};", id, quote(&dep));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{AdvisoryFinding, PackageReport};
+    use crate::test_support::AdvisoryText;
+
+    #[test]
+    fn test_render_colors_vulnerable_nodes() {
+        let lockfile: Lockfile = "version = 3\n\n[[package]]\nname = \"app\"\nversion = \"0.1.0\"\ndependencies = [\"zip\"]\n\n[[package]]\nname = \"zip\"\nversion = \"0.6.6\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n"
+            .parse()
+            .unwrap();
+        let mut finding =
+            AdvisoryFinding::from_advisory(&AdvisoryText::new("RUSTSEC-2099-0001", "zip").with_versions("patched = []").to_advisory());
+        finding.severity = Severity::Critical;
+        let report = VulnReport {
+            packages: vec![PackageReport {
+                package_name: "zip".to_string(),
+                package_version: "0.6.6".to_string(),
+                max_severity: Severity::Critical,
+                advisories: vec![finding],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let dot = render(&lockfile, Some(&report));
+        assert!(dot.starts_with("digraph dependencies {\n"));
+        assert!(dot.contains("    \"app@0.1.0\";\n"));
+        assert!(dot.contains(
+            "    \"zip@0.6.6\" [fillcolor=\"#b2182b\", fontcolor=\"white\", tooltip=\"critical: RUSTSEC-2099-0001\"];\n"
+        ));
+        assert!(dot.contains("    \"app@0.1.0\" -> \"zip@0.6.6\";\n"));
+
+        // Without a scan only the graph is drawn
+        assert!(!render(&lockfile, None).contains("fillcolor=\"#"));
+    }
+}
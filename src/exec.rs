@@ -0,0 +1,54 @@
+use std::fmt;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// External programs. Every process rustpj starts (`cargo generate-lockfile` for a project
+// without a lockfile, `cargo metadata` for licenses, `cargo --version` in doctor) is built
+// through `command`, which consults the process-wide policy. Under
+// `ExternalCommands::Forbidden` (--no-exec) nothing is spawned: the caller gets a
+// `PolicyViolation` naming the command it wanted to run, and can fall back to what is on
+// disk already (licenses from the manifests) or fail with it (no lockfile to scan).
+
+/// 是否允许启动外部程序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExternalCommands {
+    #[default]
+    Allowed,
+    // --no-exec：只读取磁盘上已有的文件
+    Forbidden,
+}
+
+static FORBIDDEN: AtomicBool = AtomicBool::new(false);
+
+pub fn set_policy(policy: ExternalCommands) {
+    FORBIDDEN.store(policy == ExternalCommands::Forbidden, Ordering::SeqCst);
+}
+
+pub fn policy() -> ExternalCommands {
+    if FORBIDDEN.load(Ordering::SeqCst) { ExternalCommands::Forbidden } else { ExternalCommands::Allowed }
+}
+
+/// 策略禁止执行的命令；可从 anyhow::Error 中 downcast 出来
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyViolation {
+    pub command: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "refusing to run `{}`: external commands are forbidden (--no-exec)", self.command)
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+/// 构造 `program args...`；策略为 Forbidden 时返回 PolicyViolation
+pub fn command(program: &str, args: &[&str]) -> Result<Command, PolicyViolation> {
+    if policy() == ExternalCommands::Forbidden {
+        let command = std::iter::once(program).chain(args.iter().copied()).collect::<Vec<_>>().join(" ");
+        return Err(PolicyViolation { command });
+    }
+    let mut command = Command::new(program);
+    command.args(args);
+    Ok(command)
+}
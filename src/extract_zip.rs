@@ -1,54 +1,419 @@
+use flate2::read::GzDecoder;
 use std::fs::{self, File};
-use std::io;
-use std::path::Path;
-use zip::read::ZipArchive;
-use anyhow::{Result, Context};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use tar::EntryType;
+use zip::read::{ZipArchive, ZipFile};
+use zip::result::ZipError;
+use anyhow::{Result, Context, bail};
 
 pub struct TomlLockExtractor;
 
+// Zip-bomb protection: a single entry may not expand beyond this, and neither may the
+// archive as a whole. Both limits are enforced on the bytes actually written, not on the
+// sizes declared in the (attacker-controlled) central directory.
+const MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+pub(crate) const MAX_TOTAL_SIZE: u64 = 1024 * 1024 * 1024;
+// 解压时每次读写的缓冲区大小，内存占用与条目大小无关
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    max_entry: u64,
+    max_total: u64,
+}
+
+const DEFAULT_LIMITS: Limits = Limits { max_entry: MAX_ENTRY_SIZE, max_total: MAX_TOTAL_SIZE };
+
+/// 解压失败而被跳过的条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedEntry {
+    pub name: String,
+    pub error: String,
+}
+
 impl TomlLockExtractor {
     // Extract the entire ZIP payload. We previously extracted only .toml/.lock, which
     // prevented `cargo generate-lockfile` from working because Cargo requires a real
     // target (src/main.rs, src/lib.rs, or explicit [[bin]]) to parse the manifest.
     // Using `mangled_name()` ensures any path traversal inside the ZIP is neutralized.
-    pub fn extract_toml_and_lock_files(zip_path: &str, output_dir: &str) -> Result<()> {
+    //
+    // An entry that cannot be read or written (a name the filesystem rejects, a path
+    // clashing with a file, a corrupt entry) is skipped and returned, so that one bad
+    // entry in a big archive does not prevent scanning the rest. The size limits still
+    // abort the whole extraction, and so does an archive where nothing could be extracted.
+    pub fn extract_toml_and_lock_files(zip_path: &str, output_dir: &str) -> Result<Vec<SkippedEntry>> {
+        Self::extract_with_limits(zip_path, output_dir, DEFAULT_LIMITS)
+    }
+
+    fn extract_with_limits(zip_path: &str, output_dir: &str, limits: Limits) -> Result<Vec<SkippedEntry>> {
         fs::create_dir_all(output_dir).context("无法创建输出目录")?;
 
         let file = File::open(zip_path).context("无法打开 ZIP 文件")?;
         let mut archive = ZipArchive::new(file).context("无效的 ZIP 文件")?;
+        let mut total = 0u64;
+        let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+        let mut skipped = Vec::new();
+        let mut extracted = 0usize;
+        crate::progress::emit(crate::progress::ProgressEvent::ExtractStart { entries: Some(archive.len()) });
 
         for i in 0..archive.len() {
-            let mut entry = archive
-                .by_index(i)
-                .context(format!("无法读取 ZIP 中的文件索引 {}", i))?;
-
-            let rel = entry.mangled_name();
-            let out_path = Path::new(output_dir).join(&rel);
-
-            if entry.is_dir() {
-                fs::create_dir_all(&out_path)
-                    .with_context(|| format!("无法创建目录: {}", out_path.display()))?;
+            // Encrypted entries cannot be read without a password; everything else still is
+            let encrypted = matches!(
+                archive.by_index(i),
+                Err(ZipError::UnsupportedArchive(reason)) if reason == ZipError::PASSWORD_REQUIRED
+            );
+            if encrypted {
+                let name = archive.by_index_raw(i).map(|e| e.mangled_name().display().to_string()).unwrap_or_default();
+                crate::console::warn("zip-entry-encrypted", format!("encrypted ZIP entry skipped: {}", name));
                 continue;
             }
-
-            if let Some(parent) = out_path.parent() {
-                fs::create_dir_all(parent)
-                    .with_context(|| format!("无法创建目录: {}", parent.display()))?;
+            let name = archive.by_index_raw(i).map(|e| e.mangled_name().display().to_string()).unwrap_or_else(|_| format!("#{}", i));
+            let mut entry = match archive.by_index(i) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    skipped.push(SkippedEntry { name, error: format!("无法读取 ZIP 中的文件索引 {}: {}", i, e) });
+                    continue;
+                }
+            };
+            match extract_entry(&mut entry, Path::new(output_dir), limits, &mut total, &mut buffer)? {
+                Ok(true) => extracted += 1,
+                Ok(false) => {}
+                Err(e) => skipped.push(SkippedEntry { name, error: format!("{:#}", e) }),
             }
+        }
+
+        if extracted == 0 && !skipped.is_empty() {
+            bail!("ZIP 中没有可以解压的文件（{} 个条目失败，第一个: {}: {}）", skipped.len(), skipped[0].name, skipped[0].error);
+        }
+        Ok(skipped)
+    }
+
+    // A published `.crate` package: a gzip-compressed tar holding `<name>-<version>/`, with
+    // the normalized Cargo.toml and, for crates with binaries, usually a Cargo.lock. Same
+    // limits and skipping as for ZIPs. Only regular files and directories are extracted;
+    // links (which `cargo package` never writes) are skipped, as they could point outside
+    // the output directory. A tar is read sequentially, so a corrupt stream ends the
+    // extraction instead of skipping one entry.
+    pub fn extract_crate_file(crate_path: &str, output_dir: &str) -> Result<Vec<SkippedEntry>> {
+        Self::extract_crate_with_limits(crate_path, output_dir, DEFAULT_LIMITS)
+    }
+
+    fn extract_crate_with_limits(crate_path: &str, output_dir: &str, limits: Limits) -> Result<Vec<SkippedEntry>> {
+        fs::create_dir_all(output_dir).context("无法创建输出目录")?;
 
-            let mut out_file = File::create(&out_path)
-                .with_context(|| format!("无法创建文件: {}", out_path.display()))?;
-            io::copy(&mut entry, &mut out_file)
-                .with_context(|| format!("无法写入文件: {}", out_path.display()))?;
+        let file = File::open(crate_path).context("无法打开 .crate 文件")?;
+        let mut archive = tar::Archive::new(GzDecoder::new(file));
+        let mut total = 0u64;
+        let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+        let mut skipped = Vec::new();
+        let mut extracted = 0usize;
+        crate::progress::emit(crate::progress::ProgressEvent::ExtractStart { entries: None });
 
-            // Only print a line for interesting files to keep logs tidy
-            if let Some(name) = rel.file_name().and_then(|s| s.to_str()) {
-                if name.ends_with(".toml") || name.ends_with(".lock") || name == "main.rs" {
-                    println!("EXTRACTED: {} -> {}", name, out_path.display());
+        for entry in archive.entries().context("无效的 .crate 文件")? {
+            let mut entry = entry.context("无法读取 .crate 中的条目")?;
+            let raw = entry.path().map(|p| p.into_owned()).context("无法读取 .crate 中的条目")?;
+            let rel = sanitized_path(&raw);
+            let name = rel.display().to_string();
+            if rel.as_os_str().is_empty() {
+                continue;
+            }
+            let out_path = Path::new(output_dir).join(&rel);
+            match entry.header().entry_type() {
+                EntryType::Directory => {
+                    if let Err(e) = fs::create_dir_all(&out_path) {
+                        skipped.push(SkippedEntry { name, error: format!("无法创建目录: {}: {}", out_path.display(), e) });
+                    }
+                    continue;
+                }
+                EntryType::Regular | EntryType::Continuous => {}
+                other => {
+                    skipped.push(SkippedEntry { name, error: format!("不是普通文件（{:?}）", other) });
+                    continue;
                 }
             }
+            let size = entry.header().size().unwrap_or(0);
+            if size > limits.max_entry {
+                bail!("条目过大: {} 声明 {} 字节，上限 {} 字节", name, size, limits.max_entry);
+            }
+            match write_entry(&mut entry, &rel, &out_path, limits, &mut total, &mut buffer)? {
+                Ok(_) => extracted += 1,
+                Err(e) => skipped.push(SkippedEntry { name, error: format!("{:#}", e) }),
+            }
+        }
+
+        if extracted == 0 && !skipped.is_empty() {
+            bail!(".crate 中没有可以解压的文件（{} 个条目失败，第一个: {}: {}）", skipped.len(), skipped[0].name, skipped[0].error);
+        }
+        Ok(skipped)
+    }
+}
+
+/// 是否是 gzip 压缩的文件（`.crate` 包）；按文件头判断，不看扩展名
+pub fn is_gzip(path: &Path) -> Result<bool> {
+    let mut magic = [0u8; 2];
+    let mut file = File::open(path).with_context(|| format!("无法打开 {}", path.display()))?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(magic == [0x1f, 0x8b]),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("无法读取 {}", path.display())),
+    }
+}
+
+/// gzip 文件是否具有 `.crate` 包的结构：tar 中有 `<name>-<version>/Cargo.toml`。读到该条目
+/// 为止；不是 tar 或读取出错时为 false
+pub fn is_crate_package(path: &Path) -> Result<bool> {
+    let file = File::open(path).with_context(|| format!("无法打开 {}", path.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let Ok(entries) = archive.entries() else { return Ok(false) };
+    for entry in entries {
+        let Ok(entry) = entry else { return Ok(false) };
+        let Ok(entry_path) = entry.path() else { continue };
+        let parts: Vec<Component> = entry_path.components().collect();
+        if let [Component::Normal(top), Component::Normal(file)] = parts.as_slice()
+            && *file == "Cargo.toml"
+            && top.to_string_lossy().contains('-')
+        {
+            return Ok(true);
         }
+    }
+    Ok(false)
+}
+
+// Like zip's `mangled_name`: keeps only the normal components, so `..`, absolute paths and
+// drive prefixes cannot lead outside the output directory
+fn sanitized_path(path: &Path) -> PathBuf {
+    path.components().filter_map(|c| match c {
+        Component::Normal(part) => Some(part),
+        _ => None,
+    }).collect()
+}
+
+// The outer error aborts the extraction (size limits); the inner one only skips this entry.
+// Ok(true) when a file was written, Ok(false) for a directory.
+fn extract_entry(
+    entry: &mut ZipFile,
+    output_dir: &Path,
+    limits: Limits,
+    total: &mut u64,
+    buffer: &mut [u8],
+) -> Result<Result<bool>> {
+    let rel = entry.mangled_name();
+    let out_path = output_dir.join(&rel);
+
+    if entry.is_dir() {
+        return Ok(fs::create_dir_all(&out_path)
+            .with_context(|| format!("无法创建目录: {}", out_path.display()))
+            .map(|_| false));
+    }
+
+    // Declared sizes can lie, but when they are honest this fails before writing anything
+    if entry.size() > limits.max_entry {
+        bail!("ZIP 条目过大: {} 声明 {} 字节，上限 {} 字节", rel.display(), entry.size(), limits.max_entry);
+    }
+
+    write_entry(entry, &rel, &out_path, limits, total, buffer)
+}
+
+// Copies one entry to out_path under the size limits, the part shared by ZIP and .crate
+// extraction. Errors are nested as in `extract_entry`.
+fn write_entry(
+    entry: &mut dyn Read,
+    rel: &Path,
+    out_path: &Path,
+    limits: Limits,
+    total: &mut u64,
+    buffer: &mut [u8],
+) -> Result<Result<bool>> {
+    if let Some(parent) = out_path.parent()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        return Ok(Err(anyhow::Error::new(e).context(format!("无法创建目录: {}", parent.display()))));
+    }
+    let mut out_file = match File::create(out_path) {
+        Ok(file) => file,
+        Err(e) => return Ok(Err(anyhow::Error::new(e).context(format!("无法创建文件: {}", out_path.display())))),
+    };
+    let mut written = 0u64;
+    let copied = loop {
+        let n = match entry.read(buffer) {
+            Ok(0) => break Ok(()),
+            Ok(n) => n,
+            Err(e) => break Err(anyhow::Error::new(e).context(format!("无法读取条目: {}", rel.display()))),
+        };
+        written += n as u64;
+        *total += n as u64;
+        if written > limits.max_entry {
+            bail!("条目解压后超过 {} 字节上限: {}", limits.max_entry, rel.display());
+        }
+        if *total > limits.max_total {
+            bail!("解压后总大小超过 {} 字节上限", limits.max_total);
+        }
+        if let Err(e) = out_file.write_all(&buffer[..n]) {
+            break Err(anyhow::Error::new(e).context(format!("无法写入文件: {}", out_path.display())));
+        }
+    };
+    if let Err(e) = copied {
+        // Don't leave a truncated file behind for discovery to trip over
+        drop(out_file);
+        let _ = fs::remove_file(out_path);
+        return Ok(Err(e));
+    }
+
+    // Only print a line for interesting files to keep logs tidy
+    if let Some(name) = rel.file_name().and_then(|s| s.to_str())
+        && (name.ends_with(".toml") || name.ends_with(".lock") || name == "main.rs")
+    {
+        crate::status!("EXTRACTED: {} -> {}", name, out_path.display());
+    }
+    Ok(Ok(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    // 4 MiB of zeros deflates to a few KiB, which is exactly the shape of a zip bomb
+    fn write_zip(path: &Path, size: usize) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        zip.start_file("proj/Cargo.toml", options).unwrap();
+        zip.write_all(b"[package]\nname = \"proj\"\n").unwrap();
+        zip.start_file("proj/big.bin", options).unwrap();
+        let chunk = vec![0u8; 1024 * 1024];
+        for _ in 0..size / chunk.len() {
+            zip.write_all(&chunk).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_large_entry_within_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("p.zip");
+        write_zip(&zip_path, 4 * 1024 * 1024);
+        let out = dir.path().join("out");
+
+        TomlLockExtractor::extract_with_limits(zip_path.to_str().unwrap(), out.to_str().unwrap(), DEFAULT_LIMITS)
+            .unwrap();
+        assert_eq!(fs::metadata(out.join("proj/big.bin")).unwrap().len(), 4 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_size_caps_abort_extraction() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("p.zip");
+        write_zip(&zip_path, 4 * 1024 * 1024);
+        let zip_path = zip_path.to_str().unwrap();
+
+        let out = dir.path().join("entry");
+        let limits = Limits { max_entry: 1024 * 1024, max_total: u64::MAX };
+        let err = TomlLockExtractor::extract_with_limits(zip_path, out.to_str().unwrap(), limits).unwrap_err();
+        assert!(err.to_string().contains("big.bin"), "{}", err);
+
+        let out = dir.path().join("total");
+        let limits = Limits { max_entry: u64::MAX, max_total: 2 * 1024 * 1024 };
+        let err = TomlLockExtractor::extract_with_limits(zip_path, out.to_str().unwrap(), limits).unwrap_err();
+        assert!(err.to_string().contains("总大小"), "{}", err);
+    }
+
+    #[test]
+    fn test_bad_entries_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("p.zip");
+        let mut zip = ZipWriter::new(File::create(&zip_path).unwrap());
+        let options = FileOptions::default();
+        zip.start_file("proj/Cargo.toml", options).unwrap();
+        zip.write_all(b"[package]\nname = \"proj\"\n").unwrap();
+        // `notes` becomes a file, so nothing can be created below it
+        zip.start_file("proj/notes", options).unwrap();
+        zip.write_all(b"todo").unwrap();
+        zip.start_file("proj/notes/today.txt", options).unwrap();
+        zip.write_all(b"clash").unwrap();
+        zip.start_file("proj/Cargo.lock", options).unwrap();
+        zip.write_all(b"version = 3\n").unwrap();
+        zip.finish().unwrap();
+
+        let out = dir.path().join("out");
+        let skipped = TomlLockExtractor::extract_with_limits(zip_path.to_str().unwrap(), out.to_str().unwrap(), DEFAULT_LIMITS)
+            .unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].name, Path::new("proj/notes/today.txt").display().to_string());
+        assert!(out.join("proj/Cargo.toml").is_file());
+        assert!(out.join("proj/Cargo.lock").is_file());
+
+        // Nothing usable at all is still an error
+        let zip_path = dir.path().join("bad.zip");
+        let mut zip = ZipWriter::new(File::create(&zip_path).unwrap());
+        zip.start_file("proj/Cargo.toml", options).unwrap();
+        zip.finish().unwrap();
+        let out = dir.path().join("bad");
+        fs::create_dir_all(out.join("proj/Cargo.toml")).unwrap();
+        let err = TomlLockExtractor::extract_with_limits(zip_path.to_str().unwrap(), out.to_str().unwrap(), DEFAULT_LIMITS)
+            .unwrap_err();
+        assert!(err.to_string().contains("Cargo.toml"), "{}", err);
+    }
+
+    // Raw names, as `tar::Builder` refuses to write `..` itself
+    fn tar_entry(tar: &mut tar::Builder<impl Write>, name: &str, kind: EntryType, data: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(kind);
+        header.set_size(data.len() as u64);
+        if kind == EntryType::Symlink {
+            header.set_link_name("/etc/passwd").unwrap();
+        }
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append(&header, data).unwrap();
+    }
+
+    #[test]
+    fn test_crate_files_are_extracted_inside_the_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let crate_path = dir.path().join("proj-0.1.0.crate");
+        let gz = flate2::write::GzEncoder::new(File::create(&crate_path).unwrap(), flate2::Compression::default());
+        let mut tar = tar::Builder::new(gz);
+        tar_entry(&mut tar, "proj-0.1.0/Cargo.toml", EntryType::Regular, b"[package]\nname = \"proj\"\n");
+        tar_entry(&mut tar, "proj-0.1.0/../../escape.txt", EntryType::Regular, b"contained");
+        tar_entry(&mut tar, "proj-0.1.0/passwd", EntryType::Symlink, b"");
+        tar.into_inner().unwrap().finish().unwrap();
+        assert!(is_gzip(&crate_path).unwrap());
+        assert!(is_crate_package(&crate_path).unwrap());
+
+        let out = dir.path().join("out");
+        let skipped = TomlLockExtractor::extract_crate_with_limits(crate_path.to_str().unwrap(), out.to_str().unwrap(), DEFAULT_LIMITS)
+            .unwrap();
+        assert!(out.join("proj-0.1.0/Cargo.toml").is_file());
+        assert_eq!(fs::read_to_string(out.join("proj-0.1.0/escape.txt")).unwrap(), "contained");
+        assert!(!dir.path().join("escape.txt").exists());
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].name, Path::new("proj-0.1.0/passwd").display().to_string());
+        assert!(!out.join("proj-0.1.0/passwd").exists());
+
+        let limits = Limits { max_entry: 4, max_total: u64::MAX };
+        let err = TomlLockExtractor::extract_crate_with_limits(crate_path.to_str().unwrap(), dir.path().join("small").to_str().unwrap(), limits)
+            .unwrap_err();
+        assert!(err.to_string().contains("Cargo.toml"), "{}", err);
+    }
+
+    #[test]
+    fn test_other_gzip_files_are_not_crate_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("Cargo.lock.gz");
+        let mut gz = flate2::write::GzEncoder::new(File::create(&plain).unwrap(), flate2::Compression::default());
+        gz.write_all(b"version = 3\n").unwrap();
+        gz.finish().unwrap();
+        assert!(is_gzip(&plain).unwrap());
+        assert!(!is_crate_package(&plain).unwrap());
 
-        Ok(())
+        // A tarball without the `<name>-<version>/` directory
+        let flat = dir.path().join("flat.tar.gz");
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(File::create(&flat).unwrap(), flate2::Compression::default()));
+        tar_entry(&mut tar, "Cargo.toml", EntryType::Regular, b"[package]\nname = \"proj\"\n");
+        tar.into_inner().unwrap().finish().unwrap();
+        assert!(!is_crate_package(&flat).unwrap());
     }
 }
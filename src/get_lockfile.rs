@@ -1,48 +1,483 @@
-use cargo_lock::Lockfile;
+use anyhow::Context;
+use cargo_lock::{Dependency, Lockfile, Package, ResolveVersion};
+use crate::cargo_config::RegistryConfig;
 use crate::extract_zip::TomlLockExtractor;
+use crate::vendor;
+use std::collections::HashMap;
 use std::fs;
-use std::process::Command;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 use walkdir::WalkDir;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // Return both the parsed lockfile and the detected project root directory
 pub struct LockDiscovery {
     pub lockfile: Lockfile,
+    // 各包在 Cargo.lock 中的行号
+    pub lines: LockfileLines,
     pub project_root: PathBuf,
+    // Cargo.lock 在项目内的相对路径（去掉 GitHub ZIP 的顶层目录），例如 `Cargo.lock`
+    pub manifest_path: PathBuf,
+    // 来自 lockfile 同目录 Cargo.toml 的 package.name，没有时用目录名
+    pub project_name: String,
+    // lockfile 旁有 Cargo.toml；只上传了 Cargo.lock 时为 false，不能运行 cargo metadata
+    pub manifests_available: bool,
 }
 
-pub fn get_lockfile(zip_path: &str) -> Result<LockDiscovery, anyhow::Error> {
-    let output_dir = "./tmp";
-    
+impl LockDiscovery {
+    /// lockfile 的格式版本（1-4）
+    pub fn resolve_version(&self) -> u32 {
+        u32::from(self.lockfile.version)
+    }
+}
+
+/// 各 lockfile 共同的格式版本；没有 lockfile 或版本不一致时为 None（各项目的版本见其报告）
+pub fn common_resolve_version(projects: &[LockDiscovery]) -> Option<u32> {
+    let first = projects.first()?.resolve_version();
+    projects.iter().all(|p| p.resolve_version() == first).then_some(first)
+}
+
+// Name of the project rooted at `root`: `[package] name` from its manifest, or the
+// directory name for virtual workspaces and manifests that fail to parse.
+fn project_name(root: &Path) -> String {
+    let from_manifest = fs::read_to_string(root.join("Cargo.toml"))
+        .ok()
+        .and_then(|text| text.parse::<toml::Table>().ok())
+        .and_then(|manifest| manifest.get("package")?.get("name")?.as_str().map(str::to_string));
+    from_manifest.unwrap_or_else(|| {
+        root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+    })
+}
+
+fn discovery(output_dir: &Path, lock_path: &Path, mut lockfile: Lockfile) -> Result<LockDiscovery, anyhow::Error> {
+    let project_root = lock_path
+        .parent()
+        .map(|p| p.to_path_buf())
+        .ok_or_else(|| anyhow::anyhow!("Failed to determine project root from Cargo.lock"))?;
+    // `vendor/` next to the lockfile, plus any directory source the cargo config names
+    let mut vendor_dirs = vec![project_root.join("vendor")];
+    // A broken cargo config only loses registry attribution, it does not stop the scan
+    match RegistryConfig::discover(&project_root, output_dir) {
+        Ok(Some((path, registries))) => {
+            let config_root = path.parent().and_then(Path::parent).unwrap_or(&project_root);
+            vendor_dirs.extend(registries.directory_sources.iter().map(|dir| config_root.join(dir)));
+            let rewritten = registries.apply(&mut lockfile);
+            if rewritten > 0 {
+                crate::status!(
+                    "{} package(s) from a crates.io mirror configured in {}",
+                    rewritten,
+                    archive_relative_path(output_dir, &path).display()
+                );
+            }
+        }
+        Ok(None) => {}
+        Err(e) => crate::console::warn("cargo-config-invalid", format!("{:#}; registry configuration ignored", e)),
+    }
+    reconcile_vendored(output_dir, &vendor_dirs, &mut lockfile);
+    warn_unused_patches(output_dir, lock_path, &lockfile);
+    if lockfile.version < ResolveVersion::V3 {
+        crate::console::warn(
+            "lockfile-format-outdated",
+            format!(
+                "{} uses the V{} lockfile format written by old cargo releases; regenerate it with a current cargo (`cargo update --workspace`)",
+                archive_relative_path(output_dir, lock_path).display(),
+                u32::from(lockfile.version)
+            ),
+        );
+    }
+    Ok(LockDiscovery {
+        lockfile,
+        lines: LockfileLines::load(lock_path),
+        manifest_path: archive_relative_path(output_dir, lock_path),
+        project_name: project_name(&project_root),
+        manifests_available: project_root.join("Cargo.toml").is_file(),
+        project_root,
+    })
+}
+
+fn reconcile_vendored(output_dir: &Path, dirs: &[PathBuf], lockfile: &mut Lockfile) {
+    let vendored = vendor::discover(dirs);
+    if vendored.is_empty() {
+        return;
+    }
+    let result = vendor::reconcile(lockfile, &vendored);
+    let mut locations: Vec<String> = vendored
+        .iter()
+        .filter_map(|c| c.dir.parent())
+        .map(|d| archive_relative_path(output_dir, d).display().to_string())
+        .collect();
+    locations.sort();
+    locations.dedup();
+    crate::status!(
+        "{} vendored crate(s) in {}: {} match Cargo.lock, {} added to the scan",
+        vendored.len(),
+        locations.join(", "),
+        result.matched,
+        result.added.len()
+    );
+    for (krate, locked) in &result.mismatched {
+        let locked: Vec<String> = locked.iter().map(|v| v.to_string()).collect();
+        crate::console::warn(
+            "vendored-version-mismatch",
+            format!(
+                "vendored {} is version {} but Cargo.lock has {}; both are scanned",
+                krate.name,
+                krate.version,
+                locked.join(", ")
+            ),
+        );
+    }
+}
+
+// Build output and vendored crates; `cargo vendor` leaves a `.cargo-checksum.json` in every
+// crate it copies, wherever the vendor directory is
+fn skipped_dir(entry: &walkdir::DirEntry) -> bool {
+    entry.file_type().is_dir()
+        && (matches!(entry.file_name().to_str(), Some("target" | "vendor"))
+            || entry.path().join(".cargo-checksum.json").is_file())
+}
+
+/// 合并多个 lockfile 的包（按 name、version、source 去重，重复的包合并各自的依赖），
+/// 用于对整个压缩包生成 SBOM 和汇总报告
+pub fn merge_lockfiles<'a>(lockfiles: impl IntoIterator<Item = &'a Lockfile>) -> Lockfile {
+    let mut lockfiles = lockfiles.into_iter();
+    let mut merged = lockfiles.next().cloned().expect("at least one lockfile to merge");
+    let mut seen: HashMap<(String, String, Option<String>), usize> =
+        merged.packages.iter().enumerate().map(|(i, p)| (package_key(p), i)).collect();
+    for lockfile in lockfiles {
+        for package in &lockfile.packages {
+            match seen.get(&package_key(package)) {
+                // Path crates of the same name@version in two workspaces, most often
+                Some(&i) => {
+                    let existing = &mut merged.packages[i];
+                    for dep in &package.dependencies {
+                        if !existing.dependencies.contains(dep) {
+                            existing.dependencies.push(dep.clone());
+                        }
+                    }
+                }
+                None => {
+                    seen.insert(package_key(package), merged.packages.len());
+                    merged.packages.push(package.clone());
+                }
+            }
+        }
+    }
+    merged
+}
+
+fn package_key(package: &cargo_lock::Package) -> (String, String, Option<String>) {
+    (
+        package.name.to_string(),
+        package.version.to_string(),
+        package.source.as_ref().map(|s| s.to_string()),
+    )
+}
+
+// Path of `path` relative to the extraction dir, without the single wrapper directory
+// GitHub adds to its archives (`repo-main/...`).
+fn archive_relative_path(output_dir: &Path, path: &Path) -> PathBuf {
+    let rel = path.strip_prefix(output_dir).unwrap_or(path);
+    let top_level: Vec<_> = fs::read_dir(output_dir)
+        .map(|rd| rd.filter_map(|e| e.ok()).collect())
+        .unwrap_or_default();
+    let wrapped = top_level.len() == 1 && top_level[0].path().is_dir();
+    let mut components = rel.components();
+    if wrapped && rel.components().count() > 1 {
+        components.next();
+    }
+    components.as_path().to_path_buf()
+}
+
+/// Cargo.lock 中每个 `[[package]]` 块的起始行（从 1 开始），按 (name, version) 索引
+#[derive(Debug, Clone, Default)]
+pub struct LockfileLines(HashMap<(String, String), usize>);
+
+impl LockfileLines {
+    pub fn from_text(text: &str) -> Self {
+        let mut lines = HashMap::new();
+        // (start line, name, version) of the block being read
+        let mut block: Option<(usize, Option<String>, Option<String>)> = None;
+        let mut finish = |block: Option<(usize, Option<String>, Option<String>)>| {
+            if let Some((start, Some(name), Some(version))) = block {
+                lines.entry((name, version)).or_insert(start);
+            }
+        };
+        for (i, line) in text.trim_start_matches('\u{feff}').lines().enumerate() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                finish(block.take());
+                if line == "[[package]]" {
+                    block = Some((i + 1, None, None));
+                }
+                continue;
+            }
+            let Some((_, name, version)) = &mut block else {
+                continue;
+            };
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches('"').to_string();
+            match key.trim() {
+                "name" => *name = Some(value),
+                "version" => *version = Some(value),
+                _ => {}
+            }
+        }
+        finish(block);
+        LockfileLines(lines)
+    }
+
+    /// 读取失败时返回空表，所有行号都为 None
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path).map(|text| Self::from_text(&text)).unwrap_or_default()
+    }
+
+    pub fn line_of(&self, name: &str, version: &str) -> Option<usize> {
+        self.0.get(&(name.to_string(), version.to_string())).copied()
+    }
+}
+
+/// 读取并解析 Cargo.lock；去掉 Windows 工具常加的 UTF-8 BOM，并把 CRLF 统一为 LF
+pub fn load_lockfile(path: &Path) -> Result<Lockfile, anyhow::Error> {
+    let bytes = fs::read(path)?;
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&bytes);
+    let text = std::str::from_utf8(bytes)
+        .map_err(|e| anyhow::anyhow!("{} is not valid UTF-8: {}", path.display(), e))?;
+    let mut lockfile = text.replace("\r\n", "\n").parse::<Lockfile>()?;
+    resolve_replacements(&mut lockfile);
+    Ok(lockfile)
+}
+
+// `[replace]` leaves a stub in Cargo.lock: the original package, with no dependencies of
+// its own and `replace = "<name> <version> (<source>)"` naming the entry that is built in
+// its place. Dependents still point at the stub, so it would be scanned and listed in the
+// SBOM as if it were used. Point them at the replacement and drop the stub; a stub whose
+// replacement is missing from the lockfile stays. `[patch]` needs nothing here: cargo
+// writes the patched package itself, at its own version and source, and the patches it
+// could not apply are only listed under `[[patch.unused]]` (see `warn_unused_patches`).
+// Returns how many stubs were dropped.
+fn resolve_replacements(lockfile: &mut Lockfile) -> usize {
+    let mut resolved = Vec::new();
+    for (i, stub) in lockfile.packages.iter().enumerate() {
+        let Some(target) = &stub.replace else { continue };
+        let candidates: Vec<&Package> = lockfile
+            .packages
+            .iter()
+            .filter(|p| p.replace.is_none() && target.matches(p))
+            .collect();
+        let replacement = candidates
+            .iter()
+            .find(|p| Dependency::from(**p).source == target.source)
+            .or(candidates.first());
+        if let Some(replacement) = replacement {
+            resolved.push((i, Dependency::from(stub), Dependency::from(*replacement)));
+        }
+    }
+    for (_, stub, replacement) in &resolved {
+        for dep in lockfile.packages.iter_mut().flat_map(|p| p.dependencies.iter_mut()) {
+            if dep.name == stub.name && dep.version == stub.version && (dep.source.is_none() || dep.source == stub.source) {
+                *dep = replacement.clone();
+            }
+        }
+    }
+    for (i, _, _) in resolved.iter().rev() {
+        lockfile.packages.remove(*i);
+    }
+    resolved.len()
+}
+
+// `[[patch.unused]]` lists patches cargo could not apply, typically because the patched
+// version does not satisfy the requirement; the crate they were meant to override is
+// still built, and scanned, at the version in the lockfile.
+fn warn_unused_patches(output_dir: &Path, lock_path: &Path, lockfile: &Lockfile) {
+    if lockfile.patch.unused.is_empty() {
+        return;
+    }
+    let unused: Vec<String> = lockfile.patch.unused.iter().map(|d| format!("{}@{}", d.name, d.version)).collect();
+    crate::console::warn(
+        "patch-unused",
+        format!(
+            "{} has [patch] entries cargo did not use: {}; the crates they override are scanned at their locked versions",
+            archive_relative_path(output_dir, lock_path).display(),
+            unused.join(", ")
+        ),
+    );
+}
+
+/// ZIP 解压使用的临时目录，扫描结束后删除
+pub const OUTPUT_DIR: &str = "./tmp";
+
+// `--keep-temp`: leave the extracted files in place for debugging
+static KEEP_TEMP: AtomicBool = AtomicBool::new(false);
+
+pub fn set_keep_temp(keep: bool) {
+    KEEP_TEMP.store(keep, Ordering::SeqCst);
+}
+
+// Directories of the live TempGuards. A guard on another thread never drops when main
+// returns early (--timeout), so main removes whatever is still listed here instead.
+// Whoever takes a directory off the list removes it, so it is removed once.
+static LIVE_TEMP_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+fn live_temp_dirs() -> std::sync::MutexGuard<'static, Vec<PathBuf>> {
+    LIVE_TEMP_DIRS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 运行结束时删除临时目录，无论成功、出错返回还是 panic；`--keep-temp` 时保留
+pub struct TempGuard {
+    dir: PathBuf,
+    keep: bool,
+}
+
+impl TempGuard {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        live_temp_dirs().push(dir.clone());
+        TempGuard { dir, keep: KEEP_TEMP.load(Ordering::SeqCst) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for TempGuard {
+    fn drop(&mut self) {
+        let mut live = live_temp_dirs();
+        // Already removed by clean_live_temp_dirs
+        let Some(index) = live.iter().position(|d| *d == self.dir) else { return };
+        live.swap_remove(index);
+        drop(live);
+        remove_temp_dir(&self.dir, self.keep);
+    }
+}
+
+/// 删除所有仍存活的 `TempGuard` 的目录（包括其他线程上的），供提前结束进程时使用
+pub fn clean_live_temp_dirs() {
+    clean_live_temp_dirs_where(|_| true);
+}
+
+fn clean_live_temp_dirs_where(select: impl Fn(&Path) -> bool) {
+    let dirs: Vec<PathBuf> = {
+        let mut live = live_temp_dirs();
+        let (taken, kept) = std::mem::take(&mut *live).into_iter().partition(|d| select(d));
+        *live = kept;
+        taken
+    };
+    for dir in dirs {
+        clean_temp_dir(&dir);
+    }
+}
+
+/// 删除临时目录（`--keep-temp` 时只打印其位置）；失败只记录警告，不会 panic
+pub fn clean_temp_dir(dir: &Path) {
+    remove_temp_dir(dir, KEEP_TEMP.load(Ordering::SeqCst));
+}
+
+fn remove_temp_dir(dir: &Path, keep: bool) {
+    if keep {
+        if dir.exists() {
+            crate::status!("Temporary files kept in {} (--keep-temp)", dir.display());
+        }
+        return;
+    }
+    match fs::remove_dir_all(dir) {
+        // Runs that fail before extracting never created it
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            crate::console::warn("temp-cleanup", format!("failed to clean temporary files in {}: {}", dir.display(), e));
+        }
+        _ => {}
+    }
+}
+
+/// 把压缩包中的 Cargo.toml / Cargo.lock 解压到干净的临时目录
+pub fn extract_archive(zip_path: &str) -> Result<(), anyhow::Error> {
+    extract_archive_to(zip_path, Path::new(OUTPUT_DIR))
+}
+
+/// 同 `extract_archive`，解压到指定目录（批量扫描时每个压缩包各用一个目录）；
+/// 也接受 `.crate` 包（gzip 压缩的 tar），按文件头和包内结构识别
+pub fn extract_archive_to(zip_path: &str, output_dir: &Path) -> Result<(), anyhow::Error> {
     // 确保有一个干净的临时目录
     if fs::metadata(output_dir).is_ok() {
         fs::remove_dir_all(output_dir)?;
     }
     fs::create_dir_all(output_dir)?;
 
-    TomlLockExtractor::extract_toml_and_lock_files(zip_path, output_dir)?;
-    
-    // 在解压目录中递归查找 Cargo.lock 文件
-    for entry in WalkDir::new(output_dir).into_iter().filter_map(|e| e.ok()) {
+    let skipped = if crate::extract_zip::is_gzip(Path::new(zip_path))? {
+        if !crate::extract_zip::is_crate_package(Path::new(zip_path))? {
+            anyhow::bail!("{} is gzip-compressed but not a .crate package (no <name>-<version>/Cargo.toml inside)", zip_path);
+        }
+        crate::status!("Detected a .crate package (gzip tar)");
+        TomlLockExtractor::extract_crate_file(zip_path, &output_dir.to_string_lossy())?
+    } else {
+        TomlLockExtractor::extract_toml_and_lock_files(zip_path, &output_dir.to_string_lossy())?
+    };
+    // The rest of the project can do without a stray entry, but not without its manifests:
+    // a lockfile that failed to extract would silently be regenerated with other versions
+    if let Some(entry) = skipped.iter().find(|e| {
+        matches!(Path::new(&e.name).file_name().and_then(|n| n.to_str()), Some("Cargo.lock" | "Cargo.toml"))
+    }) {
+        anyhow::bail!("failed to extract {}: {}", entry.name, entry.error);
+    }
+    for entry in &skipped {
+        crate::console::warn("zip-entry-skipped", format!("archive entry {} skipped: {}", entry.name, entry.error));
+    }
+    Ok(())
+}
+
+/// 查找解压目录中的所有 Cargo.lock（一个仓库里可以有多个独立的工作区）
+pub fn discover_lockfiles() -> Result<Vec<LockDiscovery>, anyhow::Error> {
+    discover_lockfiles_in(Path::new(OUTPUT_DIR))
+}
+
+/// 同 `discover_lockfiles`，在指定的解压目录中查找；一个都没有时尝试离线生成
+pub fn discover_lockfiles_in(output_dir: &Path) -> Result<Vec<LockDiscovery>, anyhow::Error> {
+    // 在解压目录中递归查找 Cargo.lock 文件。Build output and vendored sources are not
+    // projects of their own, even when they happen to contain a lockfile. A lockfile that
+    // does not load is an error: skipping it could end in a freshly generated lockfile,
+    // with versions other than the ones the project builds with.
+    let mut found = Vec::new();
+    let entries = WalkDir::new(output_dir)
+        .into_iter()
+        .filter_entry(|e| !skipped_dir(e))
+        .filter_map(|e| e.ok());
+    for entry in entries {
         if entry.file_name() == "Cargo.lock" {
-            let lock_path = entry.path();
-            if let Ok(lockfile) = Lockfile::load(lock_path) {
-                let project_root = lock_path
-                    .parent()
-                    .map(|p| p.to_path_buf())
-                    .ok_or_else(|| anyhow::anyhow!("Failed to determine project root from Cargo.lock"))?;
-                return Ok(LockDiscovery { lockfile, project_root });
-            }
+            let rel = entry.path().strip_prefix(output_dir).unwrap_or(entry.path());
+            // One message: the caller may only print the outermost context
+            let lockfile = load_lockfile(entry.path())
+                .map_err(|e| anyhow::anyhow!("failed to load {}: {:#}", rel.display(), e))?;
+            found.push(discovery(output_dir, entry.path(), lockfile)?);
         }
     }
+    if !found.is_empty() {
+        // Outermost first, so the repository's main lockfile leads
+        found.sort_by(|a, b| {
+            let depth = |d: &LockDiscovery| d.manifest_path.components().count();
+            depth(a).cmp(&depth(b)).then_with(|| a.manifest_path.cmp(&b.manifest_path))
+        });
+        return Ok(found);
+    }
+    generate_lockfile(output_dir).map(|d| vec![d])
+}
+
+fn generate_lockfile(output_dir: &Path) -> Result<LockDiscovery, anyhow::Error> {
 
     // 如果没有找到 Cargo.lock，尝试查找项目根目录的 Cargo.toml
     // 之前这里强制要求同目录下存在 src/ 才认为是项目根目录。
     // 但我们在 ZIP 解包时只提取了 .toml/.lock 文件，并不会把 src/ 解出来，
     // 导致即使存在 Cargo.toml 仍然无法识别项目根目录，进而无法生成 Cargo.lock。
     // 因此这里放宽判断：找到任意 Cargo.toml 就视为候选根目录。
+    // 与查找 Cargo.lock 一样跳过构建输出和 vendored 依赖的清单
     let mut project_root = None;
-    for entry in WalkDir::new(output_dir).into_iter().filter_map(|e| e.ok()) {
+    let entries = WalkDir::new(output_dir)
+        .into_iter()
+        .filter_entry(|e| !skipped_dir(e))
+        .filter_map(|e| e.ok());
+    for entry in entries {
         if entry.file_name() == "Cargo.toml" {
             let cargo_dir = entry.path().parent()
                 .ok_or_else(|| anyhow::anyhow!("无法获取 Cargo.toml 所在目录"))?;
@@ -54,27 +489,232 @@ pub fn get_lockfile(zip_path: &str) -> Result<LockDiscovery, anyhow::Error> {
 
     // 如果找到项目根目录，尝试生成 lock 文件
     if let Some(root) = project_root {
-        println!("\nNote: No Cargo.lock found, attempting to generate offline...");
+        crate::status!("\nNote: No Cargo.lock found, attempting to generate offline...");
         
         // 运行 cargo generate-lockfile
-        let status = Command::new("cargo")
+        // Output is captured and relayed so that it never mixes into a report streamed to
+        // stdout, and so that it ends up in the run log
+        // --no-exec: the PolicyViolation stays downcastable, so callers can tell it from a failed run
+        let output = crate::exec::command("cargo", &["generate-lockfile"])
+            .context("the archive has no Cargo.lock and generating one needs cargo")?
             .current_dir(&root)
-            .arg("generate-lockfile")
-            .status()
+            .output()
             .map_err(|e| anyhow::anyhow!("无法执行 cargo generate-lockfile: {}", e))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !stdout.trim().is_empty() {
+            crate::status!("{}", stdout.trim_end());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stderr.trim().is_empty() {
+            crate::console::record(stderr.trim_end());
+            crate::console::eprint_line(stderr.trim_end());
+        }
 
-        if !status.success() {
+        if !output.status.success() {
             return Err(anyhow::anyhow!("生成 Cargo.lock 失败，请检查项目依赖配置是否正确"));
         }
 
-        println!("OK generated Cargo.lock");
+        crate::status!("OK generated Cargo.lock");
 
         // 尝试加载生成的 lock 文件
         let lock_path = root.join("Cargo.lock");
-        return Lockfile::load(&lock_path)
-            .map(|lockfile| LockDiscovery { lockfile, project_root: root })
-            .map_err(|e| anyhow::anyhow!("无法加载生成的 Cargo.lock: {}", e));
+        let lockfile = load_lockfile(&lock_path).map_err(|e| anyhow::anyhow!("无法加载生成的 Cargo.lock: {}", e))?;
+        return discovery(output_dir, &lock_path, lockfile);
     }
     
     Err(anyhow::anyhow!("在 ZIP 文件中找不到有效的 Rust 项目结构（需要 Cargo.toml 和 src 目录）。请确保 ZIP 文件包含完整的 Rust 项目"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockfile_lines_point_at_package_blocks() {
+        let text = "\u{feff}# generated\r\nversion = 3\r\n\r\n[[package]]\r\nname = \"a\"\r\nversion = \"1.0.0\"\r\ndependencies = [\r\n \"b\",\r\n]\r\n\r\n[[package]]\r\nversion = \"0.2.0\"\r\nname = \"b\"\r\n\r\n[metadata]\r\nname = \"c\"\r\nversion = \"9.9.9\"\r\n";
+        let lines = LockfileLines::from_text(text);
+        assert_eq!(lines.line_of("a", "1.0.0"), Some(4));
+        assert_eq!(lines.line_of("b", "0.2.0"), Some(11));
+        assert_eq!(lines.line_of("a", "2.0.0"), None);
+        assert_eq!(lines.line_of("c", "9.9.9"), None);
+    }
+
+    #[test]
+    fn test_load_lockfile_with_bom_and_crlf() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Cargo.lock");
+        let text = "version = 3\r\n\r\n[[package]]\r\nname = \"demo\"\r\nversion = \"0.1.0\"\r\n";
+        fs::write(&path, [b"\xEF\xBB\xBF".as_slice(), text.as_bytes()].concat()).unwrap();
+
+        let lockfile = load_lockfile(&path).unwrap();
+        assert_eq!(lockfile.packages.len(), 1);
+        assert_eq!(lockfile.packages[0].name.as_str(), "demo");
+    }
+
+    #[test]
+    fn test_discover_multiple_lockfiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("repo-main");
+        let lock = |name: &str| format!("version = 3\n\n[[package]]\nname = \"{}\"\nversion = \"0.1.0\"\n", name);
+        for (sub, name) in [("", "app"), ("tools/xtask", "xtask"), ("target/package/app-0.1.0", "app")] {
+            let project = root.join(sub);
+            fs::create_dir_all(&project).unwrap();
+            fs::write(project.join("Cargo.lock"), lock(name)).unwrap();
+            fs::write(project.join("Cargo.toml"), format!("[package]\nname = \"{}\"\n", name)).unwrap();
+        }
+        fs::create_dir_all(root.join("fuzz")).unwrap();
+        fs::write(root.join("fuzz/Cargo.lock"), lock("app-fuzz")).unwrap();
+        fs::write(root.join("fuzz/Cargo.toml"), "[workspace]\n").unwrap();
+
+        let found = discover_lockfiles_in(dir.path()).unwrap();
+        let projects: Vec<(String, String)> = found
+            .iter()
+            .map(|d| (d.project_name.clone(), d.manifest_path.to_string_lossy().replace('\\', "/")))
+            .collect();
+        assert_eq!(
+            projects,
+            vec![
+                ("app".to_string(), "Cargo.lock".to_string()),
+                ("fuzz".to_string(), "fuzz/Cargo.lock".to_string()),
+                ("xtask".to_string(), "tools/xtask/Cargo.lock".to_string()),
+            ]
+        );
+
+        let merged = merge_lockfiles(found.iter().map(|d| &d.lockfile));
+        let names: Vec<&str> = merged.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["app", "app-fuzz", "xtask"]);
+        assert_eq!(common_resolve_version(&found), Some(3));
+
+        // One of them regenerated by a newer cargo
+        let mut mixed = found;
+        mixed[1].lockfile.version = ResolveVersion::V4;
+        assert_eq!(common_resolve_version(&mixed), None);
+        assert_eq!(common_resolve_version(&mixed[..1]), Some(3));
+    }
+
+    #[test]
+    fn test_merged_duplicates_union_their_dependencies() {
+        let registry = "source = \"registry+https://github.com/rust-lang/crates.io-index\"";
+        let first: Lockfile = format!(
+            "version = 3\n\n[[package]]\nname = \"shared\"\nversion = \"0.1.0\"\ndependencies = [\"libc\"]\n\n[[package]]\nname = \"libc\"\nversion = \"0.2.0\"\n{}\n",
+            registry
+        )
+        .parse()
+        .unwrap();
+        let second: Lockfile = format!(
+            "version = 3\n\n[[package]]\nname = \"shared\"\nversion = \"0.1.0\"\ndependencies = [\"libc\", \"log\"]\n\n[[package]]\nname = \"libc\"\nversion = \"0.2.0\"\n{0}\n\n[[package]]\nname = \"log\"\nversion = \"0.4.0\"\n{0}\n",
+            registry
+        )
+        .parse()
+        .unwrap();
+        let merged = merge_lockfiles([&first, &second]);
+        assert_eq!(merged.packages.len(), 3);
+        let deps: Vec<&str> = merged.packages[0].dependencies.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(deps, ["libc", "log"]);
+    }
+
+    #[test]
+    fn test_vendored_crates_from_cargo_config_are_scanned() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("repo-main");
+        let write = |path: &str, text: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write("Cargo.toml", "[package]\nname = \"app\"\nversion = \"0.1.0\"\n");
+        write(
+            "Cargo.lock",
+            "version = 3\n\n[[package]]\nname = \"app\"\nversion = \"0.1.0\"\ndependencies = [\"time\"]\n\n[[package]]\nname = \"time\"\nversion = \"0.1.45\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        );
+        write(
+            ".cargo/config.toml",
+            "[source.crates-io]\nreplace-with = \"vendored-sources\"\n\n[source.vendored-sources]\ndirectory = \"third_party\"\n",
+        );
+        write("third_party/time/Cargo.toml", "[package]\nname = \"time\"\nversion = \"0.1.45\"\n");
+        write("third_party/zip/Cargo.toml", "[package]\nname = \"zip\"\nversion = \"0.5.13\"\n");
+        // A vendored crate's own lockfile is not a project
+        write("third_party/zip/Cargo.lock", "version = 3\n");
+        write("third_party/zip/.cargo-checksum.json", "{\"files\":{}}");
+
+        let found = discover_lockfiles_in(dir.path()).unwrap();
+        assert_eq!(found.len(), 1);
+        let packages: Vec<String> = found[0].lockfile.packages.iter().map(|p| format!("{}@{}", p.name, p.version)).collect();
+        assert_eq!(packages, ["app@0.1.0", "time@0.1.45", "zip@0.5.13"]);
+    }
+
+    #[test]
+    fn test_v1_and_v4_lockfiles_give_the_same_checksums() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lockfiles");
+        let checksums = |lockfile: &Lockfile| -> Vec<(String, Option<String>)> {
+            let mut checksums: Vec<_> = lockfile
+                .packages
+                .iter()
+                .map(|p| (format!("{}@{}", p.name, p.version), p.checksum.as_ref().map(|c| c.to_string())))
+                .collect();
+            checksums.sort();
+            checksums
+        };
+        let v1 = load_lockfile(&fixtures.join("v1.lock")).unwrap();
+        let v4 = load_lockfile(&fixtures.join("v4.lock")).unwrap();
+        assert_eq!(v1.version, ResolveVersion::V1);
+        assert_eq!(v4.version, ResolveVersion::V4);
+        assert_eq!(checksums(&v1), checksums(&v4));
+        assert_eq!(checksums(&v1).iter().filter(|(_, c)| c.is_some()).count(), 2);
+    }
+
+    #[test]
+    fn test_temp_guard_cleans_up_on_every_exit() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("tmp");
+        let extracted = || {
+            fs::create_dir_all(dir.join("proj")).unwrap();
+            fs::write(dir.join("proj/Cargo.lock"), "version = 3\n").unwrap();
+        };
+
+        extracted();
+        let failing = || -> Result<(), anyhow::Error> {
+            let _guard = TempGuard::new(&dir);
+            anyhow::bail!("scan failed")
+        };
+        assert!(failing().is_err());
+        assert!(!dir.exists());
+
+        extracted();
+        let panicked = std::panic::catch_unwind(|| {
+            let _guard = TempGuard::new(&dir);
+            panic!("scan panicked");
+        });
+        assert!(panicked.is_err());
+        assert!(!dir.exists());
+
+        // Nothing extracted yet: nothing to remove, and no panic
+        drop(TempGuard::new(&dir));
+
+        extracted();
+        drop(TempGuard { dir: dir.clone(), keep: true });
+        assert!(dir.join("proj/Cargo.lock").is_file());
+    }
+
+    #[test]
+    fn test_abandoned_temp_guards_are_cleaned_up() {
+        let root = tempfile::tempdir().unwrap();
+        let dirs = [root.path().join("rustpj-batch-a"), root.path().join("rustpj-batch-b")];
+        for dir in &dirs {
+            fs::create_dir_all(dir.join("proj")).unwrap();
+        }
+        // A worker that is still busy when main gives up never drops its guard
+        std::mem::forget(TempGuard::new(&dirs[0]));
+        let finished = TempGuard::new(&dirs[1]);
+
+        // Other tests have guards of their own in the same process
+        clean_live_temp_dirs_where(|d| d.starts_with(root.path()));
+        assert!(!dirs[0].exists() && !dirs[1].exists());
+        assert!(!live_temp_dirs().iter().any(|d| d.starts_with(root.path())));
+
+        // The guard that drops afterwards finds its directory gone and leaves a new one alone
+        fs::create_dir_all(&dirs[1]).unwrap();
+        drop(finished);
+        assert!(dirs[1].exists());
+    }
+}
@@ -1,20 +1,39 @@
-use cargo_lock::Lockfile;
+use crate::cargo_metadata::{self, CargoMetadata, CrateInfo, LicenseHistogram, PackageKey};
+use crate::cpe::CpeMappings;
+use crate::graph::DependencyGraph;
+use crate::output::Stamp;
+use crate::workspace::WorkspaceMembers;
+use crate::project::ProjectIdentity;
+use crate::purl::package_url;
+use crate::registry::RegistryMap;
+use crate::scanner::VulnReport;
+use crate::signing::sha256_bytes;
+use crate::vex::{self, Vulnerability};
+use anyhow::{Context, bail};
+use cargo_lock::{Lockfile, Package};
+use flate2::read::GzDecoder;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, Read};
 use std::path::Path;
-use std::process::Command;
+use std::str::FromStr;
 
+/// CycloneDX SBOM 文档
 #[derive(Debug, Serialize, Deserialize)]
-struct CycloneDxBom {
+pub struct CycloneDxBom {
     #[serde(rename = "bomFormat")]
     bom_format: String,
     #[serde(rename = "specVersion")]
     spec_version: String,
+    #[serde(rename = "serialNumber")]
+    serial_number: String,
     version: u32,
     metadata: Metadata,
     components: Vec<Component>,
     dependencies: Vec<Dependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    vulnerabilities: Vec<Vulnerability>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,24 +46,70 @@ struct Dependency {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Metadata {
-    timestamp: String,
-    tools: Vec<Tool>,
+pub struct Metadata {
+    pub timestamp: String,
+    pub tools: Vec<Tool>,
+    // SBOM 描述的主体（根 crate），与 components 中同一 bom-ref 的组件相同
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<Component>,
+    // 依赖边的来源（`rustpj:dependency-graph`），生成过程中的警告（`rustpj:warning:<code>`），
+    // 以及有许可证信息时各许可证的组件数（`rustpj:license-count:<expression>`）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Property {
+    pub name: String,
+    pub value: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Tool {
+pub struct Tool {
     vendor: String,
     name: String,
     version: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Component {
+impl Default for Tool {
+    fn default() -> Self {
+        Tool {
+            vendor: "Custom".to_string(),
+            name: "cargo-sbom-generator".to_string(),
+            version: "1.0.0".to_string(),
+        }
+    }
+}
+
+/// CycloneDX 组件类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ComponentType {
+    Application,
+    Framework,
+    #[default]
+    Library,
+    Container,
+    OperatingSystem,
+    Device,
+    Firmware,
+    File,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Component {
     #[serde(rename = "type")]
-    component_type: String,
+    component_type: ComponentType,
+    // 只有 --project-group 给出的项目组件才有
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
     name: String,
+    // 项目组件没有版本时为空并省略
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     version: String,
+    // 只有映射表中有该 crate 时才生成
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpe: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     purl: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -52,9 +117,29 @@ struct Component {
     bom_ref: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     licenses: Option<Vec<License>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    // 只经由 dev-dependency 引入的包为 `excluded`（需要 cargo metadata 的依赖图）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(rename = "externalReferences")]
+    external_references: Vec<ExternalReference>,
+    // 工作区成员带有 `rustpj:workspace-member`，过程宏带有 `rustpj:proc-macro`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    properties: Vec<Property>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExternalReference {
+    #[serde(rename = "type")]
+    reference_type: String,
+    url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct License {
     #[serde(skip_serializing_if = "Option::is_none")]
     license: Option<LicenseChoice>,
@@ -62,7 +147,7 @@ struct License {
     expression: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct LicenseChoice {
     #[serde(skip_serializing_if = "Option::is_none")]
     id: Option<String>,
@@ -70,124 +155,566 @@ struct LicenseChoice {
     name: Option<String>,
 }
 
+impl CycloneDxBom {
+    pub fn serial_number(&self) -> &str {
+        &self.serial_number
+    }
+
+    /// 读取之前写出的 SBOM（可以是 gzip 压缩的）
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path).with_context(|| format!("failed to open SBOM {}", path.display()))?;
+        let reader: Box<dyn Read> = if crate::output::is_compressed(&path.to_string_lossy()) {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+        let bom: CycloneDxBom =
+            serde_json::from_reader(reader).with_context(|| format!("failed to parse SBOM {}", path.display()))?;
+        if bom.bom_format != "CycloneDX" {
+            bail!("{} is not a CycloneDX SBOM (bomFormat {:?})", path.display(), bom.bom_format);
+        }
+        Ok(bom)
+    }
+
+    /// 把本次生成的 SBOM 并入之前的 SBOM（self）；见 `MergeConflicts`
+    pub fn merge(&mut self, new: CycloneDxBom, conflicts: MergeConflicts) -> anyhow::Result<()> {
+        let mut listed: HashMap<String, usize> = HashMap::new();
+        for (i, component) in self.components.iter().enumerate() {
+            if let Some(bom_ref) = &component.bom_ref {
+                listed.insert(bom_ref.clone(), i);
+            }
+        }
+        for component in new.components {
+            let Some(existing) = component.bom_ref.as_ref().and_then(|r| listed.get(r)).map(|&i| &self.components[i]) else {
+                if let Some(bom_ref) = &component.bom_ref {
+                    listed.insert(bom_ref.clone(), self.components.len());
+                }
+                self.components.push(component);
+                continue;
+            };
+            if (&existing.name, &existing.version, &existing.purl) != (&component.name, &component.version, &component.purl) {
+                let message = format!(
+                    "bom-ref {} is {} in the existing SBOM but {} in this scan",
+                    component.bom_ref.as_deref().unwrap_or_default(),
+                    identity(existing),
+                    identity(&component)
+                );
+                match conflicts {
+                    MergeConflicts::Error => bail!("{}; pass --merge-conflicts warn to keep the existing component", message),
+                    MergeConflicts::Warn => crate::console::warn("sbom-merge-conflict", format!("{}; keeping the existing one", message)),
+                }
+            }
+        }
+
+        let mut edges: HashMap<String, usize> =
+            self.dependencies.iter().enumerate().map(|(i, d)| (d.reference.clone(), i)).collect();
+        for dependency in new.dependencies {
+            match edges.get(&dependency.reference) {
+                Some(&i) => {
+                    let merged = &mut self.dependencies[i].depends_on;
+                    for dep in dependency.depends_on.into_iter().flatten() {
+                        let list = merged.get_or_insert_with(Vec::new);
+                        if !list.contains(&dep) {
+                            list.push(dep);
+                        }
+                    }
+                }
+                None => {
+                    edges.insert(dependency.reference.clone(), self.dependencies.len());
+                    self.dependencies.push(dependency);
+                }
+            }
+        }
+
+        let known: HashSet<String> = self.vulnerabilities.iter().map(|v| v.bom_ref.clone()).collect();
+        self.vulnerabilities.extend(new.vulnerabilities.into_iter().filter(|v| !known.contains(&v.bom_ref)));
+
+        // A new document; the subject stays the one of the first scan
+        self.serial_number = new.serial_number;
+        self.version = new.version;
+        self.metadata.timestamp = new.metadata.timestamp;
+        self.metadata.tools = new.metadata.tools;
+        if self.metadata.component.is_none() {
+            self.metadata.component = new.metadata.component;
+        }
+        let counted = |p: &Property| p.name.starts_with(LICENSE_COUNT_PREFIX);
+        let had_counts = self.metadata.properties.iter().chain(&new.metadata.properties).any(counted);
+        // The merged graph is only as precise as its least precise half
+        let graphs: Vec<bool> = self
+            .metadata
+            .properties
+            .iter()
+            .chain(&new.metadata.properties)
+            .filter(|p| p.name == DEPENDENCY_GRAPH)
+            .map(|p| p.value != "resolved")
+            .collect();
+        let mut properties: Vec<Property> = Vec::new();
+        if !graphs.is_empty() {
+            properties.push(dependency_graph_property(graphs.contains(&true)));
+        }
+        let listed = |p: &Property| counted(p) || p.name == DEPENDENCY_GRAPH;
+        for property in self.metadata.properties.drain(..).chain(new.metadata.properties).filter(|p| !listed(p)) {
+            if !properties.iter().any(|p| p.name == property.name && p.value == property.value) {
+                properties.push(property);
+            }
+        }
+        if had_counts {
+            properties.extend(license_properties(&component_licenses(&self.components)));
+        }
+        self.metadata.properties = properties;
+        Ok(())
+    }
+}
+
+/// `--merge-sbom` 中同一 bom-ref 在两份 SBOM 中指向不同的包（名称、版本或 PURL 不同）时的处理
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeConflicts {
+    // 中止，不写出 SBOM
+    #[default]
+    Error,
+    // 保留已有的组件，记一条 `sbom-merge-conflict` 警告
+    Warn,
+}
+
+impl FromStr for MergeConflicts {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "error" => Ok(MergeConflicts::Error),
+            "warn" => Ok(MergeConflicts::Warn),
+            _ => bail!("invalid merge conflict mode: {} (expected error or warn)", s),
+        }
+    }
+}
+
+fn identity(component: &Component) -> String {
+    match &component.purl {
+        Some(purl) => purl.clone(),
+        None => format!("{}@{}", component.name, component.version),
+    }
+}
+
+// The license histogram over the components of a merged SBOM, counted like `LicenseHistogram::build`
+fn component_licenses(components: &[Component]) -> LicenseHistogram {
+    let mut histogram = LicenseHistogram::default();
+    for component in components {
+        let license = component.licenses.iter().flatten().find_map(|l| {
+            l.expression.clone().or_else(|| l.license.as_ref().and_then(|c| c.id.clone().or_else(|| c.name.clone())))
+        });
+        let key = match license {
+            Some(license) => cargo_metadata::normalize_license(&license),
+            None => {
+                histogram.unknown.push(format!("{}@{}", component.name, component.version));
+                cargo_metadata::UNKNOWN_LICENSE.to_string()
+            }
+        };
+        *histogram.counts.entry(key).or_default() += 1;
+    }
+    histogram
+}
+
+/// 生成 SBOM 所需的除 lockfile 以外的全部输入
+#[derive(Clone, Copy)]
+pub struct SbomOptions<'a> {
+    // 来自一次 `cargo metadata`；没有时省略许可证及描述、作者、链接等组件信息
+    pub metadata: Option<&'a CargoMetadata>,
+    pub members: &'a WorkspaceMembers,
+    pub cpes: &'a CpeMappings,
+    // 配置文件 `[component_types]` 中按 crate 名指定的组件类型
+    pub component_types: &'a BTreeMap<String, ComponentType>,
+    // 给出时其中的漏洞嵌入为 CycloneDX `vulnerabilities`
+    pub report: Option<&'a VulnReport>,
+    // 整个项目的标识，决定 `metadata.component`；没有时取主体 crate
+    pub project: Option<&'a ProjectIdentity>,
+    // 配置文件 `[registries]`，决定私有 registry 包的 purl
+    pub registries: &'a RegistryMap,
+    pub stamp: Stamp,
+}
+
+// Returns the generated BOM's serialNumber so that a standalone VEX document can refer to it.
+// The BOM itself comes from `build_sbom`; this adds the run's warnings and writes it out.
 pub fn generate_sbom_from_lockfile(
     lockfile: &Lockfile,
-    project_root: &Path,
+    options: &SbomOptions,
     output_path: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // 读取并解析 Cargo.lock
-    // let lockfile = Lockfile::load(lockfile_path)?;
-    
-    println!("Fetching license information...");
-    
-    // 一次性获取所有许可证信息
-    let license_cache = fetch_all_licenses(Some(project_root))?;
-    
-    println!("Building SBOM...");
-    
+) -> Result<String, Box<dyn std::error::Error>> {
+    crate::status!("Building SBOM...");
+    let bom = build_with_warnings(lockfile, options);
+    // 序列化为 JSON（或 Protobuf）并写入文件（路径以 .gz 结尾时压缩）
+    write_bom(output_path, &bom, crate::output::OutputSettings::current())?;
+    Ok(report_written(&bom, output_path))
+}
+
+// `--merge-sbom`: the SBOM of this scan merged into `base`, an SBOM written earlier. With
+// `overwrite` the output may replace an existing file, which is how `base` itself is
+// extended in place.
+pub fn generate_merged_sbom(
+    lockfile: &Lockfile,
+    options: &SbomOptions,
+    mut base: CycloneDxBom,
+    conflicts: MergeConflicts,
+    output_path: &str,
+    overwrite: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    crate::status!("Building SBOM and merging it into the existing one...");
+    let before = base.components.len();
+    base.merge(build_with_warnings(lockfile, options), conflicts)?;
+    let mut settings = crate::output::OutputSettings::current();
+    settings.overwrite |= overwrite;
+    write_bom(output_path, &base, settings)?;
+    crate::status!("Merged {} new components into {} existing ones", base.components.len() - before, before);
+    Ok(report_written(&base, output_path))
+}
+
+// `.cdx.bin` paths get the Protobuf encoding, anything else JSON
+fn write_bom(path: &str, bom: &CycloneDxBom, settings: crate::output::OutputSettings) -> anyhow::Result<()> {
+    if crate::cyclonedx_proto::is_protobuf(path) {
+        crate::output::write_bytes_with(path, &crate::cyclonedx_proto::encode(bom)?, settings)?;
+    } else {
+        crate::output::write_json_with(path, bom, settings)?;
+    }
+    Ok(())
+}
+
+fn build_with_warnings(lockfile: &Lockfile, options: &SbomOptions) -> CycloneDxBom {
+    let mut bom = build_sbom(lockfile, options);
+    // Only warnings raised so far; the SBOM is written before the report
+    let warnings = crate::console::warnings()
+        .into_iter()
+        .map(|w| Property { name: format!("rustpj:warning:{}", w.code), value: w.message });
+    bom.metadata.properties.splice(0..0, warnings);
+    bom
+}
+
+fn report_written(bom: &CycloneDxBom, output_path: &str) -> String {
+    crate::status!("SBOM generated successfully at: {}", output_path);
+    crate::status!("Total components: {}", bom.components.len());
+    crate::status!("Total dependencies: {}", bom.dependencies.len());
+    if !bom.vulnerabilities.is_empty() {
+        crate::status!("Embedded vulnerabilities: {}", bom.vulnerabilities.len());
+    }
+    bom.serial_number.clone()
+}
+
+// bom-refs are `name@version`. A lockfile can hold the same name@version from two sources
+// (crates.io and a git fork, say); then the crates.io entry, or failing that the first one,
+// keeps the plain ref, which is also what the vulnerability `affects` point at, and the
+// others get a `#<hash of the source>` suffix so that every ref stays unique. Entries
+// repeating the same name, version and source share one ref; `build_sbom` merges them
+// into one component.
+pub struct BomRefs(HashMap<PackageKey, String>);
+
+impl BomRefs {
+    pub fn new(lockfile: &Lockfile) -> Self {
+        let mut groups: HashMap<String, Vec<&Package>> = HashMap::new();
+        for package in &lockfile.packages {
+            let group = groups.entry(format!("{}@{}", package.name, package.version)).or_default();
+            if !group.iter().any(|p| p.source == package.source) {
+                group.push(package);
+            }
+        }
+
+        let mut refs = HashMap::new();
+        for (plain, packages) in groups {
+            let primary = packages
+                .iter()
+                .position(|p| crate::online::is_default_crates_io(&p.source))
+                .unwrap_or(0);
+            for (i, package) in packages.iter().enumerate() {
+                let source = package.source.as_ref().map(|s| s.to_string());
+                let bom_ref = if i == primary {
+                    plain.clone()
+                } else {
+                    let digest = sha256_bytes(source.as_deref().unwrap_or("path").as_bytes());
+                    format!("{}#{}", plain, &digest[..8])
+                };
+                refs.insert(source_key(package, source), bom_ref);
+            }
+        }
+        BomRefs(refs)
+    }
+
+    pub fn get(&self, package: &Package) -> String {
+        self.by_key(&source_key(package, package.source.as_ref().map(|s| s.to_string())))
+    }
+
+    fn by_key(&self, key: &PackageKey) -> String {
+        self.0.get(key).cloned().unwrap_or_else(|| format!("{}@{}", key.0, key.1))
+    }
+}
+
+fn source_key(package: &Package, source: Option<String>) -> PackageKey {
+    (package.name.to_string(), package.version.to_string(), source)
+}
+
+// The crate the BOM is about: the lockfile's `root` (V1), else the first root nobody
+// depends on, i.e. the top of the workspace (or of the graph, without members)
+fn subject(lockfile: &Lockfile, graph: &DependencyGraph) -> Option<usize> {
+    if let Some(node) = lockfile.root.as_ref().and_then(|root| graph.node_of(root)) {
+        return Some(node);
+    }
+    let roots = graph.roots();
+    roots.iter().copied().find(|&n| graph.dependents_of(n).is_empty()).or(roots.first().copied())
+}
+
+/// 包的直接依赖的 bom-ref，SBOM 的 dependsOn 与 DOT 图的边都由此生成
+pub fn depends_on(graph: &DependencyGraph, refs: &BomRefs, node: usize) -> Vec<String> {
+    graph.dependencies_of(node).iter().map(|&dep| refs.get(graph.package(dep))).collect()
+}
+
+/// 由 lockfile 和 options 构建 SBOM，不读写任何文件
+pub fn build_sbom(lockfile: &Lockfile, options: &SbomOptions) -> CycloneDxBom {
+    let SbomOptions { metadata, members, cpes, component_types, report, project, registries, stamp } = *options;
     // 创建组件列表和依赖关系映射
     let mut components = Vec::new();
     let mut dependencies = Vec::new();
+    let refs = BomRefs::new(lockfile);
+    let graph = DependencyGraph::from_lockfile(lockfile);
+    let subject = subject(lockfile, &graph);
+    let mut subject_component = None;
+    // Edges come from `cargo metadata` where it resolved the package, else from the lockfile
+    let resolved = metadata.and_then(|m| m.resolve.as_ref());
+    let shipped = resolved.zip(metadata).and_then(|(resolved, m)| {
+        let roots: Vec<PackageKey> = m.workspace_members.iter().map(|(n, v)| (n.clone(), v.clone(), None)).collect();
+        (!roots.is_empty()).then(|| resolved.without_dev(&roots))
+    });
+    let mut approximate = false;
     
-    for package in &lockfile.packages {
+    for (node, package) in lockfile.packages.iter().enumerate() {
         let version = package.version.to_string();
         let name = package.name.as_str();
         
         // 生成 PURL (Package URL)
-        let purl = format!("pkg:cargo/{}@{}", name, version);
-        let bom_ref = format!("{}@{}", name, version);
+        let purl = package_url(package, registries);
+        let bom_ref = refs.get(package);
         
-        // 从缓存中获取许可证信息
-        let licenses = license_cache
-            .get(&(name.to_string(), version.clone()))
-            .map(|license_str| parse_license_expression(license_str));
+        // 从 cargo metadata 中获取许可证、描述、作者和链接；离线拿不到时这些字段都省略
+        let info = metadata.and_then(|m| m.get(name, &version));
+        let licenses = info
+            .and_then(|info| info.license.as_deref())
+            .map(parse_license_expression);
         
+        let key = source_key(package, package.source.as_ref().map(|s| s.to_string()));
+        let resolved_deps = resolved.and_then(|r| r.dependencies(&key));
+        let scope = match &shipped {
+            Some(shipped) if resolved_deps.is_some() && !shipped.contains(&key) => Some("excluded".to_string()),
+            _ => None,
+        };
+        let member = members.get(package);
+        let proc_macro = info.is_some_and(|i| i.proc_macro);
+        let mut properties = Vec::new();
+        if member.is_some() {
+            properties.push(Property { name: "rustpj:workspace-member".to_string(), value: "true".to_string() });
+        }
+        if proc_macro {
+            properties.push(Property { name: "rustpj:proc-macro".to_string(), value: "true".to_string() });
+        }
+        // The configured type wins; then what the member's manifest says, with the bin/lib split;
+        // the root crate without a manifest is the application; everything else is a library
+        let component_type = match component_types.get(name) {
+            Some(&configured) => configured,
+            None => match member {
+                Some(m) if m.application => ComponentType::Application,
+                Some(_) => ComponentType::Library,
+                None if subject == Some(node) => ComponentType::Application,
+                None => ComponentType::Library,
+            },
+        };
         components.push(Component {
-            component_type: "library".to_string(),
+            component_type,
+            group: None,
             name: name.to_string(),
             version: version.clone(),
+            cpe: cpes.cpe(package),
             purl: Some(purl),
             bom_ref: Some(bom_ref.clone()),
             licenses,
+            author: info.map(|i| i.authors.join(", ")).filter(|a| !a.is_empty()),
+            description: info.and_then(|i| i.description.clone()),
+            scope,
+            external_references: external_references(info, package),
+            properties,
         });
         
+        if subject == Some(node) {
+            subject_component = components.last().cloned();
+        }
+
         // 构建依赖关系
-        let mut depends_on = Vec::new();
-        for dep in &package.dependencies {
-            // Try to resolve by name; Cargo.lock may contain multiple versions of a crate.
-            // We conservatively include edges by name only in offline mode.
-            if let Some(dep_pkg) = lockfile
-                .packages
-                .iter()
-                .find(|p| p.name.as_str() == dep.name.as_str())
-            {
-                let dep_ref = format!("{}@{}", dep.name.as_str(), dep_pkg.version);
-                depends_on.push(dep_ref);
+        let depends_on = match resolved_deps {
+            Some(deps) => {
+                let mut depends_on: Vec<String> = Vec::new();
+                for dep in deps {
+                    let bom_ref = refs.by_key(&dep.package);
+                    if !depends_on.contains(&bom_ref) {
+                        depends_on.push(bom_ref);
+                    }
+                }
+                depends_on
             }
-        }
-        
+            None => {
+                approximate = true;
+                depends_on(&graph, &refs, node)
+            }
+        };
         dependencies.push(Dependency {
             reference: bom_ref,
             depends_on: if depends_on.is_empty() { None } else { Some(depends_on) },
         });
     }
     
+    merge_duplicates(&mut components, &mut dependencies);
+    if let Some(project) = project {
+        subject_component = Some(project_component(project, subject_component, &mut components, &mut dependencies, &graph, &refs));
+    }
+
     // 创建 SBOM
-    let bom = CycloneDxBom {
+    CycloneDxBom {
         bom_format: "CycloneDX".to_string(),
         spec_version: "1.4".to_string(),
+        serial_number: stamp.serial_number(),
         version: 1,
         metadata: Metadata {
-            timestamp: chrono::Utc::now().to_rfc3339(),
-            tools: vec![Tool {
-                vendor: "Custom".to_string(),
-                name: "cargo-sbom-generator".to_string(),
-                version: "1.0.0".to_string(),
-            }],
+            timestamp: stamp.timestamp(),
+            tools: vec![Tool::default()],
+            component: subject_component,
+            properties: std::iter::once(dependency_graph_property(approximate))
+                .chain(metadata.map(|m| license_properties(&LicenseHistogram::build(lockfile, m))).into_iter().flatten())
+                .collect(),
         },
         components,
         dependencies,
-    };
-    
-    // 序列化为 JSON
-    let json = serde_json::to_string_pretty(&bom)?;
-    
-    // 写入文件
-    fs::write(output_path, json)?;
-    
-    println!("SBOM generated successfully at: {}", output_path);
-    println!("Total components: {}", bom.components.len());
-    println!("Total dependencies: {}", bom.dependencies.len());
-    
-    Ok(())
+        vulnerabilities: report
+            .map(|r| vex::vulnerabilities_from_report(r, ""))
+            .unwrap_or_default(),
+    }
 }
 
-fn fetch_all_licenses(current_dir: Option<&Path>) -> Result<HashMap<(String, String), String>, Box<dyn std::error::Error>> {
-    let mut license_map = HashMap::new();
-    // Try to execute `cargo metadata` in the extracted project root in offline mode.
-    let mut cmd = Command::new("cargo");
-    cmd.args(["metadata", "--format-version=1", "--offline", "--locked"]);
-    if let Some(dir) = current_dir { cmd.current_dir(dir); }
-    let output = cmd.output()?;
-    if !output.status.success() {
-        eprintln!("Warning: cargo metadata failed in offline mode, licenses will not be included");
-        return Ok(license_map);
+// The project as `metadata.component`. When it is the subject crate (same name, and the
+// same version if it has one) that crate's component is used, with the group added to the
+// listed copy as well. Otherwise (a virtual workspace, a placeholder or overridden name)
+// the project gets a component of its own, outside `components`, whose dependencies are
+// the workspace roots; its ref gets a `#project` suffix should it clash with a listed one.
+fn project_component(
+    project: &ProjectIdentity,
+    subject: Option<Component>,
+    components: &mut [Component],
+    dependencies: &mut Vec<Dependency>,
+    graph: &DependencyGraph,
+    refs: &BomRefs,
+) -> Component {
+    if let Some(subject) = subject
+        && subject.name == project.name
+        && project.version.as_ref().is_none_or(|v| *v == subject.version)
+    {
+        let listed = components.iter_mut().find(|c| c.bom_ref == subject.bom_ref);
+        return match listed {
+            Some(listed) => {
+                listed.group = project.group.clone();
+                listed.clone()
+            }
+            None => subject,
+        };
     }
-    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)?;
-    
-    // 遍历所有包并提取许可证
-    if let Some(packages) = metadata["packages"].as_array() {
-        for pkg in packages {
-            if let (Some(name), Some(version), Some(license)) = (
-                pkg["name"].as_str(),
-                pkg["version"].as_str(),
-                pkg["license"].as_str(),
-            ) {
-                license_map.insert((name.to_string(), version.to_string()), license.to_string());
+    let version = project.version.clone().unwrap_or_default();
+    let mut bom_ref = if version.is_empty() { project.name.clone() } else { format!("{}@{}", project.name, version) };
+    if dependencies.iter().any(|d| d.reference == bom_ref) {
+        bom_ref.push_str("#project");
+    }
+    let mut roots: Vec<String> = graph.roots().iter().map(|&n| refs.get(graph.package(n))).collect();
+    roots.sort();
+    roots.dedup();
+    dependencies.push(Dependency {
+        reference: bom_ref.clone(),
+        depends_on: if roots.is_empty() { None } else { Some(roots) },
+    });
+    Component {
+        component_type: ComponentType::Application,
+        group: project.group.clone(),
+        name: project.name.clone(),
+        version,
+        cpe: None,
+        purl: None,
+        bom_ref: Some(bom_ref),
+        licenses: None,
+        author: None,
+        description: None,
+        scope: None,
+        external_references: Vec::new(),
+        properties: Vec::new(),
+    }
+}
+
+const DEPENDENCY_GRAPH: &str = "rustpj:dependency-graph";
+
+// `resolved`: every edge is from `cargo metadata`, which knows the dependency kinds.
+// `approximate`: some or all are from Cargo.lock, whose edges point at the exact packages
+// but do not say whether they are dev-, build- or normal dependencies.
+fn dependency_graph_property(approximate: bool) -> Property {
+    let value = if approximate { "approximate" } else { "resolved" };
+    Property { name: DEPENDENCY_GRAPH.to_string(), value: value.to_string() }
+}
+
+// CycloneDX requires unique bom-refs, so a package listed twice (same name, version and
+// source, hence the same ref) becomes one component, the first, depending on everything
+// either entry depends on. `components` and `dependencies` are parallel here.
+fn merge_duplicates(components: &mut Vec<Component>, dependencies: &mut Vec<Dependency>) {
+    let mut first: HashMap<String, usize> = HashMap::new();
+    let mut keep = Vec::with_capacity(components.len());
+    for i in 0..dependencies.len() {
+        let reference = dependencies[i].reference.clone();
+        match first.get(&reference) {
+            Some(&kept) => {
+                if let Some(extra) = dependencies[i].depends_on.take() {
+                    let merged = dependencies[kept].depends_on.get_or_insert_with(Vec::new);
+                    for dep in extra {
+                        if !merged.contains(&dep) {
+                            merged.push(dep);
+                        }
+                    }
+                }
+                keep.push(false);
+            }
+            None => {
+                first.insert(reference, i);
+                keep.push(true);
             }
         }
     }
-    Ok(license_map)
+    let mut flags = keep.iter();
+    components.retain(|_| *flags.next().unwrap());
+    let mut flags = keep.iter();
+    dependencies.retain(|_| *flags.next().unwrap());
+}
+
+const LICENSE_COUNT_PREFIX: &str = "rustpj:license-count:";
+
+// One `rustpj:license-count:<expression>` per histogram entry, most common first
+fn license_properties(histogram: &LicenseHistogram) -> Vec<Property> {
+    histogram
+        .ranked()
+        .into_iter()
+        .map(|(license, count)| Property { name: format!("{}{}", LICENSE_COUNT_PREFIX, license), value: count.to_string() })
+        .collect()
+}
+
+// The manifest's links, then for a git dependency the repository at the locked commit
+// (`<url>#<commit>`), which is what was actually built
+fn external_references(info: Option<&CrateInfo>, package: &Package) -> Vec<ExternalReference> {
+    let mut references: Vec<ExternalReference> = info
+        .into_iter()
+        .flat_map(|info| [("website", &info.homepage), ("vcs", &info.repository), ("documentation", &info.documentation)])
+        .filter_map(|(kind, url)| {
+            url.as_ref().map(|url| ExternalReference { reference_type: kind.to_string(), url: url.clone() })
+        })
+        .collect();
+    if let Some(source) = package.source.as_ref().filter(|s| s.is_git())
+        && let Some(commit) = source.precise()
+    {
+        references.push(ExternalReference { reference_type: "vcs".to_string(), url: format!("{}#{}", source.url(), commit) });
+    }
+    references
 }
 
 fn parse_license_expression(license_str: &str) -> Vec<License> {
@@ -209,3 +736,424 @@ fn parse_license_expression(license_str: &str) -> Vec<License> {
         }]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use std::path::Path;
+    use std::str::FromStr;
+
+    fn sbom(
+        lockfile: &Lockfile,
+        metadata: Option<&CargoMetadata>,
+        members: &WorkspaceMembers,
+        cpes: &CpeMappings,
+        component_types: &BTreeMap<String, ComponentType>,
+    ) -> CycloneDxBom {
+        build_sbom(lockfile, &SbomOptions { metadata, members, cpes, component_types, report: None, project: None, registries: &RegistryMap::default(), stamp: Stamp::now() })
+    }
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+dependencies = ["serde"]
+
+[[package]]
+name = "serde"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    fn component<'a>(bom: &'a CycloneDxBom, name: &str) -> &'a Component {
+        bom.components.iter().find(|c| c.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_components_enriched_from_metadata() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        let metadata = CargoMetadata::from_json(&json!({
+            "packages": [{
+                "id": "serde-id", "name": "serde", "version": "1.0.0",
+                "license": "MIT OR Apache-2.0",
+                "description": "A serialization framework\n",
+                "authors": ["Erick Tryzelaar", "David Tolnay"],
+                "homepage": "https://serde.rs",
+                "repository": "https://github.com/serde-rs/serde",
+                "documentation": null,
+            }],
+        }));
+        let bom = sbom(&lockfile, Some(&metadata), &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+        let serde = component(&bom, "serde");
+
+        assert_eq!(serde.description.as_deref(), Some("A serialization framework"));
+        assert_eq!(serde.author.as_deref(), Some("Erick Tryzelaar, David Tolnay"));
+        let refs: Vec<_> = serde.external_references.iter().map(|r| (r.reference_type.as_str(), r.url.as_str())).collect();
+        assert_eq!(refs, vec![("website", "https://serde.rs"), ("vcs", "https://github.com/serde-rs/serde")]);
+        let counts: Vec<_> = bom.metadata.properties.iter().map(|p| (p.name.as_str(), p.value.as_str())).collect();
+        assert_eq!(
+            counts,
+            [
+                ("rustpj:dependency-graph", "approximate"),
+                ("rustpj:license-count:Apache-2.0 OR MIT", "1"),
+                ("rustpj:license-count:unknown", "1")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_edges_from_resolved_graph() {
+        let lockfile = Lockfile::from_str(
+            r#"
+version = 3
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+dependencies = ["log 0.4.20", "tempfile"]
+
+[[package]]
+name = "log"
+version = "0.3.9"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["log 0.4.20"]
+
+[[package]]
+name = "log"
+version = "0.4.20"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "tempfile"
+version = "3.8.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["log 0.3.9"]
+"#,
+        )
+        .unwrap();
+        let registry = "registry+https://github.com/rust-lang/crates.io-index";
+        let package = |id: &str, name: &str, version: &str, source: Option<&str>| {
+            json!({"id": id, "name": name, "version": version, "source": source})
+        };
+        let dep = |pkg: &str, kind: Option<&str>| json!({"pkg": pkg, "dep_kinds": [{"kind": kind, "target": null}]});
+        let metadata = CargoMetadata::from_json(&json!({
+            "packages": [
+                package("demo-id", "demo", "0.1.0", None),
+                package("log-old", "log", "0.3.9", Some(registry)),
+                package("log-new", "log", "0.4.20", Some(registry)),
+                package("tempfile-id", "tempfile", "3.8.0", Some(registry)),
+            ],
+            "workspace_members": ["demo-id"],
+            "resolve": {"nodes": [
+                {"id": "demo-id", "deps": [dep("log-new", None), dep("tempfile-id", Some("dev"))]},
+                {"id": "log-old", "deps": [dep("log-new", None)]},
+                {"id": "log-new", "deps": []},
+                {"id": "tempfile-id", "deps": [dep("log-old", None)]},
+            ]},
+        }));
+        let bom = sbom(&lockfile, Some(&metadata), &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+        let depends_on = |r: &str| bom.dependencies.iter().find(|d| d.reference == r).unwrap().depends_on.clone().unwrap_or_default();
+        assert_eq!(depends_on("demo@0.1.0"), ["log@0.4.20", "tempfile@3.8.0"]);
+        assert_eq!(depends_on("log@0.3.9"), ["log@0.4.20"]);
+        assert_eq!(bom.metadata.properties[0].value, "resolved");
+
+        // tempfile and the old log only come in through a dev-dependency
+        let scopes: Vec<_> = bom.components.iter().map(|c| (c.bom_ref.as_deref().unwrap(), c.scope.as_deref())).collect();
+        assert_eq!(
+            scopes,
+            [
+                ("demo@0.1.0", None),
+                ("log@0.3.9", Some("excluded")),
+                ("log@0.4.20", None),
+                ("tempfile@3.8.0", Some("excluded"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_git_components_reference_the_locked_commit() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lockfiles/git.lock");
+        let lockfile = Lockfile::load(path).unwrap();
+        let bom = sbom(&lockfile, None, &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+        let refs: Vec<_> = component(&bom, "by-branch").external_references.iter().map(|r| (r.reference_type.as_str(), r.url.as_str())).collect();
+        assert_eq!(refs, [("vcs", "https://github.com/acme/by-branch#5f2c1a9e8d7b6c5a4f3e2d1c0b9a8f7e6d5c4b3a")]);
+        assert!(component(&bom, "serde").external_references.is_empty());
+    }
+
+    #[test]
+    fn test_components_without_metadata_degrade() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        let bom = sbom(&lockfile, None, &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+        let value = serde_json::to_value(component(&bom, "serde")).unwrap();
+        for field in ["licenses", "author", "description", "externalReferences"] {
+            assert!(value.get(field).is_none(), "{} should be omitted", field);
+        }
+        // Nothing to count without a license lookup, and only the lockfile's edges
+        let properties: Vec<_> = bom.metadata.properties.iter().map(|p| (p.name.as_str(), p.value.as_str())).collect();
+        assert_eq!(properties, [("rustpj:dependency-graph", "approximate")]);
+    }
+
+    #[test]
+    fn test_cpe_only_for_mapped_components() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cpe.toml");
+        fs::write(&path, "[serde]\nvendor = \"serde-rs\"\nproduct = \"serde\"\n").unwrap();
+        let bom = sbom(&lockfile, None, &WorkspaceMembers::default(), &CpeMappings::load(&path).unwrap(), &BTreeMap::new());
+        assert_eq!(component(&bom, "serde").cpe.as_deref(), Some("cpe:2.3:a:serde-rs:serde:1.0.0:*:*:*:*:*:*:*"));
+        let demo = serde_json::to_value(component(&bom, "demo")).unwrap();
+        assert!(demo.get("cpe").is_none());
+    }
+
+    #[test]
+    fn test_subject_component_matches_listed_component() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        let bom = sbom(&lockfile, None, &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+        let subject = bom.metadata.component.as_ref().unwrap();
+        assert_eq!((subject.name.as_str(), subject.bom_ref.as_deref()), ("demo", Some("demo@0.1.0")));
+        let listed = bom.components.iter().find(|c| c.bom_ref == subject.bom_ref).unwrap();
+        assert_eq!(serde_json::to_value(listed).unwrap(), serde_json::to_value(subject).unwrap());
+
+        // Without workspace members the package nobody depends on is the subject
+        let lockfile = Lockfile::from_str(&LOCKFILE.replace(
+            "version = \"0.1.0\"\n",
+            "version = \"0.1.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        ))
+        .unwrap();
+        let bom = sbom(&lockfile, None, &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+        assert_eq!(bom.metadata.component.unwrap().bom_ref.as_deref(), Some("demo@0.1.0"));
+    }
+
+    #[test]
+    fn test_workspace_member_component_types() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/workspace");
+        let lockfile = Lockfile::load(root.join("Cargo.lock")).unwrap();
+        let bom = sbom(&lockfile, None, &WorkspaceMembers::discover(&root), &CpeMappings::default(), &BTreeMap::new());
+
+        let kind = |name: &str| {
+            let component = component(&bom, name);
+            let member = component.properties.iter().any(|p| p.name == "rustpj:workspace-member" && p.value == "true");
+            (component.component_type, member)
+        };
+        assert_eq!(kind("fixture-app"), (ComponentType::Application, true));
+        assert_eq!(kind("fixture-core"), (ComponentType::Library, true));
+        assert_eq!(kind("itoa"), (ComponentType::Library, false));
+    }
+
+    #[test]
+    fn test_component_types_inferred_and_overridden() {
+        let lockfile = Lockfile::from_str(&format!(
+            "{}\n[[package]]\nname = \"serde_derive\"\nversion = \"1.0.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+            LOCKFILE.replace("dependencies = [\"serde\"]", "dependencies = [\"serde\", \"serde_derive\"]")
+        ))
+        .unwrap();
+        let metadata = CargoMetadata::from_json(&json!({
+            "packages": [{
+                "id": "serde_derive-id", "name": "serde_derive", "version": "1.0.0",
+                "targets": [{"name": "serde_derive", "kind": ["proc-macro"]}],
+            }],
+        }));
+        let bom = sbom(&lockfile, Some(&metadata), &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+        // Without a manifest the root crate is the application
+        assert_eq!(component(&bom, "demo").component_type, ComponentType::Application);
+        assert_eq!(component(&bom, "serde").component_type, ComponentType::Library);
+        let derive = component(&bom, "serde_derive");
+        assert_eq!(derive.component_type, ComponentType::Library);
+        assert!(derive.properties.iter().any(|p| p.name == "rustpj:proc-macro" && p.value == "true"));
+
+        let overrides = BTreeMap::from([("serde_derive".to_string(), ComponentType::Framework), ("demo".to_string(), ComponentType::Library)]);
+        let bom = sbom(&lockfile, Some(&metadata), &WorkspaceMembers::default(), &CpeMappings::default(), &overrides);
+        assert_eq!(component(&bom, "serde_derive").component_type, ComponentType::Framework);
+        assert_eq!(component(&bom, "demo").component_type, ComponentType::Library);
+        assert_eq!(serde_json::to_value(component(&bom, "serde_derive")).unwrap()["type"], "framework");
+    }
+
+    #[test]
+    fn test_colliding_name_version_gets_unique_refs() {
+        let mut lockfile = Lockfile::from_str(r#"
+version = 3
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+dependencies = [
+ "log 0.4.20 (git+https://example.com/log-fork?branch=main#0123456789abcdef0123456789abcdef01234567)",
+ "tools",
+]
+
+[[package]]
+name = "log"
+version = "0.4.20"
+source = "git+https://example.com/log-fork?branch=main#0123456789abcdef0123456789abcdef01234567"
+
+[[package]]
+name = "log"
+version = "0.4.20"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "tools"
+version = "0.1.0"
+dependencies = ["log 0.4.20 (registry+https://github.com/rust-lang/crates.io-index)"]
+"#).unwrap();
+        // A repeated crates.io entry must not push the crates.io release off its plain ref
+        lockfile.packages.push(lockfile.packages[2].clone());
+        let bom = sbom(&lockfile, None, &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+
+        let refs: Vec<&str> = bom.components.iter().map(|c| c.bom_ref.as_deref().unwrap()).collect();
+        assert_eq!(refs.len(), 4);
+        let fork = refs.iter().find(|r| r.starts_with("log@0.4.20#")).unwrap();
+        assert!(refs.contains(&"log@0.4.20"));
+        assert_eq!(refs.iter().collect::<std::collections::HashSet<_>>().len(), refs.len());
+
+        let depends = |r: &str| {
+            bom.dependencies.iter().find(|d| d.reference == r).unwrap().depends_on.clone().unwrap_or_default()
+        };
+        assert_eq!(depends("demo@0.1.0"), vec![fork.to_string(), "tools@0.1.0".to_string()]);
+        assert_eq!(depends("tools@0.1.0"), vec!["log@0.4.20".to_string()]);
+    }
+
+    #[test]
+    fn test_merge_into_existing_sbom() {
+        let metadata = CargoMetadata::from_json(&json!({
+            "packages": [{"id": "serde-id", "name": "serde", "version": "1.0.0", "license": "MIT"}],
+        }));
+        let first = sbom(&Lockfile::from_str(LOCKFILE).unwrap(), Some(&metadata), &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sbom.json");
+        fs::write(&path, serde_json::to_string(&first).unwrap()).unwrap();
+        let mut merged = CycloneDxBom::load(&path).unwrap();
+
+        let second = Lockfile::from_str(
+            r#"
+version = 3
+
+[[package]]
+name = "itoa"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["itoa"]
+
+[[package]]
+name = "tool"
+version = "0.2.0"
+dependencies = ["serde"]
+"#,
+        )
+        .unwrap();
+        let second = sbom(&second, None, &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+        let serial = second.serial_number.clone();
+        merged.merge(second, MergeConflicts::Error).unwrap();
+
+        let refs: Vec<&str> = merged.components.iter().map(|c| c.bom_ref.as_deref().unwrap()).collect();
+        assert_eq!(refs, ["demo@0.1.0", "serde@1.0.0", "itoa@1.0.0", "tool@0.2.0"]);
+        let serde = merged.dependencies.iter().find(|d| d.reference == "serde@1.0.0").unwrap();
+        assert_eq!(serde.depends_on, Some(vec!["itoa@1.0.0".to_string()]));
+        assert_eq!(merged.dependencies.len(), 4);
+        // The license of the first scan's serde is kept; the counts cover every component
+        assert_eq!(component(&merged, "serde").licenses.as_ref().unwrap()[0].license.as_ref().unwrap().id.as_deref(), Some("MIT"));
+        let counts: Vec<_> = merged.metadata.properties.iter().map(|p| (p.name.as_str(), p.value.as_str())).collect();
+        assert_eq!(
+            counts,
+            [
+                ("rustpj:dependency-graph", "approximate"),
+                ("rustpj:license-count:unknown", "3"),
+                ("rustpj:license-count:MIT", "1")
+            ]
+        );
+        assert_eq!(merged.serial_number, serial);
+        assert_eq!(merged.metadata.component.as_ref().unwrap().name, "demo");
+    }
+
+    #[test]
+    fn test_merge_conflicting_bom_ref() {
+        let forked = Lockfile::from_str(
+            "version = 3\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.0\"\nsource = \"git+https://github.com/acme/serde?rev=abc#abc\"\n",
+        )
+        .unwrap();
+        let build = |lockfile: &Lockfile| sbom(lockfile, None, &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+        let original = Lockfile::from_str(LOCKFILE).unwrap();
+
+        let mut merged = build(&original);
+        let error = merged.merge(build(&forked), MergeConflicts::Error).unwrap_err().to_string();
+        assert!(error.starts_with("bom-ref serde@1.0.0 is pkg:cargo/serde@1.0.0 in the existing SBOM but"), "{}", error);
+
+        let mut merged = build(&original);
+        merged.merge(build(&forked), MergeConflicts::Warn).unwrap();
+        assert_eq!(component(&merged, "serde").purl.as_deref(), Some("pkg:cargo/serde@1.0.0"));
+        assert!(crate::console::warnings().iter().any(|w| w.code == "sbom-merge-conflict"));
+        assert!("ignore".parse::<MergeConflicts>().is_err());
+    }
+
+    #[test]
+    fn test_duplicate_packages_become_one_component() {
+        let mut lockfile = Lockfile::from_str(&format!(
+            "{}\n[[package]]\nname = \"itoa\"\nversion = \"1.0.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+            LOCKFILE
+        ))
+        .unwrap();
+        // The same serde entry once more, this time depending on itoa
+        let mut serde = lockfile.packages.iter().find(|p| p.name.as_str() == "serde").unwrap().clone();
+        let itoa = lockfile.packages.iter().find(|p| p.name.as_str() == "itoa").unwrap();
+        serde.dependencies.push(cargo_lock::Dependency::from(itoa));
+        lockfile.packages.push(serde);
+
+        let bom = sbom(&lockfile, None, &WorkspaceMembers::default(), &CpeMappings::default(), &BTreeMap::new());
+        let refs: Vec<&str> = bom.components.iter().map(|c| c.bom_ref.as_deref().unwrap()).collect();
+        assert_eq!(refs, ["demo@0.1.0", "serde@1.0.0", "itoa@1.0.0"]);
+        let refs: Vec<&str> = bom.dependencies.iter().map(|d| d.reference.as_str()).collect();
+        assert_eq!(refs, ["demo@0.1.0", "serde@1.0.0", "itoa@1.0.0"]);
+        let serde = bom.dependencies.iter().find(|d| d.reference == "serde@1.0.0").unwrap();
+        assert_eq!(serde.depends_on, Some(vec!["itoa@1.0.0".to_string()]));
+    }
+
+    #[test]
+    fn test_project_identity_as_metadata_component() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        let build = |project: &ProjectIdentity| {
+            let (members, cpes, types) = (WorkspaceMembers::default(), CpeMappings::default(), BTreeMap::new());
+            let options = SbomOptions {
+                metadata: None,
+                members: &members,
+                cpes: &cpes,
+                component_types: &types,
+                report: None,
+                project: Some(project),
+                registries: &RegistryMap::default(),
+                stamp: Stamp::now(),
+            };
+            build_sbom(&lockfile, &options)
+        };
+
+        // The subject crate itself: its component, group added to the listed copy too
+        let project = ProjectIdentity { name: "demo".to_string(), version: None, group: Some("com.example".to_string()) };
+        let bom = build(&project);
+        let subject = bom.metadata.component.as_ref().unwrap();
+        assert_eq!(subject.bom_ref.as_deref(), Some("demo@0.1.0"));
+        assert_eq!(component(&bom, "demo").group.as_deref(), Some("com.example"));
+
+        // Anything else gets its own component depending on the workspace roots
+        let project = ProjectIdentity { name: "acme".to_string(), version: Some("2.0.0".to_string()), group: None };
+        let bom = build(&project);
+        let subject = bom.metadata.component.as_ref().unwrap();
+        assert_eq!((subject.name.as_str(), subject.bom_ref.as_deref()), ("acme", Some("acme@2.0.0")));
+        assert_eq!(subject.component_type, ComponentType::Application);
+        assert!(bom.components.iter().all(|c| c.name != "acme"));
+        let root = bom.dependencies.iter().find(|d| d.reference == "acme@2.0.0").unwrap();
+        assert_eq!(root.depends_on, Some(vec!["demo@0.1.0".to_string()]));
+
+        // Not clashing with a listed component of that name@version
+        let project = ProjectIdentity { name: "serde".to_string(), version: Some("1.0.0".to_string()), group: None };
+        let bom = build(&project);
+        assert_eq!(bom.metadata.component.unwrap().bom_ref.as_deref(), Some("serde@1.0.0#project"));
+    }
+}
@@ -0,0 +1,326 @@
+use crate::graph::{DependencyGraph, DependencyKind};
+use crate::output::Stamp;
+use crate::purl::package_url;
+use crate::registry::RegistryMap;
+use anyhow::{Result, anyhow};
+use cargo_lock::Lockfile;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+
+// GitHub dependency submission API snapshot
+// https://docs.github.com/en/rest/dependency-graph/dependency-submission
+#[derive(Debug, Serialize)]
+pub struct Snapshot {
+    version: u32,
+    job: Job,
+    sha: String,
+    #[serde(rename = "ref")]
+    git_ref: String,
+    detector: Detector,
+    scanned: String,
+    manifests: BTreeMap<String, Manifest>,
+}
+
+#[derive(Debug, Serialize)]
+struct Job {
+    correlator: String,
+    id: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Detector {
+    name: String,
+    version: String,
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Manifest {
+    name: String,
+    file: ManifestFile,
+    resolved: BTreeMap<String, ResolvedPackage>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestFile {
+    source_location: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ResolvedPackage {
+    package_url: String,
+    relationship: String,
+    dependencies: Vec<String>,
+}
+
+/// 快照中 job / sha / ref 字段的来源：命令行参数优先，其次是 GitHub Actions 的环境变量
+#[derive(Debug, Default, Clone)]
+pub struct SnapshotContext {
+    pub job_id: Option<String>,
+    pub correlator: Option<String>,
+    pub sha: Option<String>,
+    pub git_ref: Option<String>,
+}
+
+impl SnapshotContext {
+    // Fill any field left unset on the command line from the standard GITHUB_* variables
+    pub fn with_env_defaults(mut self) -> Self {
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+        self.job_id = self.job_id.or_else(|| var("GITHUB_RUN_ID"));
+        self.correlator = self.correlator.or_else(|| match (var("GITHUB_WORKFLOW"), var("GITHUB_JOB")) {
+            (Some(workflow), Some(job)) => Some(format!("{}_{}", workflow, job)),
+            (Some(workflow), None) => Some(workflow),
+            (None, job) => job,
+        });
+        self.sha = self.sha.or_else(|| var("GITHUB_SHA"));
+        self.git_ref = self.git_ref.or_else(|| var("GITHUB_REF"));
+        self
+    }
+}
+
+pub fn build_snapshot(
+    lockfile: &Lockfile,
+    manifest_path: &str,
+    context: &SnapshotContext,
+    registries: &RegistryMap,
+    stamp: &Stamp,
+) -> Result<Snapshot> {
+    let sha = context
+        .sha
+        .clone()
+        .ok_or_else(|| anyhow!("github-snapshot requires a commit sha (--github-sha or GITHUB_SHA)"))?;
+    let git_ref = context
+        .git_ref
+        .clone()
+        .ok_or_else(|| anyhow!("github-snapshot requires a git ref (--github-ref or GITHUB_REF)"))?;
+    if !git_ref.starts_with("refs/") {
+        return Err(anyhow!("git ref must be fully qualified (e.g. refs/heads/main), got: {}", git_ref));
+    }
+
+    let graph = DependencyGraph::from_lockfile(lockfile);
+    let kinds = graph.kinds();
+
+    // Key resolved entries by crate name, falling back to name@version when a crate
+    // appears in several versions so that keys stay unique.
+    let mut name_counts: HashMap<&str, usize> = HashMap::new();
+    for pkg in &lockfile.packages {
+        if pkg.source.is_some() {
+            *name_counts.entry(pkg.name.as_str()).or_default() += 1;
+        }
+    }
+
+    let mut resolved = BTreeMap::new();
+    for pkg in &lockfile.packages {
+        let Some(kind) = kinds.get(&(pkg.name.to_string(), pkg.version.to_string())) else {
+            continue;
+        };
+
+        let dependencies = pkg
+            .dependencies
+            .iter()
+            .filter_map(|dep| graph.resolve_dependency(dep))
+            .map(|p| package_url(p, registries))
+            .collect();
+
+        let key = if name_counts.get(pkg.name.as_str()).copied().unwrap_or(0) > 1 {
+            format!("{}@{}", pkg.name.as_str(), pkg.version)
+        } else {
+            pkg.name.to_string()
+        };
+
+        resolved.insert(
+            key,
+            ResolvedPackage {
+                package_url: package_url(pkg, registries),
+                relationship: match kind {
+                    DependencyKind::Direct => "direct".to_string(),
+                    DependencyKind::Transitive => "indirect".to_string(),
+                },
+                dependencies,
+            },
+        );
+    }
+
+    let mut manifests = BTreeMap::new();
+    manifests.insert(
+        manifest_path.to_string(),
+        Manifest {
+            name: manifest_path.to_string(),
+            file: ManifestFile { source_location: manifest_path.to_string() },
+            resolved,
+        },
+    );
+
+    Ok(Snapshot {
+        version: 0,
+        job: Job {
+            correlator: context.correlator.clone().unwrap_or_else(|| "rustpj".to_string()),
+            id: context
+                .job_id
+                .clone()
+                .unwrap_or_else(|| stamp.time.timestamp().to_string()),
+        },
+        sha,
+        git_ref,
+        detector: Detector {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            url: "https://github.com/yuwenkuo/rustpj".to_string(),
+        },
+        scanned: stamp.timestamp(),
+        manifests,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::str::FromStr;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+dependencies = ["serde 1.0.0", "log"]
+
+[[package]]
+name = "serde"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["serde 2.0.0"]
+
+[[package]]
+name = "serde"
+version = "2.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "log"
+version = "0.4.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    fn context() -> SnapshotContext {
+        SnapshotContext {
+            job_id: Some("42".to_string()),
+            correlator: Some("ci_scan".to_string()),
+            sha: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+            git_ref: Some("refs/heads/main".to_string()),
+        }
+    }
+
+    // Minimal JSON Schema subset: type, required, properties, additionalProperties,
+    // items, minLength/maxLength and enum.
+    fn validate(schema: &Value, value: &Value, path: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        if let Some(ty) = schema["type"].as_str() {
+            let ok = match ty {
+                "object" => value.is_object(),
+                "array" => value.is_array(),
+                "string" => value.is_string(),
+                "integer" => value.is_i64() || value.is_u64(),
+                _ => true,
+            };
+            if !ok {
+                errors.push(format!("{}: expected {}", path, ty));
+                return errors;
+            }
+        }
+        if let Some(allowed) = schema["enum"].as_array()
+            && !allowed.contains(value)
+        {
+            errors.push(format!("{}: {} not in enum", path, value));
+        }
+        if let (Some(s), Some(min)) = (value.as_str(), schema["minLength"].as_u64())
+            && (s.len() as u64) < min
+        {
+            errors.push(format!("{}: shorter than {}", path, min));
+        }
+        if let (Some(s), Some(max)) = (value.as_str(), schema["maxLength"].as_u64())
+            && (s.len() as u64) > max
+        {
+            errors.push(format!("{}: longer than {}", path, max));
+        }
+        if let Some(obj) = value.as_object() {
+            for key in schema["required"].as_array().into_iter().flatten() {
+                let key = key.as_str().unwrap();
+                if !obj.contains_key(key) {
+                    errors.push(format!("{}: missing required '{}'", path, key));
+                }
+            }
+            for (key, child) in obj {
+                let child_path = format!("{}/{}", path, key);
+                if let Some(sub) = schema["properties"].get(key) {
+                    errors.extend(validate(sub, child, &child_path));
+                } else if schema["additionalProperties"].is_object() {
+                    errors.extend(validate(&schema["additionalProperties"], child, &child_path));
+                } else if schema["additionalProperties"] == Value::Bool(false) {
+                    errors.push(format!("{}: unexpected property", child_path));
+                }
+            }
+        }
+        if let (Some(items), Some(arr)) = (schema.get("items"), value.as_array()) {
+            for (i, child) in arr.iter().enumerate() {
+                errors.extend(validate(items, child, &format!("{}/{}", path, i)));
+            }
+        }
+        errors
+    }
+
+    #[test]
+    fn test_snapshot_matches_schema_fixture() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        let snapshot = build_snapshot(&lockfile, "Cargo.lock", &context(), &RegistryMap::default(), &Stamp::now()).unwrap();
+        let value = serde_json::to_value(&snapshot).unwrap();
+
+        let schema: Value =
+            serde_json::from_str(include_str!("../tests/fixtures/github-snapshot.schema.json")).unwrap();
+        let errors = validate(&schema, &value, "");
+        assert!(errors.is_empty(), "schema violations: {:?}", errors);
+    }
+
+    #[test]
+    fn test_snapshot_relationships_and_keys() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        let snapshot = build_snapshot(&lockfile, "Cargo.lock", &context(), &RegistryMap::default(), &Stamp::now()).unwrap();
+        let resolved = &snapshot.manifests["Cargo.lock"].resolved;
+
+        // The workspace crate itself is not listed
+        assert!(!resolved.contains_key("demo"));
+        assert_eq!(resolved["log"].relationship, "direct");
+        assert_eq!(resolved["serde@1.0.0"].relationship, "direct");
+        assert_eq!(resolved["serde@2.0.0"].relationship, "indirect");
+        assert_eq!(resolved["serde@1.0.0"].dependencies, vec!["pkg:cargo/serde@2.0.0".to_string()]);
+        assert_eq!(resolved["log"].package_url, "pkg:cargo/log@0.4.0");
+    }
+
+    #[test]
+    fn test_snapshot_requires_sha_and_ref() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        let ctx = SnapshotContext { sha: None, ..context() };
+        assert!(build_snapshot(&lockfile, "Cargo.lock", &ctx, &RegistryMap::default(), &Stamp::now()).is_err());
+
+        let ctx = SnapshotContext { git_ref: Some("main".to_string()), ..context() };
+        assert!(build_snapshot(&lockfile, "Cargo.lock", &ctx, &RegistryMap::default(), &Stamp::now()).is_err());
+    }
+
+    #[test]
+    fn test_private_registry_purls_carry_repository_url() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lockfiles/private-registry.lock");
+        let lockfile = Lockfile::load(path).unwrap();
+        let registries: RegistryMap =
+            toml::from_str("[\"https://crates.internal.example/index\"]\nname = \"internal\"\nrepository_url = \"https://crates.internal.example\"\n")
+                .unwrap();
+        let snapshot = build_snapshot(&lockfile, "Cargo.lock", &context(), &registries, &Stamp::now()).unwrap();
+        let resolved = &snapshot.manifests["Cargo.lock"].resolved;
+        assert_eq!(
+            resolved["acme-auth"].package_url,
+            "pkg:cargo/acme-auth@1.4.0?repository_url=https%3A%2F%2Fcrates.internal.example"
+        );
+        assert_eq!(resolved["serde"].package_url, "pkg:cargo/serde@1.0.200");
+    }
+}
@@ -0,0 +1,626 @@
+use cargo_lock::package::SourceKind;
+use cargo_lock::{Dependency, Lockfile, Package};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+// The lockfile's dependency graph with edges resolved to concrete packages. Nodes are
+// indices into `lockfile.packages`; a `Dependency` names its target by name and version
+// (plus source when the same name@version comes from several places), so two versions of
+// a crate are always distinct nodes. The SBOM's dependsOn, the DOT graph, direct vs
+// transitive classification, `why` and `--with-deps` all go through this one resolution.
+//
+// Roots are the workspace members. A lockfile without any (e.g. one recovered from an
+// auditable binary that lost its root) falls back to the packages nobody depends on.
+// Non-root packages nobody depends on are orphans: cargo leaves them behind, e.g. for
+// a removed target-specific dependency, and nothing reaches them from a root.
+//
+// A transitive package is attributed to the direct dependencies that pull it in
+// (`direct_dependencies_reaching`): walking up from it, every direct dependency met
+// before a root counts. A workspace member that depends on another member is a root,
+// not a direct dependency, so the walk stops there and the member's own direct
+// dependencies are the ones named.
+//
+// `stats` summarizes the structure for the report's `metadata.graph_stats`. Depth is the
+// shortest distance from a root (what `cargo tree` shows the first time a crate appears),
+// and it and the direct dependency count only mean something relative to the workspace:
+// with fallback roots both are left out.
+
+/// 包是被工作区成员直接引入，还是只经由其他 crate 间接引入
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    Direct,
+    Transitive,
+}
+
+/// 工作区成员：没有 source 的包（本地路径包），以及 V1 lockfile 中的 root
+pub fn workspace_members(lockfile: &Lockfile) -> Vec<&Package> {
+    let mut members: Vec<&Package> = lockfile
+        .packages
+        .iter()
+        .filter(|p| p.source.is_none())
+        .collect();
+    if let Some(root) = &lockfile.root
+        && !members.iter().any(|m| m.name == root.name && m.version == root.version)
+    {
+        members.push(root);
+    }
+    members
+}
+
+/// 由 lockfile 构建的依赖图
+pub struct DependencyGraph<'a> {
+    packages: &'a [Package],
+    by_key: HashMap<(String, String), Vec<usize>>,
+    // 正向边：包 -> 它的依赖；反向边：包 -> 依赖它的包
+    forward: Vec<Vec<usize>>,
+    reverse: Vec<Vec<usize>>,
+    roots: HashSet<usize>,
+    // 根节点来自工作区成员，而不是退回到无人依赖的包
+    workspace_roots: bool,
+}
+
+/// 依赖图的结构统计，写入报告的 `metadata.graph_stats`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GraphStats {
+    // lockfile 中的包条目数，以及解析出的依赖边数
+    pub total_packages: usize,
+    pub total_dependencies: usize,
+    // 不同的 crate 名称数；有多个版本（或来源）的 crate 数
+    pub distinct_crates: usize,
+    pub duplicated_crates: usize,
+    // 无法确定工作区根时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_members: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direct_dependencies: Option<usize>,
+    // 从工作区成员出发的最大依赖深度（直接依赖为 1）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    pub sources: SourceCounts,
+}
+
+/// 按来源统计的包数；path 包括工作区成员
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SourceCounts {
+    pub registry: usize,
+    pub git: usize,
+    pub path: usize,
+}
+
+/// 反向依赖树（类似 `cargo tree -i`）：从某个包一直向上到工作区成员
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InverseTree {
+    pub name: String,
+    pub version: String,
+    // 工作区成员，依赖链到此为止
+    pub root: bool,
+    // 该节点的上游已在前面展开过（或构成环），这里不再重复
+    pub repeated: bool,
+    pub dependents: Vec<InverseTree>,
+}
+
+impl<'a> DependencyGraph<'a> {
+    pub fn from_lockfile(lockfile: &'a Lockfile) -> Self {
+        let packages = lockfile.packages.as_slice();
+        let mut by_key: HashMap<(String, String), Vec<usize>> = HashMap::new();
+        for (i, package) in packages.iter().enumerate() {
+            by_key.entry((package.name.to_string(), package.version.to_string())).or_default().push(i);
+        }
+
+        let mut graph = DependencyGraph {
+            packages,
+            by_key,
+            forward: vec![Vec::new(); packages.len()],
+            reverse: vec![Vec::new(); packages.len()],
+            roots: HashSet::new(),
+            workspace_roots: false,
+        };
+        for (from, package) in packages.iter().enumerate() {
+            for dep in &package.dependencies {
+                if let Some(to) = graph.resolve(dep)
+                    && !graph.forward[from].contains(&to)
+                {
+                    graph.forward[from].push(to);
+                    graph.reverse[to].push(from);
+                }
+            }
+        }
+        for member in workspace_members(lockfile) {
+            graph.roots.extend(graph.node_of(member));
+        }
+        graph.workspace_roots = !graph.roots.is_empty();
+        if graph.roots.is_empty() {
+            graph.roots = (0..packages.len()).filter(|&i| graph.reverse[i].is_empty()).collect();
+        }
+        graph
+    }
+
+    // Same name@version from several sources: prefer the one whose source matches
+    fn resolve(&self, dep: &Dependency) -> Option<usize> {
+        let candidates = self.by_key.get(&(dep.name.to_string(), dep.version.to_string()))?;
+        let by_source = dep.source.as_ref().and_then(|source| {
+            candidates.iter().copied().find(|&i| self.packages[i].source.as_ref() == Some(source))
+        });
+        by_source.or_else(|| candidates.first().copied())
+    }
+
+    /// 依赖项解析到的包
+    pub fn resolve_dependency(&self, dep: &Dependency) -> Option<&'a Package> {
+        self.resolve(dep).map(|node| self.package(node))
+    }
+
+    /// 某个 lockfile 条目对应的节点（按名称、版本和 source 匹配）
+    pub fn node_of(&self, package: &Package) -> Option<usize> {
+        let candidates = self.by_key.get(&(package.name.to_string(), package.version.to_string()))?;
+        candidates.iter().copied().find(|&i| self.packages[i].source == package.source)
+    }
+
+    pub fn len(&self) -> usize {
+        self.packages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    pub fn package(&self, node: usize) -> &'a Package {
+        &self.packages[node]
+    }
+
+    /// 名称匹配（指定 version 时还要求版本相同）的所有节点，按 lockfile 顺序
+    pub fn find(&self, name: &str, version: Option<&semver::Version>) -> Vec<usize> {
+        (0..self.packages.len())
+            .filter(|&i| self.packages[i].name.as_str() == name && version.is_none_or(|v| *v == self.packages[i].version))
+            .collect()
+    }
+
+    /// 直接依赖该节点的包
+    pub fn dependents_of(&self, node: usize) -> &[usize] {
+        &self.reverse[node]
+    }
+
+    /// 该节点的直接依赖
+    pub fn dependencies_of(&self, node: usize) -> &[usize] {
+        &self.forward[node]
+    }
+
+    pub fn is_root(&self, node: usize) -> bool {
+        self.roots.contains(&node)
+    }
+
+    /// 所有根节点，按 lockfile 顺序
+    pub fn roots(&self) -> Vec<usize> {
+        let mut roots: Vec<usize> = self.roots.iter().copied().collect();
+        roots.sort_unstable();
+        roots
+    }
+
+    /// 不是根、也没有任何包依赖的节点
+    pub fn orphans(&self) -> Vec<usize> {
+        (0..self.packages.len()).filter(|&i| !self.is_root(i) && self.reverse[i].is_empty()).collect()
+    }
+
+    /// 被某个根节点直接依赖（且自身不是根）
+    pub fn is_direct(&self, node: usize) -> bool {
+        !self.is_root(node) && self.reverse[node].iter().any(|&d| self.is_root(d))
+    }
+
+    /// 直接 / 传递依赖；根节点返回 None
+    pub fn kind(&self, node: usize) -> Option<DependencyKind> {
+        if self.is_root(node) {
+            None
+        } else if self.is_direct(node) {
+            Some(DependencyKind::Direct)
+        } else {
+            Some(DependencyKind::Transitive)
+        }
+    }
+
+    /// 结构统计；没有工作区成员时不给出深度与直接依赖数
+    pub fn stats(&self) -> GraphStats {
+        let names: BTreeSet<&str> = self.packages.iter().map(|p| p.name.as_str()).collect();
+        let mut sources = SourceCounts::default();
+        for package in self.packages {
+            match package.source.as_ref().map(|s| s.kind()) {
+                None | Some(SourceKind::Path) => sources.path += 1,
+                Some(SourceKind::Git(_)) => sources.git += 1,
+                Some(_) => sources.registry += 1,
+            }
+        }
+        let mut stats = GraphStats {
+            total_packages: self.packages.len(),
+            total_dependencies: self.forward.iter().map(Vec::len).sum(),
+            distinct_crates: names.len(),
+            duplicated_crates: names
+                .iter()
+                .filter(|&&name| self.packages.iter().filter(|p| p.name.as_str() == name).nth(1).is_some())
+                .count(),
+            sources,
+            ..Default::default()
+        };
+        if self.workspace_roots {
+            stats.workspace_members = Some(self.roots.len());
+            stats.direct_dependencies = Some((0..self.packages.len()).filter(|&i| self.is_direct(i)).count());
+            stats.max_depth = Some(self.max_depth());
+        }
+        stats
+    }
+
+    // Breadth-first from all roots at once; 0 when the roots have no dependencies
+    fn max_depth(&self) -> usize {
+        let mut depth = vec![None; self.packages.len()];
+        let mut queue: VecDeque<usize> = self.roots().into_iter().collect();
+        for &root in &queue {
+            depth[root] = Some(0);
+        }
+        let mut max = 0;
+        while let Some(node) = queue.pop_front() {
+            let next = depth[node].unwrap_or_default() + 1;
+            for &dep in &self.forward[node] {
+                if depth[dep].is_none() {
+                    depth[dep] = Some(next);
+                    max = max.max(next);
+                    queue.push_back(dep);
+                }
+            }
+        }
+        max
+    }
+
+    /// 所有非根包的直接 / 传递划分，键为 (name, version)；同一 name@version 有一处是直接依赖即为直接依赖
+    pub fn kinds(&self) -> HashMap<(String, String), DependencyKind> {
+        let mut kinds = HashMap::new();
+        for node in 0..self.packages.len() {
+            let Some(kind) = self.kind(node) else {
+                continue;
+            };
+            let package = self.package(node);
+            let entry = kinds
+                .entry((package.name.to_string(), package.version.to_string()))
+                .or_insert(kind);
+            if kind == DependencyKind::Direct {
+                *entry = kind;
+            }
+        }
+        kinds
+    }
+
+    /// 从 root 到 target 的依赖路径（两端都包含），最短的在前，最多 limit 条；路径中不会重复经过同一节点
+    pub fn paths_to(&self, root: usize, target: usize, limit: usize) -> Vec<Vec<usize>> {
+        // Only nodes that can reach the target are worth expanding
+        let mut reaches = HashSet::from([target]);
+        let mut stack = vec![target];
+        while let Some(node) = stack.pop() {
+            for &dependent in &self.reverse[node] {
+                if reaches.insert(dependent) {
+                    stack.push(dependent);
+                }
+            }
+        }
+
+        let mut paths = Vec::new();
+        if limit == 0 || !reaches.contains(&root) {
+            return paths;
+        }
+        // Breadth-first over partial paths, so complete paths come out shortest first
+        let mut queue = VecDeque::from([vec![root]]);
+        while let Some(path) = queue.pop_front() {
+            let last = path[path.len() - 1];
+            if last == target {
+                paths.push(path);
+                if paths.len() == limit {
+                    break;
+                }
+                continue;
+            }
+            for &next in &self.forward[last] {
+                if reaches.contains(&next) && !path.contains(&next) {
+                    let mut extended = path.clone();
+                    extended.push(next);
+                    queue.push_back(extended);
+                }
+            }
+        }
+        paths
+    }
+
+    /// 经由哪些直接依赖可以从根节点到达 target（target 自身是直接依赖时包含它），按 lockfile 顺序；
+    /// target 是根节点或孤立节点时为空
+    pub fn direct_dependencies_reaching(&self, target: usize) -> Vec<usize> {
+        let mut seen = HashSet::from([target]);
+        let mut stack = vec![target];
+        while let Some(node) = stack.pop() {
+            if self.is_root(node) {
+                continue;
+            }
+            for &dependent in &self.reverse[node] {
+                if seen.insert(dependent) {
+                    stack.push(dependent);
+                }
+            }
+        }
+        let mut direct: Vec<usize> = seen.into_iter().filter(|&n| self.is_direct(n)).collect();
+        direct.sort_unstable();
+        direct
+    }
+
+    /// 从任一根节点到 target 的最短路径；target 自身是根时只含它自己，孤立节点返回 None
+    pub fn shortest_path(&self, target: usize) -> Option<Vec<usize>> {
+        self.roots()
+            .into_iter()
+            .filter_map(|root| self.paths_to(root, target, 1).pop())
+            .min_by_key(|path| path.len())
+    }
+
+    /// 从 node 向上展开到工作区成员的反向依赖树；已展开过的节点只出现一次，之后标记为 repeated
+    pub fn inverse_tree(&self, node: usize) -> InverseTree {
+        self.inverse_subtree(node, &mut HashSet::new())
+    }
+
+    fn inverse_subtree(&self, node: usize, expanded: &mut HashSet<usize>) -> InverseTree {
+        let package = self.package(node);
+        let mut tree = InverseTree {
+            name: package.name.to_string(),
+            version: package.version.to_string(),
+            root: self.is_root(node),
+            repeated: false,
+            dependents: Vec::new(),
+        };
+        if !expanded.insert(node) {
+            tree.repeated = !self.reverse[node].is_empty();
+            return tree;
+        }
+        let mut dependents = self.reverse[node].clone();
+        dependents.sort_by(|&a, &b| {
+            let (a, b) = (self.package(a), self.package(b));
+            (a.name.as_str(), &a.version).cmp(&(b.name.as_str(), &b.version))
+        });
+        tree.dependents = dependents.into_iter().map(|d| self.inverse_subtree(d, expanded)).collect();
+        tree
+    }
+}
+
+impl InverseTree {
+    /// 缩进的文本树，格式与 `cargo tree -i` 相同
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{}", self.label());
+        self.render_children("", &mut out);
+        out
+    }
+
+    fn label(&self) -> String {
+        let mut label = format!("{} v{}", self.name, self.version);
+        if self.root {
+            label.push_str(" (workspace)");
+        }
+        if self.repeated {
+            label.push_str(" (*)");
+        }
+        label
+    }
+
+    fn render_children(&self, prefix: &str, out: &mut String) {
+        for (i, child) in self.dependents.iter().enumerate() {
+            let last = i + 1 == self.dependents.len();
+            let _ = writeln!(out, "{}{}{}", prefix, if last { "└── " } else { "├── " }, child.label());
+            child.render_children(&format!("{}{}", prefix, if last { "    " } else { "│   " }), out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // app -> chrono -> time 0.1; app -> time 0.3; tool -> chrono
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["chrono", "time 0.3.20"]
+
+[[package]]
+name = "chrono"
+version = "0.4.19"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["time 0.1.45"]
+
+[[package]]
+name = "time"
+version = "0.1.45"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "time"
+version = "0.3.20"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "tool"
+version = "0.1.0"
+dependencies = ["chrono"]
+"#;
+
+    #[test]
+    fn test_inverse_tree_is_version_aware() {
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let graph = DependencyGraph::from_lockfile(&lockfile);
+
+        let old = graph.find("time", Some(&"0.1.45".parse().unwrap()));
+        assert_eq!(old.len(), 1);
+        assert_eq!(
+            graph.inverse_tree(old[0]).render(),
+            "time v0.1.45\n└── chrono v0.4.19\n    ├── app v0.1.0 (workspace)\n    └── tool v0.1.0 (workspace)\n"
+        );
+
+        let all = graph.find("time", None);
+        assert_eq!(all.len(), 2);
+        let new = graph.inverse_tree(all[1]);
+        assert_eq!(new.dependents.len(), 1);
+        assert_eq!(new.dependents[0].name, "app");
+        assert!(graph.find("serde", None).is_empty());
+    }
+
+    #[test]
+    fn test_shared_dependents_expanded_once() {
+        // chrono -> app closes a cycle, as a dev-dependency collapsed into the lockfile can
+        let lockfile: Lockfile = LOCKFILE
+            .replace("dependencies = [\"time 0.1.45\"]", "dependencies = [\"app\", \"time 0.1.45\"]")
+            .parse()
+            .unwrap();
+        let graph = DependencyGraph::from_lockfile(&lockfile);
+        let chrono = graph.find("chrono", None)[0];
+        let tree = graph.inverse_tree(chrono);
+        let app = &tree.dependents[0];
+        assert_eq!(app.name, "app");
+        // app's only dependent is chrono itself, which is already being expanded
+        assert_eq!(app.dependents[0].name, "chrono");
+        assert!(app.dependents[0].repeated);
+        assert!(tree.render().contains("chrono v0.4.19 (*)"));
+    }
+
+    #[test]
+    fn test_stats() {
+        // A git dependency of the old time, one level deeper than anything else
+        let lockfile: Lockfile = format!(
+            "{}\n[[package]]\nname = \"shim\"\nversion = \"0.1.0\"\nsource = \"git+https://github.com/example/shim?branch=main#0123456789abcdef0123456789abcdef01234567\"\n",
+            LOCKFILE.replace("version = \"0.1.45\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n", "version = \"0.1.45\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\ndependencies = [\"shim\"]\n")
+        )
+        .parse()
+        .unwrap();
+        let stats = DependencyGraph::from_lockfile(&lockfile).stats();
+        assert_eq!(
+            stats,
+            GraphStats {
+                total_packages: 6,
+                total_dependencies: 5,
+                distinct_crates: 5,
+                duplicated_crates: 1,
+                workspace_members: Some(2),
+                direct_dependencies: Some(2),
+                max_depth: Some(3),
+                sources: SourceCounts { registry: 3, git: 1, path: 2 },
+            }
+        );
+
+        // Without workspace members the roots are a guess, so depth is not reported
+        let recovered: Lockfile = "version = 3\n\n[[package]]\nname = \"hello\"\nversion = \"0.1.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\ndependencies = [\"chrono\"]\n\n[[package]]\nname = \"chrono\"\nversion = \"0.4.19\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n"
+            .parse()
+            .unwrap();
+        let stats = DependencyGraph::from_lockfile(&recovered).stats();
+        assert_eq!((stats.total_packages, stats.total_dependencies, stats.sources.registry), (2, 1, 2));
+        assert_eq!((stats.workspace_members, stats.direct_dependencies, stats.max_depth), (None, None, None));
+        assert!(!serde_json::to_string(&stats).unwrap().contains("max_depth"));
+    }
+
+    #[test]
+    fn test_roots_direct_and_orphans() {
+        // stale is left over in the lockfile with nothing depending on it
+        let lockfile: Lockfile = format!(
+            "{}\n[[package]]\nname = \"stale\"\nversion = \"1.0.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+            LOCKFILE
+        )
+        .parse()
+        .unwrap();
+        let graph = DependencyGraph::from_lockfile(&lockfile);
+        let node = |name: &str, version: &str| graph.find(name, Some(&version.parse().unwrap()))[0];
+
+        let names = |nodes: Vec<usize>| nodes.into_iter().map(|n| graph.package(n).name.to_string()).collect::<Vec<_>>();
+        assert_eq!(names(graph.roots()), ["app", "tool"]);
+        assert_eq!(names(graph.orphans()), ["stale"]);
+
+        assert!(graph.is_direct(node("chrono", "0.4.19")));
+        assert!(graph.is_direct(node("time", "0.3.20")));
+        assert!(!graph.is_direct(node("time", "0.1.45")));
+        assert!(!graph.is_direct(node("app", "0.1.0")));
+        assert_eq!(graph.kind(node("app", "0.1.0")), None);
+        assert_eq!(graph.kind(node("time", "0.1.45")), Some(DependencyKind::Transitive));
+        assert_eq!(graph.kind(node("stale", "1.0.0")), Some(DependencyKind::Transitive));
+
+        let kinds = graph.kinds();
+        assert_eq!(kinds.get(&("time".to_string(), "0.3.20".to_string())), Some(&DependencyKind::Direct));
+        assert!(!kinds.contains_key(&("tool".to_string(), "0.1.0".to_string())));
+        assert_eq!(graph.shortest_path(node("stale", "1.0.0")), None);
+    }
+
+    #[test]
+    fn test_paths_to_shortest_first_and_limited() {
+        // app -> a -> c -> target, app -> b -> target, app -> target (only for the 0.2 version)
+        let lockfile: Lockfile = r#"
+version = 3
+
+[[package]]
+name = "a"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["c"]
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["a", "b", "target 0.2.0"]
+
+[[package]]
+name = "b"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["target 0.1.0"]
+
+[[package]]
+name = "c"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["target 0.1.0", "a"]
+
+[[package]]
+name = "target"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "target"
+version = "0.2.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#
+        .parse()
+        .unwrap();
+        let graph = DependencyGraph::from_lockfile(&lockfile);
+        let app = graph.find("app", None)[0];
+        let old = graph.find("target", Some(&"0.1.0".parse().unwrap()))[0];
+        let new = graph.find("target", Some(&"0.2.0".parse().unwrap()))[0];
+        let render = |path: &Vec<usize>| path.iter().map(|&n| graph.package(n).name.as_str()).collect::<Vec<_>>().join(" -> ");
+
+        // the a <-> c cycle doesn't produce endless paths
+        let paths: Vec<String> = graph.paths_to(app, old, 10).iter().map(render).collect();
+        assert_eq!(paths, ["app -> b -> target", "app -> a -> c -> target"]);
+        assert_eq!(graph.paths_to(app, old, 1).len(), 1);
+        assert!(graph.paths_to(app, old, 0).is_empty());
+        assert_eq!(graph.paths_to(app, new, 10), vec![vec![app, new]]);
+        assert!(graph.is_direct(new) && !graph.is_direct(old));
+        assert!(graph.paths_to(old, app, 10).is_empty());
+        assert_eq!(graph.shortest_path(app), Some(vec![app]));
+
+        // c is only reached through a
+        let names = |nodes: Vec<usize>| nodes.into_iter().map(|n| graph.package(n).name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names(graph.direct_dependencies_reaching(old)), ["a", "b"]);
+        assert_eq!(graph.direct_dependencies_reaching(new), [new]);
+        assert!(graph.direct_dependencies_reaching(app).is_empty());
+    }
+
+    #[test]
+    fn test_without_workspace_members_roots_are_undepended_packages() {
+        let lockfile: Lockfile = LOCKFILE
+            .replace("version = \"0.1.0\"\n", "version = \"0.1.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n")
+            .parse()
+            .unwrap();
+        let graph = DependencyGraph::from_lockfile(&lockfile);
+        let roots: Vec<&str> = graph.roots().into_iter().map(|n| graph.package(n).name.as_str()).collect();
+        assert_eq!(roots, ["app", "tool"]);
+        assert!(graph.orphans().is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+use cargo_lock::package::GitReference;
+use cargo_lock::Lockfile;
+use serde::Serialize;
+
+// Dependency hygiene, independent of the advisory DB: packages on a pre-release version
+// (`1.0.0-beta.1`, from the semver `pre` field) and packages taken from a git repository,
+// which track whatever commit the lockfile pinned rather than a published release. Both
+// are listed in the report (`prerelease_packages`, `git_packages`) and counted in its
+// summary; neither changes the exit code. Workspace members and other path packages are
+// the project's own code and are left out.
+//
+// Every git package also says how Cargo.toml pinned it. Only a `rev` (or a `tag`, which
+// nobody is supposed to move) names a fixed commit; a `branch`, or no pin at all, follows
+// whatever the branch points at on the next `cargo update`, and a force-push can make
+// the locked commit disappear altogether. Those are repeated in `git_dependencies` and
+// warned about, and a `[[policy]]` rule with `when = "git_branch"` can fail the run.
+
+/// 使用预发布版本的依赖
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PrereleasePackage {
+    pub package_name: String,
+    pub package_version: String,
+    // lockfile 中的 source，如 registry+https://github.com/rust-lang/crates.io-index
+    pub source: String,
+}
+
+/// git 依赖在 Cargo.toml 中的固定方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GitPin {
+    Rev,
+    Tag,
+    Branch,
+    // 没有指定 branch / tag / rev，跟随默认分支
+    DefaultBranch,
+}
+
+impl GitPin {
+    /// 跟随分支，下次 `cargo update` 就可能换成另一个提交
+    pub fn follows_branch(self) -> bool {
+        matches!(self, GitPin::Branch | GitPin::DefaultBranch)
+    }
+}
+
+/// 来自 git 仓库的依赖
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GitPackage {
+    pub package_name: String,
+    pub package_version: String,
+    pub repository: String,
+    pub pin: GitPin,
+    // Cargo.toml 中指定的 branch= / tag= / rev=；跟随默认分支时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    // lockfile 锁定的提交
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+pub fn prerelease(lockfile: &Lockfile) -> Vec<PrereleasePackage> {
+    lockfile
+        .packages
+        .iter()
+        .filter(|p| !p.version.pre.is_empty())
+        .filter_map(|p| {
+            let source = p.source.as_ref().filter(|s| !s.is_path())?;
+            Some(PrereleasePackage {
+                package_name: p.name.to_string(),
+                package_version: p.version.to_string(),
+                source: source.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn git_sourced(lockfile: &Lockfile) -> Vec<GitPackage> {
+    lockfile
+        .packages
+        .iter()
+        .filter_map(|p| {
+            let source = p.source.as_ref().filter(|s| s.is_git())?;
+            // The default branch has no `branch=` of its own
+            let reference = source.git_reference().and_then(|r| r.pretty_ref(false)).map(|r| r.to_string());
+            Some(GitPackage {
+                package_name: p.name.to_string(),
+                package_version: p.version.to_string(),
+                repository: source.url().to_string(),
+                pin: match source.git_reference() {
+                    Some(GitReference::Rev(_)) => GitPin::Rev,
+                    Some(GitReference::Tag(_)) => GitPin::Tag,
+                    Some(GitReference::Branch(_)) if reference.is_some() => GitPin::Branch,
+                    _ => GitPin::DefaultBranch,
+                },
+                reference,
+                commit: source.precise().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// 跟随分支的 git 依赖
+pub fn branch_pinned(git: &[GitPackage]) -> Vec<GitPackage> {
+    git.iter().filter(|p| p.pin.follows_branch()).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "demo"
+version = "0.2.0-dev"
+
+[[package]]
+name = "rand"
+version = "0.9.0-beta.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "patched"
+version = "0.3.0"
+source = "git+https://github.com/acme/patched?tag=v0.3.0#0123456789abcdef0123456789abcdef01234567"
+
+[[package]]
+name = "tracking"
+version = "0.1.0-alpha"
+source = "git+https://github.com/acme/tracking#89abcdef0123456789abcdef0123456789abcdef"
+"#;
+
+    #[test]
+    fn test_prerelease_and_git_packages() {
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let prerelease: Vec<String> =
+            prerelease(&lockfile).iter().map(|p| format!("{}@{}", p.package_name, p.package_version)).collect();
+        // The workspace member's own -dev version is not a dependency risk
+        assert_eq!(prerelease, ["rand@0.9.0-beta.1", "tracking@0.1.0-alpha"]);
+
+        let git = git_sourced(&lockfile);
+        assert_eq!(
+            git[0],
+            GitPackage {
+                package_name: "patched".to_string(),
+                package_version: "0.3.0".to_string(),
+                repository: "https://github.com/acme/patched".to_string(),
+                pin: GitPin::Tag,
+                reference: Some("tag=v0.3.0".to_string()),
+                commit: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+            }
+        );
+        assert_eq!(git[1].reference, None);
+        assert_eq!(git.len(), 2);
+    }
+
+    #[test]
+    fn test_git_pins_from_fixture() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lockfiles/git.lock");
+        let lockfile = Lockfile::load(path).unwrap();
+        let git = git_sourced(&lockfile);
+        let pins: Vec<(&str, GitPin, Option<&str>)> =
+            git.iter().map(|p| (p.package_name.as_str(), p.pin, p.reference.as_deref())).collect();
+        assert_eq!(
+            pins,
+            [
+                ("by-branch", GitPin::Branch, Some("branch=develop")),
+                ("by-rev", GitPin::Rev, Some("rev=0b1c2d3")),
+                ("by-tag", GitPin::Tag, Some("tag=v2.0.1")),
+                ("default-branch", GitPin::DefaultBranch, None),
+            ]
+        );
+        assert_eq!(git[1].commit.as_deref(), Some("0b1c2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c"));
+        let branches: Vec<String> = branch_pinned(&git).into_iter().map(|p| p.package_name).collect();
+        assert_eq!(branches, ["by-branch", "default-branch"]);
+        assert_eq!(serde_json::to_value(GitPin::DefaultBranch).unwrap(), "default-branch");
+    }
+}
@@ -0,0 +1,127 @@
+use crate::description::advisory_url;
+use crate::scanner::{PackageReport, VulnReport};
+use cargo_lock::Lockfile;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// `--report-format junit`: the scan as a JUnit XML test report, for CI systems that only
+// surface results through their test report UI (Jenkins, GitLab). Every package in the
+// lockfile is one test case, `<name>@<version>` in the class `cargo`:
+//
+//   passed   no findings
+//   failed   one or more findings; the failure's `type` is the highest severity, its
+//            `message` lists the advisory ids and its text has the details of each
+//   skipped  left out of the scan (--exclude-workspace)
+//
+// The counts are the report's: `tests` is `total_packages`, `failures` is
+// `summary.vulnerable_packages`. Triaged findings still fail their package, as they are
+// still in the report's `packages`; the failure text carries the analysis state.
+
+/// 生成 JUnit XML；name 为测试套件名（被扫描的文件名）
+pub fn render(report: &VulnReport, lockfile: &Lockfile, name: &str) -> String {
+    let findings: HashMap<String, &PackageReport> =
+        report.packages.iter().map(|p| (format!("{}@{}", p.package_name, p.package_version), p)).collect();
+    let failures = report.summary.vulnerable_packages;
+    let skipped = report.excluded_workspace_members.len();
+    let tests = report.total_packages;
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<testsuites name=\"rustpj\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\">",
+        tests, failures, skipped
+    );
+    let timestamp = match report.metadata.generated_at.as_str() {
+        "" => String::new(),
+        at => format!(" timestamp=\"{}\"", escape(at)),
+    };
+    let _ = writeln!(
+        out,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\"{}>",
+        escape(name), tests, failures, skipped, timestamp
+    );
+    if let Some(commit) = &report.metadata.advisory_db_commit {
+        out.push_str("    <properties>\n");
+        let _ = writeln!(out, "      <property name=\"advisory_db_commit\" value=\"{}\"/>", escape(commit));
+        out.push_str("    </properties>\n");
+    }
+    for pkg in &lockfile.packages {
+        let id = format!("{}@{}", pkg.name, pkg.version);
+        let open = format!("    <testcase classname=\"cargo\" name=\"{}\"", escape(&id));
+        if report.excluded_workspace_members.contains(&id) {
+            let _ = writeln!(out, "{}>\n      <skipped message=\"workspace member not scanned (--exclude-workspace)\"/>\n    </testcase>", open);
+            continue;
+        }
+        match findings.get(&id) {
+            None => {
+                let _ = writeln!(out, "{}/>", open);
+            }
+            Some(found) => {
+                let _ = writeln!(out, "{}>", open);
+                let ids: Vec<&str> = found.advisories.iter().map(|f| f.id.as_str()).collect();
+                let max = found.advisories.iter().map(|f| f.severity).max().unwrap_or_default();
+                let _ = writeln!(
+                    out,
+                    "      <failure type=\"{}\" message=\"{}\">{}</failure>",
+                    max,
+                    escape(&format!("{} {}: {}", found.advisories.len(), plural(found.advisories.len()), ids.join(", "))),
+                    escape(&details(found))
+                );
+                out.push_str("    </testcase>\n");
+            }
+        }
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 { "advisory" } else { "advisories" }
+}
+
+// One block per finding: id and severity, the advisory's first line, the fix, the link
+fn details(pkg: &PackageReport) -> String {
+    let mut text = String::new();
+    for finding in &pkg.advisories {
+        let _ = writeln!(text, "{} ({})", finding.id, finding.severity);
+        if let Some(summary) = finding.description.lines().find(|l| !l.trim().is_empty()) {
+            let _ = writeln!(text, "  {}", summary.trim());
+        }
+        let _ = writeln!(text, "  {}", finding.remediation);
+        // The state's snake_case name, as in the JSON report
+        if let Some(state) = finding.analysis.as_ref().and_then(|a| serde_json::to_value(a.state).ok()) {
+            let _ = writeln!(text, "  Triaged: {}", state.as_str().unwrap_or_default());
+        }
+        let _ = writeln!(text, "  {}", advisory_url(&finding.id));
+    }
+    text
+}
+
+// XML attribute and text escaping; control characters other than tab and newline are not
+// allowed in XML 1.0 at all and are dropped
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' => out.push(c),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a<b> & \"c\"\u{1}"), "a&lt;b&gt; &amp; &quot;c&quot;");
+    }
+}
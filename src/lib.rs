@@ -0,0 +1,68 @@
+//! Rust 项目依赖漏洞扫描 / SBOM 生成
+//!
+//! 已经持有 `cargo_lock::Lockfile` 的调用方可以跳过 ZIP 解压与项目发现，直接使用 [`Scanner`]：
+//!
+//! ```no_run
+//! use rustpj::Scanner;
+//!
+//! let scanner = Scanner::new("./data/advisory-db")?;
+//! let report = scanner.scan_from_path("Cargo.lock")?;
+//! println!("{} vulnerabilities", report.summary.total_vulnerabilities);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+#[macro_use]
+pub mod console;
+mod extract_zip;
+pub mod get_lockfile;
+pub mod scanner;
+pub mod advisory_source;
+pub mod severity;
+pub mod cvss_sources;
+pub mod get_sbom;
+pub mod cyclonedx_proto;
+pub mod dot;
+pub mod graph;
+pub mod workspace;
+pub mod project;
+pub mod package_filter;
+pub mod description;
+mod purl;
+pub mod cpe;
+pub mod github_snapshot;
+pub mod config;
+pub mod baseline;
+pub mod policy;
+pub mod vex;
+pub mod trivy;
+pub mod junit;
+pub mod cargo_metadata;
+pub mod exec;
+pub mod cargo_config;
+pub mod registry;
+pub mod vendor;
+pub mod db_update;
+pub mod msrv;
+pub mod signing;
+pub mod template;
+pub mod triage;
+pub mod bundle;
+pub mod sink;
+#[cfg(feature = "s3")]
+pub mod s3_sink;
+pub mod output;
+pub mod batch;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod typosquat;
+pub mod staleness;
+pub mod hygiene;
+pub mod online;
+pub mod download;
+pub mod timing;
+pub mod progress;
+pub mod auditable;
+#[cfg(test)]
+mod test_support;
+
+pub use scanner::{ScanOutcome, Scanner, VulnReport};
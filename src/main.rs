@@ -1,80 +1,1240 @@
-mod extract_zip;
-mod get_lockfile;
-mod scanner;
-mod get_sbom;
-
-use std::path::Path;
-use anyhow::{Context, Result};
-use get_lockfile::get_lockfile;
-use scanner::Scanner;
+mod cli;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result, bail};
+use rustpj::batch;
+use rustpj::bundle::{self, Artifact, ArtifactKind};
+use rustpj::{auditable, cargo_metadata, console, description, dot, download, github_snapshot, msrv, online, output, policy, package_filter, signing, sink, staleness, status, template, trivy, junit, triage, typosquat, vex};
+use rustpj::cargo_metadata::LicenseHistogram;
+use rustpj::policy::{CvssGate, GateAction, PolicyAction};
+use cli::{Command, Options, ProgressFormat, ReportFormat, USAGE};
+use rustpj::baseline::Baseline;
+use rustpj::db_update::{self, UpdateAction};
+use rustpj::config::ScanConfig;
+use rustpj::get_lockfile::{OUTPUT_DIR, TempGuard, clean_live_temp_dirs, discover_lockfiles, extract_archive, load_lockfile, merge_lockfiles, LockDiscovery};
+use rustpj::graph::{DependencyGraph, InverseTree};
+use rustpj::package_filter::PackageFilter;
+use rustpj::project::ProjectIdentity;
+use rustpj::timing::Timings;
+use rustpj::progress::{self, ProgressEvent};
+use rustpj::workspace::WorkspaceMembers;
+use rustpj::advisory_source::AdvisorySource;
+use rustpj::scanner::{ProjectReport, Scanner};
+use rustpj::severity::Severity;
 use std::env;
-use get_sbom::generate_sbom_from_lockfile;
+use rustpj::cpe::CpeMappings;
+use rustpj::cvss_sources::AliasScores;
+use rustpj::get_sbom::{CycloneDxBom, SbomOptions, generate_merged_sbom, generate_sbom_from_lockfile};
+use rustpj::output::Stamp;
+
+// 进程退出码
+const EXIT_OK: u8 = 0;
+const EXIT_VULNERABLE: u8 = 1;
+const EXIT_ERROR: u8 = 2;
+// verify 子命令：清单签名或文件摘要不一致
+const EXIT_VERIFY_FAILED: u8 = 1;
+// doctor 子命令：有关键检查未通过
+const EXIT_DOCTOR_FAILED: u8 = 1;
+
+fn main() -> ExitCode {
+    let mut opts = match Options::parse(env::args().skip(1)) {
+        Ok(opts) if opts.help => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!("{}", USAGE);
+            eprintln!("Example: rustpj ./demo/project.zip");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+    // Every artifact path is absolute from here on, so the logged locations are unambiguous
+    match env::current_dir() {
+        Ok(cwd) => opts.resolve_paths(&cwd),
+        Err(e) => {
+            eprintln!("Error: cannot determine the current directory: {}", e);
+            return ExitCode::from(EXIT_ERROR);
+        }
+    }
+    if opts.quiet {
+        console::set_quiet();
+    }
+    if opts.progress_format == ProgressFormat::Ndjson {
+        progress::NdjsonObserver::install();
+    }
+
+    let result = match opts.timeout {
+        Some(limit) => run_with_timeout(opts, limit),
+        None => dispatch(&opts).map_err(|e| e.to_string()),
+    };
+    let code = match result {
+        Ok(code) => code,
+        Err(e) => {
+            if progress::stderr_takes_events() {
+                progress::emit(ProgressEvent::Error { message: e });
+            } else {
+                eprintln!("Error: {}", e);
+            }
+            EXIT_ERROR
+        }
+    };
+    progress::emit(ProgressEvent::Done { exit_code: code });
+    ExitCode::from(code)
+}
+
+fn dispatch(opts: &Options) -> Result<u8, Box<dyn std::error::Error>> {
+    output::set_json_style(opts.json_style);
+    output::set_overwrite(opts.force);
+    rustpj::get_lockfile::set_keep_temp(opts.keep_temp);
+    if opts.no_exec {
+        rustpj::exec::set_policy(rustpj::exec::ExternalCommands::Forbidden);
+    }
+    check_outputs(opts)?;
+    match &opts.command {
+        Command::Scan => run(opts),
+        Command::Verify { dir, public_key } => run_verify(dir, public_key),
+        Command::Doctor => run_doctor(opts, &opts.output_dir()),
+        Command::Query { name, version, json } => run_query(opts, name, version, *json),
+        Command::Batch { inputs, jobs } => run_batch(opts, inputs, *jobs),
+        Command::Why { package, input, json } => run_why(package, input, *json),
+        Command::DbUpdate => run_db_update(opts),
+        Command::Serve { listen, jobs } => run_serve(opts, listen, *jobs),
+    }
+}
+
+// Set while the --interactive prompts wait for an answer; that time does not count against --timeout
+static PROMPTING: AtomicBool = AtomicBool::new(false);
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Ensure tmp gets cleaned even if we early-return on errors
-    struct TempGuard<'a>(&'a str);
-    impl<'a> Drop for TempGuard<'a> {
-        fn drop(&mut self) {
-            if let Err(e) = std::fs::remove_dir_all(self.0) {
-                // Best-effort cleanup, don't crash on failure
-                eprintln!("Warning: failed to clean temporary files: {}", e);
+// --timeout: the pipeline runs on a worker thread and the main thread gives up waiting once
+// the limit passes. Returning from main ends the process, worker included, so the temp
+// directories have to be removed here since the guards on other threads (the scan's own,
+// every batch archive's, every serve upload's) never get to drop.
+// Subprocesses the worker started (cargo) are not killed and may outlive us.
+fn run_with_timeout(opts: Options, limit: Duration) -> Result<u8, String> {
+    const TICK: Duration = Duration::from_millis(100);
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(dispatch(&opts).map_err(|e| e.to_string()));
+    });
+    let started = Instant::now();
+    let mut last_tick = started;
+    let mut prompting = Duration::ZERO;
+    loop {
+        match rx.recv_timeout(TICK) {
+            Ok(result) => return result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let now = Instant::now();
+                if PROMPTING.load(Ordering::SeqCst) {
+                    prompting += now - last_tick;
+                }
+                last_tick = now;
+                if now - started >= limit + prompting {
+                    clean_live_temp_dirs();
+                    return Err(format!("run exceeded --timeout of {}s and was aborted", limit.as_secs()));
+                }
             }
+            // The worker panicked; the panic message is already on stderr
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Err("scan aborted unexpectedly".to_string()),
         }
     }
-    // 获取命令行参数
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: {} <path-to-zip-file>", args[0]);
-        eprintln!("Example: {} ./demo/project.zip", args[0]);
-        std::process::exit(1);
+}
+
+fn run(opts: &Options) -> Result<u8, Box<dyn std::error::Error>> {
+    if opts.report_to_stdout() || opts.report_output.as_deref() == Some("-") || opts.dot_output.as_deref() == Some("-") {
+        console::reserve_stdout();
     }
 
-    let zip_path = &args[1];
-    println!("扫描文件: {}", zip_path);
-    let _tmp_guard = TempGuard("./tmp");
-    let discovery = get_lockfile(zip_path)?;
-    let lockfile = &discovery.lockfile;
+    // 先加载配置和签名密钥，有误时尽早失败
+    let mut config = match &opts.config_path {
+        Some(path) => ScanConfig::load(path)?,
+        None => ScanConfig::default(),
+    };
+    config.online = config.online.with_overrides(
+        opts.registry_url.as_deref(),
+        opts.advisory_db_url.as_deref(),
+        opts.user_agent.as_deref(),
+    )?;
+    let sign_key = opts
+        .sign_key
+        .as_ref()
+        .map(|path| signing::load_signing_key(Path::new(path)))
+        .transpose()?;
+    // Set up now so missing credentials fail the run before the scan rather than after it
+    let sink = opts.output_url.as_deref().map(sink::open).transpose()?;
+    // Extending the output in place starts from nothing on the first run
+    let mut merge_base = match &opts.merge_sbom {
+        Some(path) if opts.merges_sbom_in_place() && !Path::new(path).exists() => None,
+        Some(path) => Some(CycloneDxBom::load(Path::new(path))?),
+        None => None,
+    };
+    let popular_crates = match &opts.popular_crates {
+        Some(path) => typosquat::PopularCrates::load(Path::new(path))?,
+        None => typosquat::PopularCrates::embedded(),
+    };
+    let cpes = match &opts.cpe_mapping {
+        Some(path) => CpeMappings::load(Path::new(path))?,
+        None => CpeMappings::embedded(),
+    };
+    let baseline = load_baseline(opts)?;
+    // 记录写出的文件，供签名清单和 --bundle 使用
+    let mut written: Vec<Artifact> = Vec::new();
+    let mut timings = Timings::new();
+
+    status!("扫描文件: {}", download::redact(&opts.zip_path));
+    // An http(s) input is downloaded first and then handled like a local file
+    let _download_guard;
+    let zip_path = if download::is_url(&opts.zip_path) {
+        _download_guard = TempGuard::new(download::DOWNLOAD_DIR);
+        let path = timings.time("download", || {
+            download::download(&opts.zip_path, Path::new(download::DOWNLOAD_DIR), &config.online)
+        })?;
+        path.to_string_lossy().to_string()
+    } else {
+        opts.zip_path.clone()
+    };
+    let zip_path = &zip_path;
+    let input = Path::new(zip_path);
+    let _tmp_guard;
+    // 压缩包中找到的各个 lockfile；输入为二进制时为空
+    let mut projects: Vec<LockDiscovery> = Vec::new();
+    // 输入可以是项目 ZIP，也可以是用 `cargo auditable` 构建的可执行文件
+    let (lockfile, manifest_path) = if auditable::is_binary(input)? {
+        status!("Detected a compiled binary, reading cargo-auditable dependency data");
+        let name = input.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let lockfile = timings.time("discover", || auditable::load_lockfile(input))?;
+        (lockfile, PathBuf::from(name))
+    } else {
+        _tmp_guard = TempGuard::new(OUTPUT_DIR);
+        timings.time("extract", || extract_archive(zip_path))?;
+        projects = timings.time("discover", discover_lockfiles)?;
+        if projects.len() > 1 {
+            status!("Found {} lockfiles; the report covers their merged dependency graph, broken down per project", projects.len());
+        }
+        // SBOM, graph and policy checks all work on the union of the lockfiles
+        let lockfile = merge_lockfiles(projects.iter().map(|p| &p.lockfile));
+        (lockfile, projects[0].manifest_path.clone())
+    };
+    // rustpj.toml of the root project, merged under --config; flags override both
+    let mut project_config = None;
+    if let Some(root) = projects.first().filter(|_| !opts.no_project_config)
+        && let Some(path) = config.merge_project_config(&root.project_root, opts.trust_project_config)?
+    {
+        let relative = path.strip_prefix(OUTPUT_DIR).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        status!("Using the project config {}", relative);
+        project_config = Some(relative);
+    }
+    let resolved = opts.with_config_defaults(&config)?;
+    let opts = &resolved;
+    // --exclude-package / [[exclude]]: gone before the SBOM, the scan and the per-project reports
+    let exclusions = opts.exclusions(&config.exclude);
+    let (lockfile, excluded) = package_filter::exclude(&lockfile, &exclusions);
+    for project in &mut projects {
+        project.lockfile = package_filter::exclude(&project.lockfile, &exclusions).0;
+    }
+    for exclusion in exclusions.iter().filter(|e| !excluded.iter().any(|x| x.exclusion == e.package.to_string())) {
+        console::warn(
+            "exclusion-unused",
+            format!("exclusion {} did not match any package in the lockfile; consider removing it", exclusion.package),
+        );
+    }
+    // --package: every output below only sees the selected crates
+    let total_in_lockfile = lockfile.packages.len();
+    let selection = opts.package_selection();
+    let lockfile = match &selection {
+        Some(selection) => selection.apply(&lockfile)?,
+        None => lockfile,
+    };
+    let lockfile = &lockfile;
+    config.registries.warn_unmapped(lockfile);
 
     // 创建输出目录
-    std::fs::create_dir_all("./output")
+    std::fs::create_dir_all(opts.output_dir())
         .context("failed to create output directory")?;
+    let sbom_path = &opts.sbom_path();
 
-    // 获取 sbom 并写入 sbom 文件
-    let sbom_path = "./output/sbom.json";
-    generate_sbom_from_lockfile(lockfile, &discovery.project_root, sbom_path)?;
+    // 一次性获取 cargo metadata（许可证、rust-version 等），离线失败或输入为二进制时为 None
+    let mut metadata: Option<cargo_metadata::CargoMetadata> = None;
+    if opts.no_license_lookup {
+        cargo_metadata::license_lookup_disabled();
+    } else if !projects.is_empty() {
+        let missing: Vec<String> =
+            projects.iter().filter(|p| !p.manifests_available).map(|p| p.manifest_path.display().to_string()).collect();
+        if !missing.is_empty() {
+            cargo_metadata::manifests_missing(&missing);
+        }
+        if missing.len() < projects.len() {
+            if opts.no_exec {
+                status!("Reading license information from the manifests (--no-exec)...");
+            } else {
+                status!("Fetching license information...");
+            }
+        }
+        for project in projects.iter().filter(|p| p.manifests_available) {
+            let fetched = timings.time("licenses", || cargo_metadata::fetch_all_metadata(&project.project_root))?;
+            match (&mut metadata, fetched) {
+                (Some(all), Some(fetched)) => all.merge(fetched),
+                (None, fetched) => metadata = fetched,
+                (Some(_), None) => {}
+            }
+        }
+    }
+    let licenses = metadata.as_ref().map(|m| LicenseHistogram::build(lockfile, m));
+    // 工作区成员的清单（区分 application / library），输入为二进制时为空
+    let mut members = WorkspaceMembers::default();
+    for project in &projects {
+        members.merge(WorkspaceMembers::discover(&project.project_root));
+    }
+    let project = ProjectIdentity::of_projects(&opts.project, &projects, input);
+    let sbom_options = |report| SbomOptions {
+        metadata: metadata.as_ref(),
+        members: &members,
+        cpes: &cpes,
+        component_types: &config.component_types,
+        report,
+        project: Some(&project),
+        registries: &config.registries,
+        stamp: Stamp::now(),
+    };
+    if opts.require_licenses {
+        let missing = cargo_metadata::missing_licenses(lockfile, metadata.as_ref());
+        if !missing.is_empty() {
+            return Err(format!(
+                "--require-licenses: {} component(s) have no license information:\n  {}",
+                missing.len(),
+                missing.join("\n  ")
+            )
+            .into());
+        }
+    }
 
-    // 初始化扫描器（使用本地 advisory DB）。允许通过环境变量覆盖默认路径。
-    let db_path = std::env::var("RUSTSEC_DB_PATH").unwrap_or_else(|_| "./data/advisory-db".to_string());
-    match Scanner::new(&db_path) {
-        Ok(scanner) => {
+    // GitHub 依赖快照只描述依赖图，不需要 advisory DB
+    if opts.report_format == ReportFormat::GithubSnapshot {
+        if !opts.no_sbom {
+            let options = sbom_options(None);
+            timings.time("sbom", || write_sbom(opts, lockfile, &options, merge_base.take(), sbom_path))?;
+            written.push(Artifact::new(ArtifactKind::Sbom, sbom_path));
+        }
+        let write_start = Instant::now();
+        let context = opts.snapshot.clone().with_env_defaults();
+        let manifest_path = manifest_path.to_string_lossy().replace('\\', "/");
+        let snapshot = github_snapshot::build_snapshot(lockfile, &manifest_path, &context, &config.registries, &Stamp::now())?;
+        output::write_json(&opts.report_path(), &snapshot)
+            .context("failed to write GitHub dependency snapshot")?;
+        status!("\nGitHub dependency snapshot written to: {}", opts.report_path());
+        if !opts.report_to_stdout() {
+            written.push(Artifact::new(ArtifactKind::Report, opts.report_path()));
+        }
+        timings.record("write", write_start.elapsed());
+        finish_outputs(opts, sign_key.as_ref(), sink.as_deref(), written, licenses.as_ref(), &mut timings)?;
+        return Ok(EXIT_OK);
+    }
+
+    // 初始化扫描器（使用本地 advisory DB）
+    let scanner = timings.time("db-load", || (!opts.no_scan).then(|| open_scanner(opts)));
+    let report = match scanner {
+        None => {
+            status!("Vulnerability scanning skipped (--no-scan)");
+            None
+        }
+        Some(Ok(scanner)) => {
+            let mut scanner = scanner
+                .with_severity_overrides(config.severity_overrides.clone())
+                .with_alias_scores(alias_scores(opts)?)
+                .with_withdrawn(opts.include_withdrawn)
+                .with_categories(opts.categories.clone())
+                .with_workspace_excluded(opts.exclude_workspace)
+                .with_registries(config.registries.clone());
+            scanner = with_collections(scanner, opts);
+            if let Some(triple) = &opts.target {
+                scanner = scanner.with_target(triple)?;
+            }
+            if !opts.quiet && !progress::stderr_takes_events() {
+                let ignore = config.ignore.clone();
+                progress::EarlyFindings::install(move |id, package| rustpj::config::suppressed_by(&ignore, id, package));
+            }
             // 扫描依赖并生成报告
-            let report = scanner.scan_lockfile(lockfile)
+            let mut report = timings.time("scan", || scanner.scan_lockfile(lockfile))
                 .context("failed to scan dependencies")?;
+            report.metadata.package_filter = selection.as_ref().map(|s| s.to_string());
+            report.metadata.excluded_packages = excluded.clone();
+            report.describe_input(&projects, &project);
+            report.metadata.project_config = project_config.clone();
+            config.apply_triage(&mut report);
+            if opts.interactive {
+                interactive_triage(opts, &scanner, &report)?;
+            }
+            for id in &report.unused_severity_overrides {
+                console::warn(
+                    "severity-override-unused",
+                    format!("severity override for {} did not match any finding; consider removing it", id),
+                );
+            }
+            report.suspicious_packages = typosquat::check(lockfile, &popular_crates);
+            for git in &report.git_dependencies {
+                let branch = git.reference.as_deref().unwrap_or("the default branch");
+                console::warn(
+                    "git-branch-pin",
+                    format!("{}@{} follows {} of {} instead of a fixed rev or tag", git.package_name, git.package_version, branch, git.repository),
+                );
+            }
+            if opts.check_msrv {
+                report.msrv_conflicts = Some(msrv::check(lockfile, metadata.as_ref()));
+            }
+            if let Some(check) = staleness_check(opts, &config) {
+                status!("Checking for dependencies without a release in {} days...", check.max_age_days());
+                let checked = timings.time("staleness", || check.check(lockfile));
+                report.stale_packages = checked.stale;
+                report.yanked_packages = checked.yanked;
+            }
+            if projects.len() > 1 {
+                for project in &projects {
+                    // A filtered crate need not appear in every project
+                    let project_lockfile = match &selection {
+                        Some(selection) => selection.select(&project.lockfile),
+                        None => project.lockfile.clone(),
+                    };
+                    let mut project_report = timings.time("scan", || scanner.scan_lockfile(&project_lockfile))
+                        .with_context(|| format!("failed to scan {}", project.manifest_path.display()))?;
+                    project_report.set_lockfile_lines(&project.lines);
+                    project_report.metadata.lockfile_version = Some(project.resolve_version());
+                    config.apply_triage(&mut project_report);
+                    let lockfile_path = project.manifest_path.to_string_lossy().replace('\\', "/");
+                    report.projects.push(ProjectReport::from_report(&project.project_name, lockfile_path, project_report));
+                }
+            }
+            if let Some(baseline) = &baseline {
+                baseline.apply(&mut report);
+            }
+            // After the baseline, so that only new findings count against a rule
+            report.policy_results = policy::evaluate(&config.policy, &report, lockfile, metadata.as_ref());
+            if let Some((gate, source)) = policy::effective_gate(opts.fail_on, config.gate.as_ref()) {
+                report.gate = Some(gate.evaluate(source, &report));
+            }
+            report.cvss_gate = cvss_gate(opts).map(|gate| gate.evaluate(&report));
+            if opts.online {
+                status!("Looking up latest releases on {}...", config.online.registry_url);
+                timings.time("online", || online::annotate_latest_releases(&mut report, lockfile, &config.online));
+            }
+            Some(report)
+        }
+        // A consumer reading stdout expects a report, and a pinned revision is an explicit
+        // request for a reproducible scan, so neither should silently skip scanning
+        // Without an SBOM the scan is the only output, so it cannot be skipped either
+        Some(Err(e)) if opts.report_to_stdout() || opts.db_rev.is_some() || opts.no_sbom => {
+            return Err(format!("failed to initialize vulnerability scanner: {}", e).into());
+        }
+        Some(Err(e)) => {
+            console::warn(
+                "scan-skipped",
+                format!("failed to initialize vulnerability scanner: {}; vulnerability scanning skipped, SBOM still generated", e),
+            );
+            None
+        }
+    };
 
-            // 将报告写入 JSON 文件
-            let report_path = Path::new("./output/vuln_report.json");
-            std::fs::write(
-                report_path,
-                serde_json::to_string_pretty(&report)?,
-            ).context("failed to write vulnerability report")?;
-
-            // 打印扫描统计
-            println!("\nScan completed!");
-            println!("Total packages scanned: {}", report.total_packages);
-            println!("Vulnerabilities found: {}", report.summary.total_vulnerabilities);
-            println!("By severity:");
-            println!("  Critical: {}", report.summary.by_severity.critical);
-            println!("  High:     {}", report.summary.by_severity.high);
-            println!("  Medium:   {}", report.summary.by_severity.medium);
-            println!("  Low:      {}", report.summary.by_severity.low);
-            println!("  Unknown:  {}", report.summary.by_severity.unknown);
-            println!("\nDetailed report written to: {}", report_path.display());
+    // 获取 sbom 并写入 sbom 文件（可选嵌入漏洞及分诊结论）
+    let sbom_serial = if opts.no_sbom {
+        None
+    } else {
+        let embedded = if opts.embed_vulnerabilities { report.as_ref() } else { None };
+        let options = sbom_options(embedded);
+        let serial = timings.time("sbom", || write_sbom(opts, lockfile, &options, merge_base.take(), sbom_path))?;
+        written.push(Artifact::new(ArtifactKind::Sbom, sbom_path));
+        Some(serial)
+    };
+
+    if let Some(dot_path) = &opts.dot_output {
+        output::write_text(dot_path, &dot::render(lockfile, report.as_ref())).context("failed to write DOT graph")?;
+        status!("Dependency graph written to: {}", dot_path);
+        if dot_path != "-" {
+            written.push(Artifact::new(ArtifactKind::Report, dot_path));
         }
-        Err(e) => {
-            eprintln!("Error: failed to initialize vulnerability scanner: {}", e);
-            eprintln!("Vulnerability scanning skipped; SBOM still generated.");
+    }
+
+    let Some(mut report) = report else {
+        finish_outputs(opts, sign_key.as_ref(), sink.as_deref(), written, licenses.as_ref(), &mut timings)?;
+        return Ok(EXIT_OK);
+    };
+    report.metadata.timings = timings.to_map();
+    report.warnings = console::warnings();
+    if opts.canonical {
+        report.canonicalize();
+    }
+
+    // 将报告写入 JSON 文件（或 stdout）
+    let write_start = Instant::now();
+    let report_path = &opts.report_path();
+    if opts.report_format == ReportFormat::Trivy {
+        let artifact_name = input.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let target = manifest_path.to_string_lossy().replace('\\', "/");
+        output::write_json(report_path, &trivy::build_trivy(&report, &artifact_name, &target))
+            .context("failed to write Trivy report")?;
+    } else if opts.report_format == ReportFormat::Junit {
+        let suite = input.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        output::write_text(report_path, &junit::render(&report, lockfile, &suite)).context("failed to write JUnit report")?;
+    } else {
+        let mut json = serde_json::to_value(&report)?;
+        description::apply(&mut json, opts.description.unwrap_or_default());
+        output::write_json(report_path, &json)
+            .context("failed to write vulnerability report")?;
+    }
+    if !opts.report_to_stdout() {
+        written.push(Artifact::new(ArtifactKind::Report, report_path));
+    }
+
+    if let Some(template_path) = &opts.report_template {
+        // --report-output presence is checked while parsing arguments
+        let output = opts.report_output.as_deref().unwrap_or("-");
+        let rendered = template::render_file(Path::new(template_path), &report)?;
+        output::write_text(output, &rendered).context("failed to write rendered report")?;
+        status!("Rendered report written to: {}", output);
+        if output != "-" {
+            written.push(Artifact::new(ArtifactKind::Report, output));
+        }
+    }
+
+    // --vex-output together with --no-sbom is rejected while parsing arguments
+    if let (Some(vex_path), Some(sbom_serial)) = (&opts.vex_output, &sbom_serial) {
+        let vex = vex::build_vex(&report, sbom_serial, &Stamp::now());
+        output::write_json(vex_path, &vex)
+            .context("failed to write VEX document")?;
+        status!("VEX document written to: {}", vex_path);
+        written.push(Artifact::new(ArtifactKind::Report, vex_path));
+    }
+    timings.record("write", write_start.elapsed());
+
+    // 打印扫描统计
+    status!("\nScan completed!");
+    status!("Total packages scanned: {}", report.total_packages);
+    if let Some(stats) = &report.metadata.graph_stats {
+        let mut line = format!(
+            "Dependency graph: {} crates ({} with several versions), {} edges",
+            stats.distinct_crates, stats.duplicated_crates, stats.total_dependencies
+        );
+        if let (Some(direct), Some(depth)) = (stats.direct_dependencies, stats.max_depth) {
+            line.push_str(&format!(", {} direct, max depth {}", direct, depth));
+        }
+        status!(
+            "{}; sources: {} registry, {} git, {} path",
+            line,
+            stats.sources.registry,
+            stats.sources.git,
+            stats.sources.path
+        );
+    }
+    if let Some(filter) = &report.metadata.package_filter {
+        status!("Package filter active: {}; {} of {} packages in the lockfile", filter, report.total_packages, total_in_lockfile);
+    }
+    if report.metadata.advisory_dbs.len() > 1 {
+        for db in &report.metadata.advisory_dbs {
+            status!("Advisory DB: {} at {}", db.path, db.commit.as_deref().unwrap_or("unknown commit"));
+        }
+    } else if let Some(commit) = &report.metadata.advisory_db_commit {
+        status!("Advisory DB commit: {}", commit);
+    }
+    status!("Vulnerabilities found: {}", report.summary.total_vulnerabilities);
+    status!("Unique advisories: {}", report.summary.unique_advisories);
+    status!("Vulnerable packages: {}", report.summary.vulnerable_packages);
+    status!("By severity:");
+    let counts = &report.summary.by_severity;
+    for (severity, label, count) in [
+        (Severity::Critical, "Critical:", counts.critical),
+        (Severity::High, "High:    ", counts.high),
+        (Severity::Medium, "Medium:  ", counts.medium),
+        (Severity::Low, "Low:     ", counts.low),
+        (Severity::Unknown, "Unknown: ", counts.unknown),
+    ] {
+        status!("  {} {}", console::paint(severity, label), count);
+    }
+    status!("Risk score: {}", report.summary.risk_score);
+    if let (Some(new), Some(path)) = (report.summary.new_since_baseline, &report.metadata.baseline) {
+        status!("New since baseline ({}): {}", path, new);
+        for pkg in &report.packages {
+            for finding in pkg.advisories.iter().filter(|f| f.is_new_since_baseline()) {
+                status!("  {}@{} {} ({})", pkg.package_name, pkg.package_version, finding.id, finding.severity);
+            }
+        }
+    }
+    let direct = &report.summary.by_direct_dependency;
+    if !direct.is_empty() {
+        // The direct dependencies worth bumping first
+        status!("Top direct dependencies by findings:");
+        for dep in direct.iter().take(5) {
+            let shared = match dep.shared_findings {
+                0 => String::new(),
+                n => format!(", {} also reachable through other direct dependencies", n),
+            };
+            status!("  {}@{}: {} finding(s), max {}{}", dep.package_name, dep.package_version, dep.findings, dep.max_severity, shared);
+        }
+        if direct.len() > 5 {
+            status!("  ... and {} more", direct.len() - 5);
+        }
+        if direct.iter().any(|d| d.shared_findings > 0) {
+            status!("  (a finding reachable through several direct dependencies is counted under each)");
+        }
+    }
+    if !report.projects.is_empty() {
+        print_projects(&report.projects);
+    }
+    if report.summary.unfixable > 0 {
+        // Kept apart from routine upgrades: these crates usually have to be replaced
+        status!("No fix available: {}", report.summary.unfixable);
+        for pkg in &report.packages {
+            for finding in pkg.advisories.iter().filter(|f| f.no_fix_available) {
+                let latest = match &finding.latest_release {
+                    Some(latest) if latest.unfixable_by_upgrade => " (already the latest release)".to_string(),
+                    Some(latest) => format!(" (latest release: {})", latest.version),
+                    None => String::new(),
+                };
+                status!("  {}@{} {}: {}{}", pkg.package_name, pkg.package_version, finding.id, finding.remediation, latest);
+            }
+        }
+    }
+    if let Some(target) = &report.metadata.target {
+        let skipped: usize = report.not_applicable_to_target.iter().map(|p| p.advisories.len()).sum();
+        status!("Not applicable to {}: {}", target, skipped);
+    }
+    for excluded in &report.metadata.excluded_packages {
+        let reason = excluded.reason.as_ref().map(|r| format!(" ({})", r)).unwrap_or_default();
+        status!("Excluded: {}@{}{}", excluded.package_name, excluded.package_version, reason);
+    }
+    if !report.excluded_workspace_members.is_empty() {
+        status!("Workspace members not scanned: {}", report.excluded_workspace_members.join(", "));
+    }
+    if let Some(filter) = &report.metadata.category_filter {
+        status!("Filtered by category ({}): {}", filter, report.summary.filtered_by_category);
+    }
+    if let Some(msrv) = &report.msrv_conflicts {
+        match &msrv.note {
+            Some(note) => status!("MSRV check: {}", note),
+            None => status!("MSRV conflicts: {}", msrv.conflicts.len()),
+        }
+    }
+    for suspicious in &report.suspicious_packages {
+        status!(
+            "Suspicious package: {}@{} resembles '{}'",
+            suspicious.package_name, suspicious.package_version, suspicious.resembles
+        );
+    }
+    for pkg in &report.prerelease_packages {
+        status!("Pre-release dependency: {}@{}", pkg.package_name, pkg.package_version);
+    }
+    for pkg in &report.git_packages {
+        match &pkg.commit {
+            Some(commit) => status!("Git dependency: {}@{} from {} at {}", pkg.package_name, pkg.package_version, pkg.repository, commit),
+            None => status!("Git dependency: {}@{} from {}", pkg.package_name, pkg.package_version, pkg.repository),
         }
     }
+    for stale in &report.stale_packages {
+        status!(
+            "Stale package: {}@{}, latest release {} on {}",
+            stale.package_name, stale.package_version, stale.latest_version, stale.latest_release_date
+        );
+    }
+    for yanked in &report.yanked_packages {
+        status!("Yanked package: {}@{} was yanked from crates.io", yanked.package_name, yanked.package_version);
+    }
+    for entry in &report.withdrawn {
+        match entry.superseded_by.as_slice() {
+            [] => status!("Withdrawn: {} ({}@{}) withdrawn {}", entry.id, entry.package_name, entry.package_version, entry.withdrawn),
+            ids => status!(
+                "Withdrawn: {} ({}@{}) withdrawn {}, superseded by {}",
+                entry.id, entry.package_name, entry.package_version, entry.withdrawn, ids.join(", ")
+            ),
+        }
+    }
+    if !report.informational.is_empty() {
+        status!("Informational advisories: {}", report.informational.len());
+    }
+    for component in &report.toolchain {
+        let ids: Vec<&str> = component.advisories.iter().map(|f| f.id.as_str()).collect();
+        status!("Toolchain: {} {} is affected by {}", component.package_name, component.package_version, ids.join(", "));
+    }
+    for result in &report.policy_results {
+        let label = match result.action {
+            PolicyAction::Fail => console::paint(Severity::Critical, "Policy fail:"),
+            PolicyAction::Warn => console::paint(Severity::Medium, "Policy warn:"),
+            PolicyAction::Note => "Policy note:".to_string(),
+        };
+        status!("{} [{}] {}", label, result.rule, result.message);
+    }
+    if let Some(gate) = &report.gate
+        && !gate.warned.is_empty()
+    {
+        status!("{} {}", console::paint(Severity::Medium, "Gate warn:"), gate.warned.join(", "));
+    }
+    if let Some(gate) = &report.cvss_gate
+        && !gate.unscored.is_empty()
+        && !gate.fail_unscored
+    {
+        status!("Not checked by --min-cvss (no CVSS score): {}", gate.unscored.join(", "));
+    }
+    status!("\nDetailed report written to: {}", report_path);
+
+    finish_outputs(opts, sign_key.as_ref(), sink.as_deref(), written, licenses.as_ref(), &mut timings)?;
+
+    if let Some(gate) = &report.gate
+        && gate.outcome == GateAction::Fail
+    {
+        match opts.fail_on {
+            Some(Severity::Unknown) => console::eprint_line("Untriaged vulnerabilities found"),
+            Some(threshold) => console::eprint_line(&format!("Untriaged vulnerabilities at or above '{}' found", threshold)),
+            None => console::eprint_line(&format!("Untriaged vulnerabilities with gate action 'fail' found: {}", gate.failed.join(", "))),
+        }
+        return Ok(EXIT_VULNERABLE);
+    }
+    if let Some(gate) = &report.cvss_gate
+        && gate.outcome == GateAction::Fail
+    {
+        console::eprint_line(&format!("Untriaged vulnerabilities with a CVSS score of at least {:.1} found: {}", gate.min_score, gate.failed.join(", ")));
+        return Ok(EXIT_VULNERABLE);
+    }
+    if opts.fail_on_suspicious && !report.suspicious_packages.is_empty() {
+        console::eprint_line("Packages with names resembling popular crates found");
+        return Ok(EXIT_VULNERABLE);
+    }
+    if policy::strictest_action(&report.policy_results) == Some(PolicyAction::Fail) {
+        console::eprint_line("Policy rules with action 'fail' were triggered");
+        return Ok(EXIT_VULNERABLE);
+    }
+
+    Ok(EXIT_OK)
+}
 
+// 按项目分列的统计表
+fn print_projects(projects: &[ProjectReport]) {
+    let name_width = projects.iter().map(|p| p.name.len()).max().unwrap_or(0).max("PROJECT".len());
+    let path_width = projects.iter().map(|p| p.lockfile_path.len()).max().unwrap_or(0).max("LOCKFILE".len());
+    status!("By project:");
+    status!(
+        "  {:<name_width$}  {:<path_width$}  {:>8}  {:>5}  {:>4}  {:>4}  {:>4}  {:>4}",
+        "PROJECT", "LOCKFILE", "PACKAGES", "VULNS", "CRIT", "HIGH", "MED", "LOW"
+    );
+    for project in projects {
+        let counts = &project.summary.by_severity;
+        status!(
+            "  {:<name_width$}  {:<path_width$}  {:>8}  {:>5}  {:>4}  {:>4}  {:>4}  {:>4}",
+            project.name,
+            project.lockfile_path,
+            project.total_packages,
+            project.summary.total_vulnerabilities,
+            counts.critical,
+            counts.high,
+            counts.medium,
+            counts.low
+        );
+    }
+}
+
+// 收尾：写签名清单（--sign-key），把所有输出打包（--bundle），再发布到 --output-url
+fn finish_outputs(
+    opts: &Options,
+    key: Option<&ed25519_dalek::SigningKey>,
+    sink: Option<&dyn sink::OutputSink>,
+    mut written: Vec<Artifact>,
+    licenses: Option<&LicenseHistogram>,
+    timings: &mut Timings,
+) -> Result<()> {
+    let write_start = Instant::now();
+    if let Some(key) = key {
+        let files: Vec<PathBuf> = written.iter().map(|a| a.path.clone()).collect();
+        let (manifest, signature) = signing::write_signed_manifest(&opts.output_dir(), &files, key)?;
+        status!("Integrity manifest written to: {} (signature: {})", manifest.display(), signature.display());
+        written.push(Artifact::new(ArtifactKind::Manifest, manifest));
+        written.push(Artifact::new(ArtifactKind::Manifest, signature));
+    }
+
+    if let Some(bundle_path) = &opts.bundle {
+        let digest = bundle::write_bundle(Path::new(bundle_path), &written, &console::run_log())?;
+        status!("Bundle written to: {}", bundle_path);
+        status!("Bundle sha256: {}", digest);
+        written.push(Artifact::new(ArtifactKind::Bundle, bundle_path));
+    }
+    timings.record("write", write_start.elapsed());
+    if let Some(sink) = sink {
+        status!("Publishing {} output(s) to {}...", written.len(), sink.location());
+        let files: Vec<PathBuf> = written.iter().map(|a| a.path.clone()).collect();
+        let published = timings.time("upload", || sink::publish(sink, &files, &opts.output_dir(), sink::Retry::default()))?;
+        for location in &published {
+            status!("Published: {}", location);
+        }
+    }
+    if let Some(licenses) = licenses {
+        print_licenses(licenses, opts.verbose);
+    }
+    status!("Timings: {}", timings.summary_line());
+    Ok(())
+}
+
+// The license histogram as a two-column table; --verbose also names the unknown ones
+fn print_licenses(licenses: &LicenseHistogram, verbose: bool) {
+    let ranked = licenses.ranked();
+    let width = ranked.iter().map(|(license, _)| license.len()).max().unwrap_or(0);
+    status!("Licenses:");
+    for (license, count) in ranked {
+        status!("  {:<width$}  {:>5}", license, count, width = width);
+    }
+    if verbose && !licenses.unknown.is_empty() {
+        status!("Components without license information:");
+        for component in &licenses.unknown {
+            status!("  {}", component);
+        }
+    }
+}
+
+fn run_verify(dir: &str, public_key: &str) -> Result<u8, Box<dyn std::error::Error>> {
+    let key = signing::load_verifying_key(Path::new(public_key))?;
+    let problems = signing::verify_dir(Path::new(dir), &key)?;
+    if problems.is_empty() {
+        println!("OK: all files in {} match the signed manifest", dir);
+        return Ok(EXIT_OK);
+    }
+    for problem in &problems {
+        console::eprint_line(&format!("FAILED: {}", problem));
+    }
+    Ok(EXIT_VERIFY_FAILED)
+}
+
+// --db-path 给出的 DB；没有时默认使用 ./data/advisory-db，允许通过环境变量覆盖
+fn advisory_db_paths(opts: &Options) -> Vec<String> {
+    if !opts.db_paths.is_empty() {
+        return opts.db_paths.clone();
+    }
+    vec![env::var("RUSTSEC_DB_PATH").unwrap_or_else(|_| "./data/advisory-db".to_string())]
+}
+
+// --db-rev is only accepted with a single DB (see cli)
+fn open_scanner(opts: &Options) -> anyhow::Result<Scanner> {
+    let paths = advisory_db_paths(opts);
+    match &opts.db_rev {
+        Some(rev) => Scanner::new_at_rev_from(&paths[0], rev, advisory_source(opts)),
+        None => Scanner::new_multi_from(&paths, advisory_source(opts)),
+    }
+}
+
+fn advisory_source(opts: &Options) -> AdvisorySource {
+    if opts.strict_db { AdvisorySource::Strict } else { AdvisorySource::Lenient }
+}
+
+fn run_doctor(opts: &Options, output_dir: &Path) -> Result<u8, Box<dyn std::error::Error>> {
+    let mut failed = false;
+
+    for db_path in advisory_db_paths(opts) {
+        match Scanner::new_multi_from(&[&db_path], advisory_source(opts)) {
+            Ok(scanner) => {
+                println!("OK: advisory DB at {} ({} advisories)", db_path, scanner.advisory_count());
+                for warning in scanner.db_warnings() {
+                    println!("WARN: skipped {}: {}", warning.path, warning.error);
+                }
+                for (id, conflict) in scanner.range_conflicts() {
+                    println!("WARN: {}: {}; patched and unaffected versions are treated as not affected", id, conflict);
+                }
+                match scanner.db_commit() {
+                    Some(commit) => println!("OK: advisory DB HEAD is {}", commit),
+                    None => println!("WARN: advisory DB HEAD commit unknown (not a git checkout?)"),
+                }
+            }
+            Err(e) => {
+                failed = true;
+                println!("FAILED: advisory DB at {}: {:#}", db_path, e);
+                println!("       set RUSTSEC_DB_PATH or run `rustpj db update` to clone the advisory DB into {}", db_path);
+            }
+        }
+    }
+
+    // cargo 只在缺少 Cargo.lock 或需要元数据时用到，缺失不影响扫描本身
+    match rustpj::exec::command("cargo", &["--version"]).map(|mut c| c.output()) {
+        Err(e) => println!("SKIP: {}", e),
+        Ok(Ok(out)) if out.status.success() => {
+            println!("OK: {}", String::from_utf8_lossy(&out.stdout).trim())
+        }
+        Ok(_) => println!("WARN: cargo not found; missing Cargo.lock files cannot be generated and licenses are omitted"),
+    }
+
+    for (label, dir) in [("temp dir", Path::new(rustpj::get_lockfile::OUTPUT_DIR)), ("output dir", output_dir)] {
+        let resolved = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        println!("OK: {} resolves to {}", label, resolved.display());
+    }
+    if let Err(e) = check_writable(output_dir) {
+        failed = true;
+        println!("FAILED: output dir {} is not writable: {}", output_dir.display(), e);
+    }
+
+    Ok(if failed { EXIT_DOCTOR_FAILED } else { EXIT_OK })
+}
+
+// The mirror comes from `[online] advisory_db_url` in --config, overridden by --db-url
+fn run_db_update(opts: &Options) -> Result<u8, Box<dyn std::error::Error>> {
+    let config = match &opts.config_path {
+        Some(path) => ScanConfig::load(path)?,
+        None => ScanConfig::default(),
+    };
+    let online = config.online.with_overrides(None, opts.advisory_db_url.as_deref(), None)?;
+    // The first DB is the upstream clone; forks are maintained by hand
+    let db_path = advisory_db_paths(opts).remove(0);
+    let update = db_update::update(Path::new(&db_path), &online.advisory_db_url)?;
+    match &update.action {
+        UpdateAction::Cloned => status!("Cloned the advisory DB into {}", db_path),
+        UpdateAction::FastForwarded { from } => status!("Fast-forwarded {} from {}", db_path, &from[..12]),
+        UpdateAction::UpToDate => status!("{} is already up to date", db_path),
+        UpdateAction::Recloned { reason } => status!("Replaced {} with a fresh clone ({})", db_path, reason),
+    }
+    status!("HEAD is now {} ({})", update.commit, update.commit_time.format("%Y-%m-%d %H:%M UTC"));
+    Ok(EXIT_OK)
+}
+
+// The scanner `batch` and `serve` load once and share between their workers
+fn shared_scanner(opts: &Options, config: &ScanConfig) -> anyhow::Result<Scanner> {
+    let mut scanner = open_scanner(opts)?
+        .with_severity_overrides(config.severity_overrides.clone())
+        .with_alias_scores(alias_scores(opts)?)
+        .with_withdrawn(opts.include_withdrawn)
+        .with_categories(opts.categories.clone())
+        .with_workspace_excluded(opts.exclude_workspace)
+        .with_registries(config.registries.clone());
+    scanner = with_collections(scanner, opts);
+    if let Some(triple) = &opts.target {
+        scanner = scanner.with_target(triple)?;
+    }
+    Ok(scanner)
+}
+
+// The SBOM on its own, or merged into the one given with --merge-sbom
+fn write_sbom(
+    opts: &Options,
+    lockfile: &cargo_lock::Lockfile,
+    options: &SbomOptions,
+    base: Option<CycloneDxBom>,
+    path: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    match base {
+        Some(base) => {
+            let conflicts = opts.merge_conflicts.unwrap_or_default();
+            generate_merged_sbom(lockfile, options, base, conflicts, path, opts.merges_sbom_in_place())
+        }
+        None => generate_sbom_from_lockfile(lockfile, options, path),
+    }
+}
+
+// --check-staleness: publish dates from --crates-snapshot, or from the registry through the cache
+fn staleness_check(opts: &Options, config: &ScanConfig) -> Option<staleness::StalenessCheck> {
+    if !opts.check_staleness {
+        return None;
+    }
+    let source = match &opts.crates_snapshot {
+        Some(dir) => staleness::ReleaseSource::Snapshot(PathBuf::from(dir)),
+        None => staleness::ReleaseSource::Registry {
+            online: config.online.clone(),
+            cache_dir: PathBuf::from(&config.staleness.cache_dir),
+            ttl: Duration::from_secs(config.staleness.cache_ttl_hours * 3600),
+        },
+    };
+    Some(staleness::StalenessCheck::new(source, &config.staleness, chrono::Utc::now().date_naive()))
+}
+
+// --collection / --toolchain; without --collection the scanner keeps its crates-only default
+fn with_collections(mut scanner: Scanner, opts: &Options) -> Scanner {
+    if !opts.collections.is_empty() {
+        scanner = scanner.with_collections(opts.collections.clone());
+    }
+    if let Some(version) = &opts.toolchain {
+        scanner = scanner.with_toolchain(version.clone());
+    }
+    scanner
+}
+
+#[cfg(feature = "server")]
+fn run_serve(opts: &Options, listen: &str, jobs: Option<usize>) -> Result<u8, Box<dyn std::error::Error>> {
+    use rustpj::server::{self, ServerState};
+    let config = match &opts.config_path {
+        Some(path) => ScanConfig::load(path)?,
+        None => ScanConfig::default(),
+    };
+    let cpes = match &opts.cpe_mapping {
+        Some(path) => CpeMappings::load(Path::new(path))?,
+        None => CpeMappings::embedded(),
+    };
+    let scanner = shared_scanner(opts, &config)?;
+    if opts.no_license_lookup {
+        status!("License lookup disabled: cargo metadata is not run on uploaded projects");
+    }
+    if opts.no_exec {
+        status!("External commands disabled: uploads need a Cargo.lock, licenses come from their manifests");
+    }
+    // Status lines keep going to the service log, but are not piled up in memory
+    console::stop_retaining();
+    let jobs = jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+    let description = opts.description.or(config.output.description()).unwrap_or_default();
+    let state = ServerState {
+        scanner,
+        config,
+        cpes,
+        description,
+        license_lookup: !opts.no_license_lookup,
+        started_at: chrono::Utc::now().to_rfc3339(),
+    };
+    server::serve(state, listen, jobs)?;
+    Ok(EXIT_OK)
+}
+
+#[cfg(not(feature = "server"))]
+fn run_serve(_opts: &Options, _listen: &str, _jobs: Option<usize>) -> Result<u8, Box<dyn std::error::Error>> {
+    Err("this build has no HTTP server; rebuild with `cargo build --release --features server`".into())
+}
+
+// --baseline: findings of an earlier report that no longer gate the exit code
+fn load_baseline(opts: &Options) -> anyhow::Result<Option<Baseline>> {
+    let baseline = opts.baseline.as_ref().map(|path| Baseline::load(Path::new(path))).transpose()?;
+    if let Some(baseline) = &baseline {
+        status!("Baseline: {} known finding(s)", baseline.len());
+    }
+    Ok(baseline)
+}
+
+// --interactive: the decisions go to the triage file and take effect on the next run
+fn interactive_triage(opts: &Options, scanner: &Scanner, report: &rustpj::VulnReport) -> anyhow::Result<()> {
+    if !std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+        console::warn("interactive-skipped", "--interactive needs a terminal on stdin; skipped the triage prompts");
+        return Ok(());
+    }
+    let mut titles = std::collections::BTreeMap::new();
+    for package in &report.packages {
+        for advisory in scanner.advisories_for_package(&package.package_name) {
+            titles.insert(advisory.metadata.id.to_string(), advisory.metadata.title.clone());
+        }
+    }
+    PROMPTING.store(true, Ordering::SeqCst);
+    let entries = triage::prompt(report, &titles, &mut std::io::stdin().lock(), &mut std::io::stderr());
+    PROMPTING.store(false, Ordering::SeqCst);
+    let entries = entries?;
+    if entries.is_empty() {
+        status!("No triage decisions recorded");
+        return Ok(());
+    }
+    let path = opts.triage_file.as_deref().unwrap_or(triage::DEFAULT_TRIAGE_FILE);
+    triage::append(Path::new(path), &entries)?;
+    status!("{} triage decision(s) appended to {}; pass it with --config on the next run", entries.len(), path);
     Ok(())
 }
+
+// --min-cvss, next to the severity gate
+fn cvss_gate(opts: &Options) -> Option<CvssGate> {
+    opts.min_cvss.map(|min_score| CvssGate { min_score, fail_unscored: opts.fail_on_unscored })
+}
+
+// --osv-scores: extra CVSS scores matched to advisories by id and alias
+fn alias_scores(opts: &Options) -> anyhow::Result<AliasScores> {
+    match &opts.osv_scores {
+        Some(dir) => AliasScores::load(Path::new(dir)),
+        None => Ok(AliasScores::default()),
+    }
+}
+
+fn run_query(opts: &Options, name: &str, version: &semver::Version, json: bool) -> Result<u8, Box<dyn std::error::Error>> {
+    let mut scanner = Scanner::new_multi_from(&advisory_db_paths(opts), advisory_source(opts))?.with_alias_scores(alias_scores(opts)?);
+    if let Some(triple) = &opts.target {
+        scanner = scanner.with_target(triple)?;
+    }
+    let findings = scanner.query(name, version);
+    if json {
+        output::write_json("-", &findings)?;
+        return Ok(EXIT_OK);
+    }
+    if findings.is_empty() {
+        println!("No advisories affect {}@{}", name, version);
+        return Ok(EXIT_OK);
+    }
+    println!("{:<20} {:<9} {:<24} DESCRIPTION", "ID", "SEVERITY", "PATCHED");
+    for finding in &findings {
+        let summary = finding.description.lines().next().unwrap_or_default();
+        println!(
+            "{:<20} {:<9} {:<24} {}",
+            finding.id,
+            finding.severity.as_str(),
+            finding.patched_versions.as_deref().unwrap_or("no fix"),
+            summary
+        );
+    }
+    Ok(EXIT_OK)
+}
+
+fn run_why(package: &PackageFilter, input: &str, json: bool) -> Result<u8, Box<dyn std::error::Error>> {
+    // The tree (or JSON) is the output; extraction progress goes to stderr
+    console::reserve_stdout();
+    let path = Path::new(input);
+    let _tmp_guard;
+    let lockfile = if path.file_name().is_some_and(|n| n == "Cargo.lock") {
+        load_lockfile(path).with_context(|| format!("failed to parse lockfile: {}", input))?
+    } else if auditable::is_binary(path)? {
+        auditable::load_lockfile(path)?
+    } else {
+        _tmp_guard = TempGuard::new(OUTPUT_DIR);
+        extract_archive(input)?;
+        let projects = discover_lockfiles()?;
+        merge_lockfiles(projects.iter().map(|p| &p.lockfile))
+    };
+    package.check(&lockfile)?;
+
+    let graph = DependencyGraph::from_lockfile(&lockfile);
+    // One tree per matching version
+    let trees: Vec<InverseTree> = graph
+        .find(&package.name, package.version.as_ref())
+        .into_iter()
+        .map(|node| graph.inverse_tree(node))
+        .collect();
+    if json {
+        output::write_json("-", &trees)?;
+        return Ok(EXIT_OK);
+    }
+    let rendered: Vec<String> = trees.iter().map(|t| t.render()).collect();
+    output::write_text("-", rendered.join("\n").trim_end())?;
+    Ok(EXIT_OK)
+}
+
+fn run_batch(opts: &Options, inputs: &[String], jobs: Option<usize>) -> Result<u8, Box<dyn std::error::Error>> {
+    if opts.report_to_stdout() {
+        console::reserve_stdout();
+    }
+    let mut config = match &opts.config_path {
+        Some(path) => ScanConfig::load(path)?,
+        None => ScanConfig::default(),
+    };
+    config.online = config.online.with_overrides(
+        opts.registry_url.as_deref(),
+        opts.advisory_db_url.as_deref(),
+        opts.user_agent.as_deref(),
+    )?;
+    let opts = &opts.with_config_defaults(&config)?;
+    let baseline = load_baseline(opts)?;
+    let archives = batch::collect_archives(inputs)?;
+    // Each archive's report also goes to its own subdirectory, under the same --force rule
+    let entry_reports = match opts.report_to_stdout() {
+        true => Vec::new(),
+        false => batch::entry_report_paths(&opts.output_dir(), &archives)?,
+    };
+    refuse_existing(opts.force, &entry_reports)?;
+
+    // 只加载一次 advisory DB，所有工作线程共享
+    let scanner = shared_scanner(opts, &config)?;
+    let jobs = jobs
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+        .min(archives.len());
+    status!("Scanning {} archives with {} workers", archives.len(), jobs);
+    // Workers share the on-disk cache, so each crate is fetched about once per batch
+    let options = batch::BatchOptions {
+        exclusions: opts.exclusions(&config.exclude),
+        registries: config.registries.clone(),
+        staleness: staleness_check(opts, &config),
+    };
+    let mut report = batch::scan_archives(Arc::new(scanner), &archives, jobs, Arc::new(options));
+    let gate = policy::effective_gate(opts.fail_on, config.gate.as_ref());
+    for entry in &mut report.entries {
+        if let (Some(scan), Some(lockfile)) = (&mut entry.report, &entry.lockfile) {
+            config.apply_triage(scan);
+            if let Some(baseline) = &baseline {
+                baseline.apply(scan);
+            }
+            // Batch never runs cargo metadata, so license rules see every package as unlicensed
+            scan.policy_results = policy::evaluate(&config.policy, scan, lockfile, None);
+            if let Some((gate, source)) = &gate {
+                scan.gate = Some(gate.evaluate(*source, scan));
+            }
+            scan.cvss_gate = cvss_gate(opts).map(|gate| gate.evaluate(scan));
+            if opts.canonical {
+                scan.canonicalize();
+            }
+        }
+    }
+
+    let report_path = &opts.report_path();
+    let mut json = serde_json::to_value(&report)?;
+    for scan in json["entries"].as_array_mut().into_iter().flatten().filter_map(|e| e.get_mut("report")) {
+        description::apply(scan, opts.description.unwrap_or_default());
+    }
+    for (entry, path) in json["entries"].as_array().into_iter().flatten().zip(&entry_reports) {
+        if let Some(scan) = entry.get("report") {
+            output::write_json(&path.to_string_lossy(), scan)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+    }
+    output::write_json(report_path, &json).context("failed to write batch report")?;
+
+    for entry in &report.entries {
+        match (&entry.report, &entry.error) {
+            (Some(scan), _) => {
+                status!("  {}: {} vulnerabilities", entry.archive, scan.summary.total_vulnerabilities);
+                for result in scan.policy_results.iter().filter(|r| r.action == PolicyAction::Fail) {
+                    status!("    Policy fail: [{}] {}", result.rule, result.message);
+                }
+            }
+            (None, Some(error)) => status!("  {}: FAILED: {}", entry.archive, error),
+            (None, None) => {}
+        }
+    }
+    status!("\nBatch completed: {} archives, {} failed", report.archives, report.failed);
+    status!("Vulnerabilities found: {}", report.total_vulnerabilities);
+    status!("Detailed report written to: {}", report_path);
+    if !entry_reports.is_empty() {
+        status!("Per-archive reports written to: {}", opts.output_dir().display());
+    }
+
+    // Incomplete results take precedence over findings
+    if report.failed > 0 {
+        return Ok(EXIT_ERROR);
+    }
+    let failed = |r: &rustpj::VulnReport| r.gate.as_ref().is_some_and(|g| g.outcome == GateAction::Fail);
+    let cvss_failed = |r: &rustpj::VulnReport| r.cvss_gate.as_ref().is_some_and(|g| g.outcome == GateAction::Fail);
+    if report.entries.iter().filter_map(|e| e.report.as_ref()).any(failed) {
+        match opts.fail_on {
+            Some(threshold) => console::eprint_line(&format!("Untriaged vulnerabilities at or above '{}' found", threshold)),
+            None => console::eprint_line("Untriaged vulnerabilities with gate action 'fail' found"),
+        }
+        return Ok(EXIT_VULNERABLE);
+    }
+    if report.entries.iter().filter_map(|e| e.report.as_ref()).any(cvss_failed) {
+        console::eprint_line(&format!("Untriaged vulnerabilities with a CVSS score of at least {:.1} found", opts.min_cvss.unwrap_or_default()));
+        return Ok(EXIT_VULNERABLE);
+    }
+    let policy_failed = |r: &rustpj::VulnReport| policy::strictest_action(&r.policy_results) == Some(PolicyAction::Fail);
+    if report.entries.iter().filter_map(|e| e.report.as_ref()).any(policy_failed) {
+        console::eprint_line("Policy rules with action 'fail' were triggered");
+        return Ok(EXIT_VULNERABLE);
+    }
+    Ok(EXIT_OK)
+}
+
+// Before any work, so an existing file is reported up front instead of after a long scan;
+// the writes themselves check again
+fn check_outputs(opts: &Options) -> Result<()> {
+    refuse_existing(opts.force, &opts.planned_outputs())
+}
+
+fn refuse_existing(force: bool, paths: &[PathBuf]) -> Result<()> {
+    if force {
+        return Ok(());
+    }
+    let existing: Vec<String> = paths
+        .iter()
+        .filter(|path| path.exists())
+        .map(|path| path.display().to_string())
+        .collect();
+    match existing.as_slice() {
+        [] => Ok(()),
+        [path] => bail!("{} already exists; pass --force to overwrite it", path),
+        paths => bail!("output files already exist; pass --force to overwrite them:\n  {}", paths.join("\n  ")),
+    }
+}
+
+fn check_writable(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(".rustpj-doctor");
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(probe)
+}
@@ -0,0 +1,136 @@
+use crate::cargo_metadata::CargoMetadata;
+use cargo_lock::Lockfile;
+use semver::Version;
+use serde::Serialize;
+
+// Minimum supported Rust version check: flags dependencies whose `rust-version`
+// is newer than what the project itself declares. Supply-chain hygiene, not security.
+
+#[derive(Debug, Serialize)]
+pub struct MsrvConflict {
+    pub package_name: String,
+    pub package_version: String,
+    pub rust_version: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MsrvReport {
+    pub project_rust_version: Option<String>,
+    pub conflicts: Vec<MsrvConflict>,
+    // 检查被跳过时的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+// `rust-version` may omit the minor/patch component ("1.70")
+fn parse_rust_version(v: &str) -> Option<Version> {
+    let v = v.trim();
+    let padded = match v.matches('.').count() {
+        0 => format!("{}.0.0", v),
+        1 => format!("{}.0", v),
+        _ => v.to_string(),
+    };
+    Version::parse(&padded).ok()
+}
+
+/// 项目的 MSRV 取所有工作区成员中声明的最低值
+pub fn check(lockfile: &Lockfile, metadata: Option<&CargoMetadata>) -> MsrvReport {
+    let Some(metadata) = metadata else {
+        return MsrvReport {
+            note: Some("cargo metadata unavailable offline; MSRV check skipped".to_string()),
+            ..Default::default()
+        };
+    };
+
+    let project = metadata
+        .workspace_members
+        .iter()
+        .filter_map(|(name, version)| metadata.get(name, version)?.rust_version.clone())
+        .filter_map(|v| parse_rust_version(&v).map(|parsed| (parsed, v)))
+        .min_by(|a, b| a.0.cmp(&b.0));
+
+    let Some((project_version, project_raw)) = project else {
+        return MsrvReport {
+            note: Some("project does not declare rust-version; MSRV check skipped".to_string()),
+            ..Default::default()
+        };
+    };
+
+    let mut conflicts = Vec::new();
+    for pkg in &lockfile.packages {
+        let name = pkg.name.as_str();
+        let version = pkg.version.to_string();
+        if metadata.workspace_members.iter().any(|(n, v)| n == name && *v == version) {
+            continue;
+        }
+        let Some(required) = metadata.get(name, &version).and_then(|i| i.rust_version.clone()) else {
+            continue;
+        };
+        if parse_rust_version(&required).is_some_and(|r| r > project_version) {
+            conflicts.push(MsrvConflict {
+                package_name: name.to_string(),
+                package_version: version,
+                rust_version: required,
+            });
+        }
+    }
+
+    MsrvReport { project_rust_version: Some(project_raw), conflicts, note: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+dependencies = ["new-dep", "old-dep"]
+
+[[package]]
+name = "new-dep"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "old-dep"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    fn metadata(project_msrv: Option<&str>) -> CargoMetadata {
+        CargoMetadata::from_json(&json!({
+            "packages": [
+                { "id": "demo-id", "name": "demo", "version": "0.1.0", "rust_version": project_msrv },
+                { "id": "new-id", "name": "new-dep", "version": "1.0.0", "rust_version": "1.80.1" },
+                { "id": "old-id", "name": "old-dep", "version": "1.0.0", "rust_version": "1.56" },
+            ],
+            "workspace_members": ["demo-id"],
+        }))
+    }
+
+    #[test]
+    fn test_flags_newer_rust_version() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        let report = check(&lockfile, Some(&metadata(Some("1.70"))));
+        assert_eq!(report.project_rust_version.as_deref(), Some("1.70"));
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].package_name, "new-dep");
+        assert_eq!(report.conflicts[0].rust_version, "1.80.1");
+    }
+
+    #[test]
+    fn test_skips_without_data() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        assert!(check(&lockfile, None).note.is_some());
+
+        let report = check(&lockfile, Some(&metadata(None)));
+        assert!(report.conflicts.is_empty());
+        assert!(report.note.is_some());
+    }
+}
@@ -0,0 +1,319 @@
+use crate::scanner::{LatestRelease, VulnReport};
+use anyhow::{Context, Result, bail};
+use cargo_lock::{Lockfile, SourceId};
+use semver::Version;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+
+// Endpoints and identification for online operations (advisory DB clone/update,
+// crates.io lookups for --online and --check-staleness). Everything defaults to the
+// public services; environments that mandate internal mirrors override them in the
+// `[online]` config section or on the command line:
+//
+//     [online]
+//     registry_url = "https://crates-mirror.example.com/api/v1/crates"
+//     advisory_db_url = "https://git.example.com/mirrors/advisory-db.git"
+//     user_agent = "acme-ci/1.0"
+//
+// Only packages from crates.io are looked up (`is_default_crates_io`): a crate from a
+// private registry or a git repository is unknown there, or worse, a different crate of
+// the same name. No query is sent for those, and their fields stay unknown.
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub const DEFAULT_REGISTRY_URL: &str = "https://crates.io/api/v1/crates";
+pub const DEFAULT_ADVISORY_DB_URL: &str = "https://github.com/RustSec/advisory-db.git";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OnlineConfig {
+    // crates.io 兼容的 registry API 地址
+    pub registry_url: String,
+    // advisory-db 的 git 仓库地址
+    pub advisory_db_url: String,
+    // HTTP 请求使用的 User-Agent
+    pub user_agent: String,
+}
+
+impl Default for OnlineConfig {
+    fn default() -> Self {
+        OnlineConfig {
+            registry_url: DEFAULT_REGISTRY_URL.to_string(),
+            advisory_db_url: DEFAULT_ADVISORY_DB_URL.to_string(),
+            user_agent: default_user_agent(),
+        }
+    }
+}
+
+// crates.io asks API clients to identify themselves
+fn default_user_agent() -> String {
+    format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+impl OnlineConfig {
+    /// 用命令行参数覆盖配置文件中的值
+    pub fn with_overrides(
+        mut self,
+        registry_url: Option<&str>,
+        advisory_db_url: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Result<Self> {
+        if let Some(url) = registry_url {
+            self.registry_url = url.to_string();
+        }
+        if let Some(url) = advisory_db_url {
+            self.advisory_db_url = url.to_string();
+        }
+        if let Some(agent) = user_agent {
+            self.user_agent = agent.to_string();
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !self.registry_url.starts_with("https://") && !self.registry_url.starts_with("http://") {
+            bail!("registry URL must be http(s): {}", self.registry_url);
+        }
+        // git also accepts ssh and local mirrors
+        let git_schemes = ["https://", "http://", "ssh://", "git@", "file://", "/"];
+        if !git_schemes.iter().any(|s| self.advisory_db_url.starts_with(s)) {
+            bail!("unsupported advisory DB URL: {}", self.advisory_db_url);
+        }
+        if self.user_agent.trim().is_empty() || self.user_agent.contains(['\r', '\n']) {
+            bail!("User-Agent must be a non-empty single line");
+        }
+        Ok(())
+    }
+
+    /// 某个 crate 在 registry API 中的地址
+    pub fn crate_url(&self, name: &str) -> String {
+        format!("{}/{}", self.registry_url.trim_end_matches('/'), name)
+    }
+
+    /// registry API 中该 crate 的原始响应
+    pub fn fetch_crate(&self, name: &str) -> Result<String> {
+        let url = self.crate_url(name);
+        ureq::get(&url)
+            .set("User-Agent", &self.user_agent)
+            .timeout(REQUEST_TIMEOUT)
+            .call()?
+            .into_string()
+            .with_context(|| format!("failed to read response from {}", url))
+    }
+
+    /// registry 中该 crate 最新发布的版本（有稳定版时取最新稳定版）
+    pub fn latest_version(&self, name: &str) -> Result<Version> {
+        let body = self.fetch_crate(name)?;
+        parse_latest_version(&body).with_context(|| format!("unexpected response from {}", self.crate_url(name)))
+    }
+}
+
+/// 包是否来自 crates.io，即可以在 registry API 中查询。crates.io 镜像此前已由
+/// `RegistryConfig::apply` 改写为 crates.io 来源；私有 registry、git、path 依赖均不是
+pub fn is_default_crates_io(source: &Option<SourceId>) -> bool {
+    source.as_ref().is_some_and(SourceId::is_default_registry)
+}
+
+// The parts of the crates.io `GET /api/v1/crates/<name>` response we need: `crate` for
+// --online, `versions` for --check-staleness. A saved snapshot may keep only the part its
+// reader needs, so both are optional here and each reader insists on its own.
+#[derive(Deserialize)]
+pub(crate) struct CrateResponse {
+    #[serde(rename = "crate")]
+    pub krate: Option<CrateInfo>,
+    #[serde(default)]
+    pub versions: Vec<PublishedVersion>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct CrateInfo {
+    pub max_stable_version: Option<String>,
+    #[serde(default)]
+    pub max_version: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct PublishedVersion {
+    pub num: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+fn parse_latest_version(body: &str) -> Result<Version> {
+    let info = serde_json::from_str::<CrateResponse>(body)?.krate.context("no crate in the response")?;
+    Ok(info.max_stable_version.unwrap_or(info.max_version).parse()?)
+}
+
+// name@version of every crates.io package in the lockfile
+fn crates_io_packages(lockfile: &Lockfile) -> HashSet<(String, String)> {
+    lockfile
+        .packages
+        .iter()
+        .filter(|p| is_default_crates_io(&p.source))
+        .map(|p| (p.name.to_string(), p.version.to_string()))
+        .collect()
+}
+
+/// 为报告中来自 crates.io 的每个漏洞包查询最新版本；查询失败的 crate 记一条警告，字段保持未知
+pub fn annotate_latest_releases(report: &mut VulnReport, lockfile: &Lockfile, online: &OnlineConfig) {
+    let crates_io = crates_io_packages(lockfile);
+    let names: BTreeSet<String> = report
+        .packages
+        .iter()
+        .chain(report.projects.iter().flat_map(|p| &p.packages))
+        .filter(|p| crates_io.contains(&(p.package_name.clone(), p.package_version.clone())))
+        .map(|p| p.package_name.clone())
+        .collect();
+    let mut latest = HashMap::new();
+    for name in names {
+        match online.latest_version(&name) {
+            Ok(version) => {
+                latest.insert(name, version);
+            }
+            Err(e) => crate::console::warn(
+                "latest-version-unavailable",
+                format!("could not look up the latest release of {}: {:#}", name, e),
+            ),
+        }
+    }
+    apply_latest_releases(report, &latest, &crates_io);
+}
+
+/// 按 crate 名 -> 最新版本填写 `latest_release`；不在表中、或不在 crates_io（来自 crates.io 的
+/// name@version）中的包保持未知
+pub fn apply_latest_releases(report: &mut VulnReport, latest: &HashMap<String, Version>, crates_io: &HashSet<(String, String)>) {
+    let projects = report.projects.iter_mut().flat_map(|p| &mut p.packages);
+    for package in report.packages.iter_mut().chain(projects) {
+        // A git fork of a crates.io crate shares its name, not its releases
+        if !crates_io.contains(&(package.package_name.clone(), package.package_version.clone())) {
+            continue;
+        }
+        let Some(version) = latest.get(&package.package_name) else {
+            continue;
+        };
+        let installed_is_latest = Version::parse(&package.package_version).is_ok_and(|installed| installed >= *version);
+        for finding in &mut package.advisories {
+            finding.latest_release = Some(LatestRelease {
+                version: version.to_string(),
+                unfixable_by_upgrade: installed_is_latest && finding.no_fix_available,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{AdvisoryFinding, PackageReport};
+    use crate::test_support::AdvisoryText;
+
+    #[test]
+    fn test_defaults_and_overrides() {
+        let online = OnlineConfig::default();
+        assert_eq!(online.crate_url("serde"), "https://crates.io/api/v1/crates/serde");
+        assert!(online.user_agent.starts_with("rustpj/"));
+
+        let online = online
+            .with_overrides(Some("https://mirror.example.com/api/v1/crates/"), None, Some("acme-ci/1.0"))
+            .unwrap();
+        assert_eq!(online.crate_url("serde"), "https://mirror.example.com/api/v1/crates/serde");
+        assert_eq!(online.advisory_db_url, DEFAULT_ADVISORY_DB_URL);
+        assert_eq!(online.user_agent, "acme-ci/1.0");
+    }
+
+    #[test]
+    fn test_latest_release_flags_unfixable_findings() {
+        let body = r#"{"crate": {"name": "time", "max_version": "0.2.0-rc.1", "max_stable_version": "0.1.45"}}"#;
+        assert_eq!(parse_latest_version(body).unwrap(), Version::new(0, 1, 45));
+        let body = r#"{"crate": {"name": "nightly-only", "max_version": "0.1.0-alpha", "max_stable_version": null}}"#;
+        assert_eq!(parse_latest_version(body).unwrap().to_string(), "0.1.0-alpha");
+        assert!(parse_latest_version(r#"{"errors": [{"detail": "Not Found"}]}"#).is_err());
+        assert!(parse_latest_version(r#"{"versions": []}"#).is_err());
+
+        let finding = |versions: &str| {
+            AdvisoryFinding::from_advisory(&AdvisoryText::new("RUSTSEC-2020-0001", "demo").with_versions(versions).to_advisory())
+        };
+        let package = |name: &str, version: &str, versions: &str| PackageReport {
+            package_name: name.to_string(),
+            package_version: version.to_string(),
+            advisories: vec![finding(versions)],
+            ..Default::default()
+        };
+        let mut report = VulnReport {
+            packages: vec![
+                package("time", "0.1.45", "patched = []"),
+                package("chrono", "0.4.19", "patched = []"),
+                package("zip", "0.6.0", "patched = [\">= 0.6.1\"]"),
+                package("offline", "1.0.0", "patched = []"),
+                package("time", "0.1.44", "patched = []"),
+            ],
+            ..Default::default()
+        };
+        let latest = HashMap::from([
+            ("time".to_string(), Version::new(0, 1, 45)),
+            ("chrono".to_string(), Version::new(0, 4, 38)),
+            ("zip".to_string(), Version::new(0, 6, 0)),
+        ]);
+        // time 0.1.44 is a git fork
+        let crates_io: HashSet<(String, String)> = [("time", "0.1.45"), ("chrono", "0.4.19"), ("zip", "0.6.0"), ("offline", "1.0.0")]
+            .into_iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect();
+        apply_latest_releases(&mut report, &latest, &crates_io);
+
+        let flags: Vec<_> = report
+            .packages
+            .iter()
+            .map(|p| p.advisories[0].latest_release.as_ref().map(|l| (l.version.as_str(), l.unfixable_by_upgrade)))
+            .collect();
+        // installed latest without a fix; a newer release exists; a fix exists; not looked up; not from crates.io
+        assert_eq!(flags, [Some(("0.1.45", true)), Some(("0.4.38", false)), Some(("0.6.0", false)), None, None]);
+    }
+
+    #[test]
+    fn test_only_crates_io_sources_are_looked_up() {
+        let source = |s: &str| Some(SourceId::from_url(s).unwrap());
+        assert!(is_default_crates_io(&source("registry+https://github.com/rust-lang/crates.io-index")));
+        assert!(is_default_crates_io(&source("sparse+https://index.crates.io/")));
+        assert!(!is_default_crates_io(&source("registry+https://registry.example.com/index")));
+        assert!(!is_default_crates_io(&source("sparse+https://registry.example.com/index/")));
+        assert!(!is_default_crates_io(&source("git+https://github.com/rust-lang/crates.io-index#abc")));
+        assert!(!is_default_crates_io(&source("git+https://github.com/acme/time?rev=abc#abc")));
+        assert!(!is_default_crates_io(&source("path+file:///src/time")));
+        assert!(!is_default_crates_io(&None));
+
+        let lockfile: Lockfile = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+
+[[package]]
+name = "internal"
+version = "1.0.0"
+source = "sparse+https://registry.example.com/index/"
+
+[[package]]
+name = "time"
+version = "0.1.45"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#
+        .parse()
+        .unwrap();
+        let mut packages: Vec<_> = crates_io_packages(&lockfile).into_iter().collect();
+        packages.sort();
+        assert_eq!(packages, [("time".to_string(), "0.1.45".to_string())]);
+    }
+
+    #[test]
+    fn test_rejects_bad_values() {
+        let online = OnlineConfig::default();
+        assert!(online.clone().with_overrides(Some("ftp://mirror"), None, None).is_err());
+        assert!(online.clone().with_overrides(None, Some("mirror"), None).is_err());
+        assert!(online.with_overrides(None, None, Some("a\nb")).is_err());
+    }
+}
@@ -0,0 +1,433 @@
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, StdoutLock, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+// Where reports, SBOMs and the other outputs go. JSON is serialized straight into the
+// file instead of through an intermediate string, which for a big monorepo means tens of
+// megabytes less in memory, and a path ending in `.gz` is gzip-compressed on the way.
+// `-` is stdout, always uncompressed.
+//
+// Files are written to a temporary file next to the destination and renamed into place
+// once complete, so a run that dies mid-write never leaves truncated JSON at the final
+// path (an earlier complete file stays as it was). Without `--force` an existing file at
+// the destination is an error rather than silently replaced.
+//
+// `--canonical-json` writes JSON with every object's keys sorted and no whitespace, so two
+// runs that produced the same document produce the same bytes and can be compared by hash.
+// Sorting goes through a `serde_json::Value`, so that style holds the whole document in
+// memory once, unlike the other two.
+
+// `--pretty` / `--minify` / `--canonical-json`, as a `JsonStyle` discriminant
+static JSON_STYLE: AtomicU8 = AtomicU8::new(JsonStyle::Pretty as u8);
+
+// `--force`: replace outputs left by an earlier run instead of refusing to
+static OVERWRITE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_json_style(style: JsonStyle) {
+    JSON_STYLE.store(style as u8, Ordering::SeqCst);
+}
+
+pub fn set_overwrite(overwrite: bool) {
+    OVERWRITE.store(overwrite, Ordering::SeqCst);
+}
+
+/// JSON 输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonStyle {
+    #[default]
+    Pretty,
+    Minified,
+    // 键按字典序排列、无空白，便于对输出做哈希
+    Canonical,
+}
+
+/// 本次运行的输出设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputSettings {
+    pub style: JsonStyle,
+    // 允许覆盖已存在的文件（`--force`）
+    pub overwrite: bool,
+}
+
+impl OutputSettings {
+    /// 当前运行选择的设置（`--pretty` / `--minify` / `--canonical-json`、`--force`；默认缩进、不覆盖）
+    pub fn current() -> Self {
+        let style = match JSON_STYLE.load(Ordering::SeqCst) {
+            s if s == JsonStyle::Minified as u8 => JsonStyle::Minified,
+            s if s == JsonStyle::Canonical as u8 => JsonStyle::Canonical,
+            _ => JsonStyle::Pretty,
+        };
+        OutputSettings {
+            style,
+            overwrite: OVERWRITE.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// 生成文档（SBOM、VEX、快照）时的序列号与时间；emitter 从参数中取用而不自己生成，
+/// 因此测试可以固定这两个值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stamp {
+    pub serial: uuid::Uuid,
+    pub time: chrono::DateTime<chrono::Utc>,
+}
+
+impl Stamp {
+    pub fn now() -> Self {
+        Stamp { serial: uuid::Uuid::new_v4(), time: chrono::Utc::now() }
+    }
+
+    /// CycloneDX 的 `serialNumber`
+    pub fn serial_number(&self) -> String {
+        format!("urn:uuid:{}", self.serial)
+    }
+
+    pub fn timestamp(&self) -> String {
+        self.time.to_rfc3339()
+    }
+}
+
+/// 把 path 解析为绝对路径：相对路径基于 base，并按字面去掉 `.` 和 `..`（不访问文件系统）
+pub fn absolute(base: &Path, path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+/// 路径以 `.gz` 结尾时输出经过 gzip 压缩
+pub fn is_compressed(path: &str) -> bool {
+    path.ends_with(".gz")
+}
+
+/// 给路径加上 `.gz`（已经是 `.gz` 或为 stdout 时不变）
+pub fn compressed_path(path: &str) -> String {
+    if path == "-" || is_compressed(path) {
+        path.to_string()
+    } else {
+        format!("{}.gz", path)
+    }
+}
+
+/// 先写入同目录下的临时文件 `.<name>.<pid>.tmp`（重命名不跨文件系统），`commit` 时再重命名到
+/// 目标路径；未提交就丢弃时删除临时文件
+pub struct AtomicFile {
+    file: File,
+    temp: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    /// 按当前运行的设置（`--force`）创建；目标已存在且不允许覆盖时报错
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::create_with(path, OutputSettings::current().overwrite)
+    }
+
+    fn create_with(path: &Path, overwrite: bool) -> io::Result<Self> {
+        if !overwrite {
+            check_target(path)?;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let temp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+        Ok(AtomicFile { file: File::create(&temp)?, temp, path: path.to_path_buf(), committed: false })
+    }
+
+    pub fn commit(mut self) -> io::Result<()> {
+        self.file.sync_all()?;
+        fs::rename(&self.temp, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+// The zip writer of `--bundle` seeks back to patch local headers
+impl Seek for AtomicFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// 不带 `--force` 时，已存在的输出文件不会被覆盖
+pub fn check_target(path: &Path) -> io::Result<()> {
+    if path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists; pass --force to overwrite it", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+enum Output {
+    Stdout(StdoutLock<'static>),
+    File(BufWriter<AtomicFile>),
+    Gzip(GzEncoder<BufWriter<AtomicFile>>),
+}
+
+impl Output {
+    fn open(path: &str, settings: OutputSettings) -> io::Result<Self> {
+        if path == "-" {
+            return Ok(Output::Stdout(io::stdout().lock()));
+        }
+        let file = BufWriter::new(AtomicFile::create_with(Path::new(path), settings.overwrite)?);
+        Ok(if is_compressed(path) { Output::Gzip(GzEncoder::new(file, Compression::default())) } else { Output::File(file) })
+    }
+
+    // The gzip trailer is only written here; dropping the encoder would swallow its errors.
+    // Dropping an unfinished output deletes the temporary file.
+    fn finish(self) -> io::Result<()> {
+        let file = match self {
+            Output::Stdout(mut out) => return out.flush(),
+            Output::File(file) => file,
+            Output::Gzip(encoder) => encoder.finish()?,
+        };
+        file.into_inner().map_err(|e| e.into_error())?.commit()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(out) => out.write(buf),
+            Output::File(file) => file.write(buf),
+            Output::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.flush(),
+            Output::File(file) => file.flush(),
+            Output::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// 按当前运行选择的格式把 value 以 JSON 写入 path
+pub fn write_json<T: Serialize + ?Sized>(path: &str, value: &T) -> io::Result<()> {
+    write_json_with(path, value, OutputSettings::current())
+}
+
+pub fn write_json_with<T: Serialize + ?Sized>(path: &str, value: &T, settings: OutputSettings) -> io::Result<()> {
+    let mut out = Output::open(path, settings)?;
+    match settings.style {
+        JsonStyle::Pretty => serde_json::to_writer_pretty(&mut out, value)?,
+        JsonStyle::Minified => serde_json::to_writer(&mut out, value)?,
+        JsonStyle::Canonical => out.write_all(&to_canonical_json(value)?)?,
+    }
+    if path == "-" {
+        writeln!(out)?;
+    }
+    out.finish()
+}
+
+/// 规范化 JSON：所有对象的键按字典序排列，不含空白；相同内容总是得到相同的字节
+pub fn to_canonical_json<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<Vec<u8>> {
+    let mut value = serde_json::to_value(value)?;
+    // preserve_order keeps insertion order in maps; canonical output must not depend on it
+    value.sort_all_objects();
+    serde_json::to_vec(&value)
+}
+
+/// 写入文本输出（模板渲染结果、DOT 图）；写到 stdout 时末尾补一个换行
+pub fn write_text(path: &str, text: &str) -> io::Result<()> {
+    write_bytes(path, text.as_bytes())
+}
+
+/// 写入任意内容（签名清单、签名等）
+pub fn write_bytes(path: &str, bytes: &[u8]) -> io::Result<()> {
+    write_bytes_with(path, bytes, OutputSettings::current())
+}
+
+pub fn write_bytes_with(path: &str, bytes: &[u8], settings: OutputSettings) -> io::Result<()> {
+    let mut out = Output::open(path, settings)?;
+    out.write_all(bytes)?;
+    if path == "-" {
+        writeln!(out)?;
+    }
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use serde::ser::Error as _;
+    use serde_json::{Value, json};
+    use std::fs::File;
+    use std::io::Read;
+
+    // The tests rewrite the same paths, as a run with `--force` would
+    fn settings(style: JsonStyle) -> OutputSettings {
+        OutputSettings { style, overwrite: true }
+    }
+
+    #[test]
+    fn test_gz_paths_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let value = json!({"packages": [{"package_name": "time", "advisories": ["RUSTSEC-2020-0071"]}]});
+
+        let plain = dir.path().join("out/report.json");
+        write_json_with(plain.to_str().unwrap(), &value, settings(JsonStyle::Pretty)).unwrap();
+        let text = fs::read_to_string(&plain).unwrap();
+        assert_eq!(text, serde_json::to_string_pretty(&value).unwrap());
+
+        let gz = dir.path().join("out/report.json.gz");
+        write_json_with(gz.to_str().unwrap(), &value, settings(JsonStyle::Pretty)).unwrap();
+        let bytes = fs::read(&gz).unwrap();
+        assert_eq!(&bytes[..2], [0x1f, 0x8b]);
+        let mut decompressed = String::new();
+        GzDecoder::new(bytes.as_slice()).read_to_string(&mut decompressed).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&decompressed).unwrap(), value);
+
+        let dot = dir.path().join("deps.dot.gz");
+        write_text(dot.to_str().unwrap(), "digraph {}\n").unwrap();
+        let mut decompressed = String::new();
+        GzDecoder::new(File::open(&dot).unwrap()).read_to_string(&mut decompressed).unwrap();
+        assert_eq!(decompressed, "digraph {}\n");
+    }
+
+    #[test]
+    fn test_minified_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sbom.json");
+        let value = json!({"components": [{"name": "time", "version": "0.1.45"}]});
+        write_json_with(path.to_str().unwrap(), &value, settings(JsonStyle::Minified)).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text, r#"{"components":[{"name":"time","version":"0.1.45"}]}"#);
+        write_json_with(path.to_str().unwrap(), &value, settings(JsonStyle::Pretty)).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().len() > text.len());
+    }
+
+    #[test]
+    fn test_canonical_output_hashes_identically() {
+        use sha2::{Digest, Sha256};
+        // Same document, keys inserted in different orders (as maps built from different
+        // lockfile iteration orders would be)
+        let first = json!({"metadata": {"b": 1, "a": [{"z": true, "y": null}]}, "bomFormat": "CycloneDX"});
+        let second = json!({"bomFormat": "CycloneDX", "metadata": {"a": [{"y": null, "z": true}], "b": 1}});
+        assert_ne!(serde_json::to_string(&first).unwrap(), serde_json::to_string(&second).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let hash = |value: &Value, name: &str| {
+            let path = dir.path().join(name);
+            write_json_with(path.to_str().unwrap(), value, settings(JsonStyle::Canonical)).unwrap();
+            Sha256::digest(fs::read(&path).unwrap())
+        };
+        assert_eq!(hash(&first, "first.json"), hash(&second, "second.json"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("first.json")).unwrap(),
+            r#"{"bomFormat":"CycloneDX","metadata":{"a":[{"y":null,"z":true}],"b":1}}"#
+        );
+        assert_ne!(hash(&first, "first.json"), hash(&json!({"bomFormat": "SPDX"}), "other.json"));
+    }
+
+    // Fails after part of the document has already gone out
+    struct Failing;
+
+    impl Serialize for Failing {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::SerializeSeq;
+            let mut seq = serializer.serialize_seq(None)?;
+            for i in 0..10_000 {
+                seq.serialize_element(&i)?;
+            }
+            Err(S::Error::custom("simulated failure"))
+        }
+    }
+
+    #[test]
+    fn test_failed_write_leaves_final_path_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["report.json", "report.json.gz"] {
+            let path = dir.path().join(name);
+            let path_str = path.to_str().unwrap();
+            assert!(write_json_with(path_str, &Failing, settings(JsonStyle::Pretty)).is_err());
+            assert!(!path.exists());
+
+            write_json_with(path_str, &json!({"total_packages": 1}), settings(JsonStyle::Pretty)).unwrap();
+            let before = fs::read(&path).unwrap();
+            assert!(write_json_with(path_str, &Failing, settings(JsonStyle::Minified)).is_err());
+            assert_eq!(fs::read(&path).unwrap(), before);
+        }
+        // No temporary files left behind
+        let mut names: Vec<_> = fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+        names.sort();
+        assert_eq!(names, ["report.json", "report.json.gz"]);
+    }
+
+    #[test]
+    fn test_existing_output_needs_force() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sbom.json");
+        let path_str = path.to_str().unwrap();
+        let keep = OutputSettings { style: JsonStyle::Pretty, overwrite: false };
+        write_json_with(path_str, &json!({"version": 1}), keep).unwrap();
+
+        let err = write_json_with(path_str, &json!({"version": 2}), keep).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(err.to_string().contains(path_str), "{}", err);
+        assert!(err.to_string().contains("--force"), "{}", err);
+        assert!(AtomicFile::create_with(&path, false).is_err());
+        assert_eq!(serde_json::from_str::<Value>(&fs::read_to_string(&path).unwrap()).unwrap(), json!({"version": 1}));
+
+        write_json_with(path_str, &json!({"version": 2}), settings(JsonStyle::Pretty)).unwrap();
+        let mut file = AtomicFile::create_with(&dir.path().join("bundle.zip"), false).unwrap();
+        file.write_all(b"PK").unwrap();
+        file.commit().unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&fs::read_to_string(&path).unwrap()).unwrap(), json!({"version": 2}));
+        // Successful writes leave only the final files
+        let mut names: Vec<_> = fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+        names.sort();
+        assert_eq!(names, ["bundle.zip", "sbom.json"]);
+    }
+
+    #[test]
+    fn test_absolute_paths() {
+        let base = Path::new("/work/project/output");
+        assert_eq!(absolute(base, Path::new("sbom.json")), Path::new("/work/project/output/sbom.json"));
+        assert_eq!(absolute(base, Path::new("./reports/../vex.json")), Path::new("/work/project/output/vex.json"));
+        assert_eq!(absolute(base, Path::new("/tmp/report.json")), Path::new("/tmp/report.json"));
+        assert_eq!(absolute(Path::new("/work/project"), Path::new("./output")), Path::new("/work/project/output"));
+    }
+
+    #[test]
+    fn test_compressed_path() {
+        assert_eq!(compressed_path("./output/sbom.json"), "./output/sbom.json.gz");
+        assert_eq!(compressed_path("report.json.gz"), "report.json.gz");
+        assert_eq!(compressed_path("-"), "-");
+    }
+}
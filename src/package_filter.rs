@@ -0,0 +1,299 @@
+use crate::graph::DependencyGraph;
+use crate::typosquat::levenshtein;
+use anyhow::{Result, anyhow, bail};
+use cargo_lock::{Lockfile, Package};
+use semver::Version;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+// `--package <name>[@<version>]` filters: the scan (and the SBOM) cover only the matching
+// lockfile entries, plus everything they depend on when `with_deps` is set.
+//
+// Exclusions are the opposite (`--exclude-package`, or `[[exclude]]` in the config file):
+// the matching entries are taken out of the lockfile before anything else sees it, and so
+// are the dependency edges pointing at them, so the SBOM has no `dependsOn` to a component
+// it does not list. What they depend on stays. Every excluded entry is recorded with its
+// reason in the report's `metadata.excluded_packages`:
+//
+//     [[exclude]]
+//     package = "openssl@0.10.55"            # <name>[@<version>]
+//     reason = "patched fork in vendor/, see SEC-123"
+
+/// 一条 `--package` 过滤条件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageFilter {
+    pub name: String,
+    pub version: Option<Version>,
+}
+
+impl FromStr for PackageFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, version) = match s.split_once('@') {
+            Some((name, version)) => {
+                let version = version.parse().map_err(|e| anyhow!("invalid version in {}: {}", s, e))?;
+                (name, Some(version))
+            }
+            None => (s, None),
+        };
+        if name.is_empty() {
+            bail!("invalid package filter: {}", s);
+        }
+        Ok(PackageFilter { name: name.to_string(), version })
+    }
+}
+
+impl fmt::Display for PackageFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{}@{}", self.name, version),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PackageFilter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl PackageFilter {
+    pub fn matches(&self, package: &Package) -> bool {
+        package.name.as_str() == self.name && self.version.as_ref().is_none_or(|v| *v == package.version)
+    }
+
+    /// lockfile 中没有匹配的包时报错：列出该包已有的版本，或名称相近的包
+    pub fn check(&self, lockfile: &Lockfile) -> Result<()> {
+        if lockfile.packages.iter().any(|p| self.matches(p)) {
+            return Ok(());
+        }
+        let versions: Vec<String> = lockfile
+            .packages
+            .iter()
+            .filter(|p| p.name.as_str() == self.name)
+            .map(|p| p.version.to_string())
+            .collect();
+        if !versions.is_empty() {
+            bail!("{} is not in the lockfile; versions present: {}", self, versions.join(", "));
+        }
+        let similar = close_matches(lockfile, &self.name);
+        if similar.is_empty() {
+            bail!("package {} is not in the lockfile", self.name);
+        }
+        bail!("package {} is not in the lockfile; did you mean {}?", self.name, similar.join(", "));
+    }
+}
+
+/// 一条排除条件及其原因
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageExclusion {
+    pub package: PackageFilter,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// 被排除的 lockfile 条目，写入报告的 `metadata.excluded_packages`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExcludedPackage {
+    pub package_name: String,
+    pub package_version: String,
+    // 命中的排除条件
+    pub exclusion: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// 去掉命中排除条件的包以及指向它们的依赖边；返回过滤后的 lockfile 和被排除的条目
+pub fn exclude(lockfile: &Lockfile, exclusions: &[PackageExclusion]) -> (Lockfile, Vec<ExcludedPackage>) {
+    let mut filtered = lockfile.clone();
+    let mut excluded = Vec::new();
+    filtered.packages.retain(|p| match exclusions.iter().find(|e| e.package.matches(p)) {
+        Some(exclusion) => {
+            excluded.push(ExcludedPackage {
+                package_name: p.name.to_string(),
+                package_version: p.version.to_string(),
+                exclusion: exclusion.package.to_string(),
+                reason: exclusion.reason.clone(),
+            });
+            false
+        }
+        None => true,
+    });
+    if !excluded.is_empty() {
+        // A dependency on a name@version that is no longer there is an edge to nothing
+        let remaining: HashSet<(String, String)> =
+            filtered.packages.iter().map(|p| (p.name.to_string(), p.version.to_string())).collect();
+        for package in &mut filtered.packages {
+            package.dependencies.retain(|d| remaining.contains(&(d.name.to_string(), d.version.to_string())));
+        }
+    }
+    (filtered, excluded)
+}
+
+/// 一组过滤条件，以及是否包含它们的传递依赖（--with-deps）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageSelection {
+    pub filters: Vec<PackageFilter>,
+    pub with_deps: bool,
+}
+
+impl PackageSelection {
+    /// 只保留匹配的包（with_deps 时再加上它们的传递依赖）；lockfile 中没有的包名是错误，并给出相近的名称
+    pub fn apply(&self, lockfile: &Lockfile) -> Result<Lockfile> {
+        for filter in &self.filters {
+            filter.check(lockfile)?;
+        }
+        Ok(self.select(lockfile))
+    }
+
+    /// 同 `apply`，但不检查每个条件是否都有匹配（用于多 lockfile 中的单个项目）
+    pub fn select(&self, lockfile: &Lockfile) -> Lockfile {
+        let graph = DependencyGraph::from_lockfile(lockfile);
+        let mut queue: Vec<usize> = (0..graph.len()).filter(|&i| self.filters.iter().any(|f| f.matches(graph.package(i)))).collect();
+        let mut keep: HashSet<usize> = queue.iter().copied().collect();
+        if self.with_deps {
+            while let Some(node) = queue.pop() {
+                for &dep in graph.dependencies_of(node) {
+                    if keep.insert(dep) {
+                        queue.push(dep);
+                    }
+                }
+            }
+        }
+
+        // Keep lockfile order; the rest of the pipeline never sees the other entries
+        let mut filtered = lockfile.clone();
+        filtered.packages = (0..graph.len()).filter(|i| keep.contains(i)).map(|i| graph.package(i).clone()).collect();
+        filtered
+    }
+}
+
+impl fmt::Display for PackageSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let filters: Vec<String> = self.filters.iter().map(|p| p.to_string()).collect();
+        f.write_str(&filters.join(", "))?;
+        if self.with_deps {
+            f.write_str(" (with dependencies)")?;
+        }
+        Ok(())
+    }
+}
+
+// Up to three lockfile names within two edits, closest first
+fn close_matches(lockfile: &Lockfile, name: &str) -> Vec<String> {
+    let mut candidates: Vec<(usize, String)> = lockfile
+        .packages
+        .iter()
+        .map(|p| p.name.to_string())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .map(|candidate| (levenshtein(&candidate.to_ascii_lowercase(), &name.to_ascii_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .collect();
+    candidates.sort();
+    candidates.into_iter().take(3).map(|(_, candidate)| candidate).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // app -> openssl -> openssl-sys -> libc; app -> time
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["openssl", "time"]
+
+[[package]]
+name = "libc"
+version = "0.2.150"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "openssl"
+version = "0.10.55"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["openssl-sys"]
+
+[[package]]
+name = "openssl-sys"
+version = "0.9.90"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["libc"]
+
+[[package]]
+name = "time"
+version = "0.1.45"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["libc"]
+"#;
+
+    fn names(lockfile: &Lockfile) -> Vec<&str> {
+        lockfile.packages.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_selection_with_and_without_deps() {
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let mut selection = PackageSelection { filters: vec!["openssl".parse().unwrap()], with_deps: false };
+        assert_eq!(names(&selection.apply(&lockfile).unwrap()), vec!["openssl"]);
+
+        selection.with_deps = true;
+        assert_eq!(names(&selection.apply(&lockfile).unwrap()), vec!["libc", "openssl", "openssl-sys"]);
+        assert_eq!(selection.to_string(), "openssl (with dependencies)");
+
+        let selection = PackageSelection { filters: vec!["time@0.1.45".parse().unwrap()], with_deps: false };
+        assert_eq!(names(&selection.apply(&lockfile).unwrap()), vec!["time"]);
+    }
+
+    #[test]
+    fn test_unknown_package_lists_close_matches() {
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let apply = |filter: &str| {
+            PackageSelection { filters: vec![filter.parse().unwrap()], with_deps: false }
+                .apply(&lockfile)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(apply("opensl"), "package opensl is not in the lockfile; did you mean openssl?");
+        assert_eq!(apply("time@0.3.0"), "time@0.3.0 is not in the lockfile; versions present: 0.1.45");
+        assert_eq!(apply("tokio-util"), "package tokio-util is not in the lockfile");
+        assert!("@1.0.0".parse::<PackageFilter>().is_err());
+    }
+
+    #[test]
+    fn test_exclusion_drops_package_and_its_edges() {
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let exclusions = [PackageExclusion {
+            package: "openssl@0.10.55".parse().unwrap(),
+            reason: Some("patched fork".to_string()),
+        }];
+        let (filtered, excluded) = exclude(&lockfile, &exclusions);
+        assert_eq!(names(&filtered), vec!["app", "libc", "openssl-sys", "time"]);
+        let app = &filtered.packages[0];
+        assert_eq!(app.dependencies.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), vec!["time"]);
+        assert_eq!(
+            excluded,
+            [ExcludedPackage {
+                package_name: "openssl".to_string(),
+                package_version: "0.10.55".to_string(),
+                exclusion: "openssl@0.10.55".to_string(),
+                reason: Some("patched fork".to_string()),
+            }]
+        );
+
+        // Another version of the crate is not excluded
+        let exclusions = [PackageExclusion { package: "time@0.3.0".parse().unwrap(), reason: None }];
+        let (filtered, excluded) = exclude(&lockfile, &exclusions);
+        assert_eq!(filtered.packages.len(), lockfile.packages.len());
+        assert!(excluded.is_empty());
+    }
+}
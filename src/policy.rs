@@ -0,0 +1,646 @@
+use crate::cargo_metadata::{self, CargoMetadata};
+use crate::graph::{DependencyGraph, DependencyKind};
+use crate::scanner::VulnReport;
+use crate::severity::Severity;
+use cargo_lock::Lockfile;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Declarative policy rules from the `[[policy]]` tables of the config file:
+//
+//     [[policy]]
+//     name = "no-criticals"
+//     when = "severity_count"               # condition type, see `Condition`
+//     severity = "critical"
+//     more_than = 0                         # optional, defaults to 0
+//     action = "fail"                       # fail / warn / note
+//     message = "critical vulnerabilities must be fixed before release"
+//
+// Every rule is evaluated after the scan; the ones that fire are listed in the report's
+// `policy_results`, and the most severe action decides the exit code.
+//
+// The severity gate is the finer-grained form of `--fail-on`, an action per severity
+// from the `[gate]` table:
+//
+//     [gate]
+//     critical = "fail"
+//     high = "fail"
+//     medium = "warn"                       # fail / warn / ignore; unlisted is ignore
+//
+// It looks at the same findings as `--fail-on` (untriaged, not in the baseline). A
+// `--fail-on` threshold on the command line replaces the table, as the map "fail at or
+// above the threshold, ignore the rest". The effective map and its outcome are written
+// to the report's `gate`, so the exit code can be reproduced from the report alone.
+//
+// `--min-cvss <score>` gates on the numeric CVSS base score instead (the finding's
+// `cvss_score`, from the vector named in `severity_source`): a finding at or above the
+// score fails the run, next to whatever the severity gate decides. Findings without a
+// score are listed in `cvss_gate.unscored` and only fail with `--fail-on-unscored`.
+
+/// 规则命中后的动作，按严重程度排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Note,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PolicyRule {
+    pub name: String,
+    pub action: PolicyAction,
+    // 命中时的说明，未设置时根据条件生成
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(flatten)]
+    pub condition: Condition,
+}
+
+// A fixed set of condition types; `dependency_kind` narrows a condition to direct or
+// transitive dependencies where it applies.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "when", rename_all = "snake_case")]
+pub enum Condition {
+    // 未被分诊排除、且达到 severity 的漏洞超过 more_than 个
+    SeverityCount {
+        severity: Severity,
+        #[serde(default)]
+        more_than: usize,
+        #[serde(default)]
+        dependency_kind: Option<DependencyKind>,
+    },
+    // 命中某一类 informational advisory（unmaintained / unsound / notice）
+    Informational {
+        category: String,
+        #[serde(default)]
+        dependency_kind: Option<DependencyKind>,
+    },
+    // 组件的许可证表达式中含有被禁止的许可证（需要 cargo metadata）
+    License { deny: Vec<String> },
+    // 组件缺少许可证信息
+    MissingLicense,
+    // 依赖图中出现被禁止的 crate
+    BannedCrate { names: Vec<String> },
+    // 最新版本发布已久的依赖（需要 --check-staleness，否则从不命中）
+    Stale {
+        #[serde(default)]
+        dependency_kind: Option<DependencyKind>,
+    },
+    // 锁定的版本已被撤回的依赖（需要 --check-staleness，否则从不命中）
+    Yanked {
+        #[serde(default)]
+        dependency_kind: Option<DependencyKind>,
+    },
+    // 跟随分支而非固定 rev / tag 的 git 依赖
+    GitBranch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PolicyResult {
+    pub rule: String,
+    pub action: PolicyAction,
+    pub message: String,
+    // 触发规则的具体条目，例如 `RUSTSEC-2020-0071 (time@0.1.45)`
+    pub matched: Vec<String>,
+}
+
+/// 依次评估所有规则，返回命中的规则
+pub fn evaluate(
+    rules: &[PolicyRule],
+    report: &VulnReport,
+    lockfile: &Lockfile,
+    metadata: Option<&CargoMetadata>,
+) -> Vec<PolicyResult> {
+    let kinds = DependencyGraph::from_lockfile(lockfile).kinds();
+    let kind_matches = |name: &str, version: &str, wanted: Option<DependencyKind>| {
+        wanted.is_none_or(|wanted| kinds.get(&(name.to_string(), version.to_string())) == Some(&wanted))
+    };
+
+    let mut results = Vec::new();
+    for rule in rules {
+        let (fired, matched, default_message) = match &rule.condition {
+            Condition::SeverityCount { severity, more_than, dependency_kind } => {
+                let matched: Vec<String> = report
+                    .packages
+                    .iter()
+                    .filter(|p| kind_matches(&p.package_name, &p.package_version, *dependency_kind))
+                    .flat_map(|p| {
+                        p.advisories
+                            .iter()
+                            .filter(|f| !f.is_suppressed() && !f.in_baseline && f.severity >= *severity)
+                            .map(move |f| format!("{} ({}@{})", f.id, p.package_name, p.package_version))
+                    })
+                    .collect();
+                let message = format!(
+                    "{} untriaged finding(s) at or above {} (at most {} allowed)",
+                    matched.len(),
+                    severity,
+                    more_than
+                );
+                (matched.len() > *more_than, matched, message)
+            }
+            Condition::Informational { category, dependency_kind } => {
+                let matched: Vec<String> = report
+                    .informational
+                    .iter()
+                    .filter(|n| n.category.eq_ignore_ascii_case(category))
+                    .filter(|n| kind_matches(&n.package_name, &n.package_version, *dependency_kind))
+                    .map(|n| format!("{} ({}@{})", n.id, n.package_name, n.package_version))
+                    .collect();
+                let message = format!("{} {} crate(s) in the dependency graph", matched.len(), category);
+                (!matched.is_empty(), matched, message)
+            }
+            Condition::License { deny } => {
+                let matched: Vec<String> = lockfile
+                    .packages
+                    .iter()
+                    .filter_map(|p| {
+                        let license = metadata?.get(p.name.as_str(), &p.version.to_string())?.license.as_deref()?;
+                        let denied = cargo_metadata::license_ids(license)
+                            .into_iter()
+                            .find(|id| deny.iter().any(|d| d.eq_ignore_ascii_case(id)))?;
+                        Some(format!("{}@{} ({})", p.name, p.version, denied))
+                    })
+                    .collect();
+                let message = format!("{} component(s) use a denied license", matched.len());
+                (!matched.is_empty(), matched, message)
+            }
+            Condition::MissingLicense => {
+                let matched = cargo_metadata::missing_licenses(lockfile, metadata);
+                let message = format!("{} component(s) have no license information", matched.len());
+                (!matched.is_empty(), matched, message)
+            }
+            Condition::BannedCrate { names } => {
+                let matched: Vec<String> = lockfile
+                    .packages
+                    .iter()
+                    .filter(|p| names.iter().any(|n| n == p.name.as_str()))
+                    .map(|p| format!("{}@{}", p.name, p.version))
+                    .collect();
+                let message = format!("{} banned crate(s) in the dependency graph", matched.len());
+                (!matched.is_empty(), matched, message)
+            }
+            Condition::Stale { dependency_kind } => {
+                let matched: Vec<String> = report
+                    .stale_packages
+                    .iter()
+                    .filter(|p| kind_matches(&p.package_name, &p.package_version, *dependency_kind))
+                    .map(|p| format!("{}@{} (latest release {})", p.package_name, p.package_version, p.latest_release_date))
+                    .collect();
+                let message = format!("{} crate(s) without a release in a long time", matched.len());
+                (!matched.is_empty(), matched, message)
+            }
+            Condition::Yanked { dependency_kind } => {
+                let matched: Vec<String> = report
+                    .yanked_packages
+                    .iter()
+                    .filter(|p| kind_matches(&p.package_name, &p.package_version, *dependency_kind))
+                    .map(|p| format!("{}@{}", p.package_name, p.package_version))
+                    .collect();
+                let message = format!("{} crate(s) locked to a yanked version", matched.len());
+                (!matched.is_empty(), matched, message)
+            }
+            Condition::GitBranch => {
+                let matched: Vec<String> = report
+                    .git_dependencies
+                    .iter()
+                    .map(|p| {
+                        let branch = p.reference.as_deref().unwrap_or("default branch");
+                        format!("{}@{} ({} of {})", p.package_name, p.package_version, branch, p.repository)
+                    })
+                    .collect();
+                let message = format!("{} git crate(s) pinned to a branch", matched.len());
+                (!matched.is_empty(), matched, message)
+            }
+        };
+        if fired {
+            results.push(PolicyResult {
+                rule: rule.name.clone(),
+                action: rule.action,
+                message: rule.message.clone().unwrap_or(default_message),
+                matched,
+            });
+        }
+    }
+    results
+}
+
+/// 命中规则中最严重的动作
+pub fn strictest_action(results: &[PolicyResult]) -> Option<PolicyAction> {
+    results.iter().map(|r| r.action).max()
+}
+
+/// 严重程度门禁中的动作，按严重程度排序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GateAction {
+    #[default]
+    Ignore,
+    Warn,
+    Fail,
+}
+
+/// 门禁映射的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GateSource {
+    // 配置文件的 `[gate]`
+    Config,
+    // 命令行的 --fail-on
+    FailOn,
+}
+
+/// 配置文件 `[gate]`：严重程度 -> 动作，未列出的严重程度为 ignore
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct SeverityGate(BTreeMap<Severity, GateAction>);
+
+/// 门禁的判定结果，写入报告的 `gate`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GateResult {
+    pub source: GateSource,
+    // 生效的映射，列出每一个严重程度
+    pub actions: BTreeMap<Severity, GateAction>,
+    // 命中的最严重动作；没有命中时为 ignore
+    pub outcome: GateAction,
+    // 触发 fail / warn 的漏洞，例如 `RUSTSEC-2020-0071 (time@0.1.45)`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warned: Vec<String>,
+}
+
+const SEVERITIES: [Severity; 6] =
+    [Severity::Unknown, Severity::None, Severity::Low, Severity::Medium, Severity::High, Severity::Critical];
+
+impl SeverityGate {
+    /// `--fail-on` 对应的映射：不低于 threshold 的为 fail，其余 ignore
+    pub fn fail_on(threshold: Severity) -> Self {
+        SeverityGate(SEVERITIES.iter().filter(|&&s| s >= threshold).map(|&s| (s, GateAction::Fail)).collect())
+    }
+
+    pub fn action(&self, severity: Severity) -> GateAction {
+        self.0.get(&severity).copied().unwrap_or_default()
+    }
+
+    /// 按映射检查报告中未被分诊排除、不在 baseline 中的漏洞
+    pub fn evaluate(&self, source: GateSource, report: &VulnReport) -> GateResult {
+        let (mut failed, mut warned) = (Vec::new(), Vec::new());
+        for package in &report.packages {
+            for finding in package.advisories.iter().filter(|f| !f.is_suppressed() && !f.in_baseline) {
+                let label = format!("{} ({}@{})", finding.id, package.package_name, package.package_version);
+                match self.action(finding.severity) {
+                    GateAction::Fail => failed.push(label),
+                    GateAction::Warn => warned.push(label),
+                    GateAction::Ignore => {}
+                }
+            }
+        }
+        let outcome = match (failed.is_empty(), warned.is_empty()) {
+            (false, _) => GateAction::Fail,
+            (true, false) => GateAction::Warn,
+            (true, true) => GateAction::Ignore,
+        };
+        GateResult {
+            source,
+            actions: SEVERITIES.iter().map(|&s| (s, self.action(s))).collect(),
+            outcome,
+            failed,
+            warned,
+        }
+    }
+}
+
+/// 生效的门禁：命令行的 --fail-on 优先于配置文件的 `[gate]`；两者都没有时不设门禁
+pub fn effective_gate(fail_on: Option<Severity>, configured: Option<&SeverityGate>) -> Option<(SeverityGate, GateSource)> {
+    match (fail_on, configured) {
+        (Some(threshold), _) => Some((SeverityGate::fail_on(threshold), GateSource::FailOn)),
+        (None, Some(gate)) => Some((gate.clone(), GateSource::Config)),
+        (None, None) => None,
+    }
+}
+
+/// `--min-cvss` 门禁
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CvssGate {
+    pub min_score: f64,
+    // 没有 CVSS 分数的漏洞也判为 fail（--fail-on-unscored）
+    pub fail_unscored: bool,
+}
+
+/// `--min-cvss` 门禁的判定结果，写入报告的 `cvss_gate`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CvssGateResult {
+    pub min_score: f64,
+    pub fail_unscored: bool,
+    // fail 或 ignore
+    pub outcome: GateAction,
+    // 触发 fail 的漏洞，例如 `RUSTSEC-2020-0071 (time@0.1.45, 8.1)`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<String>,
+    // 没有 CVSS 分数的漏洞
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unscored: Vec<String>,
+}
+
+impl CvssGate {
+    /// 与严重程度门禁一样，只看未被分诊排除、不在 baseline 中的漏洞
+    pub fn evaluate(&self, report: &VulnReport) -> CvssGateResult {
+        let (mut failed, mut unscored) = (Vec::new(), Vec::new());
+        for package in &report.packages {
+            for finding in package.advisories.iter().filter(|f| !f.is_suppressed() && !f.in_baseline) {
+                let name = format!("{}@{}", package.package_name, package.package_version);
+                match finding.cvss_score {
+                    Some(score) if score >= self.min_score => failed.push(format!("{} ({}, {:.1})", finding.id, name, score)),
+                    Some(_) => {}
+                    None => unscored.push(format!("{} ({})", finding.id, name)),
+                }
+            }
+        }
+        if self.fail_unscored {
+            failed.extend(unscored.iter().cloned());
+        }
+        CvssGateResult {
+            min_score: self.min_score,
+            fail_unscored: self.fail_unscored,
+            outcome: if failed.is_empty() { GateAction::Ignore } else { GateAction::Fail },
+            failed,
+            unscored,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo_metadata::CrateInfo;
+    use crate::config::ScanConfig;
+    use crate::scanner::{AdvisoryFinding, InformationalNotice, PackageReport};
+    use crate::hygiene::{GitPackage, GitPin};
+    use crate::staleness::{StalePackage, YankedPackage};
+
+    // app -> openssl (direct) -> foreign-types (transitive); app -> ansi_term (direct)
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["ansi_term", "openssl"]
+
+[[package]]
+name = "ansi_term"
+version = "0.12.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "foreign-types"
+version = "0.3.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "openssl"
+version = "0.10.55"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["foreign-types"]
+"#;
+
+    fn finding(id: &str, severity: Severity) -> AdvisoryFinding {
+        AdvisoryFinding { id: id.to_string(), severity, no_fix_available: true, ..Default::default() }
+    }
+
+    fn report() -> VulnReport {
+        VulnReport {
+            packages: vec![PackageReport {
+                package_name: "openssl".to_string(),
+                package_version: "0.10.55".to_string(),
+                max_severity: Severity::High,
+                advisories: vec![finding("RUSTSEC-2023-0044", Severity::High), finding("RUSTSEC-2023-0072", Severity::Medium)],
+                ..Default::default()
+            }],
+            informational: vec![InformationalNotice {
+                package_name: "ansi_term".to_string(),
+                package_version: "0.12.1".to_string(),
+                id: "RUSTSEC-2021-0139".to_string(),
+                category: "unmaintained".to_string(),
+                title: "ansi_term is Unmaintained".to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn metadata() -> CargoMetadata {
+        let mut metadata = CargoMetadata::default();
+        for (name, version, license) in [
+            ("ansi_term", "0.12.1", Some("MIT")),
+            ("openssl", "0.10.55", Some("Apache-2.0")),
+            ("foreign-types", "0.3.2", Some("MIT/Apache-2.0")),
+            ("app", "0.1.0", None),
+        ] {
+            let info = CrateInfo { license: license.map(str::to_string), ..Default::default() };
+            metadata.crates.insert((name.to_string(), version.to_string()), info);
+        }
+        metadata
+    }
+
+    fn run(config: &str) -> Vec<PolicyResult> {
+        let config = ScanConfig::parse(config).unwrap();
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        evaluate(&config.policy, &report(), &lockfile, Some(&metadata()))
+    }
+
+    #[test]
+    fn test_severity_count_threshold() {
+        let results = run(r#"
+[[policy]]
+name = "no-highs"
+when = "severity_count"
+severity = "high"
+action = "fail"
+
+[[policy]]
+name = "few-mediums"
+when = "severity_count"
+severity = "medium"
+more_than = 2
+action = "warn"
+
+[[policy]]
+name = "transitive-only"
+when = "severity_count"
+severity = "low"
+dependency_kind = "transitive"
+action = "fail"
+"#);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].rule, "no-highs");
+        assert_eq!(results[0].matched, vec!["RUSTSEC-2023-0044 (openssl@0.10.55)"]);
+        assert_eq!(strictest_action(&results), Some(PolicyAction::Fail));
+    }
+
+    #[test]
+    fn test_unmaintained_direct_dependency() {
+        let results = run(r#"
+[[policy]]
+name = "unmaintained-direct"
+when = "informational"
+category = "unmaintained"
+dependency_kind = "direct"
+action = "warn"
+message = "replace unmaintained direct dependencies"
+
+[[policy]]
+name = "unsound"
+when = "informational"
+category = "unsound"
+action = "fail"
+"#);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].message, "replace unmaintained direct dependencies");
+        assert_eq!(results[0].matched, vec!["RUSTSEC-2021-0139 (ansi_term@0.12.1)"]);
+        assert_eq!(strictest_action(&results), Some(PolicyAction::Warn));
+    }
+
+    #[test]
+    fn test_license_rules() {
+        let results = run(r#"
+[[policy]]
+name = "no-apache"
+when = "license"
+deny = ["Apache-2.0"]
+action = "note"
+
+[[policy]]
+name = "licensed"
+when = "missing_license"
+action = "warn"
+"#);
+        assert_eq!(results[0].matched, vec!["foreign-types@0.3.2 (Apache-2.0)", "openssl@0.10.55 (Apache-2.0)"]);
+        assert_eq!(results[1].rule, "licensed");
+        assert_eq!(results[1].matched, vec!["app@0.1.0"]);
+    }
+
+    #[test]
+    fn test_banned_crate_and_unknown_condition() {
+        let results = run(r#"
+[[policy]]
+name = "no-openssl"
+when = "banned_crate"
+names = ["openssl"]
+action = "fail"
+"#);
+        assert_eq!(results[0].matched, vec!["openssl@0.10.55"]);
+        assert_eq!(results[0].message, "1 banned crate(s) in the dependency graph");
+
+        let bad = "[[policy]]\nname = \"x\"\nwhen = \"expression\"\naction = \"fail\"\n";
+        assert!(ScanConfig::parse(bad).is_err());
+    }
+
+    #[test]
+    fn test_stale_rule_only_fires_on_checked_reports() {
+        let config = ScanConfig::parse("[[policy]]\nname = \"fresh\"\nwhen = \"stale\"\naction = \"fail\"\n").unwrap();
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        // Without --check-staleness there is nothing to match
+        assert!(evaluate(&config.policy, &report(), &lockfile, None).is_empty());
+
+        let mut report = report();
+        report.stale_packages = vec![StalePackage {
+            package_name: "ansi_term".to_string(),
+            package_version: "0.12.1".to_string(),
+            latest_version: "0.12.1".to_string(),
+            latest_release_date: "2019-07-30".to_string(),
+            age_days: 1890,
+        }];
+        let results = evaluate(&config.policy, &report, &lockfile, None);
+        assert_eq!(results[0].matched, vec!["ansi_term@0.12.1 (latest release 2019-07-30)"]);
+    }
+
+    #[test]
+    fn test_yanked_rule_by_dependency_kind() {
+        let config = ScanConfig::parse(
+            "[[policy]]\nname = \"direct-yanked\"\nwhen = \"yanked\"\ndependency_kind = \"direct\"\naction = \"fail\"\n",
+        )
+        .unwrap();
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        assert!(evaluate(&config.policy, &report(), &lockfile, None).is_empty());
+
+        let yanked = |name: &str, version: &str| YankedPackage { package_name: name.to_string(), package_version: version.to_string() };
+        let mut report = report();
+        // foreign-types only comes in through openssl
+        report.yanked_packages = vec![yanked("foreign-types", "0.3.2")];
+        assert!(evaluate(&config.policy, &report, &lockfile, None).is_empty());
+        report.yanked_packages.push(yanked("openssl", "0.10.55"));
+        let results = evaluate(&config.policy, &report, &lockfile, None);
+        assert_eq!(results[0].matched, vec!["openssl@0.10.55"]);
+        assert_eq!(results[0].message, "1 crate(s) locked to a yanked version");
+    }
+
+    #[test]
+    fn test_git_branch_rule() {
+        let config = ScanConfig::parse("[[policy]]\nname = \"pinned\"\nwhen = \"git_branch\"\naction = \"fail\"\n").unwrap();
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        assert!(evaluate(&config.policy, &report(), &lockfile, None).is_empty());
+
+        let mut report = report();
+        report.git_dependencies = vec![GitPackage {
+            package_name: "patched".to_string(),
+            package_version: "0.3.0".to_string(),
+            repository: "https://github.com/acme/patched".to_string(),
+            pin: GitPin::Branch,
+            reference: Some("branch=main".to_string()),
+            commit: None,
+        }];
+        let results = evaluate(&config.policy, &report, &lockfile, None);
+        assert_eq!(results[0].matched, vec!["patched@0.3.0 (branch=main of https://github.com/acme/patched)"]);
+    }
+
+    #[test]
+    fn test_severity_gate_actions() {
+        let config = ScanConfig::parse("[gate]\nhigh = \"fail\"\nmedium = \"warn\"\n").unwrap();
+        let gate = config.gate.unwrap();
+        let result = gate.evaluate(GateSource::Config, &report());
+        assert_eq!(result.outcome, GateAction::Fail);
+        assert_eq!(result.failed, vec!["RUSTSEC-2023-0044 (openssl@0.10.55)"]);
+        assert_eq!(result.warned, vec!["RUSTSEC-2023-0072 (openssl@0.10.55)"]);
+        assert_eq!(result.actions[&Severity::Critical], GateAction::Ignore);
+
+        let mut report = report();
+        report.packages[0].advisories.remove(0);
+        assert_eq!(gate.evaluate(GateSource::Config, &report).outcome, GateAction::Warn);
+        assert!(ScanConfig::parse("[gate]\nhigh = \"block\"\n").is_err());
+        assert!(ScanConfig::parse("[gate]\nsevere = \"fail\"\n").is_err());
+    }
+
+    #[test]
+    fn test_min_cvss_gate() {
+        let mut report = report();
+        report.packages[0].advisories[0].cvss_score = Some(7.5);
+        let gate = CvssGate { min_score: 7.0, fail_unscored: false };
+        let result = gate.evaluate(&report);
+        assert_eq!(result.outcome, GateAction::Fail);
+        assert_eq!(result.failed, vec!["RUSTSEC-2023-0044 (openssl@0.10.55, 7.5)"]);
+        assert_eq!(result.unscored, vec!["RUSTSEC-2023-0072 (openssl@0.10.55)"]);
+
+        // The threshold is inclusive; unscored findings only fail when asked to
+        assert_eq!(CvssGate { min_score: 7.5, ..gate }.evaluate(&report).outcome, GateAction::Fail);
+        assert_eq!(CvssGate { min_score: 7.6, ..gate }.evaluate(&report).outcome, GateAction::Ignore);
+        let strict = CvssGate { min_score: 7.6, fail_unscored: true }.evaluate(&report);
+        assert_eq!(strict.failed, vec!["RUSTSEC-2023-0072 (openssl@0.10.55)"]);
+        report.packages[0].advisories[1].in_baseline = true;
+        assert_eq!(CvssGate { min_score: 7.6, fail_unscored: true }.evaluate(&report).outcome, GateAction::Ignore);
+    }
+
+    #[test]
+    fn test_fail_on_replaces_the_configured_gate() {
+        let configured = ScanConfig::parse("[gate]\nmedium = \"fail\"\n").unwrap().gate;
+        let (gate, source) = effective_gate(Some(Severity::High), configured.as_ref()).unwrap();
+        assert_eq!(source, GateSource::FailOn);
+        let result = gate.evaluate(source, &report());
+        assert_eq!(result.failed, vec!["RUSTSEC-2023-0044 (openssl@0.10.55)"]);
+        assert!(result.warned.is_empty());
+        assert_eq!(result.actions[&Severity::Medium], GateAction::Ignore);
+        assert_eq!(effective_gate(None, configured.as_ref()).unwrap().1, GateSource::Config);
+        assert!(effective_gate(None, None).is_none());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::severity::Severity;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::Write;
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Progress events for long-running scans. The pipeline reports through `emit` at a few
+// fixed points (extraction start, each package matched against the advisories, each
+// timed stage) and every registered observer sees the same sequence, so a terminal
+// progress display and a machine-readable feed cannot drift apart. With no observer
+// `emit` does nothing.
+//
+// `--progress-format ndjson` registers NdjsonObserver, which writes one JSON object per
+// event to stderr:
+//
+//     {"event":"extract_start","entries":1234}
+//     {"event":"package_scanned","index":57,"total":800,"name":"tokio"}
+//     {"event":"finding","package":"tokio","version":"1.0.0","id":"RUSTSEC-2021-0072","severity":"high"}
+//     {"event":"finding","package":"ansi_term","version":"0.12.1","id":"RUSTSEC-2021-0139","severity":null}
+//     {"event":"stage_complete","stage":"sbom","duration_ms":4321}
+//     {"event":"done","exit_code":0}
+//
+// stderr then carries nothing else: warnings, status lines that would have gone to
+// stderr and the final error become `warning` / `message` / `error` events too. Every
+// lockfile scanned reports its own `package_scanned` run (the merged graph first, then
+// each project when there are several), and a stage timed more than once completes
+// more than once.
+//
+// Otherwise, unless `--quiet`, EarlyFindings prints every critical or high finding to
+// stderr the moment the scanner matches it, so the worst news of a long scan does not
+// wait for the report. It sees findings before triage and the baseline are applied, so it
+// skips those an `[[ignore]]` entry suppresses itself; anything the baseline accepts later
+// is still printed once.
+
+/// 一个进度事件，序列化为 `{"event": "<snake_case 名称>", ...}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    // 开始解压；.crate 是顺序读取的 tar，事先不知道条目数
+    ExtractStart {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        entries: Option<usize>,
+    },
+    // 开始比对 lockfile 中的第 index 个包（从 1 开始）；被排除或跳过的包也计入
+    PackageScanned { index: usize, total: usize, name: String },
+    // 扫描中匹配到一个漏洞（分诊与 baseline 之前）
+    Finding {
+        package: String,
+        version: String,
+        id: String,
+        // 没有 CVSS 分数时为 null，与报告一致
+        #[serde(serialize_with = "Severity::serialize_known")]
+        severity: Severity,
+    },
+    // `Timings` 记录了一个阶段
+    StageComplete { stage: String, duration_ms: u64 },
+    Warning { code: String, message: String },
+    Message { text: String },
+    Error { message: String },
+    Done { exit_code: u8 },
+}
+
+/// 接收进度事件
+pub trait ProgressObserver: Send + Sync {
+    fn on_event(&self, event: &ProgressEvent);
+}
+
+static OBSERVERS: RwLock<Vec<Box<dyn ProgressObserver>>> = RwLock::new(Vec::new());
+
+// Set once NdjsonObserver owns stderr
+static STDERR_EVENTS: AtomicBool = AtomicBool::new(false);
+
+pub fn add_observer(observer: Box<dyn ProgressObserver>) {
+    if let Ok(mut observers) = OBSERVERS.write() {
+        observers.push(observer);
+    }
+}
+
+/// 把事件交给所有已注册的观察者
+pub fn emit(event: ProgressEvent) {
+    if let Ok(observers) = OBSERVERS.read() {
+        for observer in observers.iter() {
+            observer.on_event(&event);
+        }
+    }
+}
+
+/// stderr 是否只输出 NDJSON 事件；为 true 时其他文字输出也应当作为事件发出
+pub fn stderr_takes_events() -> bool {
+    STDERR_EVENTS.load(Ordering::SeqCst)
+}
+
+/// 每个事件一行 JSON，写到 stderr
+pub struct NdjsonObserver;
+
+impl NdjsonObserver {
+    /// 注册观察者，并让 stderr 只输出事件
+    pub fn install() {
+        STDERR_EVENTS.store(true, Ordering::SeqCst);
+        add_observer(Box::new(NdjsonObserver));
+    }
+}
+
+impl ProgressObserver for NdjsonObserver {
+    fn on_event(&self, event: &ProgressEvent) {
+        if let Ok(line) = serde_json::to_string(event) {
+            // One locked write per line, so events from parallel workers never interleave
+            let _ = writeln!(std::io::stderr().lock(), "{}", line);
+        }
+    }
+}
+
+// (id, package) -> whether an `[[ignore]]` entry suppresses it
+type IgnoreCheck = Box<dyn Fn(&str, &str) -> bool + Send + Sync>;
+
+/// 一发现就把 critical / high 漏洞写到 stderr
+pub struct EarlyFindings {
+    ignored: IgnoreCheck,
+    // 拆分扫描各项目时同一漏洞会再出现，只打印一次
+    printed: Mutex<HashSet<(String, String, String)>>,
+}
+
+impl EarlyFindings {
+    pub fn install(ignored: impl Fn(&str, &str) -> bool + Send + Sync + 'static) {
+        add_observer(Box::new(EarlyFindings { ignored: Box::new(ignored), printed: Mutex::new(HashSet::new()) }));
+    }
+
+    // The line to print, or None when this finding is not worth interrupting for
+    fn line(&self, event: &ProgressEvent) -> Option<String> {
+        let ProgressEvent::Finding { package, version, id, severity } = event else {
+            return None;
+        };
+        if *severity < Severity::High || (self.ignored)(id, package) {
+            return None;
+        }
+        let mut printed = self.printed.lock().ok()?;
+        printed
+            .insert((package.clone(), version.clone(), id.clone()))
+            .then(|| format!("Found {} vulnerability {} in {}@{}", severity, id, package, version))
+    }
+}
+
+impl ProgressObserver for EarlyFindings {
+    fn on_event(&self, event: &ProgressEvent) {
+        if let Some(line) = self.line(event) {
+            crate::console::record(&line);
+            crate::console::eprint_line(&line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_event_shapes() {
+        let value = |event: ProgressEvent| serde_json::to_value(event).unwrap();
+        assert_eq!(value(ProgressEvent::ExtractStart { entries: Some(1234) }), json!({"event": "extract_start", "entries": 1234}));
+        assert_eq!(value(ProgressEvent::ExtractStart { entries: None }), json!({"event": "extract_start"}));
+        assert_eq!(
+            value(ProgressEvent::PackageScanned { index: 57, total: 800, name: "tokio".to_string() }),
+            json!({"event": "package_scanned", "index": 57, "total": 800, "name": "tokio"})
+        );
+        assert_eq!(
+            value(ProgressEvent::StageComplete { stage: "sbom".to_string(), duration_ms: 4321 }),
+            json!({"event": "stage_complete", "stage": "sbom", "duration_ms": 4321})
+        );
+        assert_eq!(
+            value(ProgressEvent::Finding {
+                package: "ansi_term".to_string(),
+                version: "0.12.1".to_string(),
+                id: "RUSTSEC-2021-0139".to_string(),
+                severity: Severity::Unknown,
+            }),
+            json!({"event": "finding", "package": "ansi_term", "version": "0.12.1", "id": "RUSTSEC-2021-0139", "severity": null})
+        );
+        assert_eq!(value(ProgressEvent::Done { exit_code: 0 }), json!({"event": "done", "exit_code": 0}));
+    }
+
+    #[test]
+    fn test_early_findings_print_severe_ones_once() {
+        let early = EarlyFindings { ignored: Box::new(|id, _| id == "RUSTSEC-2020-0003"), printed: Mutex::new(HashSet::new()) };
+        let finding = |id: &str, severity| ProgressEvent::Finding {
+            package: "smallvec".to_string(),
+            version: "1.6.0".to_string(),
+            id: id.to_string(),
+            severity,
+        };
+        assert_eq!(
+            early.line(&finding("RUSTSEC-2021-0003", Severity::Critical)).as_deref(),
+            Some("Found critical vulnerability RUSTSEC-2021-0003 in smallvec@1.6.0")
+        );
+        // Again for the same package version in a per-project scan
+        assert_eq!(early.line(&finding("RUSTSEC-2021-0003", Severity::Critical)), None);
+        assert_eq!(early.line(&finding("RUSTSEC-2020-0002", Severity::Medium)), None);
+        assert_eq!(early.line(&finding("RUSTSEC-2020-0003", Severity::High)), None);
+        assert!(early.line(&finding("RUSTSEC-2020-0004", Severity::High)).is_some());
+    }
+}
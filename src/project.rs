@@ -0,0 +1,96 @@
+use crate::get_lockfile::LockDiscovery;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+// The identity of the scanned project as a whole: the SBOM's `metadata.component` and the
+// report's `metadata.project`, so that both name the same thing. Each field comes from
+// the first of:
+//
+//   1. --project-name / --project-version / --project-group
+//   2. the root Cargo.toml: `[package]` name and version, where `version.workspace = true`
+//      (and a virtual workspace, which has no `[package]`) uses `[workspace.package]`
+//   3. the input's file name without its extension, for the name only
+//
+// Cargo manifests have no group, so it only ever comes from --project-group.
+
+/// 命令行给出的项目标识（--project-name / --project-version / --project-group）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectOverrides {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub group: Option<String>,
+}
+
+/// 项目标识
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProjectIdentity {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+impl ProjectIdentity {
+    /// manifest 为根目录的 Cargo.toml（输入为二进制时为 None），input 为被扫描的文件
+    pub fn resolve(overrides: &ProjectOverrides, manifest: Option<&Path>, input: &Path) -> Self {
+        let (manifest_name, manifest_version) = manifest.map(read_manifest).unwrap_or_default();
+        let name = overrides.name.clone().or(manifest_name).unwrap_or_else(|| {
+            input.file_stem().map(|s| s.to_string_lossy().to_string()).filter(|s| !s.is_empty()).unwrap_or_else(|| "project".to_string())
+        });
+        ProjectIdentity {
+            name,
+            version: overrides.version.clone().or(manifest_version),
+            group: overrides.group.clone(),
+        }
+    }
+
+    /// 同 `resolve`，manifest 取最靠近根目录的项目的 Cargo.toml；没有项目（输入为二进制）时为 None
+    pub fn of_projects(overrides: &ProjectOverrides, projects: &[LockDiscovery], input: &Path) -> Self {
+        let root_manifest = projects.first().map(|p| p.project_root.join("Cargo.toml"));
+        Self::resolve(overrides, root_manifest.as_deref(), input)
+    }
+}
+
+// (name, version) from a manifest; a missing or unparsable one gives neither
+fn read_manifest(path: &Path) -> (Option<String>, Option<String>) {
+    let Some(manifest) = fs::read_to_string(path).ok().and_then(|text| text.parse::<toml::Table>().ok()) else {
+        return (None, None);
+    };
+    let string = |value: Option<&toml::Value>| value.and_then(|v| v.as_str()).map(str::to_string);
+    let package = manifest.get("package");
+    let name = string(package.and_then(|p| p.get("name")));
+    let version = string(package.and_then(|p| p.get("version")))
+        .or_else(|| string(manifest.get("workspace").and_then(|w| w.get("package")).and_then(|p| p.get("version"))));
+    (name, version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_fallback_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nname = \"placeholder\"\nversion = \"0.1.0\"\n").unwrap();
+        let input = Path::new("/downloads/acme-main.zip");
+
+        let overrides = ProjectOverrides { name: Some("acme".to_string()), group: Some("com.example".to_string()), ..Default::default() };
+        let identity = ProjectIdentity::resolve(&overrides, Some(&manifest), input);
+        assert_eq!((identity.name.as_str(), identity.version.as_deref()), ("acme", Some("0.1.0")));
+        assert_eq!(identity.group.as_deref(), Some("com.example"));
+
+        let identity = ProjectIdentity::resolve(&ProjectOverrides::default(), Some(&manifest), input);
+        assert_eq!((identity.name.as_str(), identity.version.as_deref()), ("placeholder", Some("0.1.0")));
+
+        // A virtual workspace: the name from the archive, the version from [workspace.package]
+        fs::write(&manifest, "[workspace]\nmembers = [\"app\"]\n\n[workspace.package]\nversion = \"2.0.0\"\n").unwrap();
+        let identity = ProjectIdentity::resolve(&ProjectOverrides::default(), Some(&manifest), input);
+        assert_eq!((identity.name.as_str(), identity.version.as_deref()), ("acme-main", Some("2.0.0")));
+
+        let identity = ProjectIdentity::resolve(&ProjectOverrides::default(), None, Path::new("my-binary"));
+        assert_eq!((identity.name.as_str(), identity.version), ("my-binary", None));
+    }
+}
@@ -0,0 +1,50 @@
+use crate::registry::RegistryMap;
+use cargo_lock::package::{SourceKind, Package};
+
+// Package URL (purl) generation for Cargo packages.
+// https://github.com/package-url/purl-spec/blob/master/PURL-TYPES.rst#cargo
+//
+// crates.io packages get the plain `pkg:cargo/<name>@<version>` form. Packages from
+// alternative registries carry a `repository_url` qualifier and git dependencies a
+// `vcs_url` qualifier, so that two crates sharing a name but coming from different
+// places never collapse into the same purl. A registry listed in the `[registries]` config
+// with a `repository_url` uses that instead of its index URL.
+pub fn package_url(package: &Package, registries: &RegistryMap) -> String {
+    let base = format!("pkg:cargo/{}@{}", package.name.as_str(), package.version);
+
+    let Some(source) = &package.source else {
+        return base;
+    };
+
+    if source.is_default_registry() {
+        return base;
+    }
+
+    match source.kind() {
+        SourceKind::Git(_) => {
+            let mut vcs_url = format!("git+{}", source.url());
+            if let Some(rev) = source.precise() {
+                vcs_url.push('@');
+                vcs_url.push_str(rev);
+            }
+            format!("{}?vcs_url={}", base, percent_encode(&vcs_url))
+        }
+        SourceKind::Path => base,
+        _ => {
+            let repository_url = registries.get(source).and_then(|r| r.repository_url.as_deref());
+            format!("{}?repository_url={}", base, percent_encode(repository_url.unwrap_or(source.url().as_str())))
+        }
+    }
+}
+
+// Percent-encode a purl qualifier value (RFC 3986 unreserved characters pass through)
+fn percent_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
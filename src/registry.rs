@@ -0,0 +1,152 @@
+use crate::cargo_config::{is_private_registry, normalize};
+use cargo_lock::Lockfile;
+use cargo_lock::package::SourceId;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// Private registries, from the `[registries]` section of --config, keyed by index URL as
+// it appears in Cargo.lock (the `sparse+` / `registry+` prefix and a trailing `/` do not
+// matter):
+//
+//     [registries."sparse+https://crates.internal.example/index/"]
+//     name = "internal"                                   # shown as the package's `source`
+//     repository_url = "https://crates.internal.example"  # optional, the SBOM purl qualifier
+//     advisories = false                                  # optional, defaults to true
+//
+// RustSec only covers crates.io, so its advisories are matched against a private package
+// purely by name. Fine when the registry re-publishes crates.io crates; a false positive
+// when an internal crate shares its name with a public one. `advisories = false` leaves a
+// registry's packages out of the vulnerability scan (counted in the report's
+// `metadata.skipped_registries`). A lockfile that uses registries missing from the table
+// gets one `registry-unmapped` warning listing them all.
+
+/// 私有 registry 的配置
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryEntry {
+    pub name: String,
+    // SBOM purl 的 repository_url；省略时取 index URL
+    #[serde(default)]
+    pub repository_url: Option<String>,
+    // 是否用 RustSec advisory 匹配该 registry 的包
+    #[serde(default = "match_advisories")]
+    pub advisories: bool,
+}
+
+fn match_advisories() -> bool {
+    true
+}
+
+/// 配置文件 `[registries]`：index URL -> registry 配置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct RegistryMap(BTreeMap<String, RegistryEntry>);
+
+impl RegistryMap {
+    /// 加入 other 中本表没有的 registry（项目配置合并到 --config 之下）
+    pub fn merge_under(&mut self, other: RegistryMap) {
+        for (index, entry) in other.0 {
+            self.0.entry(index).or_insert(entry);
+        }
+    }
+
+    /// source 对应的配置；crates.io、git 与 path 来源没有
+    pub fn get(&self, source: &SourceId) -> Option<&RegistryEntry> {
+        if !is_private_registry(source) {
+            return None;
+        }
+        let url = normalize(source.url().as_str());
+        self.0.iter().find(|(index, _)| normalize(index) == url).map(|(_, entry)| entry)
+    }
+
+    /// 报告中显示的来源：配置的名称，否则为 lockfile 中的 source；crates.io 与 path 依赖为 None
+    pub fn label(&self, source: Option<&SourceId>) -> Option<String> {
+        let source = source.filter(|s| !s.is_default_registry() && !s.is_path())?;
+        Some(self.get(source).map_or_else(|| source.to_string(), |entry| entry.name.clone()))
+    }
+
+    /// 是否有 registry 设置了 `advisories = false`
+    pub fn skips_any(&self) -> bool {
+        self.0.values().any(|entry| !entry.advisories)
+    }
+
+    /// 是否跳过该来源的包的漏洞匹配（`advisories = false`）
+    pub fn skips_advisories(&self, source: Option<&SourceId>) -> bool {
+        source.and_then(|s| self.get(s)).is_some_and(|entry| !entry.advisories)
+    }
+
+    /// lockfile 中未配置的私有 registry 及各自的包数，按 index URL 排序
+    pub fn unmapped(&self, lockfile: &Lockfile) -> BTreeMap<String, usize> {
+        let mut unmapped = BTreeMap::new();
+        for source in lockfile.packages.iter().filter_map(|p| p.source.as_ref()) {
+            if is_private_registry(source) && self.get(source).is_none() {
+                *unmapped.entry(normalize(source.url().as_str())).or_insert(0) += 1;
+            }
+        }
+        unmapped
+    }
+
+    /// 对未配置的私有 registry 给出一条汇总警告
+    pub fn warn_unmapped(&self, lockfile: &Lockfile) {
+        let unmapped = self.unmapped(lockfile);
+        if unmapped.is_empty() {
+            return;
+        }
+        let list: Vec<String> = unmapped.iter().map(|(url, count)| format!("{} ({})", url, count)).collect();
+        crate::console::warn(
+            "registry-unmapped",
+            format!(
+                "{} package(s) from registries not in the [registries] config: {}; RustSec advisories are matched against them by name only",
+                unmapped.values().sum::<usize>(),
+                list.join(", ")
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn fixture() -> Lockfile {
+        Lockfile::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lockfiles/private-registry.lock")).unwrap()
+    }
+
+    #[test]
+    fn test_mapped_and_unmapped_registries() {
+        let map: RegistryMap = toml::from_str(
+            r#"
+["https://crates.internal.example/index"]
+name = "internal"
+repository_url = "https://crates.internal.example"
+advisories = false
+"#,
+        )
+        .unwrap();
+        let lockfile = fixture();
+        let source = |name: &str| lockfile.packages.iter().find(|p| p.name.as_str() == name).unwrap().source.as_ref();
+
+        // The prefix and trailing slash of the lockfile's source don't matter
+        assert_eq!(map.get(source("acme-auth").unwrap()).unwrap().name, "internal");
+        assert_eq!(map.label(source("acme-auth")).as_deref(), Some("internal"));
+        assert_eq!(map.label(source("acme-ledger")).as_deref(), Some("registry+https://git.partner.example/cargo-index"));
+        assert_eq!(map.label(source("serde")), None);
+        assert_eq!(map.label(source("billing")), None);
+        assert!(map.skips_advisories(source("acme-auth")));
+        assert!(!map.skips_advisories(source("acme-ledger")) && !map.skips_advisories(source("serde")));
+
+        let unmapped: Vec<(String, usize)> = map.unmapped(&lockfile).into_iter().collect();
+        assert_eq!(unmapped, [("https://git.partner.example/cargo-index".to_string(), 1)]);
+        assert_eq!(RegistryMap::default().unmapped(&lockfile).len(), 2);
+
+        let purl = |name: &str| {
+            crate::purl::package_url(lockfile.packages.iter().find(|p| p.name.as_str() == name).unwrap(), &map)
+        };
+        assert_eq!(purl("acme-auth"), "pkg:cargo/acme-auth@1.4.0?repository_url=https%3A%2F%2Fcrates.internal.example");
+        assert_eq!(
+            purl("acme-ledger"),
+            "pkg:cargo/acme-ledger@0.9.1?repository_url=https%3A%2F%2Fgit.partner.example%2Fcargo-index"
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use crate::sink::{ObjectLocation, OutputSink};
+use anyhow::{Context, Result, bail};
+use s3::creds::Credentials;
+use s3::{Bucket, Region};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// `--output-url s3://bucket/prefix/` (the `s3` feature). Credentials come from the usual
+// AWS chain: `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY` (/ `AWS_SESSION_TOKEN`), then
+// the `AWS_PROFILE` (or default) profile in ~/.aws/credentials, then web identity and the
+// instance metadata service. The region is `AWS_REGION` or `AWS_DEFAULT_REGION`
+// (us-east-1 when neither is set); `AWS_ENDPOINT_URL` points at an S3-compatible store
+// such as MinIO instead, addressed path-style.
+
+pub struct S3Sink {
+    bucket: Box<Bucket>,
+    location: ObjectLocation,
+}
+
+impl S3Sink {
+    pub fn new(location: ObjectLocation) -> Result<Self> {
+        let region_name = env::var("AWS_REGION")
+            .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|_| "us-east-1".to_string());
+        let endpoint = env::var("AWS_ENDPOINT_URL").ok().filter(|e| !e.is_empty());
+        let region = match &endpoint {
+            Some(endpoint) => Region::Custom { region: region_name, endpoint: endpoint.clone() },
+            None => region_name.parse().with_context(|| format!("invalid AWS region: {}", region_name))?,
+        };
+        let credentials = Credentials::default().context("no AWS credentials found for the S3 upload")?;
+        let mut bucket = Bucket::new(&location.bucket, region, credentials)
+            .with_context(|| format!("failed to set up S3 bucket {}", location.bucket))?;
+        if endpoint.is_some() {
+            bucket = bucket.with_path_style();
+        }
+        Ok(S3Sink { bucket, location })
+    }
+}
+
+impl OutputSink for S3Sink {
+    fn put(&self, name: &str, path: &Path, content_type: &str) -> Result<String> {
+        let body = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let response = self.bucket.put_object_with_content_type(self.location.key(name), &body, content_type)?;
+        if !(200..300).contains(&response.status_code()) {
+            bail!("S3 answered {}: {}", response.status_code(), String::from_utf8_lossy(response.as_slice()).trim());
+        }
+        Ok(self.location.url(name))
+    }
+
+    fn location(&self) -> String {
+        self.location.url("")
+    }
+}
@@ -1,50 +1,627 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
 use anyhow::{Context, Result};
-use cargo_lock::Lockfile;
-use rustsec::{
-    advisory::Advisory,
-    database::Database,
-    repository::git::Repository,
-};
+use cargo_lock::{Lockfile, Package};
+use rustsec::platforms::Platform;
+use rustsec::advisory::Advisory;
+pub use rustsec::Collection;
 use semver::Version;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use crate::advisory_source::{AdvisorySource, DbWarning, LoadedDb};
+use crate::console::Warning;
+use crate::cvss_sources::AliasScores;
+use crate::get_lockfile::{LockDiscovery, LockfileLines, common_resolve_version};
+use crate::hygiene::{self, GitPackage, PrereleasePackage};
+use crate::graph::{DependencyGraph, DependencyKind, GraphStats, workspace_members};
+use crate::msrv::MsrvReport;
+use crate::package_filter::{ExcludedPackage, PackageSelection};
+use crate::policy::{CvssGateResult, GateResult, PolicyResult};
+use crate::project::ProjectIdentity;
+use crate::registry::RegistryMap;
+use crate::severity::Severity;
+use crate::staleness::{StalePackage, YankedPackage};
+use crate::timing::Timings;
+use crate::typosquat::SuspiciousPackage;
+use crate::vex::Analysis;
 
 // 用于测试
 #[cfg(test)]
-use {
-    std::collections::BTreeMap,
-    std::str::FromStr,
-};
+use std::str::FromStr;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 pub struct VulnReport {
+    pub metadata: ReportMetadata,
     pub total_packages: usize,
     pub packages: Vec<PackageReport>,
     pub summary: Summary,
+    // 可选的 MSRV 冲突检查结果（--check-msrv）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msrv_conflicts: Option<MsrvReport>,
+    // 名称疑似仿冒热门 crate 的包，仅供参考（--fail-on-suspicious 时才影响退出码）
+    pub suspicious_packages: Vec<SuspiciousPackage>,
+    // 最新版本发布已久的 crates.io 依赖（--check-staleness），仅供参考，只有 policy 规则会据此影响退出码
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stale_packages: Vec<StalePackage>,
+    // 锁定的版本已被撤回的 crates.io 依赖（--check-staleness），同样只有 policy 规则会据此影响退出码
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub yanked_packages: Vec<YankedPackage>,
+    // 使用预发布版本的依赖，与 advisory 无关，仅供参考
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prerelease_packages: Vec<PrereleasePackage>,
+    // 来自 git 仓库的依赖及其锁定的提交，仅供参考
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub git_packages: Vec<GitPackage>,
+    // 其中跟随分支（branch= 或默认分支）而非固定 rev / tag 的依赖
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub git_dependencies: Vec<GitPackage>,
+    // 配置了严重程度覆盖、但本次扫描没有遇到的 advisory，便于清理过期条目
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unused_severity_overrides: Vec<String>,
+    // 只影响其他操作系统 / 架构、因此对 --target 不适用的漏洞；不计入 summary
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub not_applicable_to_target: Vec<PackageReport>,
+    // --exclude-workspace 时未参与扫描的工作区成员（name@version）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub excluded_workspace_members: Vec<String>,
+    // 被 --only-category / --exclude-category 排除的漏洞；不计入 summary，数量见 summary.filtered_by_category
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub excluded_by_category: Vec<PackageReport>,
+    // 命中 informational advisory（unmaintained / unsound / notice）的包；不计入 summary
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub informational: Vec<InformationalNotice>,
+    // 配置中命中的 policy 规则
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub policy_results: Vec<PolicyResult>,
+    // 严重程度门禁（--fail-on 或配置的 `[gate]`）的映射与判定结果；没有门禁时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gate: Option<GateResult>,
+    // --min-cvss 门禁的阈值与判定结果；未设置时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cvss_gate: Option<CvssGateResult>,
+    // `rust` 集合中影响 --toolchain 版本的 advisory，按工具链组件（std、cargo 等）分组；
+    // 升级 Rust 而不是依赖才能修复，不计入 summary
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub toolchain: Vec<PackageReport>,
+    // 已撤回、但版本范围本会命中 lockfile 的 advisory（--include-withdrawn）；不计入 summary
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub withdrawn: Vec<WithdrawnFinding>,
+    // 压缩包中有多个 lockfile 时按 lockfile 分列的结果；顶层的 packages / summary 是全部
+    // lockfile 合并去重后的结果。只有一个 lockfile 时为空，报告保持原来的扁平结构
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub projects: Vec<ProjectReport>,
+    // 运行中降级但未失败的问题（cargo metadata 不可用、跳过的 ZIP 条目等）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+/// 扫描所用的一个 advisory DB
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AdvisoryDbInfo {
+    pub path: String,
+    // HEAD 提交；--db-rev 时为指定的提交
+    pub commit: Option<String>,
 }
 
+/// 单个 lockfile（项目）的扫描结果
 #[derive(Debug, Serialize)]
+pub struct ProjectReport {
+    // 来自项目根目录 Cargo.toml 的 package.name
+    pub name: String,
+    // lockfile 在压缩包内的相对路径
+    pub lockfile_path: String,
+    // 该 lockfile 的格式版本（1-4）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lockfile_version: Option<u32>,
+    pub total_packages: usize,
+    pub packages: Vec<PackageReport>,
+    pub summary: Summary,
+}
+
+impl ProjectReport {
+    /// 从该项目 lockfile 的扫描报告（已分诊）中取出包和统计
+    pub fn from_report(name: impl Into<String>, lockfile_path: impl Into<String>, report: VulnReport) -> Self {
+        ProjectReport {
+            name: name.into(),
+            lockfile_path: lockfile_path.into(),
+            lockfile_version: report.metadata.lockfile_version,
+            total_packages: report.total_packages,
+            packages: report.packages,
+            summary: report.summary,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WithdrawnFinding {
+    pub package_name: String,
+    pub package_version: String,
+    pub id: String,
+    // 撤回日期
+    pub withdrawn: String,
+    pub aliases: Vec<String>,
+    // 通过相同的 CVE 等别名对应到的现行漏洞 id，说明两者是同一问题而非重复计数
+    pub superseded_by: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InformationalNotice {
+    pub package_name: String,
+    pub package_version: String,
+    pub id: String,
+    // unmaintained / unsound / notice 等
+    pub category: String,
+    pub title: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ReportMetadata {
+    pub tool: String,
+    pub tool_version: String,
+    // --canonical 时为空并省略
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub generated_at: String,
+    // 扫描所用 advisory DB（有多个时为第一个）的提交，配合 --db-rev 可以复现同一份报告
+    pub advisory_db_commit: Option<String>,
+    // 扫描所用的全部 advisory DB 及其提交，按 --db-path 的顺序
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub advisory_dbs: Vec<AdvisoryDbInfo>,
+    // --target 指定的目标平台
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    // 各阶段耗时（秒）
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub timings: BTreeMap<String, f64>,
+    // advisory DB 中因撤回 / 属于 informational 而不参与漏洞匹配的条目数
+    pub skipped_withdrawn: usize,
+    pub skipped_informational: usize,
+    // 配置 `advisories = false` 而未参与漏洞匹配的私有 registry（名称 -> 包数）
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub skipped_registries: BTreeMap<String, usize>,
+    // --package 过滤条件；设置时报告只覆盖匹配的包
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_filter: Option<String>,
+    // --exclude-package / 配置 `[[exclude]]` 从 lockfile 中去掉的包及原因
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub excluded_packages: Vec<ExcludedPackage>,
+    // --only-category / --exclude-category 过滤条件
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category_filter: Option<String>,
+    // --baseline 报告的路径
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<String>,
+    // 所扫描 Cargo.lock 的格式版本（1-4）；输入为二进制或多个 lockfile 版本不同时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lockfile_version: Option<u32>,
+    // 项目标识，与 SBOM 的 metadata.component 一致
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<ProjectIdentity>,
+    // 合并进来的项目配置（rustpj.toml）在压缩包内的路径
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_config: Option<String>,
+    // 加载 advisory DB 时因无法解析而跳过的文件（--strict-db 时加载直接失败）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub db_warnings: Vec<DbWarning>,
+    // 整个 lockfile 的依赖图统计（不受 --package 影响）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub graph_stats: Option<GraphStats>,
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct PackageReport {
     pub package_name: String,
     pub package_version: String,
+    // 该包所有漏洞中最高的严重程度；都没有评级时为 null
+    #[serde(serialize_with = "Severity::serialize_known")]
+    pub max_severity: Severity,
+    // 直接 / 传递依赖（工作区成员自身为空），以及从工作区成员到该包的最短依赖路径（name@version）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependency_kind: Option<DependencyKind>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependency_path: Vec<String>,
+    // 来自私有 registry 或 git 时的来源：`[registries]` 中配置的名称，否则为 lockfile 中的 source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    // 该包的 `[[package]]` 块在 Cargo.lock 中的起始行（从 1 开始），供编辑器跳转；无法确定时为 null
+    pub lockfile_line: Option<usize>,
     pub advisories: Vec<AdvisoryFinding>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct AdvisoryFinding {
     pub id: String,
+    // advisory 所在的集合：`crates`（crates.io 上的包）或 `rust`（工具链自身，如 std）
+    pub collection: Collection,
     pub description: String,
-    pub severity: Option<String>,
+    // 没有 CVSS 分数时为 null
+    #[serde(serialize_with = "Severity::serialize_known")]
+    pub severity: Severity,
+    // 决定上游严重程度的 CVSS 分数来源，如 `RUSTSEC-2020-0001 CVSS v3.1`；没有分数时为空
+    // （被 severity_overrides 覆盖时仍指向 upstream_severity 的来源）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity_source: Option<String>,
+    // severity_source 所指向量的 CVSS 基础分（0.0-10.0），供 --min-cvss 判定；没有分数时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cvss_score: Option<f64>,
+    // advisory 限定的操作系统 / CPU 架构，为空表示不限
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub affected_os: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub affected_arch: Vec<String>,
+    // 被 severity_overrides 覆盖前的原始严重程度；上游没有评级时为 null，没有覆盖时省略
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "Severity::serialize_known_opt")]
+    pub upstream_severity: Option<Severity>,
+    // 逗号拼接的旧格式，保留以兼容现有使用者
     pub unaffected_versions: String,
     pub patched_versions: Option<String>,
+    // 每个版本要求单独一项
+    pub unaffected: Vec<String>,
+    pub patched: Vec<String>,
+    // 由 patched/unaffected 反推出的受影响区间 [introduced, fixed)
+    pub affected_ranges: Vec<AffectedRange>,
+    // `high`：版本落在 advisory 明确声明的受影响区间内；`assumed`：只是不在 patched/unaffected 中
+    pub confidence: Confidence,
     pub references: Vec<String>,
+    // advisory 的 categories（如 `memory-corruption`）与 keywords
+    pub categories: Vec<String>,
+    pub keywords: Vec<String>,
+    // 没有任何已发布的修复版本，通常只能替换该 crate
+    pub no_fix_available: bool,
+    // 修复建议；没有修复版本时指向 advisory 引用中的替代 crate 或 fork
+    pub remediation: String,
+    // registry 中的最新版本（--online）；离线时为空，表示未知
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_release: Option<LatestRelease>,
+    // 分诊结论（来自 --config 的 ignore 列表），未分诊时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<Analysis>,
+    // 已出现在 --baseline 报告中（按 package、version、id 匹配），视为已接受的存量，不参与退出码判定
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub in_baseline: bool,
+}
+
+/// 默认是 crates.io 上的空条目（rustsec 的 Collection 没有 Default，只能手写）
+impl Default for AdvisoryFinding {
+    fn default() -> Self {
+        AdvisoryFinding {
+            id: String::new(),
+            collection: Collection::Crates,
+            description: String::new(),
+            severity: Severity::default(),
+            severity_source: None,
+            cvss_score: None,
+            affected_os: Vec::new(),
+            affected_arch: Vec::new(),
+            upstream_severity: None,
+            unaffected_versions: String::new(),
+            patched_versions: None,
+            unaffected: Vec::new(),
+            patched: Vec::new(),
+            affected_ranges: Vec::new(),
+            confidence: Confidence::default(),
+            references: Vec::new(),
+            categories: Vec::new(),
+            keywords: Vec::new(),
+            no_fix_available: false,
+            remediation: String::new(),
+            latest_release: None,
+            analysis: None,
+            in_baseline: false,
+        }
+    }
+}
+
+/// crate 在 registry 中最新发布的版本
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LatestRelease {
+    pub version: String,
+    // 已安装的就是最新版本且没有修复版本：升级解决不了，只能替换或等待上游
+    pub unfixable_by_upgrade: bool,
+}
+
+// `introduced` absent means "from the first release", `fixed` absent means "no fix yet"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AffectedRange {
+    pub introduced: Option<String>,
+    pub fixed: Option<String>,
+}
+
+/// 判定某版本受影响的依据
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    // 版本落在 `[affected.functions]` 声明的版本范围内
+    High,
+    // advisory 没有声明受影响范围，或版本不在其中：只因为不在 patched/unaffected 中
+    #[default]
+    Assumed,
+}
+
+/// 按 advisory 类别过滤漏洞：`only` 非空时只保留带有其中某个类别的漏洞，`exclude` 中的类别总是排除
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CategoryFilter {
+    pub only: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl CategoryFilter {
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn allows(&self, categories: &[String]) -> bool {
+        (self.only.is_empty() || categories.iter().any(|c| self.only.contains(c)))
+            && !categories.iter().any(|c| self.exclude.contains(c))
+    }
+}
+
+impl std::fmt::Display for CategoryFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if !self.only.is_empty() {
+            parts.push(format!("only {}", self.only.join(", ")));
+        }
+        if !self.exclude.is_empty() {
+            parts.push(format!("exclude {}", self.exclude.join(", ")));
+        }
+        f.write_str(&parts.join("; "))
+    }
+}
+
+impl AdvisoryFinding {
+    /// 从 advisory 创建漏洞发现记录
+    pub fn from_advisory(advisory: &Advisory) -> Self {
+        let unaffected: Vec<String> = advisory.versions.unaffected().iter().map(|v| v.to_string()).collect();
+        let patched: Vec<String> = advisory.versions.patched().iter().map(|v| v.to_string()).collect();
+        let affected = advisory.affected.as_ref();
+        let remediation = remediation(advisory, &patched);
+        let affected_ranges = rustsec::osv::ranges_for_advisory(&advisory.versions)
+            .into_iter()
+            .map(|range| AffectedRange {
+                introduced: range.introduced.map(|v| v.to_string()),
+                fixed: range.fixed.map(|v| v.to_string()),
+            })
+            .collect();
+
+        AdvisoryFinding {
+            id: advisory.metadata.id.to_string(),
+            collection: advisory.metadata.collection.unwrap_or(Collection::Crates),
+            description: advisory.metadata.description.clone(),
+            severity: advisory.severity().map_or(Severity::Unknown, Severity::from),
+            severity_source: advisory.metadata.cvss.as_ref().map(|cvss| {
+                format!("{} CVSS v3.{}", advisory.metadata.id, cvss.minor_version)
+            }),
+            cvss_score: advisory.metadata.cvss.as_ref().map(|cvss| cvss.score().value()),
+            upstream_severity: None,
+            affected_os: affected.map(|a| a.os.iter().map(|os| os.to_string()).collect()).unwrap_or_default(),
+            affected_arch: affected.map(|a| a.arch.iter().map(|arch| arch.to_string()).collect()).unwrap_or_default(),
+            unaffected_versions: unaffected.join(", "),
+            patched_versions: (!patched.is_empty()).then(|| patched.join(", ")),
+            no_fix_available: patched.is_empty(),
+            remediation,
+            unaffected,
+            patched,
+            affected_ranges,
+            confidence: Confidence::default(),
+            references: {
+                let mut references: Vec<String> = advisory.metadata.references.iter().map(|r| r.to_string()).collect();
+                references.sort();
+                references
+            },
+            categories: advisory.metadata.categories.iter().map(|c| c.to_string()).collect(),
+            keywords: advisory.metadata.keywords.iter().map(|k| k.as_str().to_string()).collect(),
+            latest_release: None,
+            analysis: None,
+            in_baseline: false,
+        }
+    }
+
+    /// 已被分诊为无需处理的漏洞不参与退出码判定
+    pub fn is_suppressed(&self) -> bool {
+        self.analysis.as_ref().is_some_and(|a| a.state.suppresses())
+    }
+
+    /// 既不在 baseline 中也未被分诊屏蔽，计入 `new_since_baseline`
+    pub fn is_new_since_baseline(&self) -> bool {
+        !self.in_baseline && !self.is_suppressed()
+    }
+}
+
+// Upgrade advice, or for advisories without a fix, a pointer to a replacement crate or fork
+// when the advisory links to one
+fn remediation(advisory: &Advisory, patched: &[String]) -> String {
+    if !patched.is_empty() {
+        return format!("Upgrade to {}", patched.join(" or "));
+    }
+    let package = advisory.metadata.package.as_str();
+    let replacement = advisory.metadata.references.iter().chain(&advisory.metadata.url).find(|url| {
+        let other_crate = matches!(url.host_str(), Some("crates.io" | "lib.rs"))
+            && url
+                .path_segments()
+                .and_then(|mut s| (s.next() == Some("crates")).then(|| s.next()).flatten())
+                .is_some_and(|name| !name.is_empty() && name != package);
+        other_crate || url.as_str().to_lowercase().contains("fork")
+    });
+    match replacement {
+        Some(url) => format!("No fixed release; consider replacing {} (see {})", package, url),
+        None => format!("No fixed release; consider replacing {} or removing the dependency", package),
+    }
+}
+
+// Semver order when both parse ("0.9.0" < "0.10.0"), plain string order otherwise
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    match (Version::parse(a), Version::parse(b)) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+impl VulnReport {
+    /// 按严重程度从高到低排序：包按 max_severity、再按名称和版本；包内漏洞按严重程度、再按 id
+    pub fn sort_by_severity(&mut self) {
+        for pkg in &mut self.packages {
+            pkg.advisories.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.id.cmp(&b.id)));
+            pkg.max_severity = pkg.advisories.first().map_or(Severity::Unknown, |f| f.severity);
+        }
+        self.packages.sort_by(|a, b| {
+            b.max_severity
+                .cmp(&a.max_severity)
+                .then_with(|| a.package_name.cmp(&b.package_name))
+                .then_with(|| compare_versions(&a.package_version, &b.package_version))
+        });
+    }
+
+    /// 规范化输出（--canonical）：包按名称、版本排序，漏洞按 id 排序，并去掉时间戳和耗时，
+    /// 使相同输入的两次扫描得到逐字节相同的报告
+    pub fn canonicalize(&mut self) {
+        let by_name_version = |a: &PackageReport, b: &PackageReport| {
+            a.package_name.cmp(&b.package_name).then_with(|| compare_versions(&a.package_version, &b.package_version))
+        };
+        self.projects.sort_by(|a, b| a.lockfile_path.cmp(&b.lockfile_path));
+        let project_packages = self.projects.iter_mut().map(|p| &mut p.packages);
+        let lists = [&mut self.packages, &mut self.not_applicable_to_target, &mut self.excluded_by_category];
+        for list in lists.into_iter().chain(project_packages) {
+            for pkg in list.iter_mut() {
+                pkg.advisories.sort_by(|a, b| a.id.cmp(&b.id));
+            }
+            list.sort_by(by_name_version);
+        }
+        self.metadata.generated_at.clear();
+        self.metadata.timings.clear();
+    }
+
+    /// 填写报告中描述输入的字段（项目标识、lockfile 格式版本、各包的行号）；scan、batch 与 serve 共用
+    pub fn describe_input(&mut self, projects: &[LockDiscovery], project: &ProjectIdentity) {
+        self.metadata.lockfile_version = common_resolve_version(projects);
+        self.metadata.project = Some(project.clone());
+        // With several lockfiles a merged package has no single line; the per-project reports carry them
+        if let [project] = projects {
+            self.set_lockfile_lines(&project.lines);
+        }
+    }
+
+    /// 按 Cargo.lock 的行号填写各包的 `lockfile_line`（有漏洞的包及不适用 / 被排除的包）
+    pub fn set_lockfile_lines(&mut self, lines: &LockfileLines) {
+        let lists = [&mut self.packages, &mut self.not_applicable_to_target, &mut self.excluded_by_category];
+        for package in lists.into_iter().flatten() {
+            package.lockfile_line = lines.line_of(&package.package_name, &package.package_version);
+        }
+    }
+
+    /// 是否存在未被分诊排除、不在 baseline 中、且严重程度不低于 threshold 的漏洞
+    pub fn has_actionable_at_or_above(&self, threshold: Severity) -> bool {
+        self.packages
+            .iter()
+            .flat_map(|p| &p.advisories)
+            .filter(|f| !f.is_suppressed() && !f.in_baseline)
+            .any(|f| f.severity >= threshold)
+    }
 }
 
 #[derive(Debug, Default, Serialize)]
 pub struct Summary {
     pub total_vulnerabilities: usize,
+    // 不同 advisory id 的数量；同一个 advisory 影响多个包（或版本）时只计一次
+    pub unique_advisories: usize,
+    // 至少有一个漏洞的包的数量
+    pub vulnerable_packages: usize,
     pub by_severity: SeverityCounts,
+    // 按严重程度加权的风险分，便于长期跟踪趋势
+    pub risk_score: u64,
+    // 没有修复版本的漏洞数量
+    pub unfixable: usize,
+    // 因类别过滤而排除的漏洞数量
+    pub filtered_by_category: usize,
+    // 使用预发布版本、来自 git 仓库的依赖数量
+    pub prerelease_packages: usize,
+    pub git_packages: usize,
+    // 不在 --baseline 报告中的漏洞数量；未指定 --baseline 时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_since_baseline: Option<usize>,
+    // 按直接依赖汇总的漏洞，最严重的在前
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub by_direct_dependency: Vec<DirectDependencySummary>,
+}
+
+/// 经由某个直接依赖引入的漏洞：升级它能解决哪些问题
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirectDependencySummary {
+    pub package_name: String,
+    pub package_version: String,
+    // 经由它可以到达的漏洞数量及其中最高的严重程度
+    pub findings: usize,
+    #[serde(serialize_with = "Severity::serialize_known")]
+    pub max_severity: Severity,
+    // 经由它可以到达的有漏洞的包（name@version），包括它自己
+    pub vulnerable_packages: Vec<String>,
+    // 其中同时也经由其他直接依赖到达的漏洞数量；这些漏洞在每个直接依赖下都计数
+    pub shared_findings: usize,
+}
+
+// Attributes each vulnerable package to the direct dependencies that pull it in. A
+// package reachable through several of them counts under each, and `shared_findings`
+// says how much of a dependency's count is such overlap.
+fn by_direct_dependency(graph: &DependencyGraph, vulnerable: &[(usize, &[AdvisoryFinding])]) -> Vec<DirectDependencySummary> {
+    let mut by_direct: BTreeMap<usize, DirectDependencySummary> = BTreeMap::new();
+    for &(node, findings) in vulnerable {
+        let package = graph.package(node);
+        let direct = graph.direct_dependencies_reaching(node);
+        for &d in &direct {
+            let entry = by_direct.entry(d).or_insert_with(|| DirectDependencySummary {
+                package_name: graph.package(d).name.to_string(),
+                package_version: graph.package(d).version.to_string(),
+                findings: 0,
+                max_severity: Severity::Unknown,
+                vulnerable_packages: Vec::new(),
+                shared_findings: 0,
+            });
+            entry.findings += findings.len();
+            entry.max_severity = findings.iter().map(|f| f.severity).fold(entry.max_severity, Severity::max);
+            entry.vulnerable_packages.push(format!("{}@{}", package.name, package.version));
+            if direct.len() > 1 {
+                entry.shared_findings += findings.len();
+            }
+        }
+    }
+    let mut summaries: Vec<DirectDependencySummary> = by_direct.into_values().collect();
+    for summary in &mut summaries {
+        summary.vulnerable_packages.sort();
+        summary.vulnerable_packages.dedup();
+    }
+    summaries.sort_by(|a, b| {
+        b.max_severity
+            .cmp(&a.max_severity)
+            .then_with(|| b.findings.cmp(&a.findings))
+            .then_with(|| a.package_name.cmp(&b.package_name))
+            .then_with(|| compare_versions(&a.package_version, &b.package_version))
+    });
+    summaries
+}
+
+/// 计算 risk_score 的权重，可在配置文件的 `[risk_weights]` 中覆盖
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskWeights {
+    pub critical: u64,
+    pub high: u64,
+    pub medium: u64,
+    pub low: u64,
+    pub unknown: u64,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        RiskWeights { critical: 10, high: 5, medium: 2, low: 1, unknown: 0 }
+    }
+}
+
+impl RiskWeights {
+    pub fn score(&self, counts: &SeverityCounts) -> u64 {
+        self.critical * counts.critical as u64
+            + self.high * counts.high as u64
+            + self.medium * counts.medium as u64
+            + self.low * counts.low as u64
+            + self.unknown * counts.unknown as u64
+    }
 }
 
 #[derive(Debug, Default, Serialize)]
@@ -56,39 +633,366 @@ pub struct SeverityCounts {
     pub unknown: usize,
 }
 
+impl SeverityCounts {
+    // CVSS "none" has no bucket of its own and is counted as unknown
+    pub fn add(&mut self, severity: Severity) {
+        match severity {
+            Severity::Critical => self.critical += 1,
+            Severity::High => self.high += 1,
+            Severity::Medium => self.medium += 1,
+            Severity::Low => self.low += 1,
+            Severity::None | Severity::Unknown => self.unknown += 1,
+        }
+    }
+
+    /// 累加另一份统计（批量扫描汇总）
+    pub fn merge(&mut self, other: &SeverityCounts) {
+        self.critical += other.critical;
+        self.high += other.high;
+        self.medium += other.medium;
+        self.low += other.low;
+        self.unknown += other.unknown;
+    }
+}
+
+/// 扫描报告及各阶段耗时
+#[derive(Debug)]
+pub struct ScanOutcome {
+    pub report: VulnReport,
+    pub timings: Timings,
+}
+
 pub struct Scanner {
-    db: Database,
+    // 所有 advisory DB 合并后的 advisory，同一 id 只有一条
+    advisories: Vec<Advisory>,
+    dbs: Vec<AdvisoryDbInfo>,
+    // 宽松加载时跳过的 advisory 文件
+    db_warnings: Vec<DbWarning>,
+    // advisory id -> 本地重新评定的严重程度
+    severity_overrides: BTreeMap<String, Severity>,
+    target: Option<&'static Platform>,
+    include_withdrawn: bool,
+    packages: Option<PackageSelection>,
+    categories: CategoryFilter,
+    exclude_workspace: bool,
+    // 其他数据库（OSV）中同一漏洞的 CVSS 分数
+    alias_scores: AliasScores,
+    // 参与扫描的 advisory 集合，默认只有 crates
+    collections: Vec<Collection>,
+    // 项目使用的 Rust 版本，`rust` 集合的 advisory 与它比对
+    toolchain: Option<Version>,
+    // 配置文件 `[registries]`
+    registries: RegistryMap,
 }
 
 impl Scanner {
     /// 从本地 git 仓库加载 advisory DB
     /// path 应指向一个 RustSec/advisory-db 的克隆
     pub fn new(db_path: impl AsRef<Path>) -> Result<Self> {
+        Self::new_multi(&[db_path])
+    }
+
+    /// 加载多个 advisory DB（相同的 RustSec 目录结构，例如上游 DB 加上内部维护的 fork）并合并。
+    /// 同一 id 的 advisory 以后面的路径为准，可以在本地修订上游的 advisory；内容不同时给出
+    /// `advisory-conflict` 警告
+    pub fn new_multi(db_paths: &[impl AsRef<Path>]) -> Result<Self> {
+        Self::new_multi_from(db_paths, AdvisorySource::default())
+    }
+
+    /// 同 `new_multi`，指定加载方式
+    pub fn new_multi_from(db_paths: &[impl AsRef<Path>], source: AdvisorySource) -> Result<Self> {
+        if db_paths.is_empty() {
+            anyhow::bail!("no advisory DB path given");
+        }
+        let mut databases = Vec::new();
+        for path in db_paths {
+            let path = path.as_ref();
+            if !path.exists() {
+                anyhow::bail!("Advisory DB path does not exist: {}", path.display());
+            }
+            let name = path.display().to_string();
+            databases.push((name.clone(), source.load(path, &name)?));
+        }
+        Ok(Self::from_databases(databases))
+    }
+
+    /// 从 advisory DB 的指定提交加载，用于可复现的扫描
+    /// 为了不改动共享的 DB 克隆，先把仓库克隆到临时目录再检出该提交
+    pub fn new_at_rev(db_path: impl AsRef<Path>, rev: &str) -> Result<Self> {
+        Self::new_at_rev_from(db_path, rev, AdvisorySource::default())
+    }
+
+    /// 同 `new_at_rev`，指定加载方式
+    pub fn new_at_rev_from(db_path: impl AsRef<Path>, rev: &str, source: AdvisorySource) -> Result<Self> {
         let path = db_path.as_ref();
         if !path.exists() {
             anyhow::bail!("Advisory DB path does not exist: {}", path.display());
         }
 
-        let repo = Repository::open(path)
+        let origin = git2::Repository::open(path)
             .context("failed to open advisory DB git repository")?;
-        
-        let db = Database::load_from_repo(&repo)
-            .context("failed to load advisory database")?;
+        let commit_id = origin
+            .revparse_single(rev)
+            .and_then(|obj| obj.peel_to_commit())
+            .map(|commit| commit.id())
+            .map_err(|_| anyhow::anyhow!("commit '{}' does not exist in advisory DB {}", rev, path.display()))?;
+
+        let checkout = tempfile::tempdir().context("failed to create temporary checkout directory")?;
+        let source_url = path
+            .canonicalize()
+            .context("failed to resolve advisory DB path")?;
+        let repo = git2::Repository::clone(&source_url.to_string_lossy(), checkout.path())
+            .context("failed to clone advisory DB for pinned checkout")?;
+        let commit = repo.find_commit(commit_id).context("pinned commit missing from clone")?;
+        repo.checkout_tree(commit.as_object(), Some(git2::build::CheckoutBuilder::new().force()))
+            .context("failed to check out pinned advisory DB commit")?;
+        repo.set_head_detached(commit_id)
+            .context("failed to move HEAD to pinned advisory DB commit")?;
+
+        // The database lives in memory after loading, so the checkout can go away
+        let name = path.display().to_string();
+        let db = source.load(checkout.path(), &name)?;
+        Ok(Self::from_databases(vec![(name, db)]))
+    }
+
+    // Later databases override earlier ones advisory by advisory; a fork that carries
+    // upstream's advisories unchanged is not a conflict
+    fn from_databases(databases: Vec<(String, LoadedDb)>) -> Self {
+        let mut dbs = Vec::new();
+        let mut db_warnings = Vec::new();
+        let mut advisories: Vec<Advisory> = Vec::new();
+        // advisory id -> (index into advisories, the DB it came from)
+        let mut by_id: HashMap<String, (usize, String)> = HashMap::new();
+        for (path, db) in databases {
+            dbs.push(AdvisoryDbInfo { path: path.clone(), commit: db.commit });
+            if !db.warnings.is_empty() {
+                crate::console::warn(
+                    "advisory-db-invalid",
+                    format!(
+                        "{} advisory file(s) in {} could not be loaded and were skipped (first: {}: {}); --strict-db makes this an error",
+                        db.warnings.len(),
+                        path,
+                        db.warnings[0].path,
+                        db.warnings[0].error
+                    ),
+                );
+                db_warnings.extend(db.warnings);
+            }
+            for advisory in db.advisories {
+                let id = advisory.metadata.id.to_string();
+                match by_id.get_mut(&id) {
+                    Some((index, from)) => {
+                        if advisories[*index] != advisory {
+                            crate::console::warn(
+                                "advisory-conflict",
+                                format!("{} differs between {} and {}; using the one from {}", id, from, path, path),
+                            );
+                        }
+                        advisories[*index] = advisory;
+                        *from = path.clone();
+                    }
+                    None => {
+                        by_id.insert(id, (advisories.len(), path.clone()));
+                        advisories.push(advisory);
+                    }
+                }
+            }
+        }
+
+        Scanner {
+            advisories,
+            dbs,
+            db_warnings,
+            severity_overrides: BTreeMap::new(),
+            target: None,
+            include_withdrawn: false,
+            packages: None,
+            categories: CategoryFilter::default(),
+            exclude_workspace: false,
+            alias_scores: AliasScores::default(),
+            collections: vec![Collection::Crates],
+            toolchain: None,
+            registries: RegistryMap::default(),
+        }
+    }
+
+    /// 选择参与扫描的 advisory 集合（见 `Collection`）。`crates` 按包名匹配 lockfile；
+    /// `rust` 是工具链自身的 advisory，只与 `with_toolchain` 给出的 Rust 版本比对，从不匹配同名 crate
+    pub fn with_collections(mut self, collections: Vec<Collection>) -> Self {
+        self.collections = collections;
+        self
+    }
+
+    /// 项目构建所用的 Rust 版本（如 1.75.0），供 `rust` 集合的 advisory 比对
+    pub fn with_toolchain(mut self, version: Version) -> Self {
+        self.toolchain = Some(version);
+        self
+    }
+
+    /// 按 advisory id 覆盖严重程度；原始值保留在 `upstream_severity`
+    pub fn with_severity_overrides(mut self, overrides: BTreeMap<String, Severity>) -> Self {
+        self.severity_overrides = overrides;
+        self
+    }
+
+    /// 同时参考 OSV 文档中的 CVSS 分数（按 advisory id 和 aliases 匹配），取最高的严重程度
+    pub fn with_alias_scores(mut self, scores: AliasScores) -> Self {
+        self.alias_scores = scores;
+        self
+    }
+
+    /// 在报告的 `withdrawn` 中列出本会命中的已撤回 advisory
+    pub fn with_withdrawn(mut self, include: bool) -> Self {
+        self.include_withdrawn = include;
+        self
+    }
+
+    /// 只扫描匹配的包（见 `PackageSelection`）；lockfile 中没有的包名会让扫描失败
+    pub fn with_packages(mut self, selection: PackageSelection) -> Self {
+        self.packages = Some(selection);
+        self
+    }
+
+    /// 按 advisory 类别过滤；被排除的漏洞记录在 `excluded_by_category`，不计入 summary
+    pub fn with_categories(mut self, filter: CategoryFilter) -> Self {
+        self.categories = filter;
+        self
+    }
+
+    /// 不扫描工作区成员（lockfile 中没有 source 的本地包），避免内部 crate 与 advisory 同名时误报；
+    /// 跳过的成员列在报告的 `excluded_workspace_members` 中
+    pub fn with_workspace_excluded(mut self, exclude: bool) -> Self {
+        self.exclude_workspace = exclude;
+        self
+    }
+
+    /// 私有 registry 配置：决定报告中包的 `source`，并跳过 `advisories = false` 的 registry
+    pub fn with_registries(mut self, registries: RegistryMap) -> Self {
+        self.registries = registries;
+        self
+    }
+
+    /// 只报告适用于该目标平台的漏洞（如 `x86_64-unknown-linux-gnu`）
+    pub fn with_target(mut self, triple: &str) -> Result<Self> {
+        let platform = Platform::find(triple)
+            .ok_or_else(|| anyhow::anyhow!("unknown target triple: {}", triple))?;
+        self.target = Some(platform);
+        Ok(self)
+    }
+
+    // An advisory scoped to other OSes/architectures cannot affect the target build
+    fn applies_to_target(&self, advisory: &Advisory) -> bool {
+        let (Some(target), Some(affected)) = (self.target, &advisory.affected) else {
+            return true;
+        };
+        (affected.os.is_empty() || affected.os.contains(&target.target_os))
+            && (affected.arch.is_empty() || affected.arch.contains(&target.target_arch))
+    }
+
+    /// 当前加载的 advisory DB（有多个时为第一个）的 HEAD 提交
+    pub fn db_commit(&self) -> Option<String> {
+        self.dbs.first().and_then(|db| db.commit.clone())
+    }
+
+    /// 加载的各个 advisory DB，按加载顺序
+    pub fn advisory_dbs(&self) -> &[AdvisoryDbInfo] {
+        &self.dbs
+    }
+
+    /// 宽松加载时跳过的 advisory 文件
+    pub fn db_warnings(&self) -> &[DbWarning] {
+        &self.db_warnings
+    }
 
-        Ok(Scanner { db })
+    /// 受影响范围与 patched / unaffected 自相矛盾的 advisory：(id, 说明)，供 `doctor` 列出
+    pub fn range_conflicts(&self) -> Vec<(String, String)> {
+        self.advisories
+            .iter()
+            .filter_map(|advisory| Some((advisory.metadata.id.to_string(), range_conflict(advisory)?)))
+            .collect()
     }
 
-    /// 扫描指定的 Cargo.lock 文件
+    /// 已加载的 advisory 数量（合并后）
+    pub fn advisory_count(&self) -> usize {
+        self.advisories.len()
+    }
+
+    /// 读取磁盘上的 Cargo.lock 并扫描
+    pub fn scan_from_path(&self, lockfile_path: impl AsRef<Path>) -> Result<VulnReport> {
+        Ok(self.scan_from_path_timed(lockfile_path)?.report)
+    }
+
+    /// 同 `scan_from_path`，并记录 discover / scan 两个阶段的耗时
+    pub fn scan_from_path_timed(&self, lockfile_path: impl AsRef<Path>) -> Result<ScanOutcome> {
+        let path = lockfile_path.as_ref();
+        let mut timings = Timings::new();
+        let lockfile = timings
+            .time("discover", || crate::get_lockfile::load_lockfile(path))
+            .with_context(|| format!("failed to parse lockfile: {}", path.display()))?;
+        let mut report = timings.time("scan", || self.scan_lockfile(&lockfile))?;
+        report.set_lockfile_lines(&LockfileLines::load(path));
+        report.metadata.timings = timings.to_map();
+        Ok(ScanOutcome { report, timings })
+    }
+
+    /// 扫描已解析的 Cargo.lock
+    ///
+    /// 只查询构造时已加载到内存中的 advisory DB，不访问网络或文件系统，
+    /// 可以放心地在沙箱环境中调用
     pub fn scan_lockfile(&self, lockfile: &Lockfile) -> Result<VulnReport> {
+        // Annotations come from the whole lockfile: a --package selection drops the roots
+        let graph = DependencyGraph::from_lockfile(lockfile);
+        let selected;
+        let lockfile = match &self.packages {
+            Some(selection) => {
+                selected = selection.apply(lockfile)?;
+                &selected
+            }
+            None => lockfile,
+        };
+
         // Aggregate findings per package
         let mut package_reports = Vec::new();
+        // graph node -> index into package_reports
+        let mut vulnerable_nodes = Vec::new();
         let mut summary = Summary::default();
+        let mut used_overrides = HashSet::new();
 
         // Pre-index advisories by package to avoid O(N*M)
         let mut by_package: HashMap<String, Vec<&Advisory>> = HashMap::new();
-        for adv in self.db.iter() {
-            // Skip withdrawn and informational advisories; keep only actionable
-            if adv.metadata.withdrawn.is_some() || adv.metadata.informational.is_some() {
+        let mut informational_by_package: HashMap<String, Vec<&Advisory>> = HashMap::new();
+        let mut withdrawn_by_package: HashMap<String, Vec<&Advisory>> = HashMap::new();
+        // Toolchain advisories name Rust's own components (std, cargo, rustdoc), which say
+        // nothing about a crate that happens to share the name
+        let mut toolchain_advisories: BTreeMap<String, Vec<&Advisory>> = BTreeMap::new();
+        let (mut skipped_withdrawn, mut skipped_informational) = (0, 0);
+        for adv in self.advisories.iter() {
+            let collection = adv.metadata.collection.unwrap_or(Collection::Crates);
+            if !self.collections.contains(&collection) {
+                continue;
+            }
+            if collection == Collection::Rust {
+                if adv.metadata.withdrawn.is_none() && adv.metadata.informational.is_none() {
+                    toolchain_advisories.entry(adv.metadata.package.to_string()).or_default().push(adv);
+                }
+                continue;
+            }
+            // Withdrawn and informational advisories never produce findings; both are kept
+            // aside so they can be reported separately
+            if adv.metadata.withdrawn.is_some() {
+                skipped_withdrawn += 1;
+                withdrawn_by_package
+                    .entry(adv.metadata.package.to_string())
+                    .or_default()
+                    .push(adv);
+                continue;
+            }
+            if adv.metadata.informational.is_some() {
+                skipped_informational += 1;
+                informational_by_package
+                    .entry(adv.metadata.package.to_string())
+                    .or_default()
+                    .push(adv);
                 continue;
             }
             by_package
@@ -98,38 +1002,146 @@ impl Scanner {
         }
 
         // Scan each package against its advisories
-        for pkg in &lockfile.packages {
-            let mut advisories_for_pkg = Vec::new();
-            if let Some(advs) = by_package.get(pkg.name.as_str()) {
-                for advisory in advs {
-                    if self.is_version_affected(&pkg.version, advisory) {
-                        let advisory_find = self.create_advisory_finding(advisory);
-
-                        // Update severity summary
-                        if let Some(sev) = &advisory_find.severity {
-                            match sev.to_uppercase().as_str() {
-                                "CRITICAL" => summary.by_severity.critical += 1,
-                                "HIGH" => summary.by_severity.high += 1,
-                                "MEDIUM" => summary.by_severity.medium += 1,
-                                "LOW" => summary.by_severity.low += 1,
-                                _ => summary.by_severity.unknown += 1,
-                            }
-                        } else {
-                            summary.by_severity.unknown += 1;
-                        }
-
-                        advisories_for_pkg.push(advisory_find);
+        let mut not_applicable = Vec::new();
+        let mut excluded_by_category = Vec::new();
+        let mut informational = Vec::new();
+        let mut withdrawn = Vec::new();
+        // alias (CVE, GHSA, ...) -> ids of live findings carrying it
+        let mut live_aliases: HashMap<String, Vec<String>> = HashMap::new();
+        let excluded_members: Vec<&Package> = if self.exclude_workspace { workspace_members(lockfile) } else { Vec::new() };
+        let is_excluded = |pkg: &Package| {
+            excluded_members.iter().any(|m| m.name == pkg.name && m.version == pkg.version && m.source == pkg.source)
+        };
+        let mut skipped_registries = BTreeMap::new();
+        let total = lockfile.packages.len();
+        for (index, pkg) in lockfile.packages.iter().enumerate() {
+            crate::progress::emit(crate::progress::ProgressEvent::PackageScanned {
+                index: index + 1,
+                total,
+                name: pkg.name.to_string(),
+            });
+            if is_excluded(pkg) {
+                continue;
+            }
+            if self.registries.skips_advisories(pkg.source.as_ref()) {
+                let name = self.registries.label(pkg.source.as_ref()).unwrap_or_default();
+                *skipped_registries.entry(name).or_insert(0) += 1;
+                continue;
+            }
+            if self.include_withdrawn {
+                for advisory in withdrawn_by_package.get(pkg.name.as_str()).into_iter().flatten() {
+                    if !self.is_version_affected(&pkg.version, advisory) || !self.applies_to_target(advisory) {
+                        continue;
                     }
+                    withdrawn.push(WithdrawnFinding {
+                        package_name: pkg.name.to_string(),
+                        package_version: pkg.version.to_string(),
+                        id: advisory.metadata.id.to_string(),
+                        withdrawn: advisory.metadata.withdrawn.as_ref().map_or_else(String::new, |d| d.to_string()),
+                        aliases: advisory.metadata.aliases.iter().map(|a| a.to_string()).collect(),
+                        superseded_by: Vec::new(),
+                    });
                 }
             }
 
-            if !advisories_for_pkg.is_empty() {
-                package_reports.push(PackageReport {
+            for advisory in informational_by_package.get(pkg.name.as_str()).into_iter().flatten() {
+                if !self.is_version_affected(&pkg.version, advisory) || !self.applies_to_target(advisory) {
+                    continue;
+                }
+                informational.push(InformationalNotice {
                     package_name: pkg.name.to_string(),
                     package_version: pkg.version.to_string(),
-                    advisories: advisories_for_pkg,
+                    id: advisory.metadata.id.to_string(),
+                    category: advisory.metadata.informational.as_ref().map_or_else(String::new, |i| i.to_string()),
+                    title: advisory.metadata.title.clone(),
+                });
+            }
+
+            let advs = by_package.get(pkg.name.as_str()).map_or(&[][..], Vec::as_slice);
+            let (mut advisories_for_pkg, other_targets) = self.match_advisories(&pkg.version, advs, &mut used_overrides);
+            let (kept, excluded): (Vec<_>, Vec<_>) =
+                advisories_for_pkg.into_iter().partition(|f| self.categories.allows(&f.categories));
+            advisories_for_pkg = kept;
+            summary.filtered_by_category += excluded.len();
+            for finding in &advisories_for_pkg {
+                crate::progress::emit(crate::progress::ProgressEvent::Finding {
+                    package: pkg.name.to_string(),
+                    version: pkg.version.to_string(),
+                    id: finding.id.clone(),
+                    severity: finding.severity,
                 });
             }
+            for advisory in advs.iter().filter(|a| advisories_for_pkg.iter().any(|f| f.id == a.metadata.id.as_str())) {
+                for alias in &advisory.metadata.aliases {
+                    live_aliases.entry(alias.to_string()).or_default().push(advisory.metadata.id.to_string());
+                }
+            }
+            for finding in &advisories_for_pkg {
+                // Update severity summary
+                summary.by_severity.add(finding.severity);
+                if finding.no_fix_available {
+                    summary.unfixable += 1;
+                }
+            }
+
+            let package_report = |advisories| {
+                let node = graph.node_of(pkg);
+                PackageReport {
+                    package_name: pkg.name.to_string(),
+                    package_version: pkg.version.to_string(),
+                    max_severity: Severity::Unknown,
+                    dependency_kind: node.and_then(|n| graph.kind(n)),
+                    dependency_path: node
+                        .and_then(|n| graph.shortest_path(n))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|n| format!("{}@{}", graph.package(n).name, graph.package(n).version))
+                        .collect(),
+                    source: self.registries.label(pkg.source.as_ref()),
+                    lockfile_line: None,
+                    advisories,
+                }
+            };
+            if !advisories_for_pkg.is_empty() {
+                if let Some(node) = graph.node_of(pkg) {
+                    vulnerable_nodes.push((node, package_reports.len()));
+                }
+                package_reports.push(package_report(advisories_for_pkg));
+            }
+            if !other_targets.is_empty() {
+                not_applicable.push(package_report(other_targets));
+            }
+            if !excluded.is_empty() {
+                excluded_by_category.push(package_report(excluded));
+            }
+        }
+
+        let mut toolchain = Vec::new();
+        match &self.toolchain {
+            Some(version) => {
+                for (component, advisories) in &toolchain_advisories {
+                    let (mut findings, _) = self.match_advisories(version, advisories, &mut used_overrides);
+                    if findings.is_empty() {
+                        continue;
+                    }
+                    findings.sort_by(|a, b| a.id.cmp(&b.id));
+                    toolchain.push(PackageReport {
+                        package_name: component.clone(),
+                        package_version: version.to_string(),
+                        max_severity: findings.iter().map(|f| f.severity).max().unwrap_or(Severity::Unknown),
+                        dependency_kind: None,
+                        dependency_path: vec![],
+                        source: None,
+                        lockfile_line: None,
+                        advisories: findings,
+                    });
+                }
+            }
+            None if self.collections.contains(&Collection::Rust) => crate::console::warn(
+                "toolchain-unknown",
+                "the rust advisory collection is selected but no --toolchain version was given; toolchain advisories were not checked",
+            ),
+            None => {}
         }
 
         // Count total advisories discovered across all packages
@@ -137,82 +1149,199 @@ impl Scanner {
             .iter()
             .map(|p| p.advisories.len())
             .sum();
+        summary.unique_advisories =
+            package_reports.iter().flat_map(|p| &p.advisories).map(|f| f.id.as_str()).collect::<HashSet<_>>().len();
+        summary.vulnerable_packages = package_reports.len();
+        summary.risk_score = RiskWeights::default().score(&summary.by_severity);
+        let vulnerable: Vec<(usize, &[AdvisoryFinding])> =
+            vulnerable_nodes.iter().map(|&(node, i)| (node, package_reports[i].advisories.as_slice())).collect();
+        summary.by_direct_dependency = by_direct_dependency(&graph, &vulnerable);
+        let prerelease_packages = hygiene::prerelease(lockfile);
+        let git_packages = hygiene::git_sourced(lockfile);
+        summary.prerelease_packages = prerelease_packages.len();
+        summary.git_packages = git_packages.len();
 
-        Ok(VulnReport { total_packages: lockfile.packages.len(), packages: package_reports, summary })
+        let mut report = VulnReport {
+            metadata: ReportMetadata {
+                tool: env!("CARGO_PKG_NAME").to_string(),
+                tool_version: env!("CARGO_PKG_VERSION").to_string(),
+                generated_at: chrono::Utc::now().to_rfc3339(),
+                advisory_db_commit: self.db_commit(),
+                advisory_dbs: self.dbs.clone(),
+                target: self.target.map(|t| t.target_triple.to_string()),
+                timings: Default::default(),
+                skipped_withdrawn,
+                skipped_informational,
+                skipped_registries,
+                package_filter: self.packages.as_ref().map(|s| s.to_string()),
+                excluded_packages: Vec::new(),
+                category_filter: (!self.categories.is_empty()).then(|| self.categories.to_string()),
+                baseline: None,
+                lockfile_version: None,
+                project: None,
+                project_config: None,
+                db_warnings: self.db_warnings.clone(),
+                graph_stats: Some(graph.stats()),
+            },
+            total_packages: lockfile.packages.len(),
+            packages: package_reports,
+            summary,
+            not_applicable_to_target: not_applicable,
+            excluded_by_category,
+            excluded_workspace_members: excluded_members.iter().map(|m| format!("{}@{}", m.name, m.version)).collect(),
+            informational,
+            toolchain,
+            prerelease_packages,
+            git_dependencies: hygiene::branch_pinned(&git_packages),
+            git_packages,
+            ..Default::default()
+        };
+        for entry in &mut withdrawn {
+            let mut superseded: Vec<String> = entry
+                .aliases
+                .iter()
+                .filter_map(|alias| live_aliases.get(alias))
+                .flatten()
+                .cloned()
+                .collect();
+            superseded.sort();
+            superseded.dedup();
+            entry.superseded_by = superseded;
+        }
+        report.withdrawn = withdrawn;
+        report.unused_severity_overrides = self
+            .severity_overrides
+            .keys()
+            .filter(|id| !used_overrides.contains(*id))
+            .cloned()
+            .collect();
+        for pkg in report.not_applicable_to_target.iter_mut().chain(&mut report.excluded_by_category) {
+            pkg.advisories.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+        report.informational.sort_by(|a, b| {
+            (&a.package_name, &a.package_version, &a.id).cmp(&(&b.package_name, &b.package_version, &b.id))
+        });
+        report.withdrawn.sort_by(|a, b| {
+            (&a.package_name, &a.package_version, &a.id).cmp(&(&b.package_name, &b.package_version, &b.id))
+        });
+        report.sort_by_severity();
+        Ok(report)
     }
 
-    /// 检查给定版本是否受某个 advisory 影响
-    fn is_version_affected(&self, version: &Version, advisory: &Advisory) -> bool {
-        // Not affected if version is explicitly in patched ranges
-        if advisory.versions.patched().iter().any(|req| req.matches(version)) {
-            return false;
+    // Findings for one package version: those that apply to the target, and those set aside
+    // for other platforms. Severity overrides are applied and recorded in `used_overrides`.
+    fn match_advisories(
+        &self,
+        version: &Version,
+        advisories: &[&Advisory],
+        used_overrides: &mut HashSet<String>,
+    ) -> (Vec<AdvisoryFinding>, Vec<AdvisoryFinding>) {
+        let mut applicable = Vec::new();
+        let mut other_targets = Vec::new();
+        for advisory in advisories {
+            let Some(confidence) = affected_confidence(version, advisory) else {
+                continue;
+            };
+            let mut finding = AdvisoryFinding::from_advisory(advisory);
+            finding.confidence = confidence;
+            self.alias_scores.apply(&mut finding, advisory);
+            if !self.applies_to_target(advisory) {
+                other_targets.push(finding);
+                continue;
+            }
+            if let Some(&severity) = self.severity_overrides.get(&finding.id) {
+                finding.upstream_severity = Some(finding.severity);
+                finding.severity = severity;
+                used_overrides.insert(finding.id.clone());
+            }
+            applicable.push(finding);
         }
+        (applicable, other_targets)
+    }
 
-        // Not affected if version matches any unaffected requirement
-        if advisory
-            .versions
-            .unaffected()
+    /// 某个 crate 的全部 advisory，不区分版本（包括已撤回和 informational 的条目）
+    pub fn advisories_for_package(&self, name: &str) -> Vec<&Advisory> {
+        // Toolchain advisories (the `rust` collection) are not about crates, whatever their name
+        self.advisories
             .iter()
-            .any(|req| req.matches(version))
-        {
-            return false;
-        }
+            .filter(|adv| adv.metadata.package.as_str() == name && adv.metadata.collection != Some(Collection::Rust))
+            .collect()
+    }
 
-        // Otherwise, assume affected
-        true
+    /// 查询某个 crate 版本受哪些漏洞影响，匹配规则与 `scan_lockfile` 相同
+    pub fn query(&self, name: &str, version: &Version) -> Vec<AdvisoryFinding> {
+        let actionable: Vec<&Advisory> = self
+            .advisories_for_package(name)
+            .into_iter()
+            .filter(|adv| adv.metadata.withdrawn.is_none() && adv.metadata.informational.is_none())
+            .collect();
+        let (mut findings, _) = self.match_advisories(version, &actionable, &mut HashSet::new());
+        findings.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.id.cmp(&b.id)));
+        findings
     }
 
-    /// 从 advisory 创建漏洞发现记录
-    fn create_advisory_finding(&self, advisory: &Advisory) -> AdvisoryFinding {
-        let unaffected_versions = advisory
-            .versions
-            .unaffected()
-            .iter()
-            .map(|v| v.to_string())
-            .collect::<Vec<_>>()
-            .join(", ");
-
-        let patched = advisory.versions.patched();
-        let patched_versions = if !patched.is_empty() {
-            Some(
-                patched
-                    .iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", "),
-            )
-        } else {
-            None
-        };
+    /// 检查给定版本是否受某个 advisory 影响
+    fn is_version_affected(&self, version: &Version, advisory: &Advisory) -> bool {
+        affected_confidence(version, advisory).is_some()
+    }
+}
 
-        AdvisoryFinding {
-            id: advisory.metadata.id.to_string(),
-            description: advisory.metadata.description.clone(),
-            severity: advisory
-                .metadata
-                .cvss
-                .as_ref()
-                .map(|c| c.severity().to_string()),
-            unaffected_versions,
-            patched_versions,
-            references: advisory
-                .metadata
-                .references
-                .iter()
-                .map(|r| r.to_string())
-                .collect(),
-        }
+// Precedence: a version matching any patched or unaffected requirement is safe, whatever
+// else the advisory says. Otherwise it is affected, with high confidence when it falls
+// within a range the advisory declares affected (`[affected.functions]`), and by
+// assumption when the advisory declares none or the version is outside all of them.
+fn affected_confidence(version: &Version, advisory: &Advisory) -> Option<Confidence> {
+    let versions = &advisory.versions;
+    if versions.patched().iter().chain(versions.unaffected()).any(|req| req.matches(version)) {
+        return None;
     }
+    let declared = declared_affected(advisory).any(|req| req.matches(version));
+    Some(if declared { Confidence::High } else { Confidence::Assumed })
+}
+
+fn declared_affected(advisory: &Advisory) -> impl Iterator<Item = &semver::VersionReq> {
+    advisory.affected.iter().flat_map(|affected| affected.functions.values().flatten())
+}
+
+// A patched or unaffected version of the advisory that one of its declared affected
+// ranges covers as well (it is treated as safe). Upstream has such advisories (a function
+// range written more broadly than the unaffected list), so this is only listed by `doctor`
+// rather than warned about on every scan. Checked at the boundary versions the
+// requirements mention, which is where two ranges start to overlap. Overlapping patched
+// and unaffected ranges never get this far: rustsec refuses such an advisory on load.
+fn range_conflict(advisory: &Advisory) -> Option<String> {
+    let versions = &advisory.versions;
+    let patched = |v: &Version| versions.patched().iter().any(|req| req.matches(v));
+    let unaffected = |v: &Version| versions.unaffected().iter().any(|req| req.matches(v));
+    let mut probes: Vec<Version> = versions
+        .patched()
+        .iter()
+        .chain(versions.unaffected())
+        .chain(declared_affected(advisory))
+        .flat_map(|req| &req.comparators)
+        .flat_map(|c| {
+            let v = Version::new(c.major, c.minor.unwrap_or(0), c.patch.unwrap_or(0));
+            [Version::new(v.major, v.minor, v.patch + 1), v]
+        })
+        .collect();
+    probes.sort();
+    probes.dedup();
+    let v = probes
+        .iter()
+        .find(|v| (patched(v) || unaffected(v)) && declared_affected(advisory).any(|req| req.matches(v)))?;
+    Some(format!("{} is {} but within a declared affected range", v, if patched(v) { "patched" } else { "unaffected" }))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{AdvisoryText, commit_advisory};
     use std::fs;
+    use std::path::PathBuf;
     use tempfile::TempDir;
 
     /// 创建一个小型测试用 advisory DB
-    fn setup_test_db() -> (TempDir, String) {
+    fn setup_test_db() -> (TempDir, PathBuf) {
         let temp_dir = tempfile::tempdir().unwrap();
         let db_path = temp_dir.path().join("advisory-db");
         fs::create_dir(&db_path).unwrap();
@@ -220,7 +1349,7 @@ mod tests {
         // 初始化 git 仓库
         git2::Repository::init(&db_path).unwrap();
         
-        (temp_dir, db_path.to_string_lossy().to_string())
+        (temp_dir, db_path)
     }
 
     // This test requires a valid RustSec advisory-db layout; ignored by default.
@@ -259,4 +1388,658 @@ mod tests {
         let result = Scanner::new("/nonexistent/path");
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_pinned_db_rev() {
+        let (_temp_dir, db_path) = setup_test_db();
+        let repo = git2::Repository::open(&db_path).unwrap();
+        let first = commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "first-crate"));
+        let second = commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0002", "second-crate"));
+
+        let pinned = Scanner::new_at_rev(&db_path, &first.to_string()).unwrap();
+        assert_eq!(pinned.advisory_count(), 1);
+        assert_eq!(pinned.db_commit(), Some(first.to_string()));
+
+        let latest = Scanner::new(&db_path).unwrap();
+        assert_eq!(latest.advisory_count(), 2);
+        assert_eq!(latest.db_commit(), Some(second.to_string()));
+
+        // The shared clone is left untouched
+        assert_eq!(repo.head().unwrap().target(), Some(second));
+
+        let err = Scanner::new_at_rev(&db_path, "0000000000000000000000000000000000000000").err().unwrap();
+        assert!(err.to_string().contains("does not exist"));
+    }
+
+    fn advisory(versions: &str) -> Advisory {
+        AdvisoryText::new("RUSTSEC-2020-0001", "demo").with_versions(versions).to_advisory()
+    }
+
+    fn range(introduced: Option<&str>, fixed: Option<&str>) -> AffectedRange {
+        AffectedRange { introduced: introduced.map(str::to_string), fixed: fixed.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_affected_ranges_without_fix() {
+        let finding = AdvisoryFinding::from_advisory(&advisory("patched = []"));
+        assert!(finding.patched.is_empty());
+        assert_eq!(finding.patched_versions, None);
+        assert_eq!(finding.affected_ranges, vec![range(None, None)]);
+    }
+
+    #[test]
+    fn test_affected_ranges_single_patch() {
+        let finding = AdvisoryFinding::from_advisory(&advisory("patched = [\">= 1.2.0\"]\nunaffected = [\"< 0.5.0\"]"));
+        assert_eq!(finding.patched, vec![">=1.2.0"]);
+        assert_eq!(finding.unaffected, vec!["<0.5.0"]);
+        assert_eq!(finding.affected_ranges, vec![range(Some("0.5.0"), Some("1.2.0"))]);
+    }
+
+    #[test]
+    fn test_no_fix_available_and_replacement() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/advisory_no_fix.md");
+        let unmaintained: Advisory = fs::read_to_string(fixture).unwrap().parse().unwrap();
+        let finding = AdvisoryFinding::from_advisory(&unmaintained);
+        assert!(finding.no_fix_available);
+        assert_eq!(
+            finding.remediation,
+            "No fixed release; consider replacing ansi_term (see https://crates.io/crates/nu-ansi-term)"
+        );
+
+        let finding = AdvisoryFinding::from_advisory(&advisory("patched = []"));
+        assert!(finding.no_fix_available);
+        assert!(finding.remediation.ends_with("or removing the dependency"));
+
+        let finding = AdvisoryFinding::from_advisory(&advisory("patched = [\">= 1.2.0\"]"));
+        assert!(!finding.no_fix_available);
+        assert_eq!(finding.remediation, "Upgrade to >=1.2.0");
+    }
+
+    #[test]
+    fn test_affected_ranges_multiple_patches() {
+        let finding = AdvisoryFinding::from_advisory(&advisory("patched = [\">= 1.8.4, < 2.0.0\", \">= 2.1.1\"]"));
+        assert_eq!(finding.patched.len(), 2);
+        assert_eq!(finding.patched_versions.as_deref(), Some(">=1.8.4, <2.0.0, >=2.1.1"));
+        assert_eq!(
+            finding.affected_ranges,
+            vec![range(None, Some("1.8.4")), range(Some("2.0.0"), Some("2.1.1"))]
+        );
+    }
+
+    #[test]
+    fn test_confidence_from_declared_affected_ranges() {
+        let v = |s: &str| Version::parse(s).unwrap();
+        // Only patched/unaffected: anything else is affected by assumption
+        let implicit = advisory("patched = [\">= 1.2.0\"]\nunaffected = [\"< 0.5.0\"]");
+        assert_eq!(affected_confidence(&v("0.4.0"), &implicit), None);
+        assert_eq!(affected_confidence(&v("1.2.0"), &implicit), None);
+        assert_eq!(affected_confidence(&v("1.0.0"), &implicit), Some(Confidence::Assumed));
+        assert_eq!(range_conflict(&implicit), None);
+
+        let explicit = advisory(
+            "patched = [\">= 1.2.0\"]\nunaffected = [\"< 0.5.0\"]\n\n[affected.functions]\n\"demo::parse\" = [\">= 0.8.0, < 1.2.0\"]",
+        );
+        assert_eq!(affected_confidence(&v("1.0.0"), &explicit), Some(Confidence::High));
+        // Neither safe nor within the declared range
+        assert_eq!(affected_confidence(&v("0.6.0"), &explicit), Some(Confidence::Assumed));
+        assert_eq!(affected_confidence(&v("1.2.0"), &explicit), None);
+        assert_eq!(range_conflict(&explicit), None);
+
+        // Patched and unaffected win over a contradicting declaration
+        let contradictory = advisory(
+            "patched = [\">= 1.2.0\"]\nunaffected = [\"< 0.5.0\"]\n\n[affected.functions]\n\"demo::parse\" = [\">= 0.3.0, < 1.3.0\"]",
+        );
+        assert_eq!(affected_confidence(&v("0.4.0"), &contradictory), None);
+        assert_eq!(affected_confidence(&v("1.2.5"), &contradictory), None);
+        assert_eq!(affected_confidence(&v("1.0.0"), &contradictory), Some(Confidence::High));
+        assert_eq!(range_conflict(&contradictory).as_deref(), Some("0.3.0 is unaffected but within a declared affected range"));
+
+        let finding = AdvisoryFinding::from_advisory(&explicit);
+        assert_eq!(serde_json::to_value(finding.confidence).unwrap(), "assumed");
+    }
+
+    #[test]
+    fn test_sort_by_severity() {
+        let finding = |id: &str, severity| {
+            let mut finding = AdvisoryFinding::from_advisory(&advisory("patched = []"));
+            finding.id = id.to_string();
+            finding.severity = severity;
+            finding
+        };
+        let package = |name: &str, advisories| PackageReport {
+            package_name: name.to_string(),
+            package_version: "1.0.0".to_string(),
+            advisories,
+            ..Default::default()
+        };
+        let mut report = VulnReport {
+            packages: vec![
+                package("zeta", vec![finding("A", Severity::Low)]),
+                package("beta", vec![finding("B", Severity::Low), finding("C", Severity::Critical)]),
+                package("alpha", vec![finding("D", Severity::Unknown)]),
+                package("gamma", vec![finding("E", Severity::Critical)]),
+            ],
+            ..Default::default()
+        };
+        report.sort_by_severity();
+
+        let order: Vec<_> = report.packages.iter().map(|p| (p.package_name.as_str(), p.max_severity)).collect();
+        assert_eq!(
+            order,
+            [
+                ("beta", Severity::Critical),
+                ("gamma", Severity::Critical),
+                ("zeta", Severity::Low),
+                ("alpha", Severity::Unknown),
+            ]
+        );
+        let beta: Vec<_> = report.packages[0].advisories.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(beta, ["C", "B"]);
+
+        // Versions of one crate in semver order, as in --canonical reports
+        let versioned = |version: &str| PackageReport { package_version: version.to_string(), ..package("zeta", vec![finding("A", Severity::Low)]) };
+        report.packages = vec![versioned("0.10.0"), versioned("0.9.0")];
+        report.sort_by_severity();
+        let versions: Vec<_> = report.packages.iter().map(|p| p.package_version.as_str()).collect();
+        assert_eq!(versions, ["0.9.0", "0.10.0"]);
+    }
+
+    #[test]
+    fn test_severity_overrides() {
+        let (_temp_dir, db_path) = setup_test_db();
+        // Rated critical upstream via CVSS 9.8
+        AdvisoryText::new("RUSTSEC-2020-0003", "rated-crate")
+            .with_metadata("cvss = \"CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H\"")
+            .write(&db_path);
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0004", "unrated-crate"));
+
+        let overrides = BTreeMap::from([
+            ("RUSTSEC-2020-0003".to_string(), Severity::Low),
+            ("RUSTSEC-2020-0004".to_string(), Severity::Critical),
+            ("RUSTSEC-2099-9999".to_string(), Severity::High),
+        ]);
+        let scanner = Scanner::new(&db_path).unwrap().with_severity_overrides(overrides);
+        let lockfile: Lockfile = "version = 3\n\n[[package]]\nname = \"rated-crate\"\nversion = \"1.0.0\"\n\n[[package]]\nname = \"unrated-crate\"\nversion = \"1.0.0\"\n"
+            .parse()
+            .unwrap();
+        let report = scanner.scan_lockfile(&lockfile).unwrap();
+
+        let finding = |id: &str| report.packages.iter().flat_map(|p| &p.advisories).find(|f| f.id == id).unwrap();
+        // Downgrade
+        assert_eq!(finding("RUSTSEC-2020-0003").severity, Severity::Low);
+        assert_eq!(finding("RUSTSEC-2020-0003").upstream_severity, Some(Severity::Critical));
+        // Upgrade
+        assert_eq!(finding("RUSTSEC-2020-0004").severity, Severity::Critical);
+        assert_eq!(finding("RUSTSEC-2020-0004").upstream_severity, Some(Severity::Unknown));
+        let json = serde_json::to_value(finding("RUSTSEC-2020-0004")).unwrap();
+        assert_eq!(json["upstream_severity"], serde_json::Value::Null);
+        assert_eq!(serde_json::to_value(finding("RUSTSEC-2020-0003")).unwrap()["upstream_severity"], "critical");
+
+        // The summary and thresholds use the overridden values
+        assert_eq!(report.summary.by_severity.critical, 1);
+        assert_eq!(report.summary.by_severity.low, 1);
+        assert_eq!(report.summary.by_severity.unknown, 0);
+        assert!(report.has_actionable_at_or_above(Severity::Critical));
+        assert_eq!(report.packages[0].package_name, "unrated-crate");
+
+        assert_eq!(report.unused_severity_overrides, vec!["RUSTSEC-2099-9999"]);
+    }
+
+    #[test]
+    fn test_target_filtering() {
+        let (_temp_dir, db_path) = setup_test_db();
+        AdvisoryText::new("RUSTSEC-2020-0005", "windows-only").with_metadata("\n[affected]\nos = [\"windows\"]").write(&db_path);
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0006", "everywhere"));
+        let lockfile: Lockfile = "version = 3\n\n[[package]]\nname = \"windows-only\"\nversion = \"1.0.0\"\n\n[[package]]\nname = \"everywhere\"\nversion = \"1.0.0\"\n"
+            .parse()
+            .unwrap();
+
+        // Without a target everything is reported, as before
+        let report = Scanner::new(&db_path).unwrap().scan_lockfile(&lockfile).unwrap();
+        assert_eq!(report.summary.total_vulnerabilities, 2);
+        assert!(report.not_applicable_to_target.is_empty());
+
+        let linux = Scanner::new(&db_path).unwrap().with_target("x86_64-unknown-linux-gnu").unwrap();
+        let report = linux.scan_lockfile(&lockfile).unwrap();
+        assert_eq!(report.summary.total_vulnerabilities, 1);
+        assert_eq!(report.packages[0].package_name, "everywhere");
+        assert_eq!(report.not_applicable_to_target[0].package_name, "windows-only");
+        assert_eq!(report.not_applicable_to_target[0].advisories[0].affected_os, vec!["windows"]);
+        assert_eq!(report.metadata.target.as_deref(), Some("x86_64-unknown-linux-gnu"));
+
+        let windows = Scanner::new(&db_path).unwrap().with_target("x86_64-pc-windows-msvc").unwrap();
+        assert_eq!(windows.scan_lockfile(&lockfile).unwrap().summary.total_vulnerabilities, 2);
+        assert!(Scanner::new(&db_path).unwrap().with_target("not-a-triple").is_err());
+    }
+
+    #[test]
+    fn test_category_filters() {
+        let (_temp_dir, db_path) = setup_test_db();
+        for (package, id, categories) in [
+            ("parser", "RUSTSEC-2020-0007", r#"["memory-corruption"]"#),
+            ("server", "RUSTSEC-2020-0008", r#"["denial-of-service"]"#),
+            ("both", "RUSTSEC-2020-0009", r#"["memory-corruption", "denial-of-service"]"#),
+        ] {
+            AdvisoryText::new(id, package)
+                .with_metadata(&format!("categories = {}\nkeywords = [\"parsing\"]", categories))
+                .write(&db_path);
+        }
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0010", "untagged"));
+        let lockfile = lockfile_of(&[("parser", "1.0.0"), ("server", "1.0.0"), ("both", "1.0.0"), ("untagged", "1.0.0")]);
+        let ids = |report: &VulnReport| {
+            let mut ids: Vec<String> = report.packages.iter().flat_map(|p| &p.advisories).map(|f| f.id.clone()).collect();
+            ids.sort();
+            ids
+        };
+
+        let report = Scanner::new(&db_path).unwrap().scan_lockfile(&lockfile).unwrap();
+        assert_eq!(report.summary.total_vulnerabilities, 4);
+        assert_eq!(report.summary.filtered_by_category, 0);
+        let parser = &report.packages.iter().find(|p| p.package_name == "parser").unwrap().advisories[0];
+        assert_eq!((parser.categories.as_slice(), parser.keywords.as_slice()), (&["memory-corruption".to_string()][..], &["parsing".to_string()][..]));
+
+        // Excluded findings stay in the report, outside the summary
+        let exclude = CategoryFilter { only: Vec::new(), exclude: vec!["denial-of-service".to_string()] };
+        let report = Scanner::new(&db_path).unwrap().with_categories(exclude).scan_lockfile(&lockfile).unwrap();
+        assert_eq!(ids(&report), ["RUSTSEC-2020-0007", "RUSTSEC-2020-0010"]);
+        assert_eq!(report.summary.total_vulnerabilities, 2);
+        assert_eq!(report.summary.filtered_by_category, 2);
+        assert_eq!(report.excluded_by_category.len(), 2);
+        assert_eq!(report.metadata.category_filter.as_deref(), Some("exclude denial-of-service"));
+
+        let only = CategoryFilter { only: vec!["memory-corruption".to_string()], exclude: Vec::new() };
+        let report = Scanner::new(&db_path).unwrap().with_categories(only).scan_lockfile(&lockfile).unwrap();
+        assert_eq!(ids(&report), ["RUSTSEC-2020-0007", "RUSTSEC-2020-0009"]);
+        assert_eq!(report.summary.filtered_by_category, 2);
+    }
+
+    #[test]
+    fn test_toolchain_advisory_does_not_match_same_named_crate() {
+        let (_temp_dir, db_path) = setup_test_db();
+        // A Cargo (the tool) advisory, filed under rust/ rather than crates/; committed below
+        AdvisoryText::new("RUSTSEC-2020-0001", "cargo").with_title("Toolchain advisory").write_to(&db_path, "rust");
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0002", "second-crate"));
+        // The `cargo` crate from crates.io, below the toolchain advisory's patched version
+        let lockfile = Lockfile::from_str(
+            "version = 3\n\n[[package]]\nname = \"cargo\"\nversion = \"1.0.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n[[package]]\nname = \"second-crate\"\nversion = \"1.0.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        )
+        .unwrap();
+
+        let report = Scanner::new(&db_path).unwrap().scan_lockfile(&lockfile).unwrap();
+        assert_eq!(report.packages.len(), 1);
+        assert_eq!(report.packages[0].package_name, "second-crate");
+        assert_eq!(report.packages[0].advisories[0].collection, Collection::Crates);
+        assert!(report.toolchain.is_empty());
+
+        let scanner = Scanner::new(&db_path)
+            .unwrap()
+            .with_collections(vec![Collection::Crates, Collection::Rust])
+            .with_toolchain(Version::new(1, 5, 0));
+        let report = scanner.scan_lockfile(&lockfile).unwrap();
+        assert_eq!(report.summary.total_vulnerabilities, 1);
+        assert!(report.packages.iter().all(|p| p.package_name != "cargo"));
+        assert_eq!((report.toolchain[0].package_name.as_str(), report.toolchain[0].package_version.as_str()), ("cargo", "1.5.0"));
+        assert_eq!(report.toolchain[0].advisories[0].collection, Collection::Rust);
+
+        // Only the toolchain collection: the crate advisory is not loaded either
+        let scanner = Scanner::new(&db_path).unwrap().with_collections(vec![Collection::Rust]).with_toolchain(Version::new(2, 0, 0));
+        let report = scanner.scan_lockfile(&lockfile).unwrap();
+        assert!(report.packages.is_empty());
+        assert!(report.toolchain.is_empty());
+    }
+
+    #[test]
+    fn test_exclude_workspace_members() {
+        let (_temp_dir, db_path) = setup_test_db();
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "first-crate"));
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0002", "second-crate"));
+        // An internal first-crate shares its name with the advised crates.io one
+        let lockfile = Lockfile::from_str(
+            r#"
+version = 3
+
+[[package]]
+name = "first-crate"
+version = "1.0.0"
+dependencies = ["second-crate"]
+
+[[package]]
+name = "second-crate"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        )
+        .unwrap();
+
+        let report = Scanner::new(&db_path).unwrap().scan_lockfile(&lockfile).unwrap();
+        assert_eq!(report.summary.total_vulnerabilities, 2);
+        assert!(report.excluded_workspace_members.is_empty());
+
+        let report = Scanner::new(&db_path).unwrap().with_workspace_excluded(true).scan_lockfile(&lockfile).unwrap();
+        assert_eq!(report.summary.total_vulnerabilities, 1);
+        assert_eq!(report.packages[0].package_name, "second-crate");
+        assert_eq!(report.excluded_workspace_members, ["first-crate@1.0.0"]);
+    }
+
+    #[test]
+    fn test_private_registry_sources() {
+        let (_temp_dir, db_path) = setup_test_db();
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "acme-auth"));
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0002", "acme-ledger"));
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lockfiles/private-registry.lock");
+        let lockfile = Lockfile::load(path).unwrap();
+        let registries: RegistryMap = toml::from_str(
+            r#"
+["sparse+https://crates.internal.example/index/"]
+name = "internal"
+advisories = false
+"#,
+        )
+        .unwrap();
+
+        let report = Scanner::new(&db_path).unwrap().with_registries(registries).scan_lockfile(&lockfile).unwrap();
+        assert_eq!(report.summary.total_vulnerabilities, 1);
+        assert_eq!(report.packages[0].package_name, "acme-ledger");
+        assert_eq!(report.packages[0].source.as_deref(), Some("registry+https://git.partner.example/cargo-index"));
+        assert_eq!(report.metadata.skipped_registries, BTreeMap::from([("internal".to_string(), 1)]));
+
+        // Without the table every registry is matched by name
+        let report = Scanner::new(&db_path).unwrap().scan_lockfile(&lockfile).unwrap();
+        assert_eq!(report.summary.total_vulnerabilities, 2);
+        assert!(report.metadata.skipped_registries.is_empty());
+    }
+
+    #[test]
+    fn test_scan_from_path() {
+        let (temp_dir, db_path) = setup_test_db();
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "first-crate"));
+        let scanner = Scanner::new(&db_path).unwrap();
+
+        let lockfile_path = temp_dir.path().join("Cargo.lock");
+        fs::write(
+            &lockfile_path,
+            "version = 3\n\n[[package]]\nname = \"first-crate\"\nversion = \"1.0.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        )
+        .unwrap();
+        let report = scanner.scan_from_path(&lockfile_path).unwrap();
+        assert_eq!(report.total_packages, 1);
+        assert_eq!(report.packages[0].advisories[0].id, "RUSTSEC-2020-0001");
+
+        assert!(scanner.scan_from_path(temp_dir.path().join("missing.lock")).is_err());
+
+        let outcome = scanner.scan_from_path_timed(&lockfile_path).unwrap();
+        assert!(outcome.timings.get("discover").is_some());
+        assert!(outcome.report.metadata.timings.contains_key("scan"));
+    }
+
+    #[test]
+    fn test_withdrawn_cross_referenced_by_alias() {
+        let (temp_dir, db_path) = setup_test_db();
+        let aliased = |id: &str| AdvisoryText::new(id, "demo").with_date("2021-01-01").with_metadata("aliases = [\"CVE-2021-1234\"]");
+        aliased("RUSTSEC-2021-0001").with_metadata("withdrawn = \"2021-02-01\"").write(&db_path);
+        aliased("RUSTSEC-2021-0002").write(&db_path);
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "other"));
+
+        let lockfile_path = temp_dir.path().join("Cargo.lock");
+        fs::write(
+            &lockfile_path,
+            "version = 3\n\n[[package]]\nname = \"demo\"\nversion = \"1.0.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        )
+        .unwrap();
+
+        let report = Scanner::new(&db_path).unwrap().scan_from_path(&lockfile_path).unwrap();
+        assert!(report.withdrawn.is_empty());
+
+        let report = Scanner::new(&db_path).unwrap().with_withdrawn(true).scan_from_path(&lockfile_path).unwrap();
+        assert_eq!(report.withdrawn.len(), 1);
+        assert_eq!(report.withdrawn[0].id, "RUSTSEC-2021-0001");
+        assert_eq!(report.withdrawn[0].withdrawn, "2021-02-01");
+        assert_eq!(report.withdrawn[0].superseded_by, vec!["RUSTSEC-2021-0002"]);
+        // Never counted, whatever the flag
+        assert_eq!(report.summary.total_vulnerabilities, 1);
+    }
+
+    #[test]
+    fn test_canonical_reports_are_byte_identical() {
+        let (temp_dir, db_path) = setup_test_db();
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0002", "first-crate"));
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "first-crate"));
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0003", "second-crate"));
+        let lockfile_path = temp_dir.path().join("Cargo.lock");
+        fs::write(
+            &lockfile_path,
+            "version = 3\n\n[[package]]\nname = \"first-crate\"\nversion = \"1.0.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n[[package]]\nname = \"second-crate\"\nversion = \"0.9.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n[[package]]\nname = \"second-crate\"\nversion = \"0.10.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        )
+        .unwrap();
+
+        let scan = || {
+            let mut report = Scanner::new(&db_path).unwrap().scan_from_path_timed(&lockfile_path).unwrap().report;
+            report.canonicalize();
+            serde_json::to_string_pretty(&report).unwrap()
+        };
+        let first = scan();
+        assert_eq!(first, scan());
+        assert!(!first.contains("generated_at") && !first.contains("timings"));
+
+        let report: serde_json::Value = serde_json::from_str(&first).unwrap();
+        let packages: Vec<String> = report["packages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| format!("{}@{}", p["package_name"].as_str().unwrap(), p["package_version"].as_str().unwrap()))
+            .collect();
+        assert_eq!(packages, vec!["first-crate@1.0.0", "second-crate@0.9.0", "second-crate@0.10.0"]);
+        let ids: Vec<&str> = report["packages"][0]["advisories"].as_array().unwrap().iter().map(|a| a["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec!["RUSTSEC-2020-0001", "RUSTSEC-2020-0002"]);
+    }
+
+    #[test]
+    fn test_query_single_crate_version() {
+        let (_temp_dir, db_path) = setup_test_db();
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "first-crate"));
+        let scanner = Scanner::new(&db_path).unwrap().with_severity_overrides(BTreeMap::from([(
+            "RUSTSEC-2020-0001".to_string(),
+            Severity::High,
+        )]));
+
+        let findings = scanner.query("first-crate", &Version::new(1, 0, 0));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::High);
+        assert!(scanner.query("first-crate", &Version::new(2, 0, 0)).is_empty());
+        assert!(scanner.query("other-crate", &Version::new(1, 0, 0)).is_empty());
+
+        assert_eq!(scanner.advisories_for_package("first-crate").len(), 1);
+        assert!(scanner.advisories_for_package("other-crate").is_empty());
+    }
+
+    #[test]
+    fn test_skipped_withdrawn_and_informational() {
+        let (temp_dir, db_path) = setup_test_db();
+        let crates = db_path.join("crates");
+        fs::create_dir_all(crates.join("ansi_term")).unwrap();
+        fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/advisory_no_fix.md"),
+            crates.join("ansi_term/RUSTSEC-2021-0139.md"),
+        )
+        .unwrap();
+        AdvisoryText::new("RUSTSEC-2020-0099", "ansi_term")
+            .with_metadata("withdrawn = \"2020-02-01\"")
+            .with_versions("patched = []")
+            .with_title("Withdrawn")
+            .write(&db_path);
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "first-crate"));
+        let scanner = Scanner::new(&db_path).unwrap();
+
+        let lockfile_path = temp_dir.path().join("Cargo.lock");
+        fs::write(
+            &lockfile_path,
+            "version = 3\n\n[[package]]\nname = \"ansi_term\"\nversion = \"0.12.1\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        )
+        .unwrap();
+        let report = scanner.scan_from_path(&lockfile_path).unwrap();
+        assert!(report.packages.is_empty());
+        assert_eq!(report.metadata.skipped_withdrawn, 1);
+        assert_eq!(report.metadata.skipped_informational, 1);
+        assert_eq!(report.informational[0].category, "unmaintained");
+    }
+
+    // 由 (name, version) 列表拼出一个 crates.io lockfile
+    fn lockfile_of(packages: &[(&str, &str)]) -> Lockfile {
+        let mut text = String::from("version = 3\n");
+        for (name, version) in packages {
+            text.push_str(&format!(
+                "\n[[package]]\nname = \"{}\"\nversion = \"{}\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+                name, version
+            ));
+        }
+        Lockfile::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn test_single_project_report_stays_flat() {
+        let (_temp_dir, db_path) = setup_test_db();
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "first-crate"));
+        let report = Scanner::new(&db_path).unwrap().scan_lockfile(&lockfile_of(&[("first-crate", "1.0.0")])).unwrap();
+
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert!(json.get("projects").is_none());
+        assert_eq!(json["packages"][0]["package_name"], "first-crate");
+        assert_eq!(json["summary"]["total_vulnerabilities"], 1);
+
+        // Two affected versions, one advisory
+        let lockfile = lockfile_of(&[("first-crate", "1.0.0"), ("first-crate", "1.1.0")]);
+        let report = Scanner::new(&db_path).unwrap().scan_lockfile(&lockfile).unwrap();
+        assert_eq!((report.summary.total_vulnerabilities, report.summary.unique_advisories), (2, 1));
+    }
+
+    #[test]
+    fn test_warnings_are_collected_into_report() {
+        crate::console::warn("test-warning", "something degraded");
+        let report = VulnReport { warnings: crate::console::warnings(), ..Default::default() };
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        let warnings = json["warnings"].as_array().unwrap();
+        assert!(warnings.iter().any(|w| w["code"] == "test-warning" && w["message"] == "something degraded"));
+        assert!(serde_json::to_value(VulnReport::default()).unwrap().get("warnings").is_none());
+    }
+
+    #[test]
+    fn test_three_project_report() {
+        let (_temp_dir, db_path) = setup_test_db();
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "first-crate"));
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0002", "second-crate"));
+        let scanner = Scanner::new(&db_path).unwrap();
+
+        let projects = [
+            ("app", "Cargo.lock", lockfile_of(&[("first-crate", "1.0.0"), ("other", "1.0.0")])),
+            ("xtask", "xtask/Cargo.lock", lockfile_of(&[("first-crate", "1.0.0"), ("second-crate", "1.0.0")])),
+            ("fuzz", "fuzz/Cargo.lock", lockfile_of(&[("other", "1.0.0")])),
+        ];
+        let merged = crate::get_lockfile::merge_lockfiles(projects.iter().map(|(_, _, l)| l));
+        let mut report = scanner.scan_lockfile(&merged).unwrap();
+        for (name, path, lockfile) in &projects {
+            report.projects.push(ProjectReport::from_report(*name, *path, scanner.scan_lockfile(lockfile).unwrap()));
+        }
+        report.canonicalize();
+
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        // A crate shared by two projects is counted once at the top level
+        assert_eq!(json["total_packages"], 3);
+        assert_eq!(json["summary"]["total_vulnerabilities"], 2);
+        let projects = json["projects"].as_array().unwrap();
+        let rows: Vec<(&str, &str, u64, u64)> = projects
+            .iter()
+            .map(|p| {
+                (
+                    p["name"].as_str().unwrap(),
+                    p["lockfile_path"].as_str().unwrap(),
+                    p["total_packages"].as_u64().unwrap(),
+                    p["summary"]["total_vulnerabilities"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![("app", "Cargo.lock", 2, 1), ("fuzz", "fuzz/Cargo.lock", 1, 0), ("xtask", "xtask/Cargo.lock", 2, 2)]
+        );
+        assert_eq!(projects[2]["packages"][1]["advisories"][0]["id"], "RUSTSEC-2020-0002");
+    }
+
+    #[test]
+    fn test_package_filter_restricts_scan() {
+        let (_temp_dir, db_path) = setup_test_db();
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "first-crate"));
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0002", "second-crate"));
+        let lockfile = lockfile_of(&[("first-crate", "1.0.0"), ("second-crate", "1.0.0")]);
+
+        let selection = PackageSelection { filters: vec!["second-crate".parse().unwrap()], with_deps: false };
+        let scanner = Scanner::new(&db_path).unwrap().with_packages(selection);
+        let report = scanner.scan_lockfile(&lockfile).unwrap();
+        assert_eq!(report.total_packages, 1);
+        assert_eq!(report.packages.len(), 1);
+        assert_eq!(report.packages[0].advisories[0].id, "RUSTSEC-2020-0002");
+        assert_eq!(report.metadata.package_filter.as_deref(), Some("second-crate"));
+
+        let selection = PackageSelection { filters: vec!["frist-crate".parse().unwrap()], with_deps: false };
+        let err = Scanner::new(&db_path).unwrap().with_packages(selection).scan_lockfile(&lockfile).unwrap_err();
+        assert!(err.to_string().contains("did you mean first-crate"), "{}", err);
+    }
+
+    #[test]
+    fn test_findings_annotated_with_dependency_path() {
+        let (_temp_dir, db_path) = setup_test_db();
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0001", "first-crate"));
+        commit_advisory(&db_path, &AdvisoryText::new("RUSTSEC-2020-0002", "second-crate"));
+        // app -> first-crate -> second-crate, app -> other-crate -> second-crate
+        let lockfile = Lockfile::from_str(
+            r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["first-crate", "other-crate"]
+
+[[package]]
+name = "first-crate"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["second-crate"]
+
+[[package]]
+name = "other-crate"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["second-crate"]
+
+[[package]]
+name = "second-crate"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        )
+        .unwrap();
+        let report = Scanner::new(&db_path).unwrap().scan_lockfile(&lockfile).unwrap();
+
+        let first = report.packages.iter().find(|p| p.package_name == "first-crate").unwrap();
+        assert_eq!(first.dependency_kind, Some(DependencyKind::Direct));
+        assert_eq!(first.dependency_path, ["app@0.1.0", "first-crate@1.0.0"]);
+        let second = report.packages.iter().find(|p| p.package_name == "second-crate").unwrap();
+        assert_eq!(second.dependency_kind, Some(DependencyKind::Transitive));
+        assert_eq!(second.dependency_path, ["app@0.1.0", "first-crate@1.0.0", "second-crate@1.0.0"]);
+
+        // second-crate's finding counts under both direct dependencies
+        let direct: Vec<(&str, usize, usize, String)> = report
+            .summary
+            .by_direct_dependency
+            .iter()
+            .map(|d| (d.package_name.as_str(), d.findings, d.shared_findings, d.vulnerable_packages.join(" ")))
+            .collect();
+        assert_eq!(
+            direct,
+            [
+                ("first-crate", 2, 1, "first-crate@1.0.0 second-crate@1.0.0".to_string()),
+                ("other-crate", 1, 1, "second-crate@1.0.0".to_string()),
+            ]
+        );
+    }
+}
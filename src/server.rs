@@ -0,0 +1,227 @@
+use crate::cargo_metadata::{self, CargoMetadata};
+use crate::config::ScanConfig;
+use crate::cpe::CpeMappings;
+use crate::description::{self, DescriptionMode};
+use crate::get_lockfile::{TempGuard, discover_lockfiles_in, extract_archive_to, merge_lockfiles};
+use crate::get_sbom::{SbomOptions, build_sbom};
+use crate::output::Stamp;
+use crate::package_filter;
+use crate::policy::GateSource;
+use crate::project::{ProjectIdentity, ProjectOverrides};
+use crate::scanner::Scanner;
+use crate::workspace::WorkspaceMembers;
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Arc;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+// `rustpj serve` (cargo feature `server`): a long-lived scanning service. The advisory DB
+// is loaded once at startup and shared by a fixed pool of worker threads, which is also
+// the concurrency limit: further requests wait in the accept queue.
+//
+//   POST /scan       body: a project ZIP; responds `{"sbom": ..., "report": ...}`
+//   GET  /healthz    liveness
+//   GET  /db/status  advisory count and commit of the loaded DB
+//
+// Every upload gets its own temp dir behind a `TempGuard`, so it is removed however the
+// request ends, a client that disconnects mid-upload included. Bodies larger than the
+// extraction limit for a whole archive are refused before anything is extracted.
+// Nothing here changes the working directory; `cargo metadata` runs with its own
+// `current_dir`. Warnings go to the service log only, as they are collected per process,
+// not per request.
+
+/// 服务启动时加载、各请求共享的状态
+pub struct ServerState {
+    pub scanner: Scanner,
+    pub config: ScanConfig,
+    pub cpes: CpeMappings,
+    pub description: DescriptionMode,
+    // false 时（--no-license-lookup）不对上传的项目执行 `cargo metadata`
+    pub license_lookup: bool,
+    pub started_at: String,
+}
+
+/// 请求失败：HTTP 状态码及返回给客户端的错误信息
+#[derive(Debug)]
+struct Failure {
+    status: u16,
+    message: String,
+}
+
+impl Failure {
+    fn new(status: u16, error: impl std::fmt::Display) -> Self {
+        Failure { status, message: error.to_string() }
+    }
+}
+
+/// 在 listen 地址上启动服务，workers 个线程并发处理请求；不会返回（除非监听失败）
+pub fn serve(state: ServerState, listen: &str, workers: usize) -> Result<()> {
+    let server = Server::http(listen).map_err(|e| anyhow!("failed to listen on {}: {}", listen, e))?;
+    status!("Serving on http://{} with {} workers ({} advisories loaded)", listen, workers, state.scanner.advisory_count());
+    run(server, state, workers);
+    Ok(())
+}
+
+/// 在已绑定的 server 上处理请求，直到 server 关闭
+pub fn run(server: Server, state: ServerState, workers: usize) {
+    let server = Arc::new(server);
+    let state = Arc::new(state);
+    let handles: Vec<_> = (0..workers.max(1))
+        .map(|_| {
+            let (server, state) = (Arc::clone(&server), Arc::clone(&state));
+            std::thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle(&state, request);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();
+    }
+}
+
+fn handle(state: &ServerState, mut request: Request) {
+    let result = match (request.method(), request.url()) {
+        (Method::Get, "/healthz") => Ok(json!({"status": "ok"})),
+        (Method::Get, "/db/status") => Ok(db_status(state)),
+        (Method::Post, "/scan") => {
+            let declared = request.body_length();
+            scan(state, declared, request.as_reader())
+        }
+        (_, "/healthz" | "/db/status" | "/scan") => Err(Failure::new(405, "method not allowed")),
+        (_, url) => Err(Failure::new(404, format!("no such endpoint: {}", url))),
+    };
+    let (status, body) = match result {
+        Ok(body) => (200, body),
+        Err(failure) => {
+            status!("{} {} failed ({}): {}", request.method(), request.url(), failure.status, failure.message);
+            (failure.status, json!({"error": failure.message}))
+        }
+    };
+    let content_type = Header::from_bytes("Content-Type", "application/json").expect("static header");
+    let response = Response::from_string(body.to_string()).with_status_code(status).with_header(content_type);
+    // The client may have gone away; its temp dir is already gone either way
+    let _ = request.respond(response);
+}
+
+fn db_status(state: &ServerState) -> Value {
+    json!({
+        "advisory_count": state.scanner.advisory_count(),
+        "advisory_db_commit": state.scanner.db_commit(),
+        "loaded_at": state.started_at,
+    })
+}
+
+fn scan(state: &ServerState, declared: Option<usize>, body: &mut dyn Read) -> Result<Value, Failure> {
+    // Refuse an oversized upload by its Content-Length, before reading any of it
+    if declared.is_some_and(|len| len as u64 > crate::extract_zip::MAX_TOTAL_SIZE) {
+        return Err(too_large());
+    }
+    let dir = tempfile::Builder::new()
+        .prefix("rustpj-serve-")
+        .tempdir()
+        .map_err(|e| Failure::new(500, format!("failed to create temporary directory: {}", e)))?;
+    let guard = TempGuard::new(dir.keep());
+    let upload = guard.path().join("upload.zip");
+    receive(body, &upload)?;
+
+    let extracted = guard.path().join("project");
+    extract_archive_to(&upload.to_string_lossy(), &extracted).map_err(|e| Failure::new(400, format!("{:#}", e)))?;
+    let projects = discover_lockfiles_in(&extracted).map_err(|e| Failure::new(400, format!("{:#}", e)))?;
+    let lockfile = merge_lockfiles(projects.iter().map(|p| &p.lockfile));
+    let (lockfile, excluded) = package_filter::exclude(&lockfile, &state.config.exclude);
+    state.config.registries.warn_unmapped(&lockfile);
+
+    let mut metadata: Option<CargoMetadata> = None;
+    let mut members = WorkspaceMembers::default();
+    let missing: Vec<String> =
+        projects.iter().filter(|p| !p.manifests_available).map(|p| p.manifest_path.display().to_string()).collect();
+    if state.license_lookup && !missing.is_empty() {
+        cargo_metadata::manifests_missing(&missing);
+    }
+    for project in &projects {
+        // Licenses are a nicety; a failing `cargo metadata` already logged a warning
+        let fetched = if state.license_lookup && project.manifests_available {
+            cargo_metadata::fetch_all_metadata(&project.project_root)
+        } else {
+            Ok(None)
+        };
+        if let Ok(Some(fetched)) = fetched {
+            match &mut metadata {
+                Some(all) => all.merge(fetched),
+                None => metadata = Some(fetched),
+            }
+        }
+        members.merge(WorkspaceMembers::discover(&project.project_root));
+    }
+
+    let mut report = state
+        .scanner
+        .scan_lockfile(&lockfile)
+        .context("failed to scan dependencies")
+        .map_err(|e| Failure::new(500, format!("{:#}", e)))?;
+    // An upload has no file name of its own to fall back on
+    let project = ProjectIdentity::of_projects(&ProjectOverrides::default(), &projects, &extracted);
+    report.describe_input(&projects, &project);
+    report.metadata.excluded_packages = excluded;
+    state.config.apply_triage(&mut report);
+    if let Some(gate) = &state.config.gate {
+        report.gate = Some(gate.evaluate(GateSource::Config, &report));
+    }
+    let sbom = build_sbom(
+        &lockfile,
+        &SbomOptions {
+            metadata: metadata.as_ref(),
+            members: &members,
+            cpes: &state.cpes,
+            component_types: &state.config.component_types,
+            report: None,
+            project: Some(&project),
+            registries: &state.config.registries,
+            stamp: Stamp::now(),
+        },
+    );
+    let mut report = serde_json::to_value(&report).map_err(|e| Failure::new(500, e))?;
+    description::apply(&mut report, state.description);
+    let sbom = serde_json::to_value(&sbom).map_err(|e| Failure::new(500, e))?;
+    Ok(json!({"sbom": sbom, "report": report}))
+}
+
+fn too_large() -> Failure {
+    let limit = crate::extract_zip::MAX_TOTAL_SIZE;
+    Failure::new(413, format!("archive exceeds the {} MiB limit", limit / (1024 * 1024)))
+}
+
+// Streams the body to disk, stopping at the archive size limit
+fn receive(body: &mut dyn Read, path: &Path) -> Result<(), Failure> {
+    let limit = crate::extract_zip::MAX_TOTAL_SIZE;
+    let mut file = File::create(path).map_err(|e| Failure::new(500, e))?;
+    let written = io::copy(&mut body.take(limit + 1), &mut file)
+        .map_err(|e| Failure::new(400, format!("failed to read the request body: {}", e)))?;
+    if written > limit {
+        return Err(too_large());
+    }
+    if written == 0 {
+        return Err(Failure::new(400, "empty request body; POST the project ZIP"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receive_rejects_empty_bodies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("upload.zip");
+        let failure = receive(&mut io::empty(), &path).unwrap_err();
+        assert_eq!(failure.status, 400);
+        receive(&mut &b"PK\x03\x04"[..], &path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"PK\x03\x04");
+    }
+}
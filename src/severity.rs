@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// 漏洞严重程度，由 CVSS 分数推导；排序为 Critical > High > Medium > Low > None > Unknown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    // advisory 没有 CVSS 分数
+    #[default]
+    Unknown,
+    // CVSS 分数为 0.0
+    None,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Unknown => "unknown",
+            Severity::None => "none",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+
+    /// Unknown 表示没有评级，对外（模板、VEX ratings）呈现为缺省
+    pub fn known(self) -> Option<Severity> {
+        (self != Severity::Unknown).then_some(self)
+    }
+
+    /// 供 `#[serde(serialize_with)]` 使用：没有评级时输出 `null`，内部排序仍用 Unknown
+    pub fn serialize_known<S: Serializer>(severity: &Severity, serializer: S) -> Result<S::Ok, S::Error> {
+        severity.known().serialize(serializer)
+    }
+
+    /// 同 `serialize_known`，用于配合 `skip_serializing_if = "Option::is_none"` 的 `Option<Severity>` 字段
+    pub fn serialize_known_opt<S: Serializer>(severity: &Option<Severity>, serializer: S) -> Result<S::Ok, S::Error> {
+        severity.and_then(Severity::known).serialize(serializer)
+    }
+
+    // ANSI color for terminal output
+    pub fn color(&self) -> &'static str {
+        match self {
+            Severity::Critical => "\x1b[1;31m",
+            Severity::High => "\x1b[31m",
+            Severity::Medium => "\x1b[33m",
+            Severity::Low => "\x1b[36m",
+            Severity::None | Severity::Unknown => "\x1b[2m",
+        }
+    }
+}
+
+impl From<rustsec::advisory::Severity> for Severity {
+    fn from(severity: rustsec::advisory::Severity) -> Self {
+        match severity {
+            rustsec::advisory::Severity::None => Severity::None,
+            rustsec::advisory::Severity::Low => Severity::Low,
+            rustsec::advisory::Severity::Medium => Severity::Medium,
+            rustsec::advisory::Severity::High => Severity::High,
+            rustsec::advisory::Severity::Critical => Severity::Critical,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "critical" => Ok(Severity::Critical),
+            "high" => Ok(Severity::High),
+            "medium" => Ok(Severity::Medium),
+            "low" => Ok(Severity::Low),
+            "none" => Ok(Severity::None),
+            "unknown" => Ok(Severity::Unknown),
+            other => anyhow::bail!("unknown severity: {}", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ordering() {
+        let ordered = [
+            Severity::Critical,
+            Severity::High,
+            Severity::Medium,
+            Severity::Low,
+            Severity::None,
+            Severity::Unknown,
+        ];
+        for pair in ordered.windows(2) {
+            assert!(pair[0] > pair[1], "{} should rank above {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_string_round_trip() {
+        for severity in [Severity::Critical, Severity::None, Severity::Unknown] {
+            assert_eq!(severity.to_string().parse::<Severity>().unwrap(), severity);
+            assert_eq!(serde_json::to_string(&severity).unwrap(), format!("\"{}\"", severity));
+        }
+        assert_eq!("HIGH".parse::<Severity>().unwrap(), Severity::High);
+        assert!("severe".parse::<Severity>().is_err());
+    }
+}
@@ -0,0 +1,281 @@
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// Integrity manifest for generated outputs: `manifest.json` lists every output file with its
+// sha256, and `manifest.json.sig` holds a detached ed25519 signature over the manifest bytes.
+
+pub const MANIFEST_NAME: &str = "manifest.json";
+pub const SIGNATURE_NAME: &str = "manifest.json.sig";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub generated: String,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    // 相对于清单所在目录的路径
+    pub path: String,
+    pub sha256: String,
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(sha256_bytes(&bytes))
+}
+
+pub fn sha256_bytes(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 加载签名私钥：支持 OpenSSH ed25519 私钥（未加密）或 32 字节原始 seed 文件
+pub fn load_signing_key(path: &Path) -> Result<SigningKey> {
+    let bytes = fs::read(path).with_context(|| format!("failed to read signing key: {}", path.display()))?;
+    if bytes.len() == 32 {
+        let seed: [u8; 32] = bytes.try_into().expect("length checked");
+        return Ok(SigningKey::from_bytes(&seed));
+    }
+    let text = String::from_utf8(bytes).map_err(|_| anyhow!("signing key is neither a 32-byte seed nor an OpenSSH key"))?;
+    parse_openssh_private_key(&text)
+}
+
+/// 加载验证公钥：支持 OpenSSH 公钥行（`ssh-ed25519 AAAA...`）、OpenSSH 私钥或 32 字节原始公钥
+pub fn load_verifying_key(path: &Path) -> Result<VerifyingKey> {
+    let bytes = fs::read(path).with_context(|| format!("failed to read public key: {}", path.display()))?;
+    if bytes.len() == 32 {
+        let raw: [u8; 32] = bytes.try_into().expect("length checked");
+        return VerifyingKey::from_bytes(&raw).map_err(|e| anyhow!("invalid ed25519 public key: {}", e));
+    }
+    let text = String::from_utf8(bytes).map_err(|_| anyhow!("unrecognized public key format"))?;
+    if text.contains("OPENSSH PRIVATE KEY") {
+        return Ok(parse_openssh_private_key(&text)?.verifying_key());
+    }
+    let mut fields = text.split_whitespace();
+    if fields.next() != Some("ssh-ed25519") {
+        bail!("only ssh-ed25519 public keys are supported");
+    }
+    let blob = STANDARD
+        .decode(fields.next().ok_or_else(|| anyhow!("truncated OpenSSH public key"))?)
+        .context("invalid base64 in OpenSSH public key")?;
+    let mut reader = SshReader(&blob);
+    if reader.string()? != b"ssh-ed25519" {
+        bail!("only ssh-ed25519 public keys are supported");
+    }
+    let raw: [u8; 32] = reader.string()?.try_into().map_err(|_| anyhow!("bad ed25519 public key length"))?;
+    VerifyingKey::from_bytes(&raw).map_err(|e| anyhow!("invalid ed25519 public key: {}", e))
+}
+
+// Reader for the length-prefixed fields of the OpenSSH key format (PROTOCOL.key)
+struct SshReader<'a>(&'a [u8]);
+
+impl<'a> SshReader<'a> {
+    fn u32(&mut self) -> Result<u32> {
+        if self.0.len() < 4 {
+            bail!("truncated OpenSSH key");
+        }
+        let (head, rest) = self.0.split_at(4);
+        self.0 = rest;
+        Ok(u32::from_be_bytes(head.try_into().expect("length checked")))
+    }
+
+    fn string(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        if self.0.len() < len {
+            bail!("truncated OpenSSH key");
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+}
+
+fn parse_openssh_private_key(text: &str) -> Result<SigningKey> {
+    let body: String = text
+        .lines()
+        .filter(|l| !l.starts_with("-----"))
+        .map(str::trim)
+        .collect();
+    let blob = STANDARD.decode(body).context("invalid base64 in OpenSSH private key")?;
+    let magic = b"openssh-key-v1\0";
+    if !blob.starts_with(magic) {
+        bail!("not an OpenSSH private key");
+    }
+
+    let mut reader = SshReader(&blob[magic.len()..]);
+    let cipher = reader.string()?;
+    let _kdf = reader.string()?;
+    let _kdf_options = reader.string()?;
+    if cipher != b"none" {
+        bail!("encrypted OpenSSH keys are not supported; decrypt it with `ssh-keygen -p`");
+    }
+    if reader.u32()? != 1 {
+        bail!("OpenSSH key files with multiple keys are not supported");
+    }
+    let _public = reader.string()?;
+
+    let mut private = SshReader(reader.string()?);
+    if private.u32()? != private.u32()? {
+        bail!("corrupt OpenSSH private key (check bytes differ)");
+    }
+    if private.string()? != b"ssh-ed25519" {
+        bail!("only ed25519 OpenSSH keys are supported");
+    }
+    let _public = private.string()?;
+    // The private part is seed || public key
+    let secret = private.string()?;
+    if secret.len() != 64 {
+        bail!("bad ed25519 private key length");
+    }
+    let seed: [u8; 32] = secret[..32].try_into().expect("length checked");
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// 为输出文件生成 manifest.json 并写入分离签名（都在输出目录 dir 中），返回两个文件的路径；
+/// 清单中的路径相对于 dir，目录之外的文件（如 `--vex-output` 指向别处）以 `../` 开头
+pub fn write_signed_manifest(dir: &Path, files: &[PathBuf], key: &SigningKey) -> Result<(PathBuf, PathBuf)> {
+    let cwd = std::env::current_dir().context("cannot determine the current directory")?;
+    let base = crate::output::absolute(&cwd, dir);
+    let mut entries = Vec::new();
+    for file in files {
+        let rel = relative_to(&base, &crate::output::absolute(&cwd, file));
+        entries.push(ManifestEntry {
+            path: rel.to_string_lossy().replace('\\', "/"),
+            sha256: sha256_file(file)?,
+        });
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let manifest = Manifest { version: 1, generated: chrono::Utc::now().to_rfc3339(), files: entries };
+    let bytes = serde_json::to_vec_pretty(&manifest)?;
+    let signature = key.sign(&bytes);
+
+    let manifest_path = dir.join(MANIFEST_NAME);
+    let signature_path = dir.join(SIGNATURE_NAME);
+    crate::output::write_bytes(&manifest_path.to_string_lossy(), &bytes)
+        .with_context(|| format!("failed to write {}", manifest_path.display()))?;
+    crate::output::write_bytes(&signature_path.to_string_lossy(), &signature.to_bytes())
+        .with_context(|| format!("failed to write {}", signature_path.display()))?;
+    Ok((manifest_path, signature_path))
+}
+
+// Both absolute and free of `.` / `..`
+fn relative_to(base: &Path, path: &Path) -> PathBuf {
+    let common = base.components().zip(path.components()).take_while(|(a, b)| a == b).count();
+    let mut rel: PathBuf = base.components().skip(common).map(|_| Component::ParentDir).collect();
+    rel.extend(path.components().skip(common));
+    rel
+}
+
+/// 校验目录中的清单签名及每个文件的摘要；返回所有不一致之处（为空表示校验通过）
+pub fn verify_dir(dir: &Path, key: &VerifyingKey) -> Result<Vec<String>> {
+    let manifest_path = dir.join(MANIFEST_NAME);
+    let signature_path = dir.join(SIGNATURE_NAME);
+    let bytes = fs::read(&manifest_path).with_context(|| format!("failed to read {}", manifest_path.display()))?;
+    let sig_bytes = fs::read(&signature_path).with_context(|| format!("failed to read {}", signature_path.display()))?;
+    let signature = Signature::from_slice(&sig_bytes).map_err(|e| anyhow!("malformed signature file: {}", e))?;
+
+    if key.verify(&bytes, &signature).is_err() {
+        return Ok(vec![format!("{}: signature does not match the public key", MANIFEST_NAME)]);
+    }
+
+    let manifest: Manifest = serde_json::from_slice(&bytes).context("manifest.json is not valid")?;
+    let mut problems = Vec::new();
+    for entry in &manifest.files {
+        let path = dir.join(&entry.path);
+        if !path.exists() {
+            problems.push(format!("{}: missing", entry.path));
+            continue;
+        }
+        let actual = sha256_file(&path)?;
+        if actual != entry.sha256 {
+            problems.push(format!("{}: digest mismatch (expected {}, got {})", entry.path, entry.sha256, actual));
+        }
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
+
+    fn outputs(dir: &Path) -> Vec<PathBuf> {
+        let sbom = dir.join("sbom.json");
+        let report = dir.join("vuln_report.json");
+        fs::write(&sbom, "{\"bomFormat\":\"CycloneDX\"}").unwrap();
+        fs::write(&report, "{\"total_packages\":0}").unwrap();
+        vec![sbom, report]
+    }
+
+    #[test]
+    fn test_sign_and_verify_with_raw_seed() {
+        let dir = tempfile::tempdir().unwrap();
+        let seed_path = dir.path().join("seed");
+        fs::write(&seed_path, [7u8; 32]).unwrap();
+        let key = load_signing_key(&seed_path).unwrap();
+
+        let out = dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+        let files = outputs(&out);
+        write_signed_manifest(&out, &files, &key).unwrap();
+
+        assert!(verify_dir(&out, &key.verifying_key()).unwrap().is_empty());
+
+        // Tampering is pinpointed to the modified file
+        fs::write(out.join("vuln_report.json"), "{\"total_packages\":1}").unwrap();
+        let problems = verify_dir(&out, &key.verifying_key()).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("vuln_report.json: digest mismatch"));
+    }
+
+    #[test]
+    fn test_paths_relative_to_the_output_directory() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let root = tempfile::tempdir().unwrap();
+        let out = root.path().join("build/out");
+        fs::create_dir_all(&out).unwrap();
+        let mut files = outputs(&out);
+        // --vex-output next to the output directory, and the directory given relative to the cwd
+        let vex = root.path().join("build/vex.json");
+        fs::write(&vex, "{}").unwrap();
+        files.push(vex);
+        let dir = relative_to(&std::env::current_dir().unwrap(), &out);
+        write_signed_manifest(&dir, &files, &key).unwrap();
+
+        let manifest: Manifest = serde_json::from_slice(&fs::read(out.join(MANIFEST_NAME)).unwrap()).unwrap();
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["../vex.json", "sbom.json", "vuln_report.json"]);
+        assert!(verify_dir(&out, &key.verifying_key()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_openssh_key_pair_and_wrong_key() {
+        let key = load_signing_key(&fixture("ed25519_test_key")).unwrap();
+        let public = load_verifying_key(&fixture("ed25519_test_key.pub")).unwrap();
+        assert_eq!(key.verifying_key(), public);
+
+        let out = tempfile::tempdir().unwrap();
+        let files = outputs(out.path());
+        write_signed_manifest(out.path(), &files, &key).unwrap();
+        assert!(verify_dir(out.path(), &public).unwrap().is_empty());
+
+        let other = SigningKey::from_bytes(&[1u8; 32]).verifying_key();
+        let problems = verify_dir(out.path(), &other).unwrap();
+        assert!(problems[0].contains("signature"));
+    }
+}
@@ -0,0 +1,272 @@
+use crate::output::AtomicFile;
+use anyhow::{Context, Result, bail};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+// Where finished outputs end up. Every output is first written to its local path as
+// usual (atomically, honouring `--force`); without `--output-url` that is all. With it,
+// once the run is done, `publish` hands the files to an `OutputSink` picked by scheme:
+//
+//     file:///srv/scans/       copy the outputs into a directory
+//     s3://bucket/prefix/      upload to S3 or a compatible store (`s3` feature)
+//
+// Objects are named after the output's path relative to the output directory below the
+// URL's prefix, e.g. `s3://bucket/prefix/sbom.json`; an output written elsewhere keeps
+// just its file name, and two outputs that would get the same name fail the publish
+// before anything is sent. Each upload is retried with a growing delay; when one
+// still fails the others are attempted anyway, and the run fails with a message naming
+// what did and did not arrive. Another object store is one more `OutputSink` and one more
+// arm in `open`; nothing else in the pipeline knows about sinks.
+
+/// 输出的发布目标
+pub trait OutputSink: Send + Sync {
+    /// 发布本地文件 path，name 为目标中的对象名；返回发布后的位置（路径或 URL）
+    fn put(&self, name: &str, path: &Path, content_type: &str) -> Result<String>;
+
+    /// 目标本身的位置，用于消息
+    fn location(&self) -> String;
+}
+
+/// 文件系统目标（file:// URL）：复制到该目录，与其他输出一样原子写入、不覆盖已有文件（除非 --force）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSink(pub PathBuf);
+
+impl OutputSink for FileSink {
+    fn put(&self, name: &str, path: &Path, _content_type: &str) -> Result<String> {
+        let target = self.0.join(name);
+        let copy = || -> std::io::Result<()> {
+            let mut file = AtomicFile::create(&target)?;
+            std::io::copy(&mut fs::File::open(path)?, &mut file)?;
+            file.commit()
+        };
+        copy().with_context(|| format!("failed to copy to {}", target.display()))?;
+        Ok(target.display().to_string())
+    }
+
+    fn location(&self) -> String {
+        self.0.display().to_string()
+    }
+}
+
+/// 按 URL 的 scheme 选择目标
+pub fn open(url: &str) -> Result<Box<dyn OutputSink>> {
+    match url.split_once("://") {
+        Some(("file", path)) if !path.is_empty() => Ok(Box::new(FileSink(PathBuf::from(path)))),
+        Some(("s3", _)) => open_s3(ObjectLocation::parse(url)?),
+        _ => bail!("unsupported output URL: {} (expected s3://bucket/prefix/ or file:///directory)", url),
+    }
+}
+
+#[cfg(feature = "s3")]
+fn open_s3(location: ObjectLocation) -> Result<Box<dyn OutputSink>> {
+    Ok(Box::new(crate::s3_sink::S3Sink::new(location)?))
+}
+
+#[cfg(not(feature = "s3"))]
+fn open_s3(location: ObjectLocation) -> Result<Box<dyn OutputSink>> {
+    bail!("{} needs S3 support; rebuild rustpj with `--features s3`", location.url(""))
+}
+
+/// 对象存储中的位置：bucket 与对象名前缀（为空或以 `/` 结尾）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectLocation {
+    pub scheme: String,
+    pub bucket: String,
+    pub prefix: String,
+}
+
+impl ObjectLocation {
+    /// 解析 `<scheme>://<bucket>/<prefix>`
+    pub fn parse(url: &str) -> Result<Self> {
+        let Some((scheme, rest)) = url.split_once("://") else {
+            bail!("invalid object storage URL: {}", url);
+        };
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            bail!("object storage URL without a bucket: {}", url);
+        }
+        let mut prefix = prefix.trim_start_matches('/').to_string();
+        if !prefix.is_empty() && !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+        Ok(ObjectLocation { scheme: scheme.to_string(), bucket: bucket.to_string(), prefix })
+    }
+
+    pub fn key(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    pub fn url(&self, name: &str) -> String {
+        format!("{}://{}/{}", self.scheme, self.bucket, self.key(name))
+    }
+}
+
+/// 上传失败时的重试次数与首次重试前的等待时间（之后每次加倍）
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    pub attempts: u32,
+    pub delay: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Retry { attempts: 3, delay: Duration::from_secs(1) }
+    }
+}
+
+/// 对象的 Content-Type，按文件扩展名判断
+pub fn content_type(name: &str) -> &'static str {
+    match Path::new(name).extension().and_then(|e| e.to_str()) {
+        Some("json") => "application/json",
+        Some("gz") => "application/gzip",
+        Some("zip") => "application/zip",
+        Some("xml") => "application/xml",
+        Some("html") => "text/html",
+        Some("md") => "text/markdown",
+        Some("dot") => "text/vnd.graphviz",
+        Some("sig") => "application/octet-stream",
+        _ => "text/plain",
+    }
+}
+
+// Relative to the output directory, `/`-separated; the file name for anything outside it
+fn object_names(files: &[PathBuf], output_dir: &Path) -> Result<Vec<String>> {
+    let names: Vec<String> = files
+        .iter()
+        .map(|path| match path.strip_prefix(output_dir) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        })
+        .collect();
+    let mut seen: BTreeMap<&str, &Path> = BTreeMap::new();
+    for (name, path) in names.iter().zip(files) {
+        if let Some(first) = seen.insert(name, path) {
+            bail!("{} and {} would both be published as {}; write one of them elsewhere", first.display(), path.display(), name);
+        }
+    }
+    Ok(names)
+}
+
+/// 把 files 逐个交给 sink，对象名相对于 output_dir；返回各文件发布后的位置。任一文件重试后
+/// 仍失败时返回错误，错误信息列出已成功和失败的文件
+pub fn publish(sink: &dyn OutputSink, files: &[PathBuf], output_dir: &Path, retry: Retry) -> Result<Vec<String>> {
+    let names = object_names(files, output_dir)?;
+    let mut published = Vec::new();
+    let mut failed = Vec::new();
+    for (name, path) in names.iter().zip(files) {
+        let mut delay = retry.delay;
+        let mut attempt = 1;
+        loop {
+            match sink.put(name, path, content_type(name)) {
+                Ok(location) => {
+                    published.push(location);
+                    break;
+                }
+                Err(e) if attempt >= retry.attempts.max(1) => {
+                    failed.push(format!("{}: {:#}", name, e));
+                    break;
+                }
+                Err(e) => {
+                    crate::console::warn("upload-retry", format!("uploading {} failed, retrying: {:#}", name, e));
+                    thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+    if !failed.is_empty() {
+        let uploaded = if published.is_empty() { "none".to_string() } else { published.join(", ") };
+        bail!(
+            "failed to upload {} of {} outputs to {} after {} attempts:\n  {}\nuploaded: {}",
+            failed.len(),
+            files.len(),
+            sink.location(),
+            retry.attempts.max(1),
+            failed.join("\n  "),
+            uploaded
+        );
+    }
+    Ok(published)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // Fails the first `failures` attempts for every name containing `flaky`, and always for `broken`
+    struct FlakySink {
+        failures: u32,
+        attempts: Mutex<Vec<String>>,
+    }
+
+    impl OutputSink for FlakySink {
+        fn put(&self, name: &str, _path: &Path, content_type: &str) -> Result<String> {
+            let mut attempts = self.attempts.lock().unwrap();
+            attempts.push(format!("{} {}", name, content_type));
+            let tries = attempts.iter().filter(|a| a.starts_with(name)).count() as u32;
+            if name.contains("broken") || (name.contains("flaky") && tries <= self.failures) {
+                bail!("503 Slow Down");
+            }
+            Ok(format!("mem://{}", name))
+        }
+
+        fn location(&self) -> String {
+            "mem://".to_string()
+        }
+    }
+
+    #[test]
+    fn test_publish_retries_and_lists_outcomes() {
+        let retry = Retry { attempts: 3, delay: Duration::ZERO };
+        let sink = FlakySink { failures: 2, attempts: Mutex::new(Vec::new()) };
+        let files = [PathBuf::from("out/sbom.json"), PathBuf::from("out/flaky.zip")];
+        assert_eq!(publish(&sink, &files, Path::new("out"), retry).unwrap(), ["mem://sbom.json", "mem://flaky.zip"]);
+        assert_eq!(sink.attempts.lock().unwrap()[0], "sbom.json application/json");
+        assert_eq!(sink.attempts.lock().unwrap().len(), 4);
+
+        let files = [PathBuf::from("out/broken.json"), PathBuf::from("out/report.html")];
+        let error = publish(&sink, &files, Path::new("out"), retry).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "failed to upload 1 of 2 outputs to mem:// after 3 attempts:\n  broken.json: 503 Slow Down\nuploaded: mem://report.html"
+        );
+    }
+
+    #[test]
+    fn test_object_names_keep_subdirectories_and_refuse_collisions() {
+        let files = [PathBuf::from("out/sbom.json"), PathBuf::from("out/app/sbom.json"), PathBuf::from("reports/scan.html")];
+        assert_eq!(object_names(&files, Path::new("out")).unwrap(), ["sbom.json", "app/sbom.json", "scan.html"]);
+
+        let sink = FlakySink { failures: 0, attempts: Mutex::new(Vec::new()) };
+        let files = [PathBuf::from("out/report.html"), PathBuf::from("elsewhere/report.html")];
+        let error = publish(&sink, &files, Path::new("out"), Retry::default()).unwrap_err().to_string();
+        assert_eq!(error, "out/report.html and elsewhere/report.html would both be published as report.html; write one of them elsewhere");
+        assert!(sink.attempts.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_locations() {
+        let location = ObjectLocation::parse("s3://scans/ci/main").unwrap();
+        assert_eq!((location.bucket.as_str(), location.prefix.as_str()), ("scans", "ci/main/"));
+        assert_eq!(location.url("sbom.json"), "s3://scans/ci/main/sbom.json");
+        assert_eq!(ObjectLocation::parse("s3://scans").unwrap().key("sbom.json"), "sbom.json");
+        assert!(ObjectLocation::parse("s3:///prefix/").is_err());
+        assert!(open("ftp://host/dir").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("sbom.json");
+        fs::write(&source, "{}").unwrap();
+        let sink = open(&format!("file://{}", dir.path().join("published").display())).unwrap();
+        let files = [source];
+        let published = publish(sink.as_ref(), &files, dir.path(), Retry::default()).unwrap();
+        assert_eq!(fs::read_to_string(&published[0]).unwrap(), "{}");
+        // Like any other output, an existing file is only replaced with --force
+        let again = publish(sink.as_ref(), &files, dir.path(), Retry { attempts: 1, delay: Duration::ZERO }).unwrap_err();
+        assert!(format!("{:#}", again).contains("already exists; pass --force"));
+    }
+}
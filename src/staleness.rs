@@ -0,0 +1,325 @@
+use crate::online::{CrateResponse, OnlineConfig};
+use anyhow::{Context, Result, bail};
+use cargo_lock::Lockfile;
+use chrono::{DateTime, NaiveDate};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Staleness heuristic (`--check-staleness`): crates.io packages whose most recent release
+// is older than a threshold are listed in the report's `stale_packages`. A crate nobody
+// has published in years is not necessarily unmaintained, so this is advisory only; it
+// changes the exit code only through a `[[policy]]` rule with `when = "stale"`. The same
+// release history says whether the locked version itself was yanked (`yanked_packages`,
+// `when = "yanked"`).
+//
+// The publish dates come from the registry API (`GET /api/v1/crates/<name>`, needs
+// `--online`) or from a local snapshot (`--crates-snapshot <dir>`), a directory of those
+// responses saved as `<name>.json`. Registry responses are cached in the same layout, so
+// the cache directory of one machine can serve as the snapshot of another; a cached
+// response younger than `cache_ttl_hours` is used instead of asking the registry again,
+// which keeps batch scans of many similar projects from hammering it:
+//
+//     [staleness]
+//     max_age_days = 1095                   # optional, defaults to 3 years
+//     cache_dir = "./data/crates-cache"     # optional
+//     cache_ttl_hours = 24                  # optional
+
+pub const DEFAULT_MAX_AGE_DAYS: u32 = 3 * 365;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StalenessConfig {
+    // 最新版本发布超过这么多天即视为 stale
+    pub max_age_days: u32,
+    // registry 响应的本地缓存目录
+    pub cache_dir: String,
+    pub cache_ttl_hours: u64,
+}
+
+impl Default for StalenessConfig {
+    fn default() -> Self {
+        StalenessConfig {
+            max_age_days: DEFAULT_MAX_AGE_DAYS,
+            cache_dir: "./data/crates-cache".to_string(),
+            cache_ttl_hours: 24,
+        }
+    }
+}
+
+/// 最新版本发布已久的依赖
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StalePackage {
+    pub package_name: String,
+    pub package_version: String,
+    // 最近一次发布的（未撤回的）版本及其发布日期
+    pub latest_version: String,
+    pub latest_release_date: String,
+    pub age_days: i64,
+}
+
+/// 锁定的版本已被撤回（yank）的依赖
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct YankedPackage {
+    pub package_name: String,
+    pub package_version: String,
+}
+
+/// `StalenessCheck::check` 的结果
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReleaseCheck {
+    pub stale: Vec<StalePackage>,
+    pub yanked: Vec<YankedPackage>,
+}
+
+/// 发布日期的来源
+#[derive(Debug, Clone)]
+pub enum ReleaseSource {
+    // 查询 registry，响应缓存在 cache_dir 中
+    Registry { online: OnlineConfig, cache_dir: PathBuf, ttl: Duration },
+    // 只读的本地快照目录（--crates-snapshot）
+    Snapshot(PathBuf),
+}
+
+pub struct StalenessCheck {
+    source: ReleaseSource,
+    max_age_days: u32,
+    today: NaiveDate,
+}
+
+impl StalenessCheck {
+    pub fn new(source: ReleaseSource, config: &StalenessConfig, today: NaiveDate) -> Self {
+        StalenessCheck { source, max_age_days: config.max_age_days, today }
+    }
+
+    pub fn max_age_days(&self) -> u32 {
+        self.max_age_days
+    }
+
+    /// 检查 lockfile 中来自 crates.io 的包；查不到发布历史的 crate 记为警告并跳过
+    pub fn check(&self, lockfile: &Lockfile) -> ReleaseCheck {
+        let registry: Vec<_> = lockfile.packages.iter().filter(|p| crate::online::is_default_crates_io(&p.source)).collect();
+        let names: BTreeSet<&str> = registry.iter().map(|p| p.name.as_str()).collect();
+        let mut histories = BTreeMap::new();
+        let mut missing = Vec::new();
+        for name in names {
+            match self.release_history(name) {
+                Ok(Some(history)) => {
+                    histories.insert(name, history);
+                }
+                Ok(None) => missing.push(name),
+                Err(e) => crate::console::warn(
+                    "staleness-unavailable",
+                    format!("could not look up the release history of {}: {:#}", name, e),
+                ),
+            }
+        }
+        // A snapshot usually covers only part of the graph; one warning instead of one per crate
+        if !missing.is_empty() {
+            crate::console::warn(
+                "staleness-unavailable",
+                format!("{} crate(s) not in the crates snapshot, not checked: {}", missing.len(), missing.join(", ")),
+            );
+        }
+        let mut stale: Vec<StalePackage> = registry
+            .iter()
+            .filter_map(|p| {
+                let (version, date) = histories.get(p.name.as_str())?.latest.as_ref()?;
+                let age_days = (self.today - *date).num_days();
+                (age_days > i64::from(self.max_age_days)).then(|| StalePackage {
+                    package_name: p.name.to_string(),
+                    package_version: p.version.to_string(),
+                    latest_version: version.to_string(),
+                    latest_release_date: date.to_string(),
+                    age_days,
+                })
+            })
+            .collect();
+        stale.sort_by(|a, b| (&a.package_name, &a.package_version).cmp(&(&b.package_name, &b.package_version)));
+        stale.dedup();
+        let mut yanked: Vec<YankedPackage> = registry
+            .iter()
+            .filter(|p| histories.get(p.name.as_str()).is_some_and(|h| h.yanked.contains(&p.version)))
+            .map(|p| YankedPackage { package_name: p.name.to_string(), package_version: p.version.to_string() })
+            .collect();
+        yanked.sort_by(|a, b| (&a.package_name, &a.package_version).cmp(&(&b.package_name, &b.package_version)));
+        yanked.dedup();
+        ReleaseCheck { stale, yanked }
+    }
+
+    // None: the snapshot has no response for this crate
+    fn release_history(&self, name: &str) -> Result<Option<ReleaseHistory>> {
+        let body = match &self.source {
+            ReleaseSource::Snapshot(dir) => {
+                let path = cache_path(dir, name);
+                match fs::read_to_string(&path) {
+                    Ok(body) => body,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                    Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+                }
+            }
+            ReleaseSource::Registry { online, cache_dir, ttl } => {
+                let path = cache_path(cache_dir, name);
+                match read_fresh(&path, *ttl) {
+                    Some(body) => body,
+                    None => {
+                        let body = online.fetch_crate(name)?;
+                        // The cache only saves requests; a failed write is no reason to give up
+                        let _ = write_cache(&path, &body);
+                        body
+                    }
+                }
+            }
+        };
+        parse_release_history(&body).map(Some)
+    }
+}
+
+// Named exactly as the package in Cargo.lock, which is how crates.io spells it
+fn cache_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.json", name))
+}
+
+fn read_fresh(path: &Path, ttl: Duration) -> Option<String> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    if modified.elapsed().unwrap_or_default() >= ttl {
+        return None;
+    }
+    fs::read_to_string(path).ok()
+}
+
+// Written under a temporary name and renamed, so a parallel batch worker never reads half a file
+fn write_cache(path: &Path, body: &str) -> std::io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+    let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+    std::io::Write::write_all(&mut temp, body.as_bytes())?;
+    temp.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+struct ReleaseHistory {
+    // 最近一次发布的未撤回版本及其发布日期；所有版本都已撤回时为 None
+    latest: Option<(Version, NaiveDate)>,
+    yanked: BTreeSet<Version>,
+}
+
+fn parse_release_history(body: &str) -> Result<ReleaseHistory> {
+    let versions = serde_json::from_str::<CrateResponse>(body).context("unexpected registry response")?.versions;
+    if versions.is_empty() {
+        bail!("no published versions");
+    }
+    let mut latest: Option<(Version, DateTime<chrono::FixedOffset>)> = None;
+    let mut yanked = BTreeSet::new();
+    for published in &versions {
+        if published.yanked {
+            yanked.insert(published.num.parse()?);
+            continue;
+        }
+        let at = DateTime::parse_from_rfc3339(&published.created_at)
+            .with_context(|| format!("invalid publish date for {}: {}", published.num, published.created_at))?;
+        if latest.as_ref().is_none_or(|(_, newest)| at > *newest) {
+            latest = Some((published.num.parse()?, at));
+        }
+    }
+    Ok(ReleaseHistory { latest: latest.map(|(version, at)| (version, at.date_naive())), yanked })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "ansi_term"
+version = "0.12.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "log"
+version = "0.4.22"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "local"
+version = "0.1.0"
+"#;
+
+    #[test]
+    fn test_latest_release_skips_yanked_versions() {
+        let body = r#"{"crate": {"name": "demo"}, "versions": [
+            {"num": "0.3.0", "created_at": "2023-05-01T10:00:00.000000+00:00", "yanked": true},
+            {"num": "0.2.1", "created_at": "2020-02-01T10:00:00.000000+00:00", "yanked": false},
+            {"num": "0.1.9", "created_at": "2020-03-01T10:00:00.000000+00:00", "yanked": false}
+        ]}"#;
+        // A backport published after the newer minor counts as the latest release
+        let history = parse_release_history(body).unwrap();
+        let (version, date) = history.latest.unwrap();
+        assert_eq!((version.to_string(), date.to_string()), ("0.1.9".to_string(), "2020-03-01".to_string()));
+        assert_eq!(history.yanked, BTreeSet::from([Version::new(0, 3, 0)]));
+        let all_yanked = r#"{"versions": [{"num": "0.1.0", "created_at": "2020-01-01T10:00:00+00:00", "yanked": true}]}"#;
+        assert_eq!(parse_release_history(all_yanked).unwrap().latest, None);
+        assert!(parse_release_history(r#"{"versions": []}"#).is_err());
+        assert!(parse_release_history(r#"{"errors": [{"detail": "Not Found"}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_lookup_flags_old_releases() {
+        let dir = tempfile::tempdir().unwrap();
+        let release = |num: &str, at: &str| format!(r#"{{"versions": [{{"num": "{}", "created_at": "{}"}}]}}"#, num, at);
+        fs::write(dir.path().join("ansi_term.json"), release("0.12.1", "2019-07-30T12:00:00+00:00")).unwrap();
+        fs::write(dir.path().join("serde.json"), release("1.0.210", "2024-09-06T12:00:00+00:00")).unwrap();
+        let yanked = r#"{"versions": [
+            {"num": "0.4.22", "created_at": "2024-06-27T12:00:00+00:00", "yanked": true},
+            {"num": "0.4.21", "created_at": "2024-02-15T12:00:00+00:00"}
+        ]}"#;
+        fs::write(dir.path().join("log.json"), yanked).unwrap();
+
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 10, 1).unwrap();
+        let check = StalenessCheck::new(ReleaseSource::Snapshot(dir.path().to_path_buf()), &StalenessConfig::default(), today);
+        let checked = check.check(&lockfile);
+        assert_eq!(
+            checked.stale,
+            [StalePackage {
+                package_name: "ansi_term".to_string(),
+                package_version: "0.12.1".to_string(),
+                latest_version: "0.12.1".to_string(),
+                latest_release_date: "2019-07-30".to_string(),
+                age_days: 1890,
+            }]
+        );
+
+        assert_eq!(checked.yanked, [YankedPackage { package_name: "log".to_string(), package_version: "0.4.22".to_string() }]);
+
+        fs::remove_file(dir.path().join("log.json")).unwrap();
+        check.check(&lockfile);
+        let warnings = crate::console::warnings();
+        assert!(warnings.iter().any(|w| w.message == "1 crate(s) not in the crates snapshot, not checked: log"));
+
+        let config = StalenessConfig { max_age_days: 10, ..Default::default() };
+        let check = StalenessCheck::new(ReleaseSource::Snapshot(dir.path().to_path_buf()), &config, today);
+        assert_eq!(check.check(&lockfile).stale.len(), 2);
+    }
+
+    #[test]
+    fn test_fresh_cache_entries_are_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = cache_path(dir.path(), "serde-json");
+        assert!(path.ends_with("serde-json.json"));
+        assert!(read_fresh(&path, Duration::from_secs(3600)).is_none());
+        write_cache(&path, "{}").unwrap();
+        assert_eq!(read_fresh(&path, Duration::from_secs(3600)).as_deref(), Some("{}"));
+        assert!(read_fresh(&path, Duration::ZERO).is_none());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::scanner::{AffectedRange, ReportMetadata, Summary, VulnReport, PackageReport};
+use crate::severity::Severity;
+use crate::vex::Analysis;
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::error::Error as _;
+use std::fs;
+use std::path::Path;
+use tera::Tera;
+
+// Custom report rendering through Tera templates (`--report-template`).
+//
+// The context handed to templates is a semi-public contract; changing or removing a
+// field breaks users' templates, so only ever add to it. Available variables:
+//
+// - `metadata`        tool, tool_version, generated_at, advisory_db_commit
+// - `summary`         total_vulnerabilities, unique_advisories, vulnerable_packages, risk_score, unfixable,
+//                     by_severity.{critical,high,medium,low,unknown}
+// - `total_packages`  number of packages in the lockfile
+// - `packages`        vulnerable packages, most severe first: package_name, package_version,
+//                     max_severity, advisories[]
+// - `findings`        every advisory flattened with its package, see `FindingContext`
+// - `fixable`         the findings that have a patched release
+// - `unfixable`       the findings without any patched release (`no_fix_available`)
+// - `report`          the full report exactly as it is serialized to JSON
+#[derive(Debug, Serialize)]
+pub struct TemplateContext<'a> {
+    pub metadata: &'a ReportMetadata,
+    pub summary: &'a Summary,
+    pub total_packages: usize,
+    pub packages: &'a [PackageReport],
+    pub findings: Vec<FindingContext<'a>>,
+    pub fixable: Vec<FindingContext<'a>>,
+    pub unfixable: Vec<FindingContext<'a>>,
+    pub report: &'a VulnReport,
+}
+
+// One row per (package, advisory) pair
+#[derive(Debug, Clone, Serialize)]
+pub struct FindingContext<'a> {
+    pub package_name: &'a str,
+    pub package_version: &'a str,
+    pub id: &'a str,
+    pub description: &'a str,
+    // critical/high/medium/low, or absent when the advisory has no CVSS score
+    pub severity: Option<Severity>,
+    // 被 severity_overrides 覆盖前的上游评级
+    pub upstream_severity: Option<Severity>,
+    pub unaffected_versions: &'a str,
+    pub patched_versions: Option<&'a str>,
+    pub unaffected: &'a [String],
+    pub patched: &'a [String],
+    pub affected_ranges: &'a [AffectedRange],
+    pub references: &'a [String],
+    pub no_fix_available: bool,
+    pub remediation: &'a str,
+    pub analysis: Option<&'a Analysis>,
+}
+
+impl<'a> TemplateContext<'a> {
+    pub fn new(report: &'a VulnReport) -> Self {
+        let findings: Vec<FindingContext> = report
+            .packages
+            .iter()
+            .flat_map(|pkg| {
+                pkg.advisories.iter().map(move |f| FindingContext {
+                    package_name: &pkg.package_name,
+                    package_version: &pkg.package_version,
+                    id: &f.id,
+                    description: &f.description,
+                    severity: f.severity.known(),
+                    upstream_severity: f.upstream_severity,
+                    unaffected_versions: &f.unaffected_versions,
+                    patched_versions: f.patched_versions.as_deref(),
+                    unaffected: &f.unaffected,
+                    patched: &f.patched,
+                    affected_ranges: &f.affected_ranges,
+                    references: &f.references,
+                    no_fix_available: f.no_fix_available,
+                    remediation: &f.remediation,
+                    analysis: f.analysis.as_ref(),
+                })
+            })
+            .collect();
+        let (unfixable, fixable) = findings.iter().cloned().partition(|f| f.no_fix_available);
+        TemplateContext {
+            metadata: &report.metadata,
+            summary: &report.summary,
+            total_packages: report.total_packages,
+            packages: &report.packages,
+            findings,
+            fixable,
+            unfixable,
+            report,
+        }
+    }
+}
+
+// Tera reports the failing line/column in the error's source chain, not the top-level message
+fn describe(err: &tera::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        message.push_str(&format!("\n  {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+/// 用模板渲染报告；HTML 模板（文件名含 .html）自动转义
+pub fn render_str(name: &str, template: &str, report: &VulnReport) -> Result<String> {
+    let mut tera = Tera::default();
+    tera.autoescape_on(vec![".html.tera", ".html", ".htm"]);
+    tera.add_raw_template(name, template)
+        .map_err(|e| anyhow!("failed to parse template {}: {}", name, describe(&e)))?;
+    let context = tera::Context::from_serialize(TemplateContext::new(report))
+        .map_err(|e| anyhow!("failed to build template context: {}", describe(&e)))?;
+    tera.render(name, &context)
+        .map_err(|e| anyhow!("failed to render template {}: {}", name, describe(&e)))
+}
+
+pub fn render_file(path: &Path, report: &VulnReport) -> Result<String> {
+    let template = fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read template {}: {}", path.display(), e))?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "report.tera".to_string());
+    render_str(&name, &template, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{AdvisoryFinding, SeverityCounts};
+
+    fn fixed_report() -> VulnReport {
+        VulnReport {
+            metadata: ReportMetadata {
+                tool: "rustpj".to_string(),
+                tool_version: "0.1.0".to_string(),
+                generated_at: "2024-01-01T00:00:00+00:00".to_string(),
+                advisory_db_commit: Some("abc123".to_string()),
+                ..Default::default()
+            },
+            total_packages: 3,
+            packages: vec![
+                PackageReport {
+                    package_name: "time".to_string(),
+                    package_version: "0.1.45".to_string(),
+                    max_severity: Severity::Medium,
+                    advisories: vec![AdvisoryFinding {
+                        id: "RUSTSEC-2020-0071".to_string(),
+                        description: "Potential segfault in <localtime_r>".to_string(),
+                        severity: Severity::Medium,
+                        patched_versions: Some(">=0.2.23".to_string()),
+                        patched: vec![">=0.2.23".to_string()],
+                        affected_ranges: vec![AffectedRange { introduced: None, fixed: Some("0.2.23".to_string()) }],
+                        references: vec!["https://example.com/advisory".to_string()],
+                        remediation: "Upgrade to >=0.2.23".to_string(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                PackageReport {
+                    package_name: "ansi_term".to_string(),
+                    package_version: "0.12.1".to_string(),
+                    max_severity: Severity::Unknown,
+                    advisories: vec![AdvisoryFinding {
+                        id: "RUSTSEC-2021-0139".to_string(),
+                        description: "ansi_term is unmaintained".to_string(),
+                        severity: Severity::Unknown,
+                        affected_ranges: vec![AffectedRange { introduced: None, fixed: None }],
+                        references: vec!["https://crates.io/crates/nu-ansi-term".to_string()],
+                        no_fix_available: true,
+                        remediation: "No fixed release; consider replacing ansi_term (see https://crates.io/crates/nu-ansi-term)"
+                            .to_string(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            ],
+            summary: Summary {
+                total_vulnerabilities: 2,
+                unique_advisories: 2,
+                vulnerable_packages: 2,
+                by_severity: SeverityCounts { medium: 1, unknown: 1, ..Default::default() },
+                risk_score: 2,
+                unfixable: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn example(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(name)
+    }
+
+    #[test]
+    fn test_example_templates_render_expected_output() {
+        let report = fixed_report();
+        for (template, expected) in [
+            ("templates/report.md.tera", "tests/fixtures/template_report.md"),
+            ("templates/report.html.tera", "tests/fixtures/template_report.html"),
+        ] {
+            let rendered = render_file(&example(template), &report).unwrap();
+            let expected = fs::read_to_string(example(expected)).unwrap();
+            assert_eq!(rendered, expected, "{} output changed", template);
+        }
+    }
+
+    #[test]
+    fn test_template_error_has_line_number() {
+        let err = render_str("bad.tera", "line one\n{% for x in findings %}\n{{ x.id }\n", &fixed_report())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("3:"), "no line number in: {}", err);
+    }
+}
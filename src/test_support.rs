@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Advisory fixtures for tests: RustSec advisory markdown and advisory DBs committed to a
+// git repository, the only form `Scanner` loads a DB from. The unit tests use this module
+// directly; the integration tests and benchmarks include it by path from tests/support,
+// so it depends on nothing but std, git2 and rustsec.
+
+/// 测试用 advisory 的 Markdown；默认在 2.0.0 修复，没有 CVSS 分数、别名等元数据
+#[derive(Debug, Clone)]
+pub struct AdvisoryText {
+    id: String,
+    package: String,
+    date: String,
+    metadata: String,
+    versions: String,
+    title: String,
+}
+
+impl AdvisoryText {
+    pub fn new(id: &str, package: &str) -> Self {
+        AdvisoryText {
+            id: id.to_string(),
+            package: package.to_string(),
+            date: "2020-01-01".to_string(),
+            metadata: String::new(),
+            versions: "patched = [\">= 2.0.0\"]".to_string(),
+            title: "Test advisory".to_string(),
+        }
+    }
+
+    pub fn with_date(mut self, date: &str) -> Self {
+        self.date = date.to_string();
+        self
+    }
+
+    /// 追加在 `[advisory]` 表末尾的 TOML 行（cvss、aliases、withdrawn 等），也可以接着开始
+    /// `[affected]` 表
+    pub fn with_metadata(mut self, toml: &str) -> Self {
+        self.metadata.push_str(toml);
+        if !self.metadata.ends_with('\n') {
+            self.metadata.push('\n');
+        }
+        self
+    }
+
+    /// `[versions]` 表的内容
+    pub fn with_versions(mut self, versions: &str) -> Self {
+        self.versions = versions.to_string();
+        self
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn markdown(&self) -> String {
+        format!(
+            "```toml\n[advisory]\nid = \"{}\"\npackage = \"{}\"\ndate = \"{}\"\n{}\n[versions]\n{}\n```\n\n# {}\n\nDescription.\n",
+            self.id, self.package, self.date, self.metadata, self.versions, self.title
+        )
+    }
+
+    pub fn to_advisory(&self) -> rustsec::Advisory {
+        self.markdown().parse().unwrap()
+    }
+
+    /// 按 RustSec 的目录结构写入 db 的 `<collection>/<package>/<id>.md`（collection 为 crates 或 rust）
+    pub fn write_to(&self, db: &Path, collection: &str) {
+        let dir = db.join(collection).join(&self.package);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(format!("{}.md", self.id)), self.markdown()).unwrap();
+    }
+
+    /// 写入 db 的 `crates/<package>/<id>.md`
+    pub fn write(&self, db: &Path) {
+        self.write_to(db, "crates");
+    }
+}
+
+/// 把目录中的所有文件提交到 git 仓库（没有时新建），返回提交 id
+pub fn commit_all(dir: &Path) -> git2::Oid {
+    let repo = git2::Repository::open(dir).or_else(|_| git2::Repository::init(dir)).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = git2::Signature::now("test", "test@example.com").unwrap();
+    let parents: Vec<git2::Commit> = repo.head().ok().and_then(|h| h.peel_to_commit().ok()).into_iter().collect();
+    let parents: Vec<&git2::Commit> = parents.iter().collect();
+    repo.commit(Some("HEAD"), &sig, &sig, "advisories", &tree, &parents).unwrap()
+}
+
+/// 写入一条 advisory 并提交，返回提交 id
+pub fn commit_advisory(db: &Path, advisory: &AdvisoryText) -> git2::Oid {
+    advisory.write(db);
+    commit_all(db)
+}
+
+/// 在 root/advisory-db 下写入这些 advisory 并提交到一个新的 git 仓库
+pub fn advisory_db(root: &Path, advisories: &[AdvisoryText]) -> PathBuf {
+    let db = root.join("advisory-db");
+    fs::create_dir_all(&db).unwrap();
+    for advisory in advisories {
+        advisory.write(&db);
+    }
+    commit_all(&db);
+    db
+}
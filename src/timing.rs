@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// 记录各阶段的耗时（按执行顺序）
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    stages: Vec<(String, Duration)>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 执行 f 并以 stage 为名记录耗时；同名阶段的耗时会累加
+    pub fn time<T>(&mut self, stage: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(stage, start.elapsed());
+        result
+    }
+
+    /// 记录一个阶段的耗时，并发出 `stage_complete` 进度事件
+    pub fn record(&mut self, stage: &str, elapsed: Duration) {
+        crate::progress::emit(crate::progress::ProgressEvent::StageComplete {
+            stage: stage.to_string(),
+            duration_ms: elapsed.as_millis() as u64,
+        });
+        match self.stages.iter_mut().find(|(name, _)| name == stage) {
+            Some((_, total)) => *total += elapsed,
+            None => self.stages.push((stage.to_string(), elapsed)),
+        }
+    }
+
+    pub fn get(&self, stage: &str) -> Option<Duration> {
+        self.stages.iter().find(|(name, _)| name == stage).map(|(_, d)| *d)
+    }
+
+    /// 以秒为单位的阶段耗时，写入报告的 `metadata.timings`
+    pub fn to_map(&self) -> BTreeMap<String, f64> {
+        self.stages
+            .iter()
+            .map(|(name, d)| (name.clone(), (d.as_secs_f64() * 1000.0).round() / 1000.0))
+            .collect()
+    }
+
+    /// 一行式的耗时概览，例如 `extract 1.2s | licenses 4.8s | scan 0.3s`
+    pub fn summary_line(&self) -> String {
+        self.stages
+            .iter()
+            .map(|(name, d)| format!("{} {:.1}s", name, d.as_secs_f64()))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_in_order_and_accumulates() {
+        let mut timings = Timings::new();
+        timings.record("extract", Duration::from_millis(1200));
+        assert_eq!(timings.time("scan", || 42), 42);
+        timings.record("extract", Duration::from_millis(300));
+
+        assert_eq!(timings.get("extract"), Some(Duration::from_millis(1500)));
+        assert!(timings.summary_line().starts_with("extract 1.5s | scan 0.0s"));
+        assert_eq!(timings.to_map()["extract"], 1.5);
+    }
+}
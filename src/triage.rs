@@ -0,0 +1,289 @@
+use crate::config::IgnoreEntry;
+use crate::output::{self, OutputSettings};
+use crate::scanner::{AdvisoryFinding, PackageReport, VulnReport};
+use crate::vex::AnalysisState;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+// Interactive triage (`--interactive`): after the scan, every finding that no `[[ignore]]`
+// entry covers yet is shown in report order (most severe first), and the answer becomes an
+// `[[ignore]]` entry with the reason as its `detail`:
+//
+//     i  ignore      not a real issue here   -> state = "false_positive"
+//     c  accept      risk accepted           -> state = "not_affected"
+//     f  fix later   affected, fix pending   -> state = "exploitable"
+//     s  skip        no entry (also Enter)
+//     a  all remaining: ask once for a decision and reason, apply it to the rest
+//     q  quit        keep the decisions made so far
+//
+// The entries are appended to the triage file, which is an ordinary config file: passing
+// it (or the `--config` it was appended to) on the next run suppresses exactly the
+// ignored and accepted findings, while "fix later" ones are recorded but still count.
+// Prompts go to stderr, so `--report -` keeps stdout for the report; without a terminal
+// on stdin the prompts are skipped with an `interactive-skipped` warning.
+
+/// --triage-file 的默认值
+pub const DEFAULT_TRIAGE_FILE: &str = "./triage.toml";
+
+/// 对一条漏洞的分诊决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Ignore,
+    Accept,
+    FixLater,
+}
+
+impl Decision {
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "i" => Some(Decision::Ignore),
+            "c" => Some(Decision::Accept),
+            "f" => Some(Decision::FixLater),
+            _ => None,
+        }
+    }
+
+    /// 写入 `[[ignore]]` 的分诊状态；只有 fix later 不会屏蔽该漏洞
+    pub fn state(self) -> AnalysisState {
+        match self {
+            Decision::Ignore => AnalysisState::FalsePositive,
+            Decision::Accept => AnalysisState::NotAffected,
+            Decision::FixLater => AnalysisState::Exploitable,
+        }
+    }
+}
+
+/// 逐条询问尚未分诊的漏洞，返回做出决定的 `[[ignore]]` 条目；titles 为 advisory id -> 标题
+pub fn prompt(
+    report: &VulnReport,
+    titles: &BTreeMap<String, String>,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<Vec<IgnoreEntry>> {
+    let pending: Vec<(&PackageReport, &AdvisoryFinding)> = report
+        .packages
+        .iter()
+        .flat_map(|p| p.advisories.iter().filter(|f| f.analysis.is_none()).map(move |f| (p, f)))
+        .collect();
+    let mut entries = Vec::new();
+    let mut remaining: Option<(Decision, String)> = None;
+    for (i, &(package, finding)) in pending.iter().enumerate() {
+        if let Some((decision, reason)) = &remaining {
+            entries.push(entry(package, finding, *decision, reason));
+            continue;
+        }
+        show(out, i + 1, pending.len(), package, finding, titles.get(&finding.id))?;
+        loop {
+            let Some(key) = ask(input, out, "[i]gnore, a[c]cept, [f]ix later, [s]kip, [a]ll remaining, [q]uit: ")? else {
+                return Ok(entries);
+            };
+            if let Some(decision) = Decision::from_key(&key) {
+                let Some(reason) = ask_reason(input, out)? else { return Ok(entries) };
+                entries.push(entry(package, finding, decision, &reason));
+                break;
+            }
+            match key.as_str() {
+                "s" | "" => break,
+                "q" => return Ok(entries),
+                "a" => {
+                    let question = format!("Decision for this and the {} remaining finding(s) [i/c/f]: ", pending.len() - i - 1);
+                    let decision = loop {
+                        let Some(key) = ask(input, out, &question)? else { return Ok(entries) };
+                        if let Some(decision) = Decision::from_key(&key) {
+                            break decision;
+                        }
+                    };
+                    let Some(reason) = ask_reason(input, out)? else { return Ok(entries) };
+                    entries.push(entry(package, finding, decision, &reason));
+                    remaining = Some((decision, reason));
+                    break;
+                }
+                other => writeln!(out, "Unknown choice: {}", other)?,
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn show(
+    out: &mut dyn Write,
+    index: usize,
+    total: usize,
+    package: &PackageReport,
+    finding: &AdvisoryFinding,
+    title: Option<&String>,
+) -> Result<()> {
+    writeln!(out, "\n[{}/{}] {} in {}@{} ({})", index, total, finding.id, package.package_name, package.package_version, finding.severity)?;
+    if let Some(title) = title {
+        writeln!(out, "  {}", title)?;
+    }
+    let affected: Vec<String> = finding
+        .affected_ranges
+        .iter()
+        .map(|r| match (&r.introduced, &r.fixed) {
+            (Some(introduced), Some(fixed)) => format!(">= {}, < {}", introduced, fixed),
+            (Some(introduced), None) => format!(">= {}", introduced),
+            (None, Some(fixed)) => format!("< {}", fixed),
+            (None, None) => "all versions".to_string(),
+        })
+        .collect();
+    writeln!(out, "  Affected: {}", if affected.is_empty() { "unknown".to_string() } else { affected.join("; ") })?;
+    writeln!(out, "  Patched:  {}", finding.patched_versions.as_deref().unwrap_or("none"))?;
+    writeln!(out, "  {}", finding.remediation)?;
+    Ok(())
+}
+
+// None at the end of the input, which ends the session like `q`
+fn ask(input: &mut dyn BufRead, out: &mut dyn Write, question: &str) -> Result<Option<String>> {
+    write!(out, "{}", question)?;
+    out.flush()?;
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_lowercase()))
+}
+
+fn ask_reason(input: &mut dyn BufRead, out: &mut dyn Write) -> Result<Option<String>> {
+    loop {
+        write!(out, "Reason: ")?;
+        out.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if !line.trim().is_empty() {
+            return Ok(Some(line.trim().to_string()));
+        }
+    }
+}
+
+fn entry(package: &PackageReport, finding: &AdvisoryFinding, decision: Decision, reason: &str) -> IgnoreEntry {
+    IgnoreEntry {
+        id: finding.id.clone(),
+        package: Some(package.package_name.clone()),
+        state: Some(decision.state()),
+        justification: None,
+        detail: Some(reason.to_string()),
+    }
+}
+
+#[derive(Serialize)]
+struct TriageFile<'a> {
+    ignore: &'a [IgnoreEntry],
+}
+
+/// 把条目追加到 path（不存在时创建），文件格式与 --config 相同
+pub fn append(path: &Path, entries: &[IgnoreEntry]) -> Result<()> {
+    let text = toml::to_string(&TriageFile { ignore: entries }).context("failed to serialize triage decisions")?;
+    let existing = match fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let content = if existing.trim().is_empty() {
+        format!("# Triage decisions from `rustpj --interactive`; pass this file with --config\n\n{}", text)
+    } else {
+        format!("{}{}\n{}", existing, if existing.ends_with('\n') { "" } else { "\n" }, text)
+    };
+    // Replaced as a whole, never half-written; growing the triage file needs no --force
+    let settings = OutputSettings { overwrite: true, ..OutputSettings::current() };
+    output::write_bytes_with(&path.to_string_lossy(), content.as_bytes(), settings)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use crate::scanner::AffectedRange;
+    use crate::severity::Severity;
+    use std::io::Cursor;
+
+    fn finding(id: &str) -> AdvisoryFinding {
+        AdvisoryFinding {
+            id: id.to_string(),
+            severity: Severity::High,
+            patched_versions: Some(">= 0.2.23".to_string()),
+            patched: vec![">= 0.2.23".to_string()],
+            affected_ranges: vec![AffectedRange { introduced: None, fixed: Some("0.2.23".to_string()) }],
+            remediation: "Upgrade to >= 0.2.23".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn report() -> VulnReport {
+        let package = |name: &str, ids: &[&str]| PackageReport {
+            package_name: name.to_string(),
+            package_version: "0.1.0".to_string(),
+            max_severity: Severity::High,
+            advisories: ids.iter().map(|id| finding(id)).collect(),
+            ..Default::default()
+        };
+        VulnReport {
+            packages: vec![
+                package("time", &["RUSTSEC-2020-0071"]),
+                package("chrono", &["RUSTSEC-2020-0159", "RUSTSEC-2020-0002"]),
+                package("zip", &["RUSTSEC-2020-0003"]),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn run(report: &VulnReport, answers: &str) -> (Vec<IgnoreEntry>, String) {
+        let mut out = Vec::new();
+        let titles = BTreeMap::from([("RUSTSEC-2020-0071".to_string(), "Potential segfault in the time crate".to_string())]);
+        let entries = prompt(report, &titles, &mut Cursor::new(answers), &mut out).unwrap();
+        (entries, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_decisions_round_trip_through_the_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("triage.toml");
+        // ignore, (typo) fix later, skip, accept
+        let (entries, shown) = run(&report(), "i\nnot built on unix\nx\nf\n\nupgrade next sprint\ns\nc\nsandboxed\n");
+        assert!(shown.contains("[1/4] RUSTSEC-2020-0071 in time@0.1.0 (high)\n  Potential segfault in the time crate\n  Affected: < 0.2.23\n"));
+        assert!(shown.contains("Unknown choice: x"));
+        assert_eq!(entries.len(), 3);
+        append(&path, &entries[..1]).unwrap();
+        append(&path, &entries[1..]).unwrap();
+
+        let config = ScanConfig::load(&path).unwrap();
+        let mut report = report();
+        config.apply_triage(&mut report);
+        let suppressed: Vec<&str> = report
+            .packages
+            .iter()
+            .flat_map(|p| &p.advisories)
+            .filter(|f| f.is_suppressed())
+            .map(|f| f.id.as_str())
+            .collect();
+        assert_eq!(suppressed, ["RUSTSEC-2020-0071", "RUSTSEC-2020-0003"]);
+        let fix_later = &report.packages[1].advisories[0];
+        assert_eq!(fix_later.analysis.as_ref().map(|a| a.state), Some(AnalysisState::Exploitable));
+        assert_eq!(report.packages[1].advisories[1].analysis, None);
+
+        // Triaged findings are not asked about again
+        let (entries, shown) = run(&report, "q\n");
+        assert!(entries.is_empty() && shown.contains("[1/1] RUSTSEC-2020-0002"));
+    }
+
+    #[test]
+    fn test_all_remaining_and_quit() {
+        let (entries, _) = run(&report(), "s\na\nz\nc\naccepted for the beta\n");
+        let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["RUSTSEC-2020-0159", "RUSTSEC-2020-0002", "RUSTSEC-2020-0003"]);
+        assert!(entries.iter().all(|e| e.detail.as_deref() == Some("accepted for the beta")));
+
+        // Quitting, or running out of input, keeps what was decided so far
+        let (entries, _) = run(&report(), "i\nfalse positive\nq\n");
+        assert_eq!(entries.len(), 1);
+        let (entries, _) = run(&report(), "i\n");
+        assert!(entries.is_empty());
+    }
+}
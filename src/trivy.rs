@@ -0,0 +1,141 @@
+use crate::description::advisory_url;
+use crate::scanner::{PackageReport, VulnReport};
+use crate::severity::Severity;
+use serde::Serialize;
+
+// `--report-format trivy`: the findings in the shape of Trivy's JSON report
+// (`trivy fs --format json`, schema version 2), for dashboards and scripts that already
+// ingest Trivy output. Each lockfile is one `Results` entry of class `lang-pkgs` and type
+// `cargo`, as Trivy reports a Cargo.lock it scanned itself:
+//
+//   VulnerabilityID   finding id (RUSTSEC-…)
+//   PkgID             <name>@<version>
+//   PkgName           package_name
+//   InstalledVersion  package_version
+//   FixedVersion      the first fixed version of every affected range, comma-separated;
+//                     absent when there is no fix
+//   Status            "fixed" when a fixed version exists, otherwise "affected"
+//   Severity          severity in upper case; no CVSS score is "UNKNOWN", as in Trivy,
+//                     and a 0.0 score (which Trivy has no level for) is "LOW"
+//   Description       the full advisory description
+//   PrimaryURL        the rustsec.org advisory page
+//   References        the advisory's references
+//
+// Only the fields above are filled in; Trivy's are otherwise optional. Findings that
+// triage marked resolved, false positive or not affected are left out, as Trivy does
+// with VEX statements.
+
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TrivyReport {
+    pub schema_version: u32,
+    // --canonical 时为空并省略
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub created_at: String,
+    pub artifact_name: String,
+    pub artifact_type: String,
+    pub results: Vec<TrivyResult>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TrivyResult {
+    pub target: String,
+    pub class: String,
+    #[serde(rename = "Type")]
+    pub kind: String,
+    // 与 Trivy 一致，没有漏洞时省略
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub vulnerabilities: Vec<TrivyVulnerability>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TrivyVulnerability {
+    #[serde(rename = "VulnerabilityID")]
+    pub vulnerability_id: String,
+    #[serde(rename = "PkgID")]
+    pub pkg_id: String,
+    pub pkg_name: String,
+    pub installed_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixed_version: Option<String>,
+    pub status: String,
+    pub severity: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(rename = "PrimaryURL")]
+    pub primary_url: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<String>,
+}
+
+/// Trivy 的严重程度名称
+pub fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Unknown => "UNKNOWN",
+        Severity::None | Severity::Low => "LOW",
+        Severity::Medium => "MEDIUM",
+        Severity::High => "HIGH",
+        Severity::Critical => "CRITICAL",
+    }
+}
+
+/// 把扫描报告转换为 Trivy JSON；target 是单个 lockfile 在压缩包内的路径
+/// （报告按项目拆分时改用各项目的 lockfile_path）
+pub fn build_trivy(report: &VulnReport, artifact_name: &str, target: &str) -> TrivyReport {
+    let results = if report.projects.is_empty() {
+        vec![result(target, &report.packages)]
+    } else {
+        report.projects.iter().map(|p| result(&p.lockfile_path, &p.packages)).collect()
+    };
+    TrivyReport {
+        schema_version: SCHEMA_VERSION,
+        created_at: report.metadata.generated_at.clone(),
+        artifact_name: artifact_name.to_string(),
+        artifact_type: "filesystem".to_string(),
+        results,
+    }
+}
+
+fn result(target: &str, packages: &[PackageReport]) -> TrivyResult {
+    let mut vulnerabilities = Vec::new();
+    for pkg in packages {
+        let findings = pkg.advisories.iter().filter(|f| !f.analysis.as_ref().is_some_and(|a| a.state.suppresses()));
+        for finding in findings {
+            let fixed: Vec<&str> = finding.affected_ranges.iter().filter_map(|r| r.fixed.as_deref()).collect();
+            vulnerabilities.push(TrivyVulnerability {
+                vulnerability_id: finding.id.clone(),
+                pkg_id: format!("{}@{}", pkg.package_name, pkg.package_version),
+                pkg_name: pkg.package_name.clone(),
+                installed_version: pkg.package_version.clone(),
+                fixed_version: (!fixed.is_empty()).then(|| fixed.join(", ")),
+                status: if fixed.is_empty() { "affected" } else { "fixed" }.to_string(),
+                severity: severity_name(finding.severity).to_string(),
+                description: finding.description.clone(),
+                primary_url: advisory_url(&finding.id),
+                references: finding.references.clone(),
+            });
+        }
+    }
+    TrivyResult {
+        target: target.to_string(),
+        class: "lang-pkgs".to_string(),
+        kind: "cargo".to_string(),
+        vulnerabilities,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity_names() {
+        assert_eq!(severity_name(Severity::Unknown), "UNKNOWN");
+        assert_eq!(severity_name(Severity::None), "LOW");
+        assert_eq!(severity_name(Severity::Critical), "CRITICAL");
+    }
+}
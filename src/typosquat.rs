@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use cargo_lock::Lockfile;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+// Lightweight typosquatting heuristic: crates.io packages whose name is one or two edits
+// away from a popular crate (e.g. `serde_jsonn`) are reported as suspicious. Purely
+// informational unless `--fail-on-suspicious` is given.
+
+const EMBEDDED_POPULAR: &str = include_str!("popular_crates.txt");
+
+// Legitimate crates that happen to sit within edit distance of a popular name
+const KNOWN_LEGITIMATE: &[&str] = &["base62", "base64ct", "libm", "neither", "sha1", "sha3", "tide", "ulid"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SuspiciousPackage {
+    pub package_name: String,
+    pub package_version: String,
+    // 与之相似的热门 crate
+    pub resembles: String,
+    pub distance: usize,
+}
+
+pub struct PopularCrates {
+    names: Vec<String>,
+}
+
+impl PopularCrates {
+    pub fn embedded() -> Self {
+        Self::parse(EMBEDDED_POPULAR)
+    }
+
+    /// 从文件加载热门 crate 列表（每行一个名字，`#` 开头为注释）
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read popular crate list: {}", path.display()))?;
+        Ok(Self::parse(&text))
+    }
+
+    fn parse(text: &str) -> Self {
+        let names = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(normalize)
+            .collect();
+        PopularCrates { names }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n == name)
+    }
+}
+
+// crates.io treats `-` and `_` as the same character and names are case-insensitive
+fn normalize(name: &str) -> String {
+    name.to_ascii_lowercase().replace('-', "_")
+}
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            current.push((prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1));
+        }
+        prev = current;
+    }
+    prev[b.len()]
+}
+
+// Short names collide all the time (`log`/`lol`), so allow fewer edits the shorter the name
+fn max_distance(name: &str) -> usize {
+    match name.len() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// 检查锁文件中来自 crates.io 的包是否与热门 crate 名称过于相似
+pub fn check(lockfile: &Lockfile, popular: &PopularCrates) -> Vec<SuspiciousPackage> {
+    let legitimate: Vec<String> = KNOWN_LEGITIMATE.iter().map(|n| normalize(n)).collect();
+    let mut findings = Vec::new();
+    for pkg in &lockfile.packages {
+        if !crate::online::is_default_crates_io(&pkg.source) {
+            continue;
+        }
+        let name = normalize(pkg.name.as_str());
+        if popular.contains(&name) || legitimate.contains(&name) {
+            continue;
+        }
+        let limit = max_distance(&name);
+        let closest = popular
+            .names
+            .iter()
+            .map(|candidate| (levenshtein(&name, candidate), candidate))
+            .filter(|(distance, _)| *distance > 0 && *distance <= limit)
+            .min();
+        if let Some((distance, candidate)) = closest {
+            findings.push(SuspiciousPackage {
+                package_name: pkg.name.as_str().to_string(),
+                package_version: pkg.version.to_string(),
+                resembles: candidate.clone(),
+                distance,
+            });
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn lockfile(names: &[&str]) -> Lockfile {
+        let mut text = String::from("version = 3\n");
+        for name in names {
+            text.push_str(&format!(
+                "\n[[package]]\nname = \"{}\"\nversion = \"1.0.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+                name
+            ));
+        }
+        Lockfile::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn test_flags_near_miss_of_popular_crate() {
+        let findings = check(&lockfile(&["serde_jsonn", "serde_json"]), &PopularCrates::embedded());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].package_name, "serde_jsonn");
+        assert_eq!(findings[0].resembles, "serde_json");
+        assert_eq!(findings[0].distance, 1);
+    }
+
+    #[test]
+    fn test_ignores_exact_forks_and_short_names() {
+        // `serde-json` is the same crates.io name, `libm` is a known legitimate crate,
+        // `lol` is too short to judge, `serde_jsonnnn` is too far away
+        let findings = check(
+            &lockfile(&["serde-json", "libm", "lol", "serde_jsonnnn"]),
+            &PopularCrates::embedded(),
+        );
+        assert!(findings.is_empty(), "{:?}", findings);
+    }
+}
@@ -0,0 +1,145 @@
+use cargo_lock::package::SourceId;
+use cargo_lock::{Lockfile, Package};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// Vendored dependencies (`cargo vendor`): one directory per crate, each with a normalized
+// Cargo.toml, under `vendor/` or whatever `[source.*] directory` the cargo config names.
+// An offline build compiles these copies, so they are what gets scanned. Copies that match
+// a lockfile entry need nothing; a copy the lockfile does not list (no lockfile shipped,
+// a hand-edited vendor tree, a version bumped in place) is added to the lockfile so the
+// scan and the SBOM still see it, and a lockfile entry whose vendored copy has another
+// version is reported rather than trusted silently.
+
+/// vendor 目录中的一个 crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VendoredCrate {
+    pub name: String,
+    pub version: semver::Version,
+    pub dir: PathBuf,
+}
+
+/// vendor 目录与 lockfile 的对比结果
+#[derive(Debug, Default)]
+pub struct Reconciliation {
+    // 与 lockfile 中的条目一致
+    pub matched: usize,
+    // lockfile 中没有、已补充进去的 crate
+    pub added: Vec<VendoredCrate>,
+    // lockfile 中同名 crate 的版本与 vendor 中的不一致：(vendor 中的 crate, lockfile 中的版本)
+    pub mismatched: Vec<(VendoredCrate, Vec<semver::Version>)>,
+}
+
+/// 读取 vendor 目录下每个 crate 的 Cargo.toml；无法解析的目录会被跳过
+pub fn discover(dirs: &[PathBuf]) -> Vec<VendoredCrate> {
+    let mut crates = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            if let Some(vendored) = read_crate(&entry.path())
+                && !crates.contains(&vendored)
+            {
+                crates.push(vendored);
+            }
+        }
+    }
+    crates.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)));
+    crates
+}
+
+fn read_crate(dir: &Path) -> Option<VendoredCrate> {
+    let text = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    let manifest: toml::Table = toml::from_str(&text).ok()?;
+    let package = manifest.get("package")?.as_table()?;
+    Some(VendoredCrate {
+        name: package.get("name")?.as_str()?.to_string(),
+        version: package.get("version")?.as_str()?.parse().ok()?,
+        dir: dir.to_path_buf(),
+    })
+}
+
+/// 把 lockfile 中没有的 vendored crate 补充进去，并记录版本不一致的条目
+pub fn reconcile(lockfile: &mut Lockfile, vendored: &[VendoredCrate]) -> Reconciliation {
+    let mut result = Reconciliation::default();
+    for krate in vendored {
+        // Lockfile entries from any source count: source replacement keeps the
+        // original (crates.io or git) source in the lockfile
+        let locked: Vec<&Package> = lockfile.packages.iter().filter(|p| p.name.as_str() == krate.name).collect();
+        if locked.iter().any(|p| p.version == krate.version) {
+            result.matched += 1;
+            continue;
+        }
+        if !locked.is_empty() {
+            result.mismatched.push((krate.clone(), locked.iter().map(|p| p.version.clone()).collect()));
+        }
+        let Ok(name) = cargo_lock::package::Name::from_str(&krate.name) else {
+            continue;
+        };
+        // `cargo vendor` replaces crates.io, so an unlisted copy is attributed to it
+        lockfile.packages.push(Package {
+            name,
+            version: krate.version.clone(),
+            source: Some(SourceId::default()),
+            checksum: None,
+            dependencies: vec![],
+            replace: None,
+        });
+        result.added.push(krate.clone());
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconcile_vendored_crates_with_lockfile() {
+        let root = tempfile::tempdir().unwrap();
+        let write = |dir: &str, name: &str, version: &str| {
+            let dir = root.path().join("vendor").join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("Cargo.toml"), format!("[package]\nname = \"{}\"\nversion = \"{}\"\n", name, version)).unwrap();
+        };
+        write("time", "time", "0.1.45");
+        write("zip", "zip", "0.5.13");
+        write("regex", "regex", "1.5.4");
+        fs::create_dir_all(root.path().join("vendor/not-a-crate")).unwrap();
+
+        let vendored = discover(&[root.path().join("vendor"), root.path().join("missing")]);
+        let names: Vec<&str> = vendored.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["regex", "time", "zip"]);
+
+        let mut lockfile: Lockfile = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["regex", "time"]
+
+[[package]]
+name = "regex"
+version = "1.4.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "time"
+version = "0.1.45"
+source = "git+https://github.com/time-rs/time?rev=abc#abc"
+"#
+        .parse()
+        .unwrap();
+        let result = reconcile(&mut lockfile, &vendored);
+        assert_eq!(result.matched, 1);
+        let added: Vec<String> = result.added.iter().map(|c| format!("{}@{}", c.name, c.version)).collect();
+        assert_eq!(added, ["regex@1.5.4", "zip@0.5.13"]);
+        assert_eq!(result.mismatched.len(), 1);
+        assert_eq!(result.mismatched[0].1, [semver::Version::new(1, 4, 0)]);
+        assert_eq!(lockfile.packages.len(), 5);
+        assert!(lockfile.packages[4].source.as_ref().unwrap().is_default_registry());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::get_sbom::{Metadata, Tool};
+use crate::output::Stamp;
+use crate::scanner::VulnReport;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// CycloneDX vulnerability analysis (VEX) support.
+// https://cyclonedx.org/docs/1.4/json/#vulnerabilities_items_analysis
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisState {
+    Resolved,
+    ResolvedWithPedigree,
+    Exploitable,
+    InTriage,
+    FalsePositive,
+    NotAffected,
+}
+
+impl AnalysisState {
+    /// 分诊结论表明该漏洞无需处理（不参与退出码判定）
+    pub fn suppresses(self) -> bool {
+        matches!(
+            self,
+            AnalysisState::Resolved
+                | AnalysisState::ResolvedWithPedigree
+                | AnalysisState::FalsePositive
+                | AnalysisState::NotAffected
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Justification {
+    CodeNotPresent,
+    CodeNotReachable,
+    RequiresConfiguration,
+    RequiresDependency,
+    RequiresEnvironment,
+    ProtectedByCompiler,
+    ProtectedAtRuntime,
+    ProtectedAtPerimeter,
+    ProtectedByMitigatingControl,
+}
+
+impl FromStr for Justification {
+    type Err = anyhow::Error;
+
+    // Accepts the CycloneDX values plus the OpenVEX/CSAF spellings people tend to copy
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "code_not_present" | "vulnerable_code_not_present" | "component_not_present" => {
+                Justification::CodeNotPresent
+            }
+            "code_not_reachable" | "vulnerable_code_not_in_execute_path" => Justification::CodeNotReachable,
+            "requires_configuration" => Justification::RequiresConfiguration,
+            "requires_dependency" => Justification::RequiresDependency,
+            "requires_environment" => Justification::RequiresEnvironment,
+            "protected_by_compiler" => Justification::ProtectedByCompiler,
+            "protected_at_runtime" | "vulnerable_code_cannot_be_controlled_by_adversary" => {
+                Justification::ProtectedAtRuntime
+            }
+            "protected_at_perimeter" => Justification::ProtectedAtPerimeter,
+            "protected_by_mitigating_control" | "inline_mitigations_already_exist" => {
+                Justification::ProtectedByMitigatingControl
+            }
+            other => return Err(anyhow!("unknown VEX justification: {}", other)),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Analysis {
+    pub state: AnalysisState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub justification: Option<Justification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Default for Analysis {
+    // Findings nobody has looked at yet
+    fn default() -> Self {
+        Analysis { state: AnalysisState::InTriage, justification: None, detail: None }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Vulnerability {
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    pub id: String,
+    pub source: VulnerabilitySource,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ratings: Vec<Rating>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recommendation: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub advisories: Vec<AdvisoryLink>,
+    pub affects: Vec<Affects>,
+    pub analysis: Analysis,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VulnerabilitySource {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Rating {
+    pub severity: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdvisoryLink {
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Affects {
+    #[serde(rename = "ref")]
+    pub reference: String,
+}
+
+// Standalone VEX document: a BOM that carries only vulnerabilities, whose `affects`
+// entries point back into the SBOM through BOM-Links (urn:cdx:<serial>/<version>#<bom-ref>).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VexDocument {
+    #[serde(rename = "bomFormat")]
+    pub bom_format: String,
+    #[serde(rename = "specVersion")]
+    pub spec_version: String,
+    #[serde(rename = "serialNumber")]
+    pub serial_number: String,
+    pub version: u32,
+    pub metadata: Metadata,
+    pub vulnerabilities: Vec<Vulnerability>,
+}
+
+/// 把扫描报告中的漏洞转换为 CycloneDX vulnerabilities；ref_prefix 非空时生成 BOM-Link
+pub fn vulnerabilities_from_report(report: &VulnReport, ref_prefix: &str) -> Vec<Vulnerability> {
+    let mut vulns = Vec::new();
+    for pkg in &report.packages {
+        let component_ref = format!("{}@{}", pkg.package_name, pkg.package_version);
+        for finding in &pkg.advisories {
+            vulns.push(Vulnerability {
+                bom_ref: format!("{}-{}", finding.id, component_ref),
+                id: finding.id.clone(),
+                source: VulnerabilitySource {
+                    name: "RustSec".to_string(),
+                    url: format!("https://rustsec.org/advisories/{}", finding.id),
+                },
+                ratings: finding
+                    .severity
+                    .known()
+                    .map(|s| Rating { severity: s.to_string() })
+                    .into_iter()
+                    .collect(),
+                description: Some(finding.description.clone()).filter(|d| !d.is_empty()),
+                recommendation: finding
+                    .patched_versions
+                    .as_ref()
+                    .map(|p| format!("Upgrade {} to a version matching: {}", pkg.package_name, p)),
+                advisories: finding.references.iter().map(|url| AdvisoryLink { url: url.clone() }).collect(),
+                affects: vec![Affects { reference: format!("{}{}", ref_prefix, component_ref) }],
+                analysis: finding.analysis.clone().unwrap_or_default(),
+            });
+        }
+    }
+    vulns
+}
+
+pub fn build_vex(report: &VulnReport, sbom_serial: &str, stamp: &Stamp) -> VexDocument {
+    // serialNumber is `urn:uuid:<uuid>`; BOM-Links use `urn:cdx:<uuid>/<version>#<ref>`
+    let uuid = sbom_serial.strip_prefix("urn:uuid:").unwrap_or(sbom_serial);
+    let prefix = format!("urn:cdx:{}/1#", uuid);
+    VexDocument {
+        bom_format: "CycloneDX".to_string(),
+        spec_version: "1.4".to_string(),
+        serial_number: stamp.serial_number(),
+        version: 1,
+        metadata: Metadata {
+            timestamp: stamp.timestamp(),
+            tools: vec![Tool::default()],
+            component: None,
+            properties: Vec::new(),
+        },
+        vulnerabilities: vulnerabilities_from_report(report, &prefix),
+    }
+}
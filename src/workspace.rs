@@ -0,0 +1,142 @@
+use cargo_lock::Package;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+// The project's own crates, read from the manifests under the extracted project root.
+// In the lockfile they are the packages without a source; the manifests add what the
+// lockfile cannot say, i.e. whether a member builds a binary. Manifests under `target/`
+// and `vendor/` belong to build output and vendored dependencies and are skipped.
+
+/// 工作区成员的清单信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberManifest {
+    pub name: String,
+    // `version.workspace = true` 或未写版本时为空，此时只按名称匹配
+    pub version: Option<String>,
+    pub manifest_path: PathBuf,
+    // 有 `[[bin]]` 目标或 `src/main.rs`
+    pub application: bool,
+}
+
+/// 项目根目录下找到的所有成员清单，按包名索引
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceMembers {
+    members: BTreeMap<String, Vec<MemberManifest>>,
+}
+
+impl WorkspaceMembers {
+    /// 读取 project_root 下的所有 Cargo.toml；无法解析的清单和虚拟工作区的根清单会被跳过
+    pub fn discover(project_root: &Path) -> Self {
+        let mut members = WorkspaceMembers::default();
+        let walker = WalkDir::new(project_root)
+            .into_iter()
+            .filter_entry(|e| !(e.file_type().is_dir() && matches!(e.file_name().to_str(), Some("target" | "vendor" | ".git"))));
+        for entry in walker.filter_map(|e| e.ok()) {
+            if entry.file_type().is_file()
+                && entry.file_name() == "Cargo.toml"
+                && let Ok(text) = fs::read_to_string(entry.path())
+                && let Some(member) = parse_manifest(entry.path(), &text)
+            {
+                members.insert(member);
+            }
+        }
+        members
+    }
+
+    fn insert(&mut self, member: MemberManifest) {
+        let entries = self.members.entry(member.name.clone()).or_default();
+        if !entries.iter().any(|m| m.manifest_path == member.manifest_path) {
+            entries.push(member);
+        }
+    }
+
+    /// 合并另一个项目根目录下找到的成员
+    pub fn merge(&mut self, other: WorkspaceMembers) {
+        for member in other.members.into_values().flatten() {
+            self.insert(member);
+        }
+    }
+
+    /// lockfile 中的本地包（没有 source）对应的成员清单
+    pub fn get(&self, package: &Package) -> Option<&MemberManifest> {
+        if package.source.is_some() {
+            return None;
+        }
+        let candidates = self.members.get(package.name.as_str())?;
+        let version = package.version.to_string();
+        candidates
+            .iter()
+            .find(|m| m.version.as_deref() == Some(version.as_str()))
+            .or_else(|| candidates.iter().find(|m| m.version.is_none()))
+    }
+}
+
+fn parse_manifest(path: &Path, text: &str) -> Option<MemberManifest> {
+    let manifest: toml::Table = toml::from_str(text).ok()?;
+    let package = manifest.get("package")?.as_table()?;
+    let name = package.get("name")?.as_str()?.to_string();
+    let version = package.get("version").and_then(|v| v.as_str()).map(str::to_string);
+    let has_bin_targets = manifest.get("bin").and_then(|b| b.as_array()).is_some_and(|bins| !bins.is_empty());
+    let dir = path.parent().unwrap_or(Path::new("."));
+    Some(MemberManifest {
+        name,
+        version,
+        manifest_path: path.to_path_buf(),
+        application: has_bin_targets || dir.join("src/main.rs").is_file(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cargo_lock::Lockfile;
+
+    #[test]
+    fn test_discover_bin_and_lib_members() {
+        let root = tempfile::tempdir().unwrap();
+        let write = |path: &str, text: &str| {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write("Cargo.toml", "[workspace]\nmembers = [\"cli\", \"core\", \"tool\"]\n\n[workspace.package]\nversion = \"0.3.0\"\n");
+        write("cli/Cargo.toml", "[package]\nname = \"demo-cli\"\nversion = \"0.3.0\"\n");
+        write("cli/src/main.rs", "fn main() {}\n");
+        write("core/Cargo.toml", "[package]\nname = \"demo-core\"\nversion.workspace = true\n");
+        write("core/src/lib.rs", "");
+        write("tool/Cargo.toml", "[package]\nname = \"demo-tool\"\nversion = \"0.1.0\"\n\n[[bin]]\nname = \"demo\"\npath = \"bin/demo.rs\"\n");
+        write("target/package/demo-cli-0.3.0/Cargo.toml", "[package]\nname = \"stale\"\nversion = \"0.0.1\"\n");
+
+        let members = WorkspaceMembers::discover(root.path());
+        let lockfile: Lockfile = r#"
+version = 3
+
+[[package]]
+name = "demo-cli"
+version = "0.3.0"
+dependencies = ["demo-core"]
+
+[[package]]
+name = "demo-core"
+version = "0.3.0"
+
+[[package]]
+name = "demo-tool"
+version = "0.1.0"
+
+[[package]]
+name = "stale"
+version = "0.0.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#
+        .parse()
+        .unwrap();
+        let application = |i: usize| members.get(&lockfile.packages[i]).map(|m| m.application);
+        assert_eq!(application(0), Some(true));
+        assert_eq!(application(1), Some(false));
+        assert_eq!(application(2), Some(true));
+        assert_eq!(application(3), None);
+    }
+}
@@ -0,0 +1,48 @@
+// `rustpj batch` end to end: per-archive `[[policy]]` results and the exit code they decide.
+
+mod support;
+
+use serde_json::Value;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+// Batch-scans the `locked` fixture project against the fixture advisory DB
+fn batch_locked(config: Option<&str>) -> (TempDir, Output) {
+    let dir = tempfile::tempdir().unwrap();
+    let db = support::fixture_advisory_db(dir.path());
+    let work = dir.path().join("work");
+    std::fs::create_dir_all(work.join("archives")).unwrap();
+    support::zip_project("locked", &work.join("archives/locked.zip"));
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rustpj"));
+    command.current_dir(&work).args(["batch", "archives", "--db-path"]).arg(&db);
+    if let Some(config) = config {
+        std::fs::write(work.join("rustpj.toml"), config).unwrap();
+        command.args(["--config", "rustpj.toml"]);
+    }
+    (dir, command.output().unwrap())
+}
+
+fn batch_report(dir: &TempDir) -> Value {
+    serde_json::from_str(&std::fs::read_to_string(dir.path().join("work/output/batch_report.json")).unwrap()).unwrap()
+}
+
+#[test]
+fn test_batch_without_policy_passes() {
+    let (dir, output) = batch_locked(None);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(batch_report(&dir)["entries"][0]["report"].get("policy_results").is_none());
+}
+
+#[test]
+fn test_batch_fail_policy_exits_1() {
+    let config = "[[policy]]\nname = \"no-libc\"\nwhen = \"banned_crate\"\nnames = [\"libc\"]\naction = \"fail\"\n";
+    let (dir, output) = batch_locked(Some(config));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(stderr.contains("Policy rules with action 'fail' were triggered"), "{}", stderr);
+
+    let results = &batch_report(&dir)["entries"][0]["report"]["policy_results"];
+    assert_eq!(results[0]["rule"], "no-libc");
+    assert_eq!(results[0]["matched"][0], "libc@0.2.150");
+}
@@ -0,0 +1,47 @@
+// `ExternalCommands::Forbidden` (--no-exec) end to end. The policy is process-wide, so these
+// tests live in their own test binary, away from the pipeline tests that generate lockfiles.
+
+mod support;
+
+use rustpj::cargo_metadata::{self, CargoMetadata};
+use rustpj::exec::{self, ExternalCommands, PolicyViolation};
+use rustpj::get_lockfile::{discover_lockfiles_in, extract_archive_to};
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+fn extract(name: &str) -> (TempDir, PathBuf) {
+    exec::set_policy(ExternalCommands::Forbidden);
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join(format!("{}.zip", name));
+    support::zip_project(name, &archive);
+    let extracted = dir.path().join("extracted");
+    extract_archive_to(&archive.to_string_lossy(), &extracted).unwrap();
+    (dir, extracted)
+}
+
+#[test]
+fn test_project_without_lockfile_is_a_policy_violation() {
+    let (_dir, extracted) = extract("unlocked");
+    let Err(error) = discover_lockfiles_in(&extracted) else {
+        panic!("found a lockfile without running cargo");
+    };
+    let violation = error.downcast_ref::<PolicyViolation>().expect("a PolicyViolation");
+    assert_eq!(violation.command, "cargo generate-lockfile");
+    assert!(!extracted.join("unlocked-main/Cargo.lock").exists());
+}
+
+#[test]
+fn test_locked_project_gets_licenses_from_manifests() {
+    let (_dir, extracted) = extract("locked");
+    let projects = discover_lockfiles_in(&extracted).unwrap();
+    assert_eq!(projects.len(), 1);
+
+    let metadata: CargoMetadata = cargo_metadata::fetch_all_metadata(&projects[0].project_root).unwrap().unwrap();
+    let app = metadata.get("locked-app", "0.1.0").unwrap();
+    assert_eq!(app.license.as_deref(), Some("MIT OR Apache-2.0"));
+    assert_eq!(app.description.as_deref(), Some("Fixture project with a Cargo.lock"));
+    assert_eq!(metadata.workspace_members, [("locked-app".to_string(), "0.1.0".to_string())]);
+    // Not vendored, and the registry is never asked
+    assert!(metadata.get("time", "0.1.45").is_none());
+    assert!(exec::command("cargo", &["--version"]).is_err());
+}
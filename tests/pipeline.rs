@@ -0,0 +1,316 @@
+// End-to-end runs of the library pipeline on the fixture projects under tests/fixtures/projects:
+// zip the project, extract it, discover its lockfile(s), scan against the fixture advisory DB
+// and build the SBOM. Each archive is built on the fly with the GitHub-style `<name>-main/`
+// top-level directory (or as a `.crate` package), and the advisory DB is turned into a git
+// repository in a temp dir.
+
+mod support;
+
+use rustpj::advisory_source::AdvisorySource;
+use rustpj::Scanner;
+use rustpj::VulnReport;
+use rustpj::baseline::Baseline;
+use rustpj::cpe::CpeMappings;
+use rustpj::get_lockfile::{discover_lockfiles_in, extract_archive_to, merge_lockfiles};
+use rustpj::get_sbom::{SbomOptions, generate_sbom_from_lockfile};
+use rustpj::output::Stamp;
+use rustpj::config::ScanConfig;
+use rustpj::policy;
+use rustpj::registry::RegistryMap;
+use rustpj::package_filter::{self, PackageExclusion};
+use rustpj::workspace::WorkspaceMembers;
+use rustpj::severity::Severity;
+use cargo_lock::Lockfile;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+struct Run {
+    report: VulnReport,
+    sbom: Value,
+    _dir: TempDir,
+}
+
+impl Run {
+    fn findings(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .report
+            .packages
+            .iter()
+            .flat_map(|p| p.advisories.iter().map(move |a| format!("{}@{} {}", p.package_name, p.package_version, a.id)))
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    fn components(&self) -> Vec<String> {
+        let mut components: Vec<String> = self.sbom["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| format!("{}@{} {}", c["name"].as_str().unwrap(), c["version"].as_str().unwrap(), c["type"].as_str().unwrap()))
+            .collect();
+        components.sort();
+        components
+    }
+}
+
+fn run_pipeline(name: &str) -> Run {
+    run_pipeline_excluding(name, &[])
+}
+
+fn run_pipeline_excluding(name: &str, exclusions: &[PackageExclusion]) -> Run {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join(format!("{}.zip", name));
+    support::zip_project(name, &archive);
+    run_archive(dir, &archive, exclusions)
+}
+
+fn run_archive(dir: TempDir, archive: &Path, exclusions: &[PackageExclusion]) -> Run {
+    let scanner = Scanner::new(support::fixture_advisory_db(dir.path())).unwrap();
+    let extracted = dir.path().join("extracted");
+    extract_archive_to(&archive.to_string_lossy(), &extracted).unwrap();
+    let projects = discover_lockfiles_in(&extracted).unwrap();
+    let lockfile = merge_lockfiles(projects.iter().map(|p| &p.lockfile));
+    let (lockfile, excluded) = package_filter::exclude(&lockfile, exclusions);
+    let mut members = WorkspaceMembers::default();
+    for project in &projects {
+        members.merge(WorkspaceMembers::discover(&project.project_root));
+    }
+
+    let mut report = scanner.scan_lockfile(&lockfile).unwrap();
+    report.metadata.excluded_packages = excluded;
+    let sbom_path = dir.path().join("output/sbom.json");
+    let options = SbomOptions {
+        metadata: None,
+        members: &members,
+        cpes: &CpeMappings::embedded(),
+        component_types: &BTreeMap::new(),
+        report: Some(&report),
+        project: None,
+        registries: &RegistryMap::default(),
+        stamp: Stamp::now(),
+    };
+    generate_sbom_from_lockfile(&lockfile, &options, &sbom_path.to_string_lossy()).unwrap();
+    let sbom = serde_json::from_str(&fs::read_to_string(&sbom_path).unwrap()).unwrap();
+    Run { report, sbom, _dir: dir }
+}
+
+#[test]
+fn test_locked_project_reports_known_advisory() {
+    let run = run_pipeline("locked");
+    assert_eq!(run.findings(), ["time@0.1.45 RUSTSEC-2020-0071"]);
+    assert_eq!(run.report.total_packages, 3);
+    assert_eq!(run.report.summary.total_vulnerabilities, 1);
+    assert_eq!(
+        run.components(),
+        ["libc@0.2.150 library", "locked-app@0.1.0 application", "time@0.1.45 library"]
+    );
+}
+
+#[test]
+fn test_patched_and_replaced_packages_are_scanned_as_built() {
+    let run = run_pipeline("patched");
+    // smallvec comes from the patched fork at 1.6.1, which has the fix; the unused
+    // time 0.2 patch leaves the vulnerable 0.1.45 in place
+    assert_eq!(run.findings(), ["time@0.1.45 RUSTSEC-2020-0071"]);
+    assert_eq!(run.report.total_packages, 4);
+    assert_eq!(
+        run.components(),
+        ["libc@0.2.150 library", "patched-app@0.1.0 application", "smallvec@1.6.1 library", "time@0.1.45 library"]
+    );
+    // The replaced registry libc is gone; time depends on the git one
+    let libc = run.sbom["components"].as_array().unwrap().iter().find(|c| c["name"] == "libc").unwrap();
+    assert!(libc["purl"].as_str().unwrap().contains("vcs_url=git%2Bhttps"), "{}", libc["purl"]);
+    let time = run.sbom["dependencies"].as_array().unwrap().iter().find(|d| d["ref"] == "time@0.1.45").unwrap();
+    assert_eq!(time["dependsOn"], json!([libc["bom-ref"]]));
+}
+
+#[test]
+fn test_crate_package_is_scanned_like_a_zip() {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("locked-app-0.1.0.crate");
+    support::crate_project("locked", "locked-app-0.1.0", &archive);
+    let run = run_archive(dir, &archive, &[]);
+    assert_eq!(run.findings(), ["time@0.1.45 RUSTSEC-2020-0071"]);
+    assert_eq!(
+        run.components(),
+        ["libc@0.2.150 library", "locked-app@0.1.0 application", "time@0.1.45 library"]
+    );
+}
+
+#[test]
+fn test_workspace_members_and_their_dependencies() {
+    let run = run_pipeline("workspace");
+    assert_eq!(run.findings(), ["smallvec@1.6.0 RUSTSEC-2021-0003"]);
+    assert_eq!(
+        run.components(),
+        ["smallvec@1.6.0 library", "ws-app@0.2.0 application", "ws-core@0.2.0 library"]
+    );
+}
+
+#[test]
+fn test_unreadable_lockfile_is_an_error_not_regenerated() {
+    use std::io::Write;
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("latin1.zip");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+    zip.start_file("latin1-main/Cargo.toml", zip::write::FileOptions::default()).unwrap();
+    zip.write_all(b"[package]\nname = \"latin1\"\nversion = \"0.1.0\"\n").unwrap();
+    zip.start_file("latin1-main/Cargo.lock", zip::write::FileOptions::default()).unwrap();
+    zip.write_all(b"version = 3\n\n[[package]]\nname = \"caf\xe9\"\nversion = \"0.1.0\"\n").unwrap();
+    zip.finish().unwrap();
+
+    let extracted = dir.path().join("extracted");
+    extract_archive_to(&archive.to_string_lossy(), &extracted).unwrap();
+    // Not skipped and regenerated from the Cargo.toml beside it
+    let err = format!("{:#}", discover_lockfiles_in(&extracted).err().unwrap());
+    assert!(err.contains("latin1-main/Cargo.lock") && err.contains("not valid UTF-8"), "{}", err);
+}
+
+#[test]
+fn test_project_without_lockfile_gets_one_generated() {
+    let run = run_pipeline("unlocked");
+    assert!(run.findings().is_empty());
+    assert_eq!(run.report.total_packages, 1);
+    assert_eq!(run.components(), ["unlocked-app@0.3.0 application"]);
+}
+
+#[test]
+fn test_excluded_package_leaves_scan_and_sbom() {
+    let exclusions = [PackageExclusion { package: "time@0.1.45".parse().unwrap(), reason: Some("patched fork".to_string()) }];
+    let run = run_pipeline_excluding("locked", &exclusions);
+    assert!(run.findings().is_empty());
+    assert_eq!(run.components(), ["libc@0.2.150 library", "locked-app@0.1.0 application"]);
+    // Nothing in the SBOM depends on the excluded component any more
+    let refs: Vec<&Value> = run.sbom["dependencies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|d| d["dependsOn"].as_array().into_iter().flatten())
+        .collect();
+    assert!(refs.iter().all(|r| !r.as_str().unwrap().contains("time")), "{:?}", refs);
+
+    let report = serde_json::to_value(&run.report).unwrap();
+    assert_eq!(
+        report["metadata"]["excluded_packages"],
+        json!([{"package_name": "time", "package_version": "0.1.45", "exclusion": "time@0.1.45", "reason": "patched fork"}])
+    );
+}
+
+#[test]
+fn test_baseline_accepts_existing_findings() {
+    let mut run = run_pipeline("locked");
+    assert!(run.report.has_actionable_at_or_above(Severity::Low));
+
+    let previous = serde_json::to_value(&run.report).unwrap();
+    let baseline = Baseline::from_report("previous.json", &previous).unwrap();
+    baseline.apply(&mut run.report);
+    assert!(run.report.packages[0].advisories[0].in_baseline);
+    assert_eq!(run.report.summary.new_since_baseline, Some(0));
+    assert!(!run.report.has_actionable_at_or_above(Severity::Low));
+
+    // A severity_count rule only counts findings the baseline does not already hold
+    let config = ScanConfig::parse("[[policy]]\nname = \"no-new\"\nwhen = \"severity_count\"\nseverity = \"low\"\naction = \"fail\"\n").unwrap();
+    let lockfile = Lockfile::load("tests/fixtures/projects/locked/Cargo.lock").unwrap();
+    assert!(policy::evaluate(&config.policy, &run.report, &lockfile, None).is_empty());
+
+    let empty = Baseline::from_report("empty.json", &json!({"packages": []})).unwrap();
+    empty.apply(&mut run.report);
+    assert_eq!(run.report.summary.new_since_baseline, Some(1));
+    assert!(run.report.has_actionable_at_or_above(Severity::Low));
+    assert_eq!(policy::evaluate(&config.policy, &run.report, &lockfile, None).len(), 1);
+}
+
+#[test]
+fn test_internal_advisory_db_amends_upstream() {
+    let dir = tempfile::tempdir().unwrap();
+    let upstream = support::fixture_advisory_db(dir.path());
+    let internal = support::fixture_db(dir.path(), "advisory-db-internal");
+    let lockfile: Lockfile = "version = 3\n\n[[package]]\nname = \"time\"\nversion = \"0.1.45\"\n\n[[package]]\nname = \"acme-vault\"\nversion = \"1.2.0\"\n"
+        .parse()
+        .unwrap();
+    let ids = |report: &VulnReport| {
+        let mut ids: Vec<String> = report.packages.iter().flat_map(|p| &p.advisories).map(|f| f.id.clone()).collect();
+        ids.sort();
+        ids
+    };
+
+    // The internal copy of RUSTSEC-2020-0071 drops upstream's OS restriction
+    let scanner = Scanner::new_multi(&[&upstream, &internal]).unwrap().with_target("x86_64-pc-windows-msvc").unwrap();
+    assert_eq!(scanner.advisory_count(), 3);
+    let report = scanner.scan_lockfile(&lockfile).unwrap();
+    assert_eq!(ids(&report), ["ACME-2024-0001", "RUSTSEC-2020-0071"]);
+    let dbs: Vec<(String, bool)> = report.metadata.advisory_dbs.iter().map(|db| (db.path.clone(), db.commit.is_some())).collect();
+    assert_eq!(dbs, [(upstream.display().to_string(), true), (internal.display().to_string(), true)]);
+    let conflict = format!(
+        "RUSTSEC-2020-0071 differs between {} and {}; using the one from {}",
+        upstream.display(),
+        internal.display(),
+        internal.display()
+    );
+    assert!(rustpj::console::warnings().iter().any(|w| w.code == "advisory-conflict" && w.message == conflict));
+
+    // In the other order upstream wins again
+    let scanner = Scanner::new_multi(&[&internal, &upstream]).unwrap().with_target("x86_64-pc-windows-msvc").unwrap();
+    assert_eq!(ids(&scanner.scan_lockfile(&lockfile).unwrap()), ["ACME-2024-0001"]);
+}
+
+#[test]
+fn test_malformed_advisory_is_skipped_unless_strict() {
+    let dir = tempfile::tempdir().unwrap();
+    // A copy of the fixture DB whose smallvec advisory has an unterminated string
+    let db = support::fixture_db(dir.path(), "advisory-db-broken");
+    let lockfile: Lockfile = "version = 3\n\n[[package]]\nname = \"time\"\nversion = \"0.1.45\"\n\n[[package]]\nname = \"smallvec\"\nversion = \"1.6.0\"\n"
+        .parse()
+        .unwrap();
+
+    let scanner = Scanner::new(&db).unwrap();
+    assert_eq!(scanner.advisory_count(), 1);
+    let report = scanner.scan_lockfile(&lockfile).unwrap();
+    let ids: Vec<&str> = report.packages.iter().flat_map(|p| &p.advisories).map(|f| f.id.as_str()).collect();
+    assert_eq!(ids, ["RUSTSEC-2020-0071"]);
+    assert!(report.metadata.advisory_db_commit.is_some());
+    let [warning] = &report.metadata.db_warnings[..] else {
+        panic!("expected one DB warning, got {:?}", report.metadata.db_warnings);
+    };
+    assert_eq!((warning.db.as_str(), warning.path.as_str()), (db.to_str().unwrap(), "crates/smallvec/RUSTSEC-2021-0003.md"));
+    assert!(warning.error.contains("TOML parse error"), "{}", warning.error);
+    assert!(rustpj::console::warnings().iter().any(|w| w.code == "advisory-db-invalid"));
+
+    let strict = Scanner::new_multi_from(&[&db], AdvisorySource::Strict).err().unwrap();
+    assert!(format!("{:#}", strict).contains("RUSTSEC-2021-0003.md"));
+    // A clean DB loads the same either way
+    let clean = support::fixture_advisory_db(dir.path());
+    assert_eq!(Scanner::new_multi_from(&[&clean], AdvisorySource::Strict).unwrap().advisory_count(), 2);
+    assert!(Scanner::new(&clean).unwrap().db_warnings().is_empty());
+}
+
+#[test]
+fn test_contradictory_affected_range_is_listed_but_not_matched() {
+    let dir = tempfile::tempdir().unwrap();
+    let scanner = Scanner::new(support::fixture_advisory_db(dir.path())).unwrap();
+    // As upstream: time's function range starts at 0.2.0, which is also listed as unaffected
+    assert_eq!(
+        scanner.range_conflicts(),
+        [("RUSTSEC-2020-0071".to_string(), "0.2.0 is unaffected but within a declared affected range".to_string())]
+    );
+
+    // The unaffected list wins over the declared range
+    let lockfile: Lockfile = "version = 3\n\n[[package]]\nname = \"time\"\nversion = \"0.2.0\"\n".parse().unwrap();
+    assert!(scanner.scan_lockfile(&lockfile).unwrap().packages.is_empty());
+}
+
+#[test]
+fn test_synthetic_data_scans() {
+    let dir = tempfile::tempdir().unwrap();
+    let scanner = Scanner::new(support::synthetic_advisory_db(dir.path(), 50)).unwrap();
+    assert_eq!(scanner.advisory_count(), 50);
+    let report = scanner.scan_lockfile(&support::synthetic_lockfile(100)).unwrap();
+    assert_eq!(report.total_packages, 101);
+    // crate-0..49 each have one advisory, patched (if at all) one minor above the locked version
+    assert_eq!(report.summary.total_vulnerabilities, 50);
+    assert_eq!(report.summary.unfixable, 8);
+}
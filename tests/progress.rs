@@ -0,0 +1,129 @@
+// Terminal output of the binary end to end: with `--progress-format ndjson` stderr must be
+// nothing but progress events, in pipeline order; `--quiet` prints nothing on success; an
+// archive with nothing but a Cargo.lock says once why licenses are missing; `doctor`
+// lists contradictory advisories.
+
+mod support;
+
+use serde_json::Value;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+// Scans the `locked` fixture project against the fixture advisory DB
+fn scan_locked(args: &[&str]) -> (TempDir, Output) {
+    scan(|archive| support::zip_project("locked", archive), &[&["--no-exec"], args].concat())
+}
+
+fn scan(archive: impl FnOnce(&Path), args: &[&str]) -> (TempDir, Output) {
+    let dir = tempfile::tempdir().unwrap();
+    let db = support::fixture_advisory_db(dir.path());
+    let work = dir.path().join("work");
+    std::fs::create_dir(&work).unwrap();
+    archive(&work.join("locked.zip"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rustpj"))
+        .current_dir(&work)
+        .args(args)
+        .arg("--db-path")
+        .arg(&db)
+        .arg("locked.zip")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    (dir, output)
+}
+
+#[test]
+fn test_ndjson_progress_events_on_stderr() {
+    let (_dir, output) = scan_locked(&["--progress-format", "ndjson"]);
+
+    let events: Vec<Value> = String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("not an event: {}: {}", line, e)))
+        .collect();
+    let names: Vec<&str> = events.iter().map(|e| e["event"].as_str().unwrap()).collect();
+    let stages: Vec<&str> = events.iter().filter_map(|e| e["stage"].as_str()).collect();
+    let scanned: Vec<(u64, u64, &str)> = events
+        .iter()
+        .filter(|e| e["event"] == "package_scanned")
+        .map(|e| (e["index"].as_u64().unwrap(), e["total"].as_u64().unwrap(), e["name"].as_str().unwrap()))
+        .collect();
+
+    assert_eq!(events[0], serde_json::json!({"event": "extract_start", "entries": 3}));
+    assert_eq!(scanned, [(1, 3, "libc"), (2, 3, "locked-app"), (3, 3, "time")]);
+    let finding = events.iter().find(|e| e["event"] == "finding").unwrap();
+    assert_eq!(
+        finding,
+        &serde_json::json!({"event": "finding", "package": "time", "version": "0.1.45", "id": "RUSTSEC-2020-0071", "severity": "medium"})
+    );
+    assert_eq!(stages, ["extract", "discover", "licenses", "db-load", "scan", "sbom", "write", "write"]);
+    assert!(events.iter().filter(|e| e["event"] == "stage_complete").all(|e| e["duration_ms"].is_u64()));
+    // Packages are scanned between loading the DB and completing the scan stage
+    let db_load = events.iter().position(|e| e["stage"] == "db-load").unwrap();
+    assert!(names[db_load + 1..db_load + 4].iter().all(|&n| n == "package_scanned"));
+    assert_eq!(events.last().unwrap(), &serde_json::json!({"event": "done", "exit_code": 0}));
+}
+
+#[test]
+fn test_quiet_prints_nothing_but_writes_the_report() {
+    let (dir, output) = scan_locked(&["--quiet"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(Path::new(&dir.path().join("work/output/vuln_report.json")).exists());
+}
+
+#[test]
+fn test_lockfile_only_archive_skips_cargo_metadata() {
+    // Without --no-exec: cargo must not even be tried in a directory without a manifest
+    let (dir, output) = scan(|archive| support::zip_lockfile("locked", archive), &[]);
+    let terminal = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    let warning = "Warning: no Cargo.toml next to Cargo.lock; cargo metadata was not run, licenses, component details and dependency scopes are unavailable";
+    assert_eq!(terminal.matches(warning).count(), 1, "{}", terminal);
+    assert!(!terminal.contains("cargo metadata failed") && !terminal.contains("Fetching license information"));
+
+    let sbom: Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("work/output/sbom.json")).unwrap()).unwrap();
+    let properties = sbom["metadata"]["properties"].as_array().unwrap();
+    assert!(properties.iter().any(|p| p["name"] == "rustpj:warning:manifests-missing"));
+    assert!(sbom["components"].as_array().unwrap().iter().all(|c| c.get("licenses").is_none()));
+}
+
+#[test]
+fn test_ndjson_failure_is_an_error_event() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustpj"))
+        .current_dir(dir.path())
+        .args(["--progress-format", "ndjson", "missing.zip"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    let events: Vec<Value> =
+        String::from_utf8(output.stderr).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    let names: Vec<&str> = events.iter().map(|e| e["event"].as_str().unwrap()).collect();
+    assert_eq!(names, ["error", "done"]);
+    assert_eq!(events[1]["exit_code"], 2);
+}
+
+#[test]
+fn test_doctor_lists_contradictory_affected_ranges() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = support::fixture_advisory_db(dir.path());
+    let output = Command::new(env!("CARGO_BIN_EXE_rustpj"))
+        .current_dir(dir.path())
+        .args(["doctor", "--no-exec", "--db-path"])
+        .arg(&db)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains(&format!("OK: advisory DB at {} (2 advisories)", db.display())), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "WARN: RUSTSEC-2020-0071: 0.2.0 is unaffected but within a declared affected range; patched and unaffected versions are treated as not affected"
+        ),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,64 @@
+// `rustpj serve` over real HTTP: the server is bound to an ephemeral port with the fixture
+// advisory DB loaded once, and fixture projects are POSTed to it. Needs the `server`
+// feature: `cargo test --features server --test server`.
+
+mod support;
+
+use rustpj::Scanner;
+use rustpj::config::ScanConfig;
+use rustpj::cpe::CpeMappings;
+use rustpj::server::{self, ServerState};
+use serde_json::Value;
+use std::fs;
+
+fn json(response: ureq::Response) -> Value {
+    serde_json::from_str(&response.into_string().unwrap()).unwrap()
+}
+
+#[test]
+fn test_scan_endpoint_returns_sbom_and_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = ServerState {
+        scanner: Scanner::new(support::fixture_advisory_db(dir.path())).unwrap(),
+        config: ScanConfig::default(),
+        cpes: CpeMappings::embedded(),
+        description: Default::default(),
+        license_lookup: false,
+        started_at: "2024-01-01T00:00:00Z".to_string(),
+    };
+    let http = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", http.server_addr().to_ip().unwrap());
+    std::thread::spawn(move || server::run(http, state, 2));
+
+    let health = json(ureq::get(&format!("{}/healthz", base)).call().unwrap());
+    assert_eq!(health["status"], "ok");
+    let db = json(ureq::get(&format!("{}/db/status", base)).call().unwrap());
+    assert_eq!(db["advisory_count"], 2);
+
+    let archive = dir.path().join("locked.zip");
+    support::zip_project("locked", &archive);
+    let response = json(ureq::post(&format!("{}/scan", base)).send_bytes(&fs::read(&archive).unwrap()).unwrap());
+    let packages = response["report"]["packages"].as_array().unwrap();
+    assert_eq!(packages.len(), 1);
+    assert_eq!(packages[0]["package_name"], "time");
+    assert_eq!(packages[0]["advisories"][0]["id"], "RUSTSEC-2020-0071");
+    assert_eq!(response["sbom"]["bomFormat"], "CycloneDX");
+    assert!(response["sbom"]["components"].as_array().unwrap().iter().any(|c| c["name"] == "libc"));
+
+    match ureq::post(&format!("{}/scan", base)).send_bytes(b"not a zip") {
+        Err(ureq::Error::Status(400, response)) => {
+            assert!(json(response)["error"].as_str().is_some());
+        }
+        other => panic!("expected 400, got {:?}", other.map(|r| r.status())),
+    }
+    assert!(matches!(ureq::get(&format!("{}/scan", base)).call(), Err(ureq::Error::Status(405, _))));
+    assert!(matches!(ureq::get(&format!("{}/nope", base)).call(), Err(ureq::Error::Status(404, _))));
+
+    // Every upload's temp dir is gone once its response is out
+    let leftovers = fs::read_dir(std::env::temp_dir())
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().starts_with("rustpj-serve-"))
+        .count();
+    assert_eq!(leftovers, 0);
+}
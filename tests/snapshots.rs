@@ -0,0 +1,223 @@
+// Golden-file tests for every output format. Each emitter gets the same hand-built report
+// and lockfile, with the volatile parts (serial numbers, timestamps) pinned through a fixed
+// `Stamp`, and its output is compared against the snapshot under tests/snapshots/.
+//
+// A new output format needs a test here. After an intended change to a format, review and
+// accept the new snapshots with `cargo insta review` (or `INSTA_UPDATE=always cargo test`).
+
+use cargo_lock::Lockfile;
+use rustpj::VulnReport;
+use rustpj::cpe::CpeMappings;
+use rustpj::description::{self, DescriptionMode};
+use rustpj::get_sbom::{SbomOptions, build_sbom};
+use rustpj::github_snapshot::{SnapshotContext, build_snapshot};
+use rustpj::output::Stamp;
+use rustpj::registry::RegistryMap;
+use rustpj::scanner::{AdvisoryFinding, AffectedRange, PackageReport, ReportMetadata, SeverityCounts, Summary};
+use rustpj::severity::Severity;
+use rustpj::vex::{Analysis, AnalysisState, Justification};
+use rustpj::workspace::WorkspaceMembers;
+use rustpj::{cyclonedx_proto, dot, junit, template, trivy, vex};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "ansi_term"
+version = "0.12.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+dependencies = [
+ "ansi_term",
+ "time",
+]
+
+[[package]]
+name = "libc"
+version = "0.2.150"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "time"
+version = "0.1.45"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = [
+ "libc",
+]
+"#;
+
+fn stamp() -> Stamp {
+    Stamp {
+        serial: uuid::Uuid::from_u128(0x6f1c_2d3e_4b5a_4c6d_8e7f_0a1b_2c3d_4e5f),
+        time: chrono::DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().into(),
+    }
+}
+
+fn lockfile() -> Lockfile {
+    LOCKFILE.parse().unwrap()
+}
+
+fn finding(id: &str, severity: Severity, patched: Option<&str>, description: &str) -> AdvisoryFinding {
+    AdvisoryFinding {
+        id: id.to_string(),
+        description: description.to_string(),
+        severity,
+        patched_versions: patched.map(str::to_string),
+        patched: patched.map(str::to_string).into_iter().collect(),
+        affected_ranges: vec![AffectedRange { introduced: None, fixed: patched.map(|p| p.trim_start_matches(">=").to_string()) }],
+        no_fix_available: patched.is_none(),
+        remediation: match patched {
+            Some(patched) => format!("Upgrade to {}", patched),
+            None => "No fixed release; consider replacing the crate".to_string(),
+        },
+        ..Default::default()
+    }
+}
+
+fn package(name: &str, version: &str, advisories: Vec<AdvisoryFinding>) -> PackageReport {
+    PackageReport {
+        package_name: name.to_string(),
+        package_version: version.to_string(),
+        max_severity: advisories.iter().map(|a| a.severity).max().unwrap_or(Severity::Unknown),
+        advisories,
+        ..Default::default()
+    }
+}
+
+fn report() -> VulnReport {
+    let mut time = finding(
+        "RUSTSEC-2020-0071",
+        Severity::Medium,
+        Some(">=0.2.23"),
+        "Potential segfault in the time crate.\n\nUnix-like operating systems may segfault due to dereferencing a dangling pointer.",
+    );
+    time.severity_source = Some("RUSTSEC-2020-0071 CVSS v3.1".to_string());
+    time.categories = vec!["code-execution".to_string(), "memory-corruption".to_string()];
+    let mut ansi_term = finding("RUSTSEC-2021-0139", Severity::Unknown, None, "ansi_term is unmaintained");
+    ansi_term.analysis = Some(Analysis {
+        state: AnalysisState::NotAffected,
+        justification: Some(Justification::CodeNotReachable),
+        detail: Some("Only used in the CLI banner".to_string()),
+    });
+    VulnReport {
+        metadata: ReportMetadata {
+            tool: "rustpj".to_string(),
+            tool_version: "0.1.0".to_string(),
+            generated_at: stamp().timestamp(),
+            advisory_db_commit: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+            ..Default::default()
+        },
+        total_packages: 4,
+        packages: vec![package("time", "0.1.45", vec![time]), package("ansi_term", "0.12.1", vec![ansi_term])],
+        summary: Summary {
+            total_vulnerabilities: 2,
+            unique_advisories: 2,
+            vulnerable_packages: 2,
+            by_severity: SeverityCounts { medium: 1, unknown: 1, ..Default::default() },
+            risk_score: 2,
+            unfixable: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn pretty(value: &impl serde::Serialize) -> String {
+    serde_json::to_string_pretty(value).unwrap()
+}
+
+#[test]
+fn snapshot_json_report() {
+    let mut json = serde_json::to_value(report()).unwrap();
+    description::apply(&mut json, DescriptionMode::default());
+    insta::assert_snapshot!(pretty(&json));
+}
+
+#[test]
+fn snapshot_cyclonedx_sbom() {
+    let lockfile = lockfile();
+    let report = report();
+    let options = SbomOptions {
+        metadata: None,
+        members: &WorkspaceMembers::default(),
+        cpes: &CpeMappings::embedded(),
+        component_types: &BTreeMap::new(),
+        report: Some(&report),
+        project: None,
+        registries: &RegistryMap::default(),
+        stamp: stamp(),
+    };
+    insta::assert_snapshot!(pretty(&build_sbom(&lockfile, &options)));
+}
+
+// Binary, so instead of a snapshot: decoding the Protobuf SBOM gives back the JSON one
+#[test]
+fn protobuf_sbom_round_trips_to_the_json_form() {
+    let lockfile = lockfile();
+    let report = report();
+    let options = SbomOptions {
+        metadata: None,
+        members: &WorkspaceMembers::default(),
+        cpes: &CpeMappings::embedded(),
+        component_types: &BTreeMap::new(),
+        report: Some(&report),
+        project: None,
+        registries: &RegistryMap::default(),
+        stamp: stamp(),
+    };
+    let bom = build_sbom(&lockfile, &options);
+    let decoded = cyclonedx_proto::decode(&cyclonedx_proto::encode(&bom).unwrap()).unwrap();
+    assert_eq!(pretty(&decoded), pretty(&bom));
+}
+
+#[test]
+fn snapshot_vex() {
+    let sbom_serial = "urn:uuid:11111111-2222-3333-4444-555555555555";
+    insta::assert_snapshot!(pretty(&vex::build_vex(&report(), sbom_serial, &stamp())));
+}
+
+#[test]
+fn snapshot_trivy_report() {
+    // ansi_term is triaged not_affected and left out
+    insta::assert_snapshot!(pretty(&trivy::build_trivy(&report(), "demo.zip", "Cargo.lock")));
+}
+
+#[test]
+fn snapshot_junit_report() {
+    // ansi_term is triaged but, like in the JSON report, still counts as a failure
+    insta::assert_snapshot!(junit::render(&report(), &lockfile(), "demo.zip"));
+}
+
+#[test]
+fn snapshot_dot_graph() {
+    insta::assert_snapshot!(dot::render(&lockfile(), Some(&report())));
+}
+
+#[test]
+fn snapshot_github_dependency_snapshot() {
+    let context = SnapshotContext {
+        job_id: Some("42".to_string()),
+        correlator: Some("ci_scan".to_string()),
+        sha: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+        git_ref: Some("refs/heads/main".to_string()),
+    };
+    let snapshot = build_snapshot(&lockfile(), "Cargo.lock", &context, &RegistryMap::default(), &stamp()).unwrap();
+    insta::assert_snapshot!(pretty(&snapshot));
+}
+
+#[test]
+fn snapshot_markdown_template() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("templates/report.md.tera");
+    insta::assert_snapshot!(template::render_file(&path, &report()).unwrap());
+}
+
+#[test]
+fn snapshot_html_template() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("templates/report.html.tera");
+    insta::assert_snapshot!(template::render_file(&path, &report()).unwrap());
+}
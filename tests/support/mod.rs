@@ -0,0 +1,126 @@
+// Advisory databases, lockfiles and project archives shared by the integration tests and
+// the benchmarks (benches/scan.rs includes this file by path): the hand-written fixtures
+// under tests/fixtures, and synthetic data generated deterministically from the requested
+// size, so benchmark runs are comparable.
+#![allow(dead_code)]
+
+use cargo_lock::Lockfile;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use zip::ZipWriter;
+use zip::write::FileOptions;
+
+// Advisory markdown and the git commit Scanner needs, shared with the crate's unit tests
+#[path = "../../src/test_support.rs"]
+mod advisories;
+pub use advisories::{AdvisoryText, commit_all};
+
+// Synthetic crates are `crate-<n>`; advisories cover the first SYNTHETIC_CRATES of them
+pub const SYNTHETIC_CRATES: usize = 500;
+
+/// tests/fixtures 目录
+pub fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// tests/fixtures/advisory-db 中手写的 advisory，提交到 dir 下一个新的 git 仓库
+pub fn fixture_advisory_db(dir: &Path) -> PathBuf {
+    fixture_db(dir, "advisory-db")
+}
+
+/// tests/fixtures/<name> 中的 advisory DB（如内部 fork advisory-db-internal），提交到 dir/<name>
+pub fn fixture_db(dir: &Path, name: &str) -> PathBuf {
+    let db = dir.join(name);
+    let source = fixtures().join(name);
+    for entry in WalkDir::new(&source).into_iter().filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        let dest = db.join(entry.path().strip_prefix(&source).unwrap());
+        fs::create_dir_all(dest.parent().unwrap()).unwrap();
+        fs::copy(entry.path(), dest).unwrap();
+    }
+    commit_all(&db);
+    db
+}
+
+/// 把 tests/fixtures/projects/<name> 打包成 GitHub 风格（顶层目录 `<name>-main/`）的 ZIP
+pub fn zip_project(name: &str, dest: &Path) {
+    let project = fixtures().join("projects").join(name);
+    let mut zip = ZipWriter::new(fs::File::create(dest).unwrap());
+    for entry in WalkDir::new(&project).into_iter().filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        let rel = entry.path().strip_prefix(&project).unwrap().to_string_lossy().replace('\\', "/");
+        zip.start_file(format!("{}-main/{}", name, rel), FileOptions::default()).unwrap();
+        zip.write_all(&fs::read(entry.path()).unwrap()).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+/// 只把 tests/fixtures/projects/<name>/Cargo.lock 打包成 ZIP，像只上传 lockfile 的流水线那样
+pub fn zip_lockfile(name: &str, dest: &Path) {
+    let mut zip = ZipWriter::new(fs::File::create(dest).unwrap());
+    zip.start_file(format!("{}-main/Cargo.lock", name), FileOptions::default()).unwrap();
+    zip.write_all(&fs::read(fixtures().join("projects").join(name).join("Cargo.lock")).unwrap()).unwrap();
+    zip.finish().unwrap();
+}
+
+/// 把 tests/fixtures/projects/<name> 打包成 `cargo package` 风格（顶层目录 `<dir>/`）的 .crate
+pub fn crate_project(name: &str, dir: &str, dest: &Path) {
+    let project = fixtures().join("projects").join(name);
+    let gz = flate2::write::GzEncoder::new(fs::File::create(dest).unwrap(), flate2::Compression::default());
+    let mut tar = tar::Builder::new(gz);
+    tar.append_dir_all(dir, &project).unwrap();
+    tar.into_inner().unwrap().finish().unwrap();
+}
+
+/// 在 dir/advisory-db 下生成 count 条 advisory，按 RustSec 的目录结构存放并提交
+pub fn synthetic_advisory_db(dir: &Path, count: usize) -> PathBuf {
+    let db = dir.join("advisory-db");
+    for i in 0..count {
+        let package = format!("crate-{}", i % SYNTHETIC_CRATES);
+        let id = format!("RUSTSEC-{}-{:04}", 2000 + i / 9999, i % 9999 + 1);
+        // Patched in a later minor for some, never for every seventh
+        let versions = if i % 7 == 0 {
+            "patched = []".to_string()
+        } else {
+            format!("patched = [\">= 1.{}.0\"]", i % 10 + 1)
+        };
+        let metadata = format!(
+            "cvss = \"CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:{c}/I:N/A:N\"\ncategories = [\"memory-corruption\"]\n\
+             aliases = [\"CVE-{year}-{i:05}\"]",
+            c = if i % 2 == 0 { "H" } else { "L" },
+            year = 2000 + i / 99999,
+        );
+        AdvisoryText::new(&id, &package)
+            .with_metadata(&metadata)
+            .with_versions(&versions)
+            .with_title(&format!("Synthetic advisory {}", i))
+            .write(&db);
+    }
+    commit_all(&db);
+    db
+}
+
+/// 由 `app`（工作区成员）和 packages 个 crates.io 包组成的 lockfile；依赖关系是一棵二叉树
+pub fn synthetic_lockfile(packages: usize) -> Lockfile {
+    let version = |i: usize| format!("1.{}.0", i % 10);
+    let mut text = String::from("version = 3\n\n[[package]]\nname = \"app\"\nversion = \"0.1.0\"\ndependencies = [\"crate-0\"]\n");
+    for i in 0..packages {
+        write!(
+            text,
+            "\n[[package]]\nname = \"crate-{}\"\nversion = \"{}\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+            i,
+            version(i)
+        )
+        .unwrap();
+        let deps: Vec<String> = [2 * i + 1, 2 * i + 2]
+            .into_iter()
+            .filter(|&d| d < packages)
+            .map(|d| format!("\"crate-{}\"", d))
+            .collect();
+        if !deps.is_empty() {
+            writeln!(text, "dependencies = [{}]", deps.join(", ")).unwrap();
+        }
+    }
+    text.parse().unwrap()
+}